@@ -1,5 +1,4 @@
-use dashmap::DashMap;
-
+use std::collections::HashSet;
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::{Arc, Weak};
 
@@ -17,11 +16,13 @@ use tokio::sync::broadcast::Sender;
 use tracing::{error, trace, warn};
 use uuid::Uuid;
 
+use crate::blocks::row_cache::{RowCache, RowCacheConfig, RowCacheMetrics};
 use crate::blocks::task_controller::{BlockTask, BlockTaskController};
 use crate::error::DatabaseError;
 use crate::rows::{
-  meta_id_from_row_id, Cell, DatabaseRow, MutexDatabaseRow, Row, RowChangeSender, RowDetail, RowId,
-  RowMeta, RowMetaKey, RowMetaUpdate, RowUpdate,
+  meta_id_from_row_id, row_from_collab, Cell, CellChangeRecord, Cells, DatabaseRow,
+  MutexDatabaseRow, Row, RowChangeSender, RowDetail, RowId, RowMeta, RowMetaKey, RowMetaUpdate,
+  RowUpdate,
 };
 use crate::views::RowOrder;
 use crate::workspace_database::DatabaseCollabService;
@@ -43,7 +44,7 @@ pub struct Block {
   collab_service: Arc<dyn DatabaseCollabService>,
   task_controller: Arc<BlockTaskController>,
   sequence: Arc<AtomicU32>,
-  pub rows: Arc<DashMap<RowId, Arc<MutexDatabaseRow>>>,
+  pub rows: Arc<RowCache>,
   pub notifier: Arc<Sender<BlockEvent>>,
   row_change_tx: RowChangeSender,
 }
@@ -66,7 +67,7 @@ impl Block {
       task_controller,
       collab_service,
       sequence: Arc::new(Default::default()),
-      rows: Arc::new(Default::default()),
+      rows: Arc::new(RowCache::new(RowCacheConfig::default())),
       notifier: Arc::new(notifier),
       row_change_tx,
     }
@@ -116,6 +117,84 @@ impl Block {
     });
   }
 
+  /// Fetch the rows that are neither in memory nor on the local disk from the remote in one
+  /// batch. The rows on the local disk are loaded when they are accessed.
+  pub fn prefetch_rows(&self, row_ids: &[RowId]) {
+    self.fetch_missing_rows(row_ids);
+  }
+
+  /// Fetch the rows that are neither in memory nor on the local disk from the remote in one
+  /// batch. Return the ids of the fetched rows.
+  pub(crate) fn fetch_missing_rows(&self, row_ids: &[RowId]) -> HashSet<RowId> {
+    let collab_db = match self.collab_db.upgrade() {
+      None => return HashSet::new(),
+      Some(collab_db) => collab_db,
+    };
+    let missing_row_ids = {
+      let read_txn = collab_db.read_txn();
+      row_ids
+        .iter()
+        .filter(|row_id| !self.rows.contains_key(*row_id))
+        .filter(|row_id| !read_txn.is_exist(self.uid, row_id.as_str()))
+        .cloned()
+        .collect::<Vec<_>>()
+    };
+    if !missing_row_ids.is_empty() {
+      trace!("prefetch {} rows from remote", missing_row_ids.len());
+      self.batch_load_rows(missing_row_ids.clone());
+    }
+    missing_row_ids.into_iter().collect()
+  }
+
+  /// Return the rows like [Block::get_rows_from_row_orders], except that the rows that are being
+  /// fetched are returned as empty rows without fetching them again.
+  pub(crate) fn get_rows_without_fetching(
+    &self,
+    row_orders: &[RowOrder],
+    fetching_row_ids: &HashSet<RowId>,
+  ) -> Vec<Row> {
+    row_orders
+      .iter()
+      .map(|row_order| {
+        if fetching_row_ids.contains(&row_order.id) {
+          return Row::empty(row_order.id.clone(), &self.database_id);
+        }
+        self
+          .get_or_init_row(&row_order.id)
+          .and_then(|row| row.lock().get_row())
+          .unwrap_or_else(|| Row::empty(row_order.id.clone(), &self.database_id))
+      })
+      .collect()
+  }
+
+  /// Remove the rows from memory. Their changes are already saved to the local disk, so they are
+  /// loaded from it when they are accessed again. The rows that are still referenced outside the
+  /// [Block] are kept, otherwise they would be loaded twice.
+  pub fn evict_rows(&self, row_ids: &[RowId]) {
+    for row_id in row_ids {
+      self.rows.evict(row_id);
+    }
+  }
+
+  /// Return true if the row is in memory.
+  pub fn is_row_loaded(&self, row_id: &RowId) -> bool {
+    self.rows.contains_key(row_id)
+  }
+
+  pub fn row_cache_config(&self) -> RowCacheConfig {
+    self.rows.config()
+  }
+
+  /// Replace the limits of the row cache. The least recently used rows that exceed the new limits
+  /// are flushed and removed from memory.
+  pub fn set_row_cache_config(&self, config: RowCacheConfig) {
+    self.rows.set_config(config);
+  }
+
+  pub fn row_cache_metrics(&self) -> RowCacheMetrics {
+    self.rows.metrics()
+  }
+
   pub fn create_rows<T>(&self, rows: Vec<T>) -> Vec<RowOrder>
   where
     T: Into<Row> + Send,
@@ -179,6 +258,14 @@ impl Block {
       .unwrap_or_else(|| Row::empty(row_id.clone(), &self.database_id))
   }
 
+  /// Return the row with given id if it's available locally. Unlike [Block::get_row], return
+  /// None instead of an empty row when the row is still being fetched from the remote.
+  pub fn get_loaded_row(&self, row_id: &RowId) -> Option<Row> {
+    self
+      .get_or_init_row(row_id)
+      .and_then(|row| row.lock().get_row())
+  }
+
   pub fn get_row_meta(&self, row_id: &RowId) -> Option<RowMeta> {
     self
       .get_or_init_row(row_id)
@@ -186,6 +273,13 @@ impl Block {
       .or_else(|| Some(RowMeta::empty()))
   }
 
+  pub fn get_row_history(&self, row_id: &RowId) -> Vec<CellChangeRecord> {
+    self
+      .get_or_init_row(row_id)
+      .map(|row| row.lock().get_history())
+      .unwrap_or_default()
+  }
+
   pub fn get_row_document_id(&self, row_id: &RowId) -> Option<String> {
     let row_id = Uuid::parse_str(row_id).ok()?;
     Some(meta_id_from_row_id(&row_id, RowMetaKey::DocumentId))
@@ -215,7 +309,7 @@ impl Block {
   pub fn delete_row(&self, row_id: &RowId) {
     let row = self.rows.remove(row_id);
     if let Some(row) = row {
-      row.1.lock().delete();
+      row.lock().delete();
     }
   }
 
@@ -223,14 +317,14 @@ impl Block {
   where
     F: FnOnce(RowUpdate),
   {
-    let row = self.rows.get(row_id).map(|r| r.value().clone());
+    // The row might have been evicted from the cache, so it's loaded from the disk again.
+    let row = self.get_or_init_row(row_id);
     match row {
       None => {
         trace!(
-          "fail to update row. the row is not in the cache: {:?}. init it",
+          "fail to update row. the row is still being fetched: {:?}",
           row_id
         );
-        self.get_or_init_row(row_id);
       },
       Some(row) => {
         row.lock().update::<F>(f);
@@ -238,15 +332,50 @@ impl Block {
     }
   }
 
+  /// Update the row like [Block::update_row] if its cells pass the validation, see
+  /// [DatabaseRow::update_with_validation]. Return [DatabaseError::RowNotExist] if the row
+  /// doesn't exist or is still being fetched.
+  pub fn update_row_with_validation<F, V>(
+    &self,
+    row_id: &RowId,
+    f: F,
+    validate: V,
+  ) -> Result<(), DatabaseError>
+  where
+    F: FnOnce(RowUpdate),
+    V: FnOnce(&Cells, &Cells) -> Result<(), DatabaseError>,
+  {
+    match self.get_or_init_row(row_id) {
+      None => Err(DatabaseError::RowNotExist),
+      Some(row) => row.lock().update_with_validation(f, validate),
+    }
+  }
+
+  /// Update the row like [Block::update_row] without sending its
+  /// [RowChange](crate::rows::RowChange)s. Return [DatabaseError::RowNotExist] if the row doesn't
+  /// exist or is still being fetched.
+  pub(crate) fn update_row_silently<F>(&self, row_id: &RowId, f: F) -> Result<(), DatabaseError>
+  where
+    F: FnOnce(RowUpdate),
+  {
+    match self.get_or_init_row(row_id) {
+      None => Err(DatabaseError::RowNotExist),
+      Some(row) => {
+        row.lock().update_silently(f);
+        Ok(())
+      },
+    }
+  }
+
   pub fn update_row_meta<F>(&self, row_id: &RowId, f: F)
   where
     F: FnOnce(RowMetaUpdate),
   {
-    let row = self.rows.get(row_id).map(|r| r.value().clone());
+    let row = self.get_or_init_row(row_id);
     match row {
       None => {
         trace!(
-          "fail to update row meta. the row is not in the cache: {:?}",
+          "fail to update row meta. the row is still being fetched: {:?}",
           row_id
         )
       },
@@ -256,10 +385,27 @@ impl Block {
     }
   }
 
+  /// Return the cells of the row without keeping the row in memory. A row that is neither in
+  /// memory nor on the local disk is not fetched from the remote, and None is returned for a row
+  /// that is being updated.
+  pub(crate) fn read_row_cells(&self, row_id: &RowId) -> Option<Cells> {
+    if let Some(row) = self.rows.get(row_id) {
+      return row.try_lock()?.get_row().map(|row| row.cells);
+    }
+    let collab_db = self.collab_db.upgrade()?;
+    if !collab_db.read_txn().is_exist(self.uid, row_id.as_ref()) {
+      return None;
+    }
+    let collab = self.create_collab_for_row(row_id).ok()?;
+    let collab = collab.lock();
+    let txn = collab.transact();
+    row_from_collab(&collab, &txn).map(|row| row.cells)
+  }
+
   /// Get the [DatabaseRow] from the cache. If the row is not in the cache, initialize it.
-  fn get_or_init_row(&self, row_id: &RowId) -> Option<Arc<MutexDatabaseRow>> {
+  pub(crate) fn get_or_init_row(&self, row_id: &RowId) -> Option<Arc<MutexDatabaseRow>> {
     let collab_db = self.collab_db.upgrade()?;
-    let row = self.rows.get(row_id).map(|r| r.value().clone());
+    let row = self.rows.get(row_id);
     match row {
       None => {
         let is_exist = collab_db.read_txn().is_exist(self.uid, row_id.as_ref());
@@ -341,7 +487,7 @@ impl Block {
     uid: i64,
     change_tx: RowChangeSender,
     collab_db: Weak<CollabKVDB>,
-    cache: Arc<DashMap<RowId, Arc<MutexDatabaseRow>>>,
+    cache: Arc<RowCache>,
     row_collab: Arc<MutexCollab>,
   ) -> Result<(), CollabError> {
     if cache.contains_key(row_id) {
@@ -387,7 +533,7 @@ impl Block {
 async fn async_create_row<T: Into<Row>>(
   uid: i64,
   row: T,
-  cache: Arc<DashMap<RowId, Arc<MutexDatabaseRow>>>,
+  cache: Arc<RowCache>,
   collab_db: Weak<CollabKVDB>,
   row_change_tx: RowChangeSender,
   collab_service: Arc<dyn DatabaseCollabService>,
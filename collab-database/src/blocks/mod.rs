@@ -1,5 +1,7 @@
 pub use block::*;
+pub use row_cache::*;
 
 mod block;
 mod queue;
+mod row_cache;
 mod task_controller;
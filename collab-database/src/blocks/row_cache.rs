@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use dashmap::DashMap;
+use parking_lot::{Mutex, RwLock};
+use tracing::trace;
+
+use crate::rows::{MutexDatabaseRow, RowId};
+
+/// The limits of the [RowCache]. When the cache exceeds one of them, the least recently used
+/// rows are flushed and dropped until it fits again. The cache is unbounded by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RowCacheConfig {
+  /// The maximum number of rows in memory. None means no limit.
+  pub max_rows: Option<usize>,
+  /// The maximum estimated size of the rows in memory. None means no limit. See
+  /// [DatabaseRow::estimated_size](crate::rows::DatabaseRow::estimated_size) for how the size of
+  /// a row is estimated.
+  pub max_bytes: Option<usize>,
+}
+
+impl RowCacheConfig {
+  pub fn unbounded() -> Self {
+    Self::default()
+  }
+
+  pub fn with_max_rows(mut self, max_rows: usize) -> Self {
+    self.max_rows = Some(max_rows);
+    self
+  }
+
+  pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+    self.max_bytes = Some(max_bytes);
+    self
+  }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RowCacheMetrics {
+  /// The number of times that a row was found in memory.
+  pub hits: u64,
+  /// The number of times that a row had to be loaded from the disk or the remote.
+  pub misses: u64,
+  /// The number of rows that were dropped to keep the cache within its limits.
+  pub evictions: u64,
+  pub rows: usize,
+  /// The estimated size of the rows. Always zero unless the cache is limited by size.
+  pub estimated_bytes: usize,
+}
+
+/// The [DatabaseRow](crate::rows::DatabaseRow)s that are in memory. The changes of a row are
+/// saved to the local disk as they are made, so a row that is dropped is loaded from the disk
+/// again when it's accessed.
+///
+/// The rows that are still referenced outside the cache are never dropped, otherwise the same
+/// row would be loaded twice.
+pub struct RowCache {
+  rows: DashMap<RowId, Arc<MutexDatabaseRow>>,
+  lru: Mutex<LruList>,
+  config: RwLock<RowCacheConfig>,
+  hits: AtomicU64,
+  misses: AtomicU64,
+  evictions: AtomicU64,
+}
+
+/// The rows in the order of their last access, from the least to the most recently used. It's a
+/// linked hash map: the rows are linked in a list whose nodes are stored in a slab, so a row is
+/// moved to the end of the list in constant time.
+#[derive(Default)]
+struct LruList {
+  /// The index of each row's node.
+  indexes: HashMap<RowId, usize>,
+  nodes: Vec<LruNode>,
+  /// The indexes of the unused nodes.
+  free_indexes: Vec<usize>,
+  head: Option<usize>,
+  tail: Option<usize>,
+  total_bytes: usize,
+}
+
+struct LruNode {
+  row_id: RowId,
+  /// The estimated size of the row. Zero if the size is not limited.
+  size: usize,
+  prev: Option<usize>,
+  next: Option<usize>,
+}
+
+impl LruList {
+  fn len(&self) -> usize {
+    self.indexes.len()
+  }
+
+  /// Move the row to the end of the list.
+  fn touch(&mut self, row_id: &RowId) {
+    if let Some(&index) = self.indexes.get(row_id) {
+      self.unlink(index);
+      self.push_back(index);
+    }
+  }
+
+  fn resize(&mut self, row_id: &RowId, size: usize) {
+    if let Some(&index) = self.indexes.get(row_id) {
+      self.set_size(index, size);
+    }
+  }
+
+  fn insert(&mut self, row_id: RowId, size: usize) {
+    self.remove(&row_id);
+    let node = LruNode {
+      row_id: row_id.clone(),
+      size: 0,
+      prev: None,
+      next: None,
+    };
+    let index = match self.free_indexes.pop() {
+      Some(index) => {
+        self.nodes[index] = node;
+        index
+      },
+      None => {
+        self.nodes.push(node);
+        self.nodes.len() - 1
+      },
+    };
+    self.indexes.insert(row_id, index);
+    self.push_back(index);
+    self.set_size(index, size);
+  }
+
+  fn remove(&mut self, row_id: &RowId) {
+    if let Some(index) = self.indexes.remove(row_id) {
+      self.unlink(index);
+      self.set_size(index, 0);
+      self.free_indexes.push(index);
+    }
+  }
+
+  /// Return the first row after the given one, or the least recently used row if it's None or not
+  /// in the list.
+  fn next_row_id(&self, after: Option<&RowId>) -> Option<RowId> {
+    let index = match after.and_then(|row_id| self.indexes.get(row_id)) {
+      None => self.head?,
+      Some(&index) => self.nodes[index].next?,
+    };
+    Some(self.nodes[index].row_id.clone())
+  }
+
+  fn set_size(&mut self, index: usize, size: usize) {
+    let node = &mut self.nodes[index];
+    self.total_bytes = self.total_bytes - node.size + size;
+    node.size = size;
+  }
+
+  fn unlink(&mut self, index: usize) {
+    let (prev, next) = (self.nodes[index].prev, self.nodes[index].next);
+    match prev {
+      None => self.head = next,
+      Some(prev) => self.nodes[prev].next = next,
+    }
+    match next {
+      None => self.tail = prev,
+      Some(next) => self.nodes[next].prev = prev,
+    }
+    self.nodes[index].prev = None;
+    self.nodes[index].next = None;
+  }
+
+  fn push_back(&mut self, index: usize) {
+    self.nodes[index].prev = self.tail;
+    match self.tail {
+      None => self.head = Some(index),
+      Some(tail) => self.nodes[tail].next = Some(index),
+    }
+    self.tail = Some(index);
+  }
+
+  fn exceeds(&self, config: &RowCacheConfig) -> bool {
+    config
+      .max_rows
+      .map(|max_rows| self.len() > max_rows)
+      .unwrap_or(false)
+      || config
+        .max_bytes
+        .map(|max_bytes| self.total_bytes > max_bytes)
+        .unwrap_or(false)
+  }
+}
+
+impl RowCache {
+  pub fn new(config: RowCacheConfig) -> Self {
+    Self {
+      rows: DashMap::new(),
+      lru: Mutex::new(LruList::default()),
+      config: RwLock::new(config),
+      hits: AtomicU64::new(0),
+      misses: AtomicU64::new(0),
+      evictions: AtomicU64::new(0),
+    }
+  }
+
+  /// Return the row and mark it as recently used. The access is counted in the metrics.
+  pub fn get(&self, row_id: &RowId) -> Option<Arc<MutexDatabaseRow>> {
+    let row = self.rows.get(row_id).map(|row| row.value().clone());
+    match &row {
+      None => {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+      },
+      Some(row) => {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        // The size changes when the row is edited, so it's estimated again
+        let size = self.estimate_size(row);
+        let mut lru = self.lru.lock();
+        lru.touch(row_id);
+        if let Some(size) = size {
+          lru.resize(row_id, size);
+        }
+      },
+    }
+    if row.is_some() && self.config.read().max_bytes.is_some() {
+      self.evict_cold_rows();
+    }
+    row
+  }
+
+  pub fn contains_key(&self, row_id: &RowId) -> bool {
+    self.rows.contains_key(row_id)
+  }
+
+  pub fn len(&self) -> usize {
+    self.rows.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.rows.is_empty()
+  }
+
+  /// Put the row into the cache and drop the least recently used rows if the cache exceeds its
+  /// limits.
+  pub fn insert(&self, row_id: RowId, row: Arc<MutexDatabaseRow>) {
+    let size = self.estimate_size(&row).unwrap_or_default();
+    self.rows.insert(row_id.clone(), row);
+    self.lru.lock().insert(row_id, size);
+    self.evict_cold_rows();
+  }
+
+  pub fn remove(&self, row_id: &RowId) -> Option<Arc<MutexDatabaseRow>> {
+    self.lru.lock().remove(row_id);
+    self.rows.remove(row_id).map(|(_, row)| row)
+  }
+
+  /// Flush and drop the row if it's not referenced outside the cache. Return true if the row was
+  /// dropped.
+  pub fn evict(&self, row_id: &RowId) -> bool {
+    let row = match self.rows.get(row_id).map(|row| row.value().clone()) {
+      None => return false,
+      Some(row) => row,
+    };
+    // One reference is held by the cache and the other by this function
+    if Arc::strong_count(&row) > 2 {
+      return false;
+    }
+    match row.try_lock() {
+      None => return false,
+      Some(row) => row.flush(),
+    }
+    drop(row);
+
+    let is_removed = self
+      .rows
+      .remove_if(row_id, |_, row| Arc::strong_count(row) == 1)
+      .is_some();
+    if is_removed {
+      self.lru.lock().remove(row_id);
+      self.evictions.fetch_add(1, Ordering::Relaxed);
+    }
+    is_removed
+  }
+
+  pub fn config(&self) -> RowCacheConfig {
+    self.config.read().clone()
+  }
+
+  /// Replace the limits of the cache and drop the rows that exceed the new limits.
+  pub fn set_config(&self, config: RowCacheConfig) {
+    let is_size_limited = config.max_bytes.is_some();
+    let was_size_limited = self.config.read().max_bytes.is_some();
+    *self.config.write() = config;
+    if is_size_limited != was_size_limited {
+      // The sizes are only estimated when they're limited
+      for entry in self.rows.iter() {
+        let size = self.estimate_size(entry.value()).unwrap_or_default();
+        self.lru.lock().resize(entry.key(), size);
+      }
+    }
+    self.evict_cold_rows();
+  }
+
+  pub fn metrics(&self) -> RowCacheMetrics {
+    RowCacheMetrics {
+      hits: self.hits.load(Ordering::Relaxed),
+      misses: self.misses.load(Ordering::Relaxed),
+      evictions: self.evictions.load(Ordering::Relaxed),
+      rows: self.rows.len(),
+      estimated_bytes: self.lru.lock().total_bytes,
+    }
+  }
+
+  /// Return the estimated size of the row. None if the size is not limited or the row is in use.
+  fn estimate_size(&self, row: &MutexDatabaseRow) -> Option<usize> {
+    if self.config.read().max_bytes.is_none() {
+      return None;
+    }
+    row.try_lock()?.estimated_size()
+  }
+
+  /// Drop the least recently used rows until the cache fits its limits. The rows that are in use
+  /// are skipped.
+  fn evict_cold_rows(&self) {
+    let config = self.config();
+    let mut skipped_row_id = None;
+    loop {
+      let row_id = {
+        let lru = self.lru.lock();
+        if !lru.exceeds(&config) {
+          return;
+        }
+        match lru.next_row_id(skipped_row_id.as_ref()) {
+          None => return,
+          Some(row_id) => row_id,
+        }
+      };
+      if self.evict(&row_id) {
+        trace!("evict the cold row: {}", row_id);
+      } else {
+        skipped_row_id = Some(row_id);
+      }
+    }
+  }
+}
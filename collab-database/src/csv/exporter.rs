@@ -0,0 +1,67 @@
+use collab::core::any_map::AnyMapExtension;
+
+use crate::database::Database;
+use crate::error::DatabaseError;
+use crate::fields::{Field, FieldType};
+use crate::rows::{any_to_string, CellValue, Row, CELL_DATA};
+use crate::views::FieldSettingsMap;
+
+/// The visibility of a field in the view's field settings. The field is not exported if it's
+/// always hidden.
+const FIELD_VISIBILITY: &str = "visibility";
+const FIELD_VISIBILITY_ALWAYS_HIDDEN: i64 = 2;
+
+/// Export the visible fields and rows of the view as csv. The fields and rows are written in the
+/// order of the view and the cells are formatted with the type options of their fields.
+pub fn export_csv(database: &Database, view_id: &str) -> Result<String, DatabaseError> {
+  let field_settings = database.get_field_settings::<FieldSettingsMap>(view_id, None);
+  let fields = database
+    .get_fields_in_view(view_id, None)
+    .into_iter()
+    .filter(|field| {
+      field_settings
+        .get(&field.id)
+        .and_then(|settings| settings.get_i64_value(FIELD_VISIBILITY))
+        != Some(FIELD_VISIBILITY_ALWAYS_HIDDEN)
+    })
+    .collect::<Vec<_>>();
+
+  let mut writer = csv::Writer::from_writer(vec![]);
+  writer.write_record(fields.iter().map(|field| field.name.as_str()))?;
+  for row in database.get_rows_for_view(view_id) {
+    if !row.visibility {
+      continue;
+    }
+    writer.write_record(fields.iter().map(|field| format_cell(field, &row)))?;
+  }
+  let content = writer
+    .into_inner()
+    .map_err(|err| DatabaseError::Internal(anyhow::anyhow!("{}", err)))?;
+  String::from_utf8(content).map_err(|err| DatabaseError::Internal(err.into()))
+}
+
+/// Format the cell of the field in the row. The values of the last edited time and created time
+/// fields are read from the row.
+fn format_cell(field: &Field, row: &Row) -> String {
+  let field_type = match FieldType::try_from(field.field_type) {
+    Ok(field_type) => field_type,
+    Err(_) => {
+      return row
+        .cells
+        .get(&field.id)
+        .and_then(|cell| cell.get(CELL_DATA))
+        .and_then(any_to_string)
+        .unwrap_or_default()
+    },
+  };
+  let value = match field_type {
+    FieldType::LastEditedTime => Some(CellValue::Timestamp(row.modified_at)),
+    FieldType::CreatedTime => Some(CellValue::Timestamp(row.created_at)),
+    _ => row
+      .cells
+      .get(&field.id)
+      .and_then(|cell| CellValue::from_cell(field_type, cell).ok())
+      .flatten(),
+  };
+  value.map(|value| value.to_text(field)).unwrap_or_default()
+}
@@ -0,0 +1,246 @@
+use std::collections::HashSet;
+use std::io::Read;
+
+use crate::database::{gen_database_id, gen_field_id, gen_row_id};
+use crate::error::DatabaseError;
+use crate::fields::{
+  DateTypeOption, Field, FieldType, SelectTypeOption, TypeOptionData, DATE_CELL_INCLUDE_TIME,
+  SELECT_OPTION_SEPARATOR,
+};
+use crate::rows::{
+  is_url, new_cell_builder, parse_checkbox, parse_date, parse_number, Cell, Cells, CreateRowParams,
+  CELL_DATA, CHECKBOX_CHECKED, CHECKBOX_UNCHECKED,
+};
+use crate::views::{
+  CreateDatabaseParams, CreateViewParams, DatabaseLayout, FieldSettingsByFieldIdMap,
+  FieldSettingsMapBuilder,
+};
+
+/// A column is imported as a select field only if it has at most this many distinct values.
+const MAX_SELECT_OPTIONS: usize = 20;
+/// A column is imported as a select field only if none of its values is longer than this.
+const MAX_SELECT_OPTION_LEN: usize = 40;
+
+/// Build the [CreateDatabaseParams] of a new grid database from the csv content. The first row
+/// of the csv is used as the field names and the first column becomes the primary field.
+///
+/// The type of each field is inferred from the values of its column. Columns that can't be
+/// inferred as number, checkbox, date, url or select option are imported as text.
+pub fn import_csv<R: Read>(
+  view_id: &str,
+  view_name: &str,
+  reader: R,
+) -> Result<CreateDatabaseParams, DatabaseError> {
+  let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+  let field_names = reader
+    .headers()?
+    .iter()
+    .enumerate()
+    .map(|(index, name)| {
+      let name = name.trim();
+      if name.is_empty() {
+        format!("Field {}", index + 1)
+      } else {
+        name.to_string()
+      }
+    })
+    .collect::<Vec<String>>();
+
+  let mut records = vec![];
+  for record in reader.records() {
+    let record = record?;
+    let values = (0..field_names.len())
+      .map(|index| record.get(index).unwrap_or_default().trim().to_string())
+      .collect::<Vec<String>>();
+    records.push(values);
+  }
+
+  let database_id = gen_database_id();
+  let mut fields = vec![];
+  let mut columns = vec![];
+  for (index, name) in field_names.into_iter().enumerate() {
+    let values = records
+      .iter()
+      .map(|record| record[index].as_str())
+      .collect::<Vec<_>>();
+    let field_type = if index == 0 {
+      FieldType::RichText
+    } else {
+      infer_field_type(&values)
+    };
+    let column = ImportColumn::new(field_type, &values);
+    let mut field = Field::new(gen_field_id(), name, field_type.value(), index == 0);
+    if let Some(type_option) = column.type_option() {
+      field = field.with_type_option_data(field_type.value(), type_option);
+    }
+    fields.push(field);
+    columns.push(column);
+  }
+
+  let rows = records
+    .iter()
+    .map(|record| {
+      let mut cells = Cells::new();
+      for ((field, column), value) in fields.iter().zip(columns.iter()).zip(record.iter()) {
+        if let Some(cell) = column.make_cell(value) {
+          cells.insert(field.id.clone(), cell);
+        }
+      }
+      CreateRowParams::new(gen_row_id(), database_id.clone()).with_cells(cells)
+    })
+    .collect();
+
+  let mut field_settings = FieldSettingsByFieldIdMap::new();
+  for field in &fields {
+    field_settings.insert(
+      field.id.clone(),
+      FieldSettingsMapBuilder::new()
+        .insert_i64_value("visibility", 0)
+        .build(),
+    );
+  }
+
+  Ok(CreateDatabaseParams {
+    database_id: database_id.clone(),
+    inline_view_id: view_id.to_string(),
+    views: vec![CreateViewParams {
+      database_id,
+      view_id: view_id.to_string(),
+      name: view_name.to_string(),
+      layout: DatabaseLayout::Grid,
+      field_settings,
+      ..Default::default()
+    }],
+    rows,
+    fields,
+  })
+}
+
+/// Infer the field type from the values of a column. Empty values are ignored.
+pub fn infer_field_type(values: &[&str]) -> FieldType {
+  let values = values
+    .iter()
+    .map(|value| value.trim())
+    .filter(|value| !value.is_empty())
+    .collect::<Vec<_>>();
+  if values.is_empty() {
+    return FieldType::RichText;
+  }
+
+  if values.iter().all(|value| parse_checkbox(value).is_some()) {
+    return FieldType::Checkbox;
+  }
+  if values.iter().all(|value| parse_number(value).is_some()) {
+    return FieldType::Number;
+  }
+  if values.iter().all(|value| parse_date(value).is_some()) {
+    return FieldType::DateTime;
+  }
+  if values.iter().all(|value| is_url(value)) {
+    return FieldType::URL;
+  }
+
+  let option_names = values
+    .iter()
+    .flat_map(|value| split_option_names(value))
+    .collect::<HashSet<_>>();
+  let is_select = option_names.len() <= MAX_SELECT_OPTIONS
+    && option_names.len() < values.len()
+    && option_names
+      .iter()
+      .all(|name| name.chars().count() <= MAX_SELECT_OPTION_LEN);
+  if is_select {
+    if values
+      .iter()
+      .any(|value| split_option_names(value).len() > 1)
+    {
+      return FieldType::MultiSelect;
+    }
+    return FieldType::SingleSelect;
+  }
+  FieldType::RichText
+}
+
+struct ImportColumn {
+  field_type: FieldType,
+  select_type_option: SelectTypeOption,
+}
+
+impl ImportColumn {
+  fn new(field_type: FieldType, values: &[&str]) -> Self {
+    let mut select_type_option = SelectTypeOption::default();
+    if field_type.is_select_option() {
+      for value in values {
+        for name in split_option_names(value) {
+          select_type_option.get_or_insert_option(name);
+        }
+      }
+    }
+    Self {
+      field_type,
+      select_type_option,
+    }
+  }
+
+  fn type_option(&self) -> Option<TypeOptionData> {
+    match self.field_type {
+      FieldType::SingleSelect | FieldType::MultiSelect => {
+        Some(self.select_type_option.clone().into())
+      },
+      FieldType::DateTime => Some(DateTypeOption::default().into()),
+      _ => None,
+    }
+  }
+
+  fn make_cell(&self, value: &str) -> Option<Cell> {
+    if value.is_empty() {
+      return None;
+    }
+    let builder = new_cell_builder(self.field_type);
+    let cell = match self.field_type {
+      FieldType::Checkbox => {
+        let is_checked = parse_checkbox(value)?;
+        let data = if is_checked {
+          CHECKBOX_CHECKED
+        } else {
+          CHECKBOX_UNCHECKED
+        };
+        builder.insert_str_value(CELL_DATA, data).build()
+      },
+      FieldType::Number => builder
+        .insert_str_value(CELL_DATA, parse_number(value)?.to_string())
+        .build(),
+      FieldType::DateTime => {
+        let (timestamp, include_time) = parse_date(value)?;
+        builder
+          .insert_str_value(CELL_DATA, timestamp.to_string())
+          .insert_bool_value(DATE_CELL_INCLUDE_TIME, include_time)
+          .build()
+      },
+      FieldType::SingleSelect | FieldType::MultiSelect => {
+        let ids = split_option_names(value)
+          .into_iter()
+          .flat_map(|name| {
+            self
+              .select_type_option
+              .get_option_by_name(name)
+              .map(|option| option.id.clone())
+          })
+          .collect::<Vec<_>>();
+        builder
+          .insert_str_value(CELL_DATA, ids.join(SELECT_OPTION_SEPARATOR))
+          .build()
+      },
+      _ => builder.insert_str_value(CELL_DATA, value).build(),
+    };
+    Some(cell)
+  }
+}
+
+fn split_option_names(value: &str) -> Vec<&str> {
+  value
+    .split(SELECT_OPTION_SEPARATOR)
+    .map(|name| name.trim())
+    .filter(|name| !name.is_empty())
+    .collect()
+}
@@ -0,0 +1,5 @@
+mod exporter;
+mod importer;
+
+pub use exporter::*;
+pub use importer::*;
@@ -1,14 +1,18 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::Deref;
 use std::rc::Rc;
 
 use std::sync::{Arc, Weak};
 
+use chrono::{DateTime, NaiveDate};
+use chrono_tz::Tz;
+
 use collab::core::any_map::AnyMapExtension;
 use collab::core::collab::MutexCollab;
 
 use collab::core::collab_state::{SnapshotState, SyncState};
 
+use collab::preclude::block::ClientID;
 use collab::preclude::{
   Collab, JsonValue, MapRefExtension, MapRefWrapper, ReadTxn, TransactionMut,
 };
@@ -16,26 +20,34 @@ use collab_entity::define::{DATABASE, DATABASE_ID};
 use collab_entity::CollabType;
 use collab_plugins::CollabKVDB;
 use nanoid::nanoid;
-use parking_lot::Mutex;
+use parking_lot::{Mutex, MutexGuard};
 use serde::{Deserialize, Serialize};
 pub use tokio_stream::wrappers::WatchStream;
 
-use crate::blocks::{Block, BlockEvent};
+use crate::blocks::{Block, BlockEvent, RowCacheConfig, RowCacheMetrics};
+use crate::database_awareness::DatabaseAwarenessState;
 use crate::database_state::DatabaseNotify;
 use crate::error::DatabaseError;
-use crate::fields::{Field, FieldChangeReceiver, FieldMap};
+use crate::fields::{
+  ConstraintRowSource, ConstraintValidator, ConstraintViolation, Field, FieldChangeReceiver,
+  FieldConstraints, FieldMap, FieldType, FieldTypeConverter,
+};
 use crate::meta::MetaMap;
 use crate::rows::{
-  CreateRowParams, CreateRowParamsValidator, Row, RowCell, RowChangeReceiver, RowDetail, RowId,
-  RowMeta, RowMetaUpdate, RowUpdate,
+  database_row_document_id_from_row_id, Cell, CellChangeRecord, CellValue, Cells, CreateRowParams,
+  CreateRowParamsValidator, DatabaseRow, Row, RowBatchChange, RowCell, RowChange,
+  RowChangeReceiver, RowComment, RowCommentThread, RowCursor, RowDetail, RowFromTemplate, RowId,
+  RowMeta, RowMetaUpdate, RowPage, RowTemplate, RowTemplateMap, RowTemplateUpdate, RowUpdate,
 };
 use crate::views::{
-  CalculationMap, CreateDatabaseParams, CreateViewParams, CreateViewParamsValidator,
-  DatabaseLayout, DatabaseView, DatabaseViewMeta, FieldOrder, FieldSettingsByFieldIdMap,
-  FieldSettingsMap, FilterMap, GroupSettingMap, LayoutSetting, OrderObjectPosition, RowOrder,
-  SortMap, ViewChangeReceiver, ViewMap,
+  default_cell_from_group, default_cells_from_filters, start_of_day, CalculationMap, CalendarEvent,
+  CalendarEventResolver, CalendarLayoutSetting, CreateDatabaseParams, CreateViewParams,
+  CreateViewParamsValidator, DatabaseLayout, DatabaseView, DatabaseViewMeta, FieldOrder,
+  FieldSettingsByFieldIdMap, FieldSettingsMap, FilterMap, GroupSettingMap, LayoutSetting,
+  OrderObjectPosition, RowOrder, SortMap, TimelineBar, TimelineBarResolver, TimelineGroup,
+  TimelineLayoutSetting, ViewChangeReceiver, ViewMap,
 };
-use crate::workspace_database::DatabaseCollabService;
+use crate::workspace_database::{DatabaseCollabService, DatabaseRelation};
 
 pub struct Database {
   #[allow(dead_code)]
@@ -44,15 +56,22 @@ pub struct Database {
   pub views: Rc<ViewMap>,
   pub fields: Rc<FieldMap>,
   pub metas: Rc<MetaMap>,
+  pub templates: Rc<RowTemplateMap>,
   /// It used to keep track of the blocks. Each block contains a list of [Row]s
   /// A database rows will be stored in multiple blocks.
   pub block: Block,
   pub notifier: DatabaseNotify,
+  /// The row relations of the workspace that the database belongs to. The links from or to the
+  /// rows are removed when the rows are removed.
+  row_relations: Option<Weak<DatabaseRelation>>,
+  constraint_validator: Mutex<ConstraintValidator>,
 }
 
 const FIELDS: &str = "fields";
 const VIEWS: &str = "views";
 const METAS: &str = "metas";
+/// The version of the database's structure. See [migrate_with_txn].
+const DATABASE_VERSION: i64 = 1;
 
 pub struct DatabaseContext {
   pub uid: i64,
@@ -152,6 +171,31 @@ impl Database {
     &self.inner
   }
 
+  /// Set the local awareness state, like the selected cell. It overrides the previous state.
+  pub fn set_awareness_local_state(&self, state: DatabaseAwarenessState) {
+    self.inner.set_awareness_state(&state)
+  }
+
+  pub fn get_awareness_local_state(&self) -> Option<DatabaseAwarenessState> {
+    self.inner.get_awareness_state()
+  }
+
+  /// Clean the local awareness state. It should be called when the database is closed.
+  pub fn clean_awareness_local_state(&self) {
+    self.inner.clean_awareness_state()
+  }
+
+  /// Subscribe to the awareness states of the clients. The callback is called until the collab of
+  /// the database is dropped, and subscribing again replaces the previous callback. The states
+  /// that are not [DatabaseAwarenessState], or are published with an unsupported version, are
+  /// skipped.
+  pub fn subscribe_awareness_state<F>(&self, f: F)
+  where
+    F: Fn(HashMap<ClientID, DatabaseAwarenessState>) + 'static,
+  {
+    self.inner.subscribe_awareness_states(f)
+  }
+
   pub fn load_all_rows(&self) {
     let row_ids = self
       .get_inline_row_orders()
@@ -181,7 +225,7 @@ impl Database {
       None => Self::create(database_id, context),
       Some(database) => {
         let collab_guard = context.collab.lock();
-        let (fields, views, metas) = collab_guard.with_origin_transact_mut(|txn| {
+        let (fields, views, metas, templates) = collab_guard.with_origin_transact_mut(|txn| {
           // { DATABASE: { FIELDS: {:} } }
           let fields = collab_guard
             .get_map_with_txn(txn, vec![DATABASE, FIELDS])
@@ -197,11 +241,15 @@ impl Database {
             .get_map_with_txn(txn, vec![DATABASE, METAS])
             .unwrap();
 
+          // The templates map is read from the database when it's used, because the databases
+          // that were created before the templates were introduced don't have it
+          let templates = RowTemplateMap::new(database.clone());
+
           let fields = FieldMap::new(fields, context.notifier.field_change_tx.clone());
           let views = ViewMap::new(views, context.notifier.view_change_tx.clone());
           let metas = MetaMap::new(metas);
 
-          (fields, views, metas)
+          (fields, views, metas, templates)
         });
 
         let block = Block::new(
@@ -221,7 +269,10 @@ impl Database {
           views: Rc::new(views),
           fields: Rc::new(fields),
           metas: Rc::new(metas),
+          templates: Rc::new(templates),
+          constraint_validator: Mutex::new(ConstraintValidator::new(&context.notifier)),
           notifier: context.notifier,
+          row_relations: None,
         })
       },
     }
@@ -233,35 +284,40 @@ impl Database {
       return Err(DatabaseError::InvalidDatabaseID("database_id is empty"));
     }
     let collab_guard = context.collab.lock();
-    let (database, fields, views, metas) = collab_guard.with_origin_transact_mut(|txn| {
-      // { DATABASE: {:} }
-      let database = collab_guard
-        .get_map_with_txn(txn, vec![DATABASE])
-        .unwrap_or_else(|| collab_guard.insert_map_with_txn(txn, DATABASE));
-
-      database.insert_str_with_txn(txn, DATABASE_ID, database_id);
-
-      // { DATABASE: { FIELDS: {:} } }
-      let fields = collab_guard
-        .get_map_with_txn(txn, vec![DATABASE, FIELDS])
-        .unwrap_or_else(|| database.create_map_with_txn(txn, FIELDS));
-
-      // { DATABASE: { FIELDS: {:}, VIEWS: {:} } }
-      let views = collab_guard
-        .get_map_with_txn(txn, vec![DATABASE, VIEWS])
-        .unwrap_or_else(|| database.create_map_with_txn(txn, VIEWS));
-
-      // { DATABASE: { FIELDS: {:},  VIEWS: {:}, METAS: {:} } }
-      let metas = collab_guard
-        .get_map_with_txn(txn, vec![DATABASE, METAS])
-        .unwrap_or_else(|| database.create_map_with_txn(txn, METAS));
-
-      (database, fields, views, metas)
-    });
+    let (database, fields, views, metas, templates) =
+      collab_guard.with_origin_transact_mut(|txn| {
+        // { DATABASE: {:} }
+        let database = collab_guard
+          .get_map_with_txn(txn, vec![DATABASE])
+          .unwrap_or_else(|| collab_guard.insert_map_with_txn(txn, DATABASE));
+
+        database.insert_str_with_txn(txn, DATABASE_ID, database_id);
+
+        // { DATABASE: { FIELDS: {:} } }
+        let fields = collab_guard
+          .get_map_with_txn(txn, vec![DATABASE, FIELDS])
+          .unwrap_or_else(|| database.create_map_with_txn(txn, FIELDS));
+
+        // { DATABASE: { FIELDS: {:}, VIEWS: {:} } }
+        let views = collab_guard
+          .get_map_with_txn(txn, vec![DATABASE, VIEWS])
+          .unwrap_or_else(|| database.create_map_with_txn(txn, VIEWS));
+
+        // { DATABASE: { FIELDS: {:},  VIEWS: {:}, METAS: {:} } }
+        let metas = collab_guard
+          .get_map_with_txn(txn, vec![DATABASE, METAS])
+          .unwrap_or_else(|| database.create_map_with_txn(txn, METAS));
+
+        // { DATABASE: { FIELDS: {:},  VIEWS: {:}, METAS: {:}, TEMPLATES: {:} } }
+        let metas = MetaMap::new(metas);
+        let templates = RowTemplateMap::new(database.clone());
+        migrate_with_txn(txn, &metas, &templates);
+
+        (database, fields, views, metas, templates)
+      });
     drop(collab_guard);
     let views = ViewMap::new(views, context.notifier.view_change_tx.clone());
     let fields = FieldMap::new(fields, context.notifier.field_change_tx.clone());
-    let metas = MetaMap::new(metas);
 
     let block = Block::new(
       context.uid,
@@ -278,7 +334,10 @@ impl Database {
       views: Rc::new(views),
       fields: Rc::new(fields),
       metas: Rc::new(metas),
+      templates: Rc::new(templates),
+      constraint_validator: Mutex::new(ConstraintValidator::new(&context.notifier)),
       notifier: context.notifier,
+      row_relations: None,
     })
   }
 
@@ -308,6 +367,11 @@ impl Database {
   /// created successfully. Otherwise, return None.
   pub fn create_row(&self, params: CreateRowParams) -> Result<RowOrder, DatabaseError> {
     let params = CreateRowParamsValidator::validate(params)?;
+    if let Some(mut validator) = self.constraint_validator() {
+      validator
+        .validate_new_rows(self, &[(&params.id, &params.cells)])
+        .map_err(DatabaseError::ConstraintViolation)?;
+    }
     let row_order = self.block.create_row(params);
     self.root.with_transact_mut(|txn| {
       self
@@ -322,6 +386,11 @@ impl Database {
   /// Create a new row from the given view.
   /// This row will be inserted into corresponding [Block]. The [RowOrder] of this row will
   /// be inserted to each view.
+  ///
+  /// The cells that the row needs to stay visible in the view can be filled in: the cells that the
+  /// view's filters imply if [CreateRowParams::fill_filter_cells] is set, and the value of the
+  /// group in the grouping field if [CreateRowParams::group_id] is set. The cells of the params
+  /// take precedence over them.
   pub fn create_row_in_view(
     &self,
     view_id: &str,
@@ -336,6 +405,18 @@ impl Database {
   /// This row will be inserted into corresponding [Block]. The [RowOrder] of this row will
   /// be inserted to each view.
   pub fn create_row_with_txn(
+    &self,
+    txn: &mut TransactionMut,
+    view_id: &str,
+    mut params: CreateRowParams,
+  ) -> Option<(usize, RowOrder)> {
+    let cells = std::mem::take(&mut params.cells);
+    params.cells = self.default_cells_in_view_with_txn(txn, view_id, &params, cells);
+    self.insert_row_with_txn(txn, view_id, params)
+  }
+
+  /// Create the row with the cells of the params and insert its [RowOrder] to each view.
+  fn insert_row_with_txn(
     &self,
     txn: &mut TransactionMut,
     view_id: &str,
@@ -355,8 +436,115 @@ impl Database {
     Some((index, row_order))
   }
 
-  /// Remove the row
-  /// The [RowOrder] of each view representing this row will be removed.
+  /// Create a new row from the template in the given view. The row starts with the template's
+  /// cells, icon and cover. The cells that the view's filters imply overwrite the template's
+  /// cells, so the new row is visible in the view.
+  pub fn create_row_from_template(
+    &self,
+    view_id: &str,
+    template_id: &str,
+    row_position: OrderObjectPosition,
+  ) -> Result<RowFromTemplate, DatabaseError> {
+    let template = self
+      .templates
+      .get_template(template_id)
+      .ok_or(DatabaseError::RowTemplateNotExist)?;
+    let (index, row_order) = self
+      .root
+      .with_transact_mut(|txn| {
+        let mut params = CreateRowParams::new(gen_row_id(), self.get_database_id_with_txn(txn))
+          .with_row_position(row_position)
+          .with_filter_cells(true);
+        let mut cells = template.cells;
+        cells.extend(
+          self
+            .default_cells_in_view_with_txn(txn, view_id, &params, Cells::new())
+            .into_inner(),
+        );
+        params.cells = cells;
+        self.insert_row_with_txn(txn, view_id, params)
+      })
+      .ok_or(DatabaseError::DatabaseViewNotExist)?;
+
+    let is_document_empty = template.document.is_none();
+    self.block.update_row_meta(&row_order.id, |meta_update| {
+      meta_update
+        .insert_icon_if_not_none(template.icon_url)
+        .insert_cover_if_not_none(template.cover_url)
+        .update_is_document_empty(is_document_empty);
+    });
+    let document_id = database_row_document_id_from_row_id(&row_order.id);
+    Ok(RowFromTemplate {
+      index,
+      row_order,
+      document_id,
+      document: template.document,
+    })
+  }
+
+  /// Add the template. A database that was created before the templates were introduced is
+  /// migrated first.
+  pub fn create_row_template(&self, template: RowTemplate) {
+    self.root.with_transact_mut(|txn| {
+      migrate_with_txn(txn, &self.metas, &self.templates);
+      self.templates.insert_template_with_txn(txn, template);
+    });
+  }
+
+  /// Return the row templates sorted by their creation time.
+  pub fn get_row_templates(&self) -> Vec<RowTemplate> {
+    self.templates.get_all_templates()
+  }
+
+  pub fn get_row_template(&self, template_id: &str) -> Option<RowTemplate> {
+    self.templates.get_template(template_id)
+  }
+
+  pub fn update_row_template<F>(&self, template_id: &str, f: F) -> Result<(), DatabaseError>
+  where
+    F: FnOnce(RowTemplateUpdate),
+  {
+    if self.templates.update_template(template_id, f) {
+      Ok(())
+    } else {
+      Err(DatabaseError::RowTemplateNotExist)
+    }
+  }
+
+  pub fn delete_row_template(&self, template_id: &str) {
+    self.templates.delete_template(template_id);
+  }
+
+  /// Return the given cells with the default cells of the view filled in. The cells of the
+  /// filters are filled in if [CreateRowParams::fill_filter_cells] is set, and the cell of the
+  /// group if [CreateRowParams::group_id] is set.
+  fn default_cells_in_view_with_txn<T: ReadTxn>(
+    &self,
+    txn: &T,
+    view_id: &str,
+    params: &CreateRowParams,
+    cells: Cells,
+  ) -> Cells {
+    let get_field = |field_id: &str| self.fields.get_field_with_txn(txn, field_id);
+    let mut default_cells = if params.fill_filter_cells {
+      let filters = self.views.get_view_filters_with_txn(txn, view_id);
+      default_cells_from_filters(&filters, get_field)
+    } else {
+      Cells::new()
+    };
+    if let Some(group_id) = params.group_id.as_deref() {
+      let group_settings = self.views.get_view_group_setting_with_txn(txn, view_id);
+      if let Some((field_id, cell)) = default_cell_from_group(&group_settings, group_id, get_field)
+      {
+        default_cells.insert(field_id, cell);
+      }
+    }
+    default_cells.extend(cells.into_inner());
+    default_cells
+  }
+
+  /// Remove the row from the database. The [RowOrder] of each view representing this row and
+  /// the links from or to the row in the row relations of the workspace are removed.
   pub fn remove_row(&self, row_id: &RowId) -> Option<Row> {
     self.root.with_transact_mut(|txn| {
       self.views.update_all_views_with_txn(txn, |_, update| {
@@ -366,9 +554,12 @@ impl Database {
 
     let row = self.block.get_row(row_id);
     self.block.delete_row(row_id);
+    self.remove_row_relations(std::slice::from_ref(row_id));
     Some(row)
   }
 
+  /// Remove the rows in one transaction. One [RowBatchChange::DidDeleteRows] is sent for all of
+  /// them. The links from or to the rows are removed like [Database::remove_row].
   pub fn remove_rows(&self, row_ids: &[RowId]) -> Vec<Row> {
     self.root.with_transact_mut(|txn| {
       self.views.update_all_views_with_txn(txn, |_, mut update| {
@@ -378,22 +569,327 @@ impl Database {
       });
     });
 
-    row_ids
+    let rows = row_ids
       .iter()
       .map(|row_id| {
         let row = self.block.get_row(row_id);
         self.block.delete_row(row_id);
         row
       })
-      .collect()
+      .collect();
+    self.remove_row_relations(row_ids);
+    self.send_batch_change(RowBatchChange::DidDeleteRows {
+      row_ids: row_ids.to_vec(),
+    });
+    rows
   }
 
-  /// Update the row
-  pub fn update_row<F>(&self, row_id: &RowId, f: F)
+  pub(crate) fn set_row_relations(&mut self, row_relations: Weak<DatabaseRelation>) {
+    self.row_relations = Some(row_relations);
+  }
+
+  fn remove_row_relations(&self, row_ids: &[RowId]) {
+    if let Some(relation) = self.row_relations.as_ref().and_then(Weak::upgrade) {
+      let row_ids = row_ids
+        .iter()
+        .map(|row_id| row_id.to_string())
+        .collect::<Vec<_>>();
+      relation
+        .row_relations()
+        .remove_rows(&self.get_database_id(), &row_ids);
+    }
+  }
+
+  /// Create the rows in one transaction of the database. The rows are inserted into every view at
+  /// the given position in the order of the params; the `row_position` of each params is ignored.
+  /// The default cells of the view are filled in like [Database::create_row_in_view].
+  ///
+  /// The rows are checked against the [FieldConstraints] before any of them is created, so either
+  /// all of them or none is created. The unique fields are checked with their index, so the check
+  /// doesn't compare every new row with every row of the database. One
+  /// [RowBatchChange::DidCreateRows] is sent for all of them. It's meant for adding many rows at
+  /// once, e.g. the rows that a paste or [import_csv](crate::csv::import_csv) produces.
+  pub fn batch_create_rows(
+    &self,
+    view_id: &str,
+    params: Vec<CreateRowParams>,
+    position: OrderObjectPosition,
+  ) -> Result<Vec<RowOrder>, DatabaseError> {
+    let params = {
+      let txn = self.root.transact();
+      if self.views.get_view_with_txn(&txn, view_id).is_none() {
+        return Err(DatabaseError::DatabaseViewNotExist);
+      }
+      params
+        .into_iter()
+        .map(|params| {
+          let mut params = CreateRowParamsValidator::validate(params)?;
+          let cells = std::mem::take(&mut params.cells);
+          params.cells = self.default_cells_in_view_with_txn(&txn, view_id, &params, cells);
+          Ok(params)
+        })
+        .collect::<Result<Vec<_>, DatabaseError>>()?
+    };
+
+    if let Some(mut validator) = self.constraint_validator() {
+      let rows = params
+        .iter()
+        .map(|params| (&params.id, &params.cells))
+        .collect::<Vec<_>>();
+      validator
+        .validate_new_rows(self, &rows)
+        .map_err(DatabaseError::ConstraintViolation)?;
+    }
+
+    let row_orders = params
+      .into_iter()
+      .map(|params| self.block.create_row(params))
+      .collect::<Vec<_>>();
+    self.root.with_transact_mut(|txn| {
+      self.views.update_all_views_with_txn(txn, |_, mut update| {
+        let mut position = position.clone();
+        for row_order in &row_orders {
+          update = update.insert_row_order(row_order, &position);
+          position = OrderObjectPosition::After(row_order.id.to_string());
+        }
+      });
+    });
+
+    self.send_batch_change(RowBatchChange::DidCreateRows {
+      view_id: view_id.to_string(),
+      row_ids: row_orders
+        .iter()
+        .map(|row_order| row_order.id.clone())
+        .collect(),
+    });
+    Ok(row_orders)
+  }
+
+  /// Update the cells of the rows. Each row is updated in one transaction, and one
+  /// [RowBatchChange::DidUpdateCells] is sent for all of them.
+  ///
+  /// The updated rows are checked against the [FieldConstraints] before any of them is updated,
+  /// so either all of them or none is updated. The unique fields are checked with their index, so
+  /// the check doesn't compare every updated row with every row of the database. Return
+  /// [DatabaseError::InvalidRowID] if one of the rows is not in the database, and
+  /// [DatabaseError::RowNotExist] if one of them is still being fetched.
+  pub fn batch_update_cells(&self, cells_by_row: Vec<(RowId, Cells)>) -> Result<(), DatabaseError> {
+    let row_ids = self
+      .get_inline_row_orders()
+      .into_iter()
+      .map(|row_order| row_order.id)
+      .collect::<HashSet<_>>();
+    if cells_by_row
+      .iter()
+      .any(|(row_id, _)| !row_ids.contains(row_id))
+    {
+      return Err(DatabaseError::InvalidRowID(
+        "the row is not in the database",
+      ));
+    }
+    // Keep the rows in memory until they're updated, so none of them fails after the others
+    // were updated
+    let _rows = cells_by_row
+      .iter()
+      .map(|(row_id, _)| {
+        self
+          .block
+          .get_or_init_row(row_id)
+          .ok_or(DatabaseError::RowNotExist)
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    if let Some(mut validator) = self.constraint_validator() {
+      let updates = cells_by_row
+        .iter()
+        .map(|(row_id, cells)| (row_id, cells))
+        .collect::<Vec<_>>();
+      validator
+        .validate_updated_cells(self, &updates)
+        .map_err(DatabaseError::ConstraintViolation)?;
+    }
+
+    let mut updated_cells = Vec::with_capacity(cells_by_row.len());
+    for (row_id, cells) in cells_by_row {
+      let field_ids = cells.keys().cloned().collect::<Vec<_>>();
+      self.block.update_row_silently(&row_id, |row_update| {
+        row_update.update_cells(|mut cells_update| {
+          for (field_id, cell) in cells.into_inner() {
+            cells_update = cells_update.insert_cell(&field_id, cell);
+          }
+        });
+      })?;
+      updated_cells.push((row_id, field_ids));
+    }
+    self.send_batch_change(RowBatchChange::DidUpdateCells {
+      cells: updated_cells,
+    });
+    Ok(())
+  }
+
+  /// Update the cells of the field in the rows, see [Database::batch_update_cells].
+  pub fn batch_update_field_cells(
+    &self,
+    field_id: &str,
+    cells: Vec<(RowId, Cell)>,
+  ) -> Result<(), DatabaseError> {
+    let cells_by_row = cells
+      .into_iter()
+      .map(|(row_id, cell)| {
+        let mut cells = Cells::new();
+        cells.insert(field_id.to_string(), cell);
+        (row_id, cells)
+      })
+      .collect();
+    self.batch_update_cells(cells_by_row)
+  }
+
+  /// Move the rows to the position in the view in one transaction, keeping their order. Every view
+  /// of the database contains all of its rows, so the rows are only moved within the view. Use
+  /// `WorkspaceDatabase::move_rows_between_views` to move them to the view of another database.
+  /// One [RowBatchChange::DidMoveRows] is sent for all of them.
+  ///
+  /// Return [DatabaseError::InvalidRowID] if one of the rows is not in the view or the position
+  /// refers to one of the moved rows.
+  pub fn batch_move_rows(
+    &self,
+    view_id: &str,
+    row_ids: &[RowId],
+    position: OrderObjectPosition,
+  ) -> Result<(), DatabaseError> {
+    if let OrderObjectPosition::Before(id) | OrderObjectPosition::After(id) = &position {
+      if row_ids.iter().any(|row_id| row_id.as_str() == id) {
+        return Err(DatabaseError::InvalidRowID(
+          "the rows can't be moved next to themselves",
+        ));
+      }
+    }
+
+    self.root.with_transact_mut(|txn| {
+      if self.views.get_view_with_txn(txn, view_id).is_none() {
+        return Err(DatabaseError::DatabaseViewNotExist);
+      }
+      let row_order_by_id = self
+        .views
+        .get_row_orders_with_txn(txn, view_id)
+        .into_iter()
+        .map(|row_order| (row_order.id.clone(), row_order))
+        .collect::<HashMap<_, _>>();
+      let row_orders = row_ids
+        .iter()
+        .map(|row_id| {
+          row_order_by_id
+            .get(row_id)
+            .cloned()
+            .ok_or(DatabaseError::InvalidRowID("the row is not in the view"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+      self.views.update_view_with_txn(txn, view_id, |mut update| {
+        for row_order in &row_orders {
+          update = update.remove_row_order(&row_order.id);
+        }
+        let mut position = position;
+        for row_order in &row_orders {
+          update = update.insert_row_order(row_order, &position);
+          position = OrderObjectPosition::After(row_order.id.to_string());
+        }
+      });
+      Ok(())
+    })?;
+
+    self.send_batch_change(RowBatchChange::DidMoveRows {
+      view_id: view_id.to_string(),
+      row_ids: row_ids.to_vec(),
+    });
+    Ok(())
+  }
+
+  fn send_batch_change(&self, change: RowBatchChange) {
+    let _ = self
+      .notifier
+      .row_change_tx
+      .send(RowChange::DidBatchUpdate(change));
+  }
+
+  /// Update the row if the changed cells pass the [FieldConstraints]. The row is not changed if
+  /// they violate any of them. Return [DatabaseError::RowNotExist] if the row doesn't exist or is
+  /// still being fetched.
+  pub fn update_row<F>(&self, row_id: &RowId, f: F) -> Result<(), DatabaseError>
   where
     F: FnOnce(RowUpdate),
   {
-    self.block.update_row(row_id, f);
+    let mut validator = match self.constraint_validator() {
+      Some(validator) => validator,
+      None => {
+        let row = self
+          .block
+          .get_or_init_row(row_id)
+          .ok_or(DatabaseError::RowNotExist)?;
+        row.lock().update(f);
+        return Ok(());
+      },
+    };
+    validator.prepare(self);
+    self
+      .block
+      .update_row_with_validation(row_id, f, |old_cells, new_cells| {
+        let changed_cells = changed_cells(old_cells, new_cells);
+        validator
+          .validate_updated_cells(self, &[(row_id, &changed_cells)])
+          .map_err(DatabaseError::ConstraintViolation)
+      })
+  }
+
+  /// Return the validator of the fields' constraints. None if no field has constraints.
+  fn constraint_validator(&self) -> Option<MutexGuard<ConstraintValidator>> {
+    let mut validator = self.constraint_validator.lock();
+    validator.refresh(self.get_fields(None));
+    if validator.is_empty() {
+      None
+    } else {
+      Some(validator)
+    }
+  }
+
+  /// Return the constraints of the field's current type.
+  pub fn get_field_constraints(&self, field_id: &str) -> Option<FieldConstraints> {
+    let field = self.fields.get_field(field_id)?;
+    Some(FieldConstraints::from_field(&field))
+  }
+
+  /// Set the constraints of the field's current type. The existing rows are not checked, use
+  /// [Database::find_constraint_violations] to find the rows that violate the new constraints.
+  pub fn update_field_constraints(
+    &self,
+    field_id: &str,
+    constraints: FieldConstraints,
+  ) -> Result<(), DatabaseError> {
+    constraints.validate()?;
+    let field = self
+      .fields
+      .get_field(field_id)
+      .ok_or(DatabaseError::FieldNotExist)?;
+    let mut type_option = field
+      .get_any_type_option(field.field_type)
+      .unwrap_or_default();
+    constraints.fill_type_option(&mut type_option);
+    self.fields.update_field(field_id, |update| {
+      // Replace the type option, so the keys of the removed constraints are deleted
+      update
+        .set_type_option(field.field_type, None)
+        .set_type_option(field.field_type, Some(type_option));
+    });
+    Ok(())
+  }
+
+  /// Return the cells of the database's rows that violate the constraints of their fields. A row
+  /// that has the same value as an earlier row in the inline view violates the unique constraint.
+  pub fn find_constraint_violations(&self) -> Vec<ConstraintViolation> {
+    match self.constraint_validator() {
+      None => vec![],
+      Some(validator) => validator.scan(&self.get_database_rows()),
+    }
   }
 
   /// Update the meta of the row
@@ -431,6 +927,89 @@ impl Database {
     self.block.get_row_meta(row_id)
   }
 
+  /// Return the changes of the row's cells, from the oldest to the newest. Each change records
+  /// the user that made it, when it was made and the cell before and after the change.
+  pub fn get_row_history(&self, row_id: &RowId) -> Vec<CellChangeRecord> {
+    self.block.get_row_history(row_id)
+  }
+
+  /// Return the comments of the row in the order that they were added.
+  pub fn get_row_comments(&self, row_id: &RowId) -> Vec<RowComment> {
+    self
+      .block
+      .get_or_init_row(row_id)
+      .map(|row| row.lock().get_comments())
+      .unwrap_or_default()
+  }
+
+  /// Return the comments of the row grouped into threads.
+  pub fn get_row_comment_threads(&self, row_id: &RowId) -> Vec<RowCommentThread> {
+    RowCommentThread::from_comments(self.get_row_comments(row_id))
+  }
+
+  /// Add a comment to the row as the user of the database. Use [RowComment::with_parent] to reply
+  /// to another comment. The changes of the comments are sent as
+  /// [RowChange::DidChangeRowComment](crate::rows::RowChange::DidChangeRowComment) to the
+  /// subscribers of [Database::subscribe_row_change].
+  pub fn add_row_comment(&self, row_id: &RowId, comment: RowComment) -> Result<(), DatabaseError> {
+    self.with_database_row(row_id, |row| row.add_comment(comment))
+  }
+
+  /// Replace the content of the comment and set its edit timestamp.
+  pub fn edit_row_comment(
+    &self,
+    row_id: &RowId,
+    comment_id: &str,
+    content: String,
+  ) -> Result<(), DatabaseError> {
+    self.with_database_row(row_id, |row| row.edit_comment(comment_id, content))
+  }
+
+  /// Delete the comment and its replies.
+  pub fn delete_row_comment(&self, row_id: &RowId, comment_id: &str) -> Result<(), DatabaseError> {
+    self.with_database_row(row_id, |row| row.delete_comment(comment_id))
+  }
+
+  pub fn add_row_comment_reaction(
+    &self,
+    row_id: &RowId,
+    comment_id: &str,
+    emoji: &str,
+  ) -> Result<(), DatabaseError> {
+    self.with_database_row(row_id, |row| row.add_comment_reaction(comment_id, emoji))
+  }
+
+  pub fn remove_row_comment_reaction(
+    &self,
+    row_id: &RowId,
+    comment_id: &str,
+    emoji: &str,
+  ) -> Result<(), DatabaseError> {
+    self.with_database_row(row_id, |row| row.remove_comment_reaction(comment_id, emoji))
+  }
+
+  /// Resolve the comment or reopen it.
+  pub fn resolve_row_comment(
+    &self,
+    row_id: &RowId,
+    comment_id: &str,
+    is_resolved: bool,
+  ) -> Result<(), DatabaseError> {
+    self.with_database_row(row_id, |row| row.resolve_comment(comment_id, is_resolved))
+  }
+
+  fn with_database_row<F, T>(&self, row_id: &RowId, f: F) -> Result<T, DatabaseError>
+  where
+    F: FnOnce(&DatabaseRow) -> Result<T, DatabaseError>,
+  {
+    let row = self
+      .block
+      .get_or_init_row(row_id)
+      .ok_or(DatabaseError::NoRequiredData)?;
+    let row = row.lock();
+    f(&row)
+  }
+
   /// Return the [RowMeta] with the given row id.
   pub fn get_row_detail(&self, row_id: &RowId) -> Option<RowDetail> {
     let row = self.block.get_row(row_id);
@@ -449,6 +1028,91 @@ impl Database {
     self.get_rows_from_row_orders(&row_orders)
   }
 
+  /// Return a page of the rows of the given view. Only the rows of the page are loaded. The rows
+  /// of the page and of the previous and the next page that are not on the local disk are
+  /// fetched from the remote in one batch, and the rows of the view that are more than two pages
+  /// away from the page are evicted from memory. Use it instead of [Database::get_rows_for_view]
+  /// for large databases.
+  pub fn get_rows_page(
+    &self,
+    view_id: &str,
+    cursor: RowCursor,
+    limit: usize,
+  ) -> Result<RowPage, DatabaseError> {
+    let row_orders = self.get_row_orders_for_view(view_id);
+    let total = row_orders.len();
+    let start = match &cursor {
+      RowCursor::Offset(offset) => (*offset).min(total),
+      RowCursor::After(row_id) => row_orders
+        .iter()
+        .position(|row_order| &row_order.id == row_id)
+        .map(|index| index + 1)
+        .ok_or(DatabaseError::InvalidRowID(
+          "the row of the cursor is not in the view",
+        ))?,
+    };
+    let end = start.saturating_add(limit).min(total);
+
+    let row_ids = |range: std::ops::Range<usize>| {
+      row_orders[range]
+        .iter()
+        .map(|row_order| row_order.id.clone())
+        .collect::<Vec<_>>()
+    };
+    // The rows of the page and of the pages around it that are not on the local disk are
+    // fetched from the remote in one batch
+    let prefetch_start = start.saturating_sub(limit);
+    let prefetch_end = end.saturating_add(limit).min(total);
+    let fetching_row_ids = self
+      .block
+      .fetch_missing_rows(&row_ids(prefetch_start..prefetch_end));
+
+    let keep_start = start.saturating_sub(limit.saturating_mul(2));
+    let keep_end = end.saturating_add(limit.saturating_mul(2)).min(total);
+    let mut evict_row_ids = row_ids(0..keep_start);
+    evict_row_ids.extend(row_ids(keep_end..total));
+    self.block.evict_rows(&evict_row_ids);
+
+    let rows = self
+      .block
+      .get_rows_without_fetching(&row_orders[start..end], &fetching_row_ids);
+    let next_cursor = if end < total {
+      Some(match rows.last() {
+        Some(row) => RowCursor::After(row.id.clone()),
+        None => RowCursor::Offset(end),
+      })
+    } else {
+      None
+    };
+    Ok(RowPage {
+      rows,
+      offset: start,
+      total,
+      next_cursor,
+    })
+  }
+
+  /// Return a page of the rows of the database, see [Database::get_rows_page].
+  pub fn get_database_rows_page(
+    &self,
+    cursor: RowCursor,
+    limit: usize,
+  ) -> Result<RowPage, DatabaseError> {
+    let inline_view_id = self.get_inline_view_id();
+    self.get_rows_page(&inline_view_id, cursor, limit)
+  }
+
+  /// Replace the limits of the in-memory rows. The rows that are removed from memory are loaded
+  /// from the local disk again when they are accessed.
+  pub fn set_row_cache_config(&self, config: RowCacheConfig) {
+    self.block.set_row_cache_config(config);
+  }
+
+  /// Return the hits, misses and evictions of the in-memory rows.
+  pub fn get_row_cache_metrics(&self) -> RowCacheMetrics {
+    self.block.row_cache_metrics()
+  }
+
   pub fn get_row_orders_for_view(&self, view_id: &str) -> Vec<RowOrder> {
     let txn = self.root.transact();
     self.views.get_row_orders_with_txn(&txn, view_id)
@@ -472,6 +1136,59 @@ impl Database {
     RowCell::new(row_id.clone(), cell)
   }
 
+  /// Return the typed value of the cell with the given field id and row id. Return None if the
+  /// cell is empty. The values of the last edited time and created time fields are read from
+  /// the row.
+  pub fn get_typed_cell(
+    &self,
+    field_id: &str,
+    row_id: &RowId,
+  ) -> Result<Option<CellValue>, DatabaseError> {
+    let field = self
+      .fields
+      .get_field(field_id)
+      .ok_or(DatabaseError::FieldNotExist)?;
+    let field_type = FieldType::try_from(field.field_type)?;
+    if let Some(cell) = self.block.get_cell(row_id, field_id) {
+      return CellValue::from_cell(field_type, &cell);
+    }
+
+    let value = match field_type {
+      FieldType::LastEditedTime => self
+        .block
+        .get_loaded_row(row_id)
+        .map(|row| CellValue::Timestamp(row.modified_at)),
+      FieldType::CreatedTime => self
+        .block
+        .get_loaded_row(row_id)
+        .map(|row| CellValue::Timestamp(row.created_at)),
+      _ => None,
+    };
+    Ok(value)
+  }
+
+  /// Validate the value against the field's type option and write it into the cell with the
+  /// given field id and row id. The cell is not changed if the value is invalid. Return
+  /// [DatabaseError::RowNotExist] if the row doesn't exist or is still being fetched.
+  pub fn update_typed_cell(
+    &self,
+    field_id: &str,
+    row_id: &RowId,
+    value: CellValue,
+  ) -> Result<(), DatabaseError> {
+    let field = self
+      .fields
+      .get_field(field_id)
+      .ok_or(DatabaseError::FieldNotExist)?;
+    value.validate(&field)?;
+    let cell = value.to_cell(FieldType::try_from(field.field_type)?);
+    self.update_row(row_id, |row| {
+      row.update_cells(|cells| {
+        cells.insert_cell(field_id, cell);
+      });
+    })
+  }
+
   /// Return list of [RowCell] for the given view and field.
   pub fn get_cells_for_field_with_txn<T: ReadTxn>(
     &self,
@@ -658,6 +1375,129 @@ impl Database {
     self.fields.insert_field_with_txn(txn, field);
   }
 
+  /// Convert the field to the given [FieldType] and rewrite its cells. For example, text is
+  /// parsed as numbers or dates, select options become their names when converted to text and
+  /// cells that can't be converted are cleared. The type options of the previous type are kept,
+  /// so converting the field back reuses them, for example the options of a select option field.
+  ///
+  /// All the rows are loaded and converted before anything is changed. The conversion fails with
+  /// [DatabaseError::RowNotExist] if a row is still being fetched, and with
+  /// [DatabaseError::ConstraintViolation] if the cells of any row, including the rows without a
+  /// cell in the field, violate the [FieldConstraints] of the new type. The cells are rewritten
+  /// and the field type is switched in a single transaction of the database. Each row is stored
+  /// in its own collab, so the cells of a row are still committed in the row's own transaction,
+  /// but they are all rewritten before the field type change is committed. Only one
+  /// [FieldChange::DidUpdateField](crate::fields::FieldChange::DidUpdateField) and one
+  /// [RowBatchChange::DidUpdateCells] are sent.
+  pub fn convert_field_type(
+    &self,
+    field_id: &str,
+    field_type: FieldType,
+  ) -> Result<Field, DatabaseError> {
+    let field = self
+      .fields
+      .get_field(field_id)
+      .ok_or(DatabaseError::FieldNotExist)?;
+    let from = FieldType::try_from(field.field_type)?;
+    if from == field_type {
+      return Ok(field);
+    }
+
+    // The rows that are not in memory are loaded. A row that is still being fetched stops the
+    // conversion, because its cell couldn't be converted.
+    let rows = self
+      .get_inline_row_orders()
+      .into_iter()
+      .map(|row_order| {
+        self
+          .block
+          .get_or_init_row(&row_order.id)
+          .map(|row| (row_order.id, row))
+          .ok_or(DatabaseError::RowNotExist)
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+    let cells = rows
+      .iter()
+      .filter_map(|(row_id, row)| {
+        let cell = row.lock().get_cell(field_id)?;
+        Some((row_id.clone(), cell))
+      })
+      .collect::<Vec<_>>();
+
+    let mut converter = FieldTypeConverter::new(&field, from, field_type);
+    for (_, cell) in &cells {
+      converter.prepare(cell);
+    }
+    let type_option = converter.type_option();
+    let mut converted_cells = cells
+      .iter()
+      .map(|(row_id, cell)| (row_id.clone(), converter.convert(cell)))
+      .collect::<HashMap<_, _>>();
+
+    let mut converted_field = field.clone();
+    converted_field.field_type = field_type.value();
+    if let Some(type_option) = &type_option {
+      converted_field
+        .type_options
+        .insert(field_type.value().to_string(), type_option.clone());
+    }
+    let mut validator = ConstraintValidator::new(&self.notifier);
+    validator.refresh(vec![converted_field]);
+    if !validator.is_empty() {
+      // Every row is checked, a row without a cell violates the required constraint.
+      let converted_rows = rows
+        .iter()
+        .map(|(row_id, _)| {
+          let mut row = Row::new(row_id.clone(), &self.get_database_id());
+          if let Some(Some(cell)) = converted_cells.get(row_id) {
+            row.cells.insert(field_id.to_string(), cell.clone());
+          }
+          row
+        })
+        .collect::<Vec<_>>();
+      if let Some(violation) = validator.scan(&converted_rows).into_iter().next() {
+        return Err(DatabaseError::ConstraintViolation(violation));
+      }
+    }
+
+    let updated_cells = self.root.with_transact_mut(|txn| {
+      let mut updated_cells = Vec::with_capacity(converted_cells.len());
+      for (row_id, row) in &rows {
+        let new_cell = match converted_cells.remove(row_id) {
+          None => continue,
+          Some(new_cell) => new_cell,
+        };
+        row.lock().update_silently(|row_update| {
+          row_update.update_cells(|cells_update| {
+            // Clear the cell first to remove the keys of the previous type.
+            let cells_update = cells_update.clear(field_id);
+            if let Some(new_cell) = new_cell {
+              cells_update.insert_cell(field_id, new_cell);
+            }
+          });
+        });
+        updated_cells.push((row_id.clone(), vec![field_id.to_string()]));
+      }
+      self.fields.update_field_with_txn(txn, field_id, |update| {
+        let update = update.set_field_type(field_type.value());
+        if let Some(type_option) = type_option {
+          update.set_type_option(field_type.value(), Some(type_option));
+        }
+      });
+      updated_cells
+    });
+    if !updated_cells.is_empty() {
+      self.send_batch_change(RowBatchChange::DidUpdateCells {
+        cells: updated_cells,
+      });
+    }
+
+    self
+      .fields
+      .get_field(field_id)
+      .ok_or(DatabaseError::FieldNotExist)
+  }
+
   pub fn delete_field(&self, field_id: &str) {
     self.root.with_transact_mut(|txn| {
       self
@@ -956,6 +1796,130 @@ impl Database {
     });
   }
 
+  /// Return the events of the calendar view that overlap the dates from `start` to `end`
+  /// (exclusive). The dates are the days in the given timezone, and the events are displayed in
+  /// this timezone. Events that only fall on weekends are skipped if the calendar hides weekends.
+  pub fn get_calendar_events(
+    &self,
+    view_id: &str,
+    start: NaiveDate,
+    end: NaiveDate,
+    timezone: Tz,
+  ) -> Result<Vec<CalendarEvent>, DatabaseError> {
+    let setting = self.get_calendar_layout_setting(view_id)?;
+    let resolver = self.calendar_event_resolver(&setting, timezone)?;
+    let range_start = start_of_day(&timezone, start).ok_or(DatabaseError::NoRequiredData)?;
+    let range_end = start_of_day(&timezone, end).ok_or(DatabaseError::NoRequiredData)?;
+    let events = self
+      .get_rows_for_view(view_id)
+      .iter()
+      .flat_map(|row| resolver.resolve(row))
+      .filter(|event| event.overlaps(&range_start, &range_end))
+      .filter(|event| setting.show_weekends || !event.is_on_weekend())
+      .collect();
+    Ok(events)
+  }
+
+  /// Move the event of the row to the new start by updating the row's date cell. The duration of
+  /// the event is kept. The start's date is used if the event is an all-day event.
+  pub fn move_calendar_event(
+    &self,
+    view_id: &str,
+    row_id: &RowId,
+    start: DateTime<Tz>,
+  ) -> Result<CalendarEvent, DatabaseError> {
+    let setting = self.get_calendar_layout_setting(view_id)?;
+    let resolver = self.calendar_event_resolver(&setting, start.timezone())?;
+    let row = self.get_row(row_id);
+    let date = resolver
+      .date_of_row(&row)
+      .ok_or(DatabaseError::NoRequiredData)?;
+    let date = resolver
+      .move_date(date, &start)
+      .ok_or(DatabaseError::NoRequiredData)?;
+    self.update_typed_cell(resolver.field_id(), row_id, CellValue::Date(date))?;
+    resolver
+      .resolve(&self.get_row(row_id))
+      .ok_or(DatabaseError::NoRequiredData)
+  }
+
+  fn get_calendar_layout_setting(
+    &self,
+    view_id: &str,
+  ) -> Result<CalendarLayoutSetting, DatabaseError> {
+    let setting = self
+      .get_layout_setting::<CalendarLayoutSetting>(view_id, &DatabaseLayout::Calendar)
+      .ok_or(DatabaseError::InvalidCalendarLayoutSetting(
+        "the view doesn't have a calendar layout setting",
+      ))?;
+    if setting.field_id.is_empty() {
+      return Err(DatabaseError::InvalidCalendarLayoutSetting(
+        "the calendar doesn't have a date field",
+      ));
+    }
+    Ok(setting)
+  }
+
+  fn calendar_event_resolver(
+    &self,
+    setting: &CalendarLayoutSetting,
+    timezone: Tz,
+  ) -> Result<CalendarEventResolver, DatabaseError> {
+    let field = self
+      .fields
+      .get_field(&setting.field_id)
+      .ok_or(DatabaseError::FieldNotExist)?;
+    CalendarEventResolver::new(field, self.fields.get_primary_field(), timezone)
+  }
+
+  /// Return the bars of the timeline view sorted by their start and end. Rows that don't have a
+  /// start date are skipped. Bars that start and end at the same time keep the order of the rows
+  /// in the view.
+  pub fn get_timeline_bars(
+    &self,
+    view_id: &str,
+    timezone: Tz,
+  ) -> Result<Vec<TimelineBar>, DatabaseError> {
+    let (_, bars) = self.timeline_bars(view_id, timezone)?;
+    Ok(bars)
+  }
+
+  /// Return the bars of the timeline view grouped by the timeline's group field. The bars of each
+  /// group are sorted like [Database::get_timeline_bars].
+  pub fn get_timeline_groups(
+    &self,
+    view_id: &str,
+    timezone: Tz,
+  ) -> Result<Vec<TimelineGroup>, DatabaseError> {
+    let (resolver, bars) = self.timeline_bars(view_id, timezone)?;
+    Ok(resolver.group(bars))
+  }
+
+  fn timeline_bars(
+    &self,
+    view_id: &str,
+    timezone: Tz,
+  ) -> Result<(TimelineBarResolver, Vec<TimelineBar>), DatabaseError> {
+    let setting = self
+      .get_layout_setting::<TimelineLayoutSetting>(view_id, &DatabaseLayout::Timeline)
+      .ok_or(DatabaseError::InvalidTimelineLayoutSetting(
+        "the view doesn't have a timeline layout setting",
+      ))?;
+    let resolver = TimelineBarResolver::new(
+      &setting,
+      |field_id| self.fields.get_field(field_id),
+      self.fields.get_primary_field(),
+      timezone,
+    )?;
+    let mut bars = self
+      .get_rows_for_view(view_id)
+      .iter()
+      .flat_map(|row| resolver.resolve(row))
+      .collect::<Vec<_>>();
+    bars.sort_by(|a, b| a.start.cmp(&b.start).then(a.end.cmp(&b.end)));
+    Ok((resolver, bars))
+  }
+
   /// Returns the field settings for the given field ids.
   /// If None, return field settings for all fields
   pub fn get_field_settings<T: From<FieldSettingsMap>>(
@@ -1137,6 +2101,8 @@ impl Database {
       height: row.height,
       visibility: row.visibility,
       row_position: OrderObjectPosition::After(row.id.into()),
+      group_id: None,
+      fill_filter_cells: false,
       created_at: timestamp,
       modified_at: timestamp,
     })
@@ -1255,6 +2221,53 @@ impl Database {
   }
 }
 
+impl ConstraintRowSource for Database {
+  fn row_ids(&self) -> Vec<RowId> {
+    self
+      .get_inline_row_orders()
+      .into_iter()
+      .map(|row_order| row_order.id)
+      .collect()
+  }
+
+  /// The rows that are not in memory are read from the local disk without being cached, so
+  /// checking the constraints doesn't load the whole database.
+  fn row_cells(&self, row_id: &RowId) -> Option<Cells> {
+    self.block.read_row_cells(row_id)
+  }
+}
+
+/// Return the cells that are different between the old and the new cells. A removed cell is an
+/// empty cell.
+fn changed_cells(old_cells: &Cells, new_cells: &Cells) -> Cells {
+  let mut cells = Cells::new();
+  for (field_id, cell) in new_cells.iter() {
+    if old_cells.get(field_id) != Some(cell) {
+      cells.insert(field_id.clone(), cell.clone());
+    }
+  }
+  for field_id in old_cells.keys() {
+    if !new_cells.contains_key(field_id) {
+      cells.insert(field_id.clone(), Cell::new());
+    }
+  }
+  cells
+}
+
+/// Bring the structure of the database up to [DATABASE_VERSION]. The migrations run when the
+/// database is created or changed, never when it's opened.
+/// - Version 1: the map of the row templates.
+fn migrate_with_txn(txn: &mut TransactionMut, metas: &MetaMap, templates: &RowTemplateMap) {
+  let version = metas.get_version_with_txn(txn);
+  if version >= DATABASE_VERSION {
+    return;
+  }
+  if version < 1 {
+    templates.migrate_with_txn(txn);
+  }
+  metas.set_version_with_txn(txn, DATABASE_VERSION);
+}
+
 pub fn gen_database_id() -> String {
   uuid::Uuid::new_v4().to_string()
 }
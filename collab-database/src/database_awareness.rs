@@ -0,0 +1,39 @@
+use collab::core::awareness::{AwarenessState, AwarenessUser};
+use serde::{Deserialize, Serialize};
+
+use crate::rows::RowId;
+
+/// The awareness state of a database, like the cell that the user selects.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DatabaseAwarenessState {
+  pub user: DatabaseAwarenessUser,
+  pub selected_cell: Option<DatabaseAwarenessCell>,
+  // The `metadata` field is an optional field (json string) that can be used to store additional
+  // information, like the color of the selection.
+  pub metadata: Option<String>,
+  pub timestamp: i64,
+}
+
+impl DatabaseAwarenessState {
+  pub fn new(user: DatabaseAwarenessUser) -> Self {
+    Self {
+      user,
+      selected_cell: None,
+      metadata: None,
+      timestamp: 0,
+    }
+  }
+}
+
+impl AwarenessState for DatabaseAwarenessState {
+  const VERSION: u32 = 1;
+}
+
+pub type DatabaseAwarenessUser = AwarenessUser;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DatabaseAwarenessCell {
+  pub view_id: String,
+  pub row_id: RowId,
+  pub field_id: String,
+}
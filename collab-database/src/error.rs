@@ -1,3 +1,5 @@
+use crate::fields::ConstraintViolation;
+
 #[derive(Debug, thiserror::Error)]
 pub enum DatabaseError {
   #[error("The database's id is invalid: {0}")]
@@ -21,6 +23,42 @@ pub enum DatabaseError {
   #[error(transparent)]
   UuidError(#[from] uuid::Error),
 
+  #[error(transparent)]
+  Csv(#[from] csv::Error),
+
+  #[error("Unknown field type: {0}")]
+  UnknownFieldType(i64),
+
+  #[error("The field is not existing")]
+  FieldNotExist,
+
+  #[error("The row is not existing")]
+  RowNotExist,
+
+  #[error("The cell's value is invalid: {0}")]
+  InvalidCellValue(String),
+
+  #[error("The calendar layout setting is invalid: {0}")]
+  InvalidCalendarLayoutSetting(&'static str),
+
+  #[error("The timeline layout setting is invalid: {0}")]
+  InvalidTimelineLayoutSetting(&'static str),
+
+  #[error("The row comment is not existing")]
+  CommentNotExist,
+
+  #[error("The row template is not existing")]
+  RowTemplateNotExist,
+
+  #[error("The field constraint is invalid: {0}")]
+  InvalidFieldConstraint(String),
+
+  #[error("The row violates the field constraint: {0}")]
+  ConstraintViolation(ConstraintViolation),
+
+  #[error("The field is not a lookup or rollup field")]
+  NotRelationField,
+
   #[error("No required data")]
   NoRequiredData,
 
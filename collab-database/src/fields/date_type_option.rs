@@ -0,0 +1,152 @@
+use chrono::DateTime;
+use collab::core::any_map::AnyMapExtension;
+
+use crate::fields::{TypeOptionData, TypeOptionDataBuilder};
+use crate::views::parse_timezone;
+
+const DATE_FORMAT: &str = "date_format";
+const TIME_FORMAT: &str = "time_format";
+const TIMEZONE_ID: &str = "timezone_id";
+
+/// The timestamp of the date cell is stored in [CELL_DATA](crate::rows::CELL_DATA) as a string
+/// of seconds. These are the other keys of the date cell.
+pub const DATE_CELL_END_TIMESTAMP: &str = "end_timestamp";
+pub const DATE_CELL_INCLUDE_TIME: &str = "include_time";
+pub const DATE_CELL_IS_RANGE: &str = "is_range";
+pub const DATE_CELL_TIMEZONE_ID: &str = "timezone_id";
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DateFormat {
+  Local = 0,
+  US = 1,
+  ISO = 2,
+  #[default]
+  Friendly = 3,
+  DayMonthYear = 4,
+}
+
+impl DateFormat {
+  pub fn value(&self) -> i64 {
+    *self as i64
+  }
+
+  /// The chrono format string of the date format.
+  pub fn format_str(&self) -> &'static str {
+    match self {
+      DateFormat::Local => "%m/%d/%Y",
+      DateFormat::US => "%Y/%m/%d",
+      DateFormat::ISO => "%Y-%m-%d",
+      DateFormat::Friendly => "%b %d, %Y",
+      DateFormat::DayMonthYear => "%d/%m/%Y",
+    }
+  }
+}
+
+impl From<i64> for DateFormat {
+  fn from(value: i64) -> Self {
+    match value {
+      0 => DateFormat::Local,
+      1 => DateFormat::US,
+      2 => DateFormat::ISO,
+      4 => DateFormat::DayMonthYear,
+      _ => DateFormat::Friendly,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimeFormat {
+  TwelveHour = 0,
+  #[default]
+  TwentyFourHour = 1,
+}
+
+impl TimeFormat {
+  pub fn value(&self) -> i64 {
+    *self as i64
+  }
+
+  /// The chrono format string of the time format.
+  pub fn format_str(&self) -> &'static str {
+    match self {
+      TimeFormat::TwelveHour => "%I:%M %p",
+      TimeFormat::TwentyFourHour => "%H:%M",
+    }
+  }
+}
+
+impl From<i64> for TimeFormat {
+  fn from(value: i64) -> Self {
+    match value {
+      0 => TimeFormat::TwelveHour,
+      _ => TimeFormat::TwentyFourHour,
+    }
+  }
+}
+
+/// The type option of the date, last edited time and created time fields.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DateTypeOption {
+  pub date_format: DateFormat,
+  pub time_format: TimeFormat,
+  /// The IANA timezone id, for example "Asia/Shanghai". Empty means UTC.
+  pub timezone_id: String,
+}
+
+impl DateTypeOption {
+  /// Format the timestamp in seconds with the date format, and the time format if the time is
+  /// included. The timestamp is formatted in the timezone of the type option, or UTC if the
+  /// timezone is empty or unknown. Return an empty string if the timestamp is out of range.
+  pub fn format_timestamp(&self, timestamp: i64, include_time: bool) -> String {
+    let date_time = match DateTime::from_timestamp(timestamp, 0) {
+      None => return "".to_string(),
+      Some(date_time) => date_time,
+    };
+    let format = if include_time {
+      format!(
+        "{} {}",
+        self.date_format.format_str(),
+        self.time_format.format_str()
+      )
+    } else {
+      self.date_format.format_str().to_string()
+    };
+    match parse_timezone(&self.timezone_id) {
+      None => date_time.format(&format).to_string(),
+      Some(timezone) => date_time
+        .with_timezone(&timezone)
+        .format(&format)
+        .to_string(),
+    }
+  }
+}
+
+impl From<TypeOptionData> for DateTypeOption {
+  fn from(data: TypeOptionData) -> Self {
+    let date_format = data
+      .get_i64_value(DATE_FORMAT)
+      .map(DateFormat::from)
+      .unwrap_or_default();
+    let time_format = data
+      .get_i64_value(TIME_FORMAT)
+      .map(TimeFormat::from)
+      .unwrap_or_default();
+    let timezone_id = data.get_str_value(TIMEZONE_ID).unwrap_or_default();
+    Self {
+      date_format,
+      time_format,
+      timezone_id,
+    }
+  }
+}
+
+impl From<DateTypeOption> for TypeOptionData {
+  fn from(data: DateTypeOption) -> Self {
+    TypeOptionDataBuilder::new()
+      .insert_i64_value(DATE_FORMAT, data.date_format.value())
+      .insert_i64_value(TIME_FORMAT, data.time_format.value())
+      .insert_str_value(TIMEZONE_ID, data.timezone_id)
+      .build()
+  }
+}
@@ -0,0 +1,613 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+
+use collab::core::any_map::AnyMapExtension;
+use regex::Regex;
+use tokio::sync::broadcast::error::TryRecvError;
+
+use crate::database_state::DatabaseNotify;
+use crate::error::DatabaseError;
+use crate::fields::{Field, FieldType, TypeOptionData};
+use crate::rows::{CellValue, Cells, Row, RowBatchChange, RowChange, RowChangeReceiver, RowId};
+use crate::views::{DatabaseViewChange, ViewChangeReceiver};
+
+const CONSTRAINT_REQUIRED: &str = "is_required";
+const CONSTRAINT_UNIQUE: &str = "is_unique";
+const CONSTRAINT_MIN: &str = "min_value";
+const CONSTRAINT_MAX: &str = "max_value";
+const CONSTRAINT_PATTERN: &str = "pattern";
+const CONSTRAINT_MAX_LENGTH: &str = "max_length";
+
+/// The constraints that the cells of a field must satisfy. They are stored in the type option of
+/// the field's current type, so they are dropped when the field is converted to another type.
+///
+/// The `min` and `max` constraints apply to number fields. The `pattern` and `max_length`
+/// constraints apply to text and url fields. The `unique` constraint compares the text
+/// representation of the cells, see [CellValue::to_text]. Empty cells only violate the `required`
+/// constraint.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FieldConstraints {
+  pub required: bool,
+  pub unique: bool,
+  pub min: Option<f64>,
+  pub max: Option<f64>,
+  /// A regular expression that the whole text must match.
+  pub pattern: Option<String>,
+  /// The maximum number of characters.
+  pub max_length: Option<usize>,
+}
+
+impl FieldConstraints {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn with_required(mut self, required: bool) -> Self {
+    self.required = required;
+    self
+  }
+
+  pub fn with_unique(mut self, unique: bool) -> Self {
+    self.unique = unique;
+    self
+  }
+
+  pub fn with_min(mut self, min: f64) -> Self {
+    self.min = Some(min);
+    self
+  }
+
+  pub fn with_max(mut self, max: f64) -> Self {
+    self.max = Some(max);
+    self
+  }
+
+  pub fn with_pattern(mut self, pattern: String) -> Self {
+    self.pattern = Some(pattern);
+    self
+  }
+
+  pub fn with_max_length(mut self, max_length: usize) -> Self {
+    self.max_length = Some(max_length);
+    self
+  }
+
+  /// Return the constraints of the field's current type.
+  pub fn from_field(field: &Field) -> Self {
+    field
+      .get_type_option::<FieldConstraints>(field.field_type)
+      .unwrap_or_default()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self == &Self::default()
+  }
+
+  /// Return an error if the constraints contradict each other or the pattern is not a valid
+  /// regular expression.
+  pub fn validate(&self) -> Result<(), DatabaseError> {
+    if let (Some(min), Some(max)) = (self.min, self.max) {
+      if min > max {
+        return Err(DatabaseError::InvalidFieldConstraint(format!(
+          "the minimum {} is greater than the maximum {}",
+          min, max
+        )));
+      }
+    }
+    if let Some(pattern) = &self.pattern {
+      anchored_regex(pattern)
+        .map_err(|err| DatabaseError::InvalidFieldConstraint(err.to_string()))?;
+    }
+    Ok(())
+  }
+
+  /// Write the constraints into the type option data. The keys of the unset constraints are
+  /// removed.
+  pub(crate) fn fill_type_option(self, data: &mut TypeOptionData) {
+    for key in [
+      CONSTRAINT_REQUIRED,
+      CONSTRAINT_UNIQUE,
+      CONSTRAINT_MIN,
+      CONSTRAINT_MAX,
+      CONSTRAINT_PATTERN,
+      CONSTRAINT_MAX_LENGTH,
+    ] {
+      data.remove(key);
+    }
+    if self.required {
+      data.insert_bool_value(CONSTRAINT_REQUIRED, true);
+    }
+    if self.unique {
+      data.insert_bool_value(CONSTRAINT_UNIQUE, true);
+    }
+    if let Some(min) = self.min {
+      data.insert_f64_value(CONSTRAINT_MIN, min);
+    }
+    if let Some(max) = self.max {
+      data.insert_f64_value(CONSTRAINT_MAX, max);
+    }
+    if let Some(pattern) = self.pattern {
+      data.insert_str_value(CONSTRAINT_PATTERN, pattern);
+    }
+    if let Some(max_length) = self.max_length {
+      data.insert_i64_value(CONSTRAINT_MAX_LENGTH, max_length as i64);
+    }
+  }
+}
+
+impl From<TypeOptionData> for FieldConstraints {
+  fn from(data: TypeOptionData) -> Self {
+    Self {
+      required: data.get_bool_value(CONSTRAINT_REQUIRED).unwrap_or(false),
+      unique: data.get_bool_value(CONSTRAINT_UNIQUE).unwrap_or(false),
+      min: data.get_f64_value(CONSTRAINT_MIN),
+      max: data.get_f64_value(CONSTRAINT_MAX),
+      pattern: data.get_str_value(CONSTRAINT_PATTERN),
+      max_length: data
+        .get_i64_value(CONSTRAINT_MAX_LENGTH)
+        .and_then(|max_length| usize::try_from(max_length).ok()),
+    }
+  }
+}
+
+/// The constraint that a cell violates.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstraintViolationKind {
+  Required,
+  /// The row has the same value as the other row.
+  Unique {
+    other_row_id: RowId,
+  },
+  LessThanMin(f64),
+  GreaterThanMax(f64),
+  PatternMismatch(String),
+  TooLong(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstraintViolation {
+  pub row_id: RowId,
+  pub field_id: String,
+  pub kind: ConstraintViolationKind,
+}
+
+impl Display for ConstraintViolation {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "the cell of the field {} ", self.field_id)?;
+    match &self.kind {
+      ConstraintViolationKind::Required => write!(f, "is required"),
+      ConstraintViolationKind::Unique { other_row_id } => {
+        write!(f, "has the same value as the row {}", other_row_id)
+      },
+      ConstraintViolationKind::LessThanMin(min) => write!(f, "is less than {}", min),
+      ConstraintViolationKind::GreaterThanMax(max) => write!(f, "is greater than {}", max),
+      ConstraintViolationKind::PatternMismatch(pattern) => {
+        write!(f, "doesn't match the pattern {}", pattern)
+      },
+      ConstraintViolationKind::TooLong(max_length) => {
+        write!(f, "is longer than {} characters", max_length)
+      },
+    }
+  }
+}
+
+/// Reads the rows of the database that the unique constraints are checked against.
+pub(crate) trait ConstraintRowSource {
+  /// Return the ids of all the rows of the database.
+  fn row_ids(&self) -> Vec<RowId>;
+
+  /// Return the cells of the row. None if the row is not available locally.
+  fn row_cells(&self, row_id: &RowId) -> Option<Cells>;
+}
+
+/// Checks the cells of rows against the constraints of the fields.
+///
+/// The compiled constraints of a field are kept until the field changes. Each unique field has an
+/// index of the text of its cells, so a row is checked without reading the other rows of the
+/// database. The index is built when the rows are checked for the first time and is kept up to
+/// date with the [RowChange]s and [DatabaseViewChange]s of the database.
+pub(crate) struct ConstraintValidator {
+  fields: Vec<ConstrainedField>,
+  /// The index of each unique field by field id.
+  indexes: HashMap<String, UniqueIndex>,
+  row_change_rx: RowChangeReceiver,
+  view_change_rx: ViewChangeReceiver,
+}
+
+impl ConstraintValidator {
+  pub(crate) fn new(notifier: &DatabaseNotify) -> Self {
+    Self {
+      fields: vec![],
+      indexes: HashMap::new(),
+      row_change_rx: notifier.row_change_tx.subscribe(),
+      view_change_rx: notifier.view_change_tx.subscribe(),
+    }
+  }
+
+  /// Compile the constraints of the fields that changed since the last call. The index of a
+  /// unique field is dropped when the field changes.
+  pub(crate) fn refresh(&mut self, fields: Vec<Field>) {
+    let mut compiled_fields = std::mem::take(&mut self.fields)
+      .into_iter()
+      .map(|field| (field.field.id.clone(), field))
+      .collect::<HashMap<_, _>>();
+    for field in fields {
+      match compiled_fields.remove(&field.id) {
+        Some(compiled_field) if compiled_field.field == field => self.fields.push(compiled_field),
+        _ => {
+          self.indexes.remove(&field.id);
+          self.fields.extend(ConstrainedField::new(field));
+        },
+      }
+    }
+    for field_id in compiled_fields.keys() {
+      self.indexes.remove(field_id);
+    }
+  }
+
+  pub(crate) fn is_empty(&self) -> bool {
+    self.fields.is_empty()
+  }
+
+  /// Check all the cells of the new rows. The rows are checked against each other and against
+  /// the other rows of the database.
+  pub(crate) fn validate_new_rows(
+    &mut self,
+    source: &dyn ConstraintRowSource,
+    rows: &[(&RowId, &Cells)],
+  ) -> Result<(), ConstraintViolation> {
+    self.validate(source, rows, false)
+  }
+
+  /// Check the updated cells of the rows. Only the fields whose cells are in the updates are
+  /// checked; a removed cell is an empty cell. The rows are checked against each other and
+  /// against the other rows of the database, so two rows can swap the values of a unique field.
+  pub(crate) fn validate_updated_cells(
+    &mut self,
+    source: &dyn ConstraintRowSource,
+    updates: &[(&RowId, &Cells)],
+  ) -> Result<(), ConstraintViolation> {
+    self.validate(source, updates, true)
+  }
+
+  fn validate(
+    &mut self,
+    source: &dyn ConstraintRowSource,
+    rows: &[(&RowId, &Cells)],
+    only_present_cells: bool,
+  ) -> Result<(), ConstraintViolation> {
+    self.prepare(source);
+    let Self {
+      fields, indexes, ..
+    } = self;
+    for field in fields.iter() {
+      let rows = rows
+        .iter()
+        .filter(|(_, cells)| !only_present_cells || cells.contains_key(&field.field.id))
+        .collect::<Vec<_>>();
+      // The new values of these rows are checked against each other instead of the index
+      let checked_row_ids = rows
+        .iter()
+        .map(|(row_id, _)| *row_id)
+        .collect::<HashSet<_>>();
+      let mut first_row_by_text = HashMap::new();
+      for (row_id, cells) in rows {
+        let text = field.cell_text(cells);
+        if let Some(kind) = field.check(text.as_deref(), cells) {
+          return Err(field.violation(row_id, kind));
+        }
+        let text = match text {
+          Some(text) if field.constraints.unique => text,
+          _ => continue,
+        };
+        let other_row_id = match first_row_by_text.get(&text) {
+          Some(other_row_id) => Some(RowId::clone(other_row_id)),
+          None => {
+            let index = indexes
+              .entry(field.field.id.clone())
+              .or_insert_with(|| UniqueIndex::build(field, source));
+            index.find_other_row(field, source, &text, &checked_row_ids)
+          },
+        };
+        if let Some(other_row_id) = other_row_id {
+          let kind = ConstraintViolationKind::Unique { other_row_id };
+          return Err(field.violation(row_id, kind));
+        }
+        first_row_by_text.insert(text, *row_id);
+      }
+    }
+    Ok(())
+  }
+
+  /// Return all the violations of the rows. A row that has the same value as an earlier row
+  /// violates the unique constraint.
+  pub(crate) fn scan(&self, rows: &[Row]) -> Vec<ConstraintViolation> {
+    let mut violations = vec![];
+    for field in &self.fields {
+      let mut first_row_by_text = HashMap::new();
+      for row in rows {
+        let text = field.cell_text(&row.cells);
+        if let Some(kind) = field.check(text.as_deref(), &row.cells) {
+          violations.push(field.violation(&row.id, kind));
+        }
+        if let (true, Some(text)) = (field.constraints.unique, text) {
+          match first_row_by_text.get(&text) {
+            None => {
+              first_row_by_text.insert(text, row.id.clone());
+            },
+            Some(other_row_id) => {
+              let kind = ConstraintViolationKind::Unique {
+                other_row_id: other_row_id.clone(),
+              };
+              violations.push(field.violation(&row.id, kind));
+            },
+          }
+        }
+      }
+    }
+    violations
+  }
+
+  /// Bring the indexes of the unique fields up to date. The changes of the rows since the last
+  /// call are applied to the existing indexes, and the missing ones are built. An index is
+  /// rebuilt if some of the changes were missed.
+  ///
+  /// It's called before the rows are checked. Call it before locking a row for an update, because
+  /// building an index reads the rows.
+  pub(crate) fn prepare(&mut self, source: &dyn ConstraintRowSource) {
+    loop {
+      match self.row_change_rx.try_recv() {
+        Ok(change) => self.apply_row_change(source, change),
+        Err(TryRecvError::Lagged(_)) => self.indexes.clear(),
+        Err(_) => break,
+      }
+    }
+    loop {
+      match self.view_change_rx.try_recv() {
+        Ok(DatabaseViewChange::DidInsertRowOrders { row_orders }) => {
+          let row_ids = row_orders.into_iter().map(|row_order| row_order.id);
+          self.index_rows(source, row_ids);
+        },
+        Ok(_) => {},
+        Err(TryRecvError::Lagged(_)) => self.indexes.clear(),
+        Err(_) => break,
+      }
+    }
+    for field in &self.fields {
+      if field.constraints.unique && !self.indexes.contains_key(&field.field.id) {
+        let index = UniqueIndex::build(field, source);
+        self.indexes.insert(field.field.id.clone(), index);
+      }
+    }
+  }
+
+  fn apply_row_change(&mut self, source: &dyn ConstraintRowSource, change: RowChange) {
+    if self.indexes.is_empty() {
+      return;
+    }
+    match change {
+      RowChange::DidUpdateCell {
+        row_id,
+        field_id,
+        value,
+      } => {
+        let field = self.fields.iter().find(|field| field.field.id == field_id);
+        if let (Some(field), Some(index)) = (field, self.indexes.get_mut(&field_id)) {
+          let mut cells = Cells::new();
+          cells.insert(field_id, value);
+          index.insert(row_id, field.cell_text(&cells));
+        }
+      },
+      RowChange::DidBatchUpdate(RowBatchChange::DidCreateRows { row_ids, .. }) => {
+        self.index_rows(source, row_ids);
+      },
+      RowChange::DidBatchUpdate(RowBatchChange::DidUpdateCells { cells }) => {
+        let row_ids = cells
+          .into_iter()
+          .filter(|(_, field_ids)| {
+            field_ids
+              .iter()
+              .any(|field_id| self.indexes.contains_key(field_id))
+          })
+          .map(|(row_id, _)| row_id)
+          .collect::<Vec<_>>();
+        self.index_rows(source, row_ids);
+      },
+      RowChange::DidBatchUpdate(RowBatchChange::DidDeleteRows { row_ids }) => {
+        for index in self.indexes.values_mut() {
+          for row_id in &row_ids {
+            index.remove(row_id);
+          }
+        }
+      },
+      _ => {},
+    }
+  }
+
+  /// Read the cells of the rows and write them into the indexes.
+  fn index_rows(
+    &mut self,
+    source: &dyn ConstraintRowSource,
+    row_ids: impl IntoIterator<Item = RowId>,
+  ) {
+    if self.indexes.is_empty() {
+      return;
+    }
+    for row_id in row_ids {
+      let cells = match source.row_cells(&row_id) {
+        None => continue,
+        Some(cells) => cells,
+      };
+      for field in &self.fields {
+        if let Some(index) = self.indexes.get_mut(&field.field.id) {
+          index.insert(row_id.clone(), field.cell_text(&cells));
+        }
+      }
+    }
+  }
+}
+
+/// The rows of a unique field by the text of their cells.
+#[derive(Default)]
+struct UniqueIndex {
+  row_ids_by_text: HashMap<String, HashSet<RowId>>,
+  text_by_row_id: HashMap<RowId, String>,
+}
+
+impl UniqueIndex {
+  fn build(field: &ConstrainedField, source: &dyn ConstraintRowSource) -> Self {
+    let mut index = Self::default();
+    for row_id in source.row_ids() {
+      if let Some(cells) = source.row_cells(&row_id) {
+        index.insert(row_id, field.cell_text(&cells));
+      }
+    }
+    index
+  }
+
+  fn insert(&mut self, row_id: RowId, text: Option<String>) {
+    self.remove(&row_id);
+    if let Some(text) = text {
+      self
+        .row_ids_by_text
+        .entry(text.clone())
+        .or_default()
+        .insert(row_id.clone());
+      self.text_by_row_id.insert(row_id, text);
+    }
+  }
+
+  fn remove(&mut self, row_id: &RowId) {
+    if let Some(text) = self.text_by_row_id.remove(row_id) {
+      if let Some(row_ids) = self.row_ids_by_text.get_mut(&text) {
+        row_ids.remove(row_id);
+        if row_ids.is_empty() {
+          self.row_ids_by_text.remove(&text);
+        }
+      }
+    }
+  }
+
+  /// Return the row, other than the excluded ones, whose cell has the text. The cells of the
+  /// found rows are read again, because the index keeps the rows that were removed or whose
+  /// cells were cleared until they are found here.
+  fn find_other_row(
+    &mut self,
+    field: &ConstrainedField,
+    source: &dyn ConstraintRowSource,
+    text: &str,
+    excluded_row_ids: &HashSet<&RowId>,
+  ) -> Option<RowId> {
+    let row_ids = self
+      .row_ids_by_text
+      .get(text)?
+      .iter()
+      .filter(|row_id| !excluded_row_ids.contains(row_id))
+      .cloned()
+      .collect::<Vec<_>>();
+    for row_id in row_ids {
+      let current_text = source
+        .row_cells(&row_id)
+        .and_then(|cells| field.cell_text(&cells));
+      if current_text.as_deref() == Some(text) {
+        return Some(row_id);
+      }
+      self.insert(row_id, current_text);
+    }
+    None
+  }
+}
+
+struct ConstrainedField {
+  field: Field,
+  field_type: FieldType,
+  constraints: FieldConstraints,
+  /// None if the field has no pattern or the pattern is invalid.
+  regex: Option<Regex>,
+}
+
+impl ConstrainedField {
+  /// Return None if the field has no constraints.
+  fn new(field: Field) -> Option<Self> {
+    let constraints = FieldConstraints::from_field(&field);
+    if constraints.is_empty() {
+      return None;
+    }
+    let field_type = FieldType::try_from(field.field_type).ok()?;
+    // The pattern is checked when the constraints are set, but the field might be changed by a
+    // client that doesn't check it.
+    let regex = constraints
+      .pattern
+      .as_deref()
+      .and_then(|pattern| anchored_regex(pattern).ok());
+    Some(Self {
+      field,
+      field_type,
+      constraints,
+      regex,
+    })
+  }
+
+  /// Return the text of the cell. None if the cell is empty.
+  fn cell_text(&self, cells: &Cells) -> Option<String> {
+    self
+      .cell_value(cells)
+      .map(|value| value.to_text(&self.field))
+      .filter(|text| !text.is_empty())
+  }
+
+  fn cell_value(&self, cells: &Cells) -> Option<CellValue> {
+    let cell = cells.get(&self.field.id)?;
+    CellValue::from_cell(self.field_type, cell).ok().flatten()
+  }
+
+  /// Check the constraints other than the unique one.
+  fn check(&self, text: Option<&str>, cells: &Cells) -> Option<ConstraintViolationKind> {
+    let constraints = &self.constraints;
+    let text = match text {
+      None if constraints.required => return Some(ConstraintViolationKind::Required),
+      None => return None,
+      Some(text) => text,
+    };
+
+    match self.field_type {
+      FieldType::Number => {
+        if let Some(CellValue::Number(number)) = self.cell_value(cells) {
+          if let Some(min) = constraints.min.filter(|min| number < *min) {
+            return Some(ConstraintViolationKind::LessThanMin(min));
+          }
+          if let Some(max) = constraints.max.filter(|max| number > *max) {
+            return Some(ConstraintViolationKind::GreaterThanMax(max));
+          }
+        }
+      },
+      FieldType::RichText | FieldType::URL => {
+        if let Some(max_length) = constraints.max_length {
+          if text.chars().count() > max_length {
+            return Some(ConstraintViolationKind::TooLong(max_length));
+          }
+        }
+        if let (Some(regex), Some(pattern)) = (&self.regex, &constraints.pattern) {
+          if !regex.is_match(text) {
+            return Some(ConstraintViolationKind::PatternMismatch(pattern.clone()));
+          }
+        }
+      },
+      _ => {},
+    }
+    None
+  }
+
+  fn violation(&self, row_id: &RowId, kind: ConstraintViolationKind) -> ConstraintViolation {
+    ConstraintViolation {
+      row_id: row_id.clone(),
+      field_id: self.field.id.clone(),
+      kind,
+    }
+  }
+}
+
+/// The pattern must match the whole text, not only a part of it.
+fn anchored_regex(pattern: &str) -> Result<Regex, regex::Error> {
+  Regex::new(&format!("^(?:{})$", pattern))
+}
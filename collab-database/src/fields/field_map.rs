@@ -148,12 +148,20 @@ impl FieldMap {
   where
     F: FnOnce(FieldUpdate),
   {
-    self.container.with_transact_mut(|txn| {
-      let map_ref = self.container.get_or_create_map_with_txn(txn, field_id);
-      let mut update = FieldUpdate::new(field_id, txn, &map_ref);
-      update = update.set_last_modified(timestamp());
-      f(update);
-    })
+    self
+      .container
+      .with_transact_mut(|txn| self.update_field_with_txn(txn, field_id, f))
+  }
+
+  /// Update a field with a transaction
+  pub fn update_field_with_txn<F>(&self, txn: &mut TransactionMut, field_id: &str, f: F)
+  where
+    F: FnOnce(FieldUpdate),
+  {
+    let map_ref = self.container.get_or_create_map_with_txn(txn, field_id);
+    let mut update = FieldUpdate::new(field_id, txn, &map_ref);
+    update = update.set_last_modified(timestamp());
+    f(update);
   }
 
   /// Delete a field with a transaction
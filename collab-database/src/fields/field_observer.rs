@@ -1,5 +1,8 @@
 use crate::fields::{field_from_map_ref, field_from_value, Field};
-use collab::preclude::{DeepEventsSubscription, DeepObservable, EntryChange, Event, MapRefWrapper};
+use collab::preclude::{
+  DeepEventsSubscription, DeepObservable, EntryChange, Event, MapRefExtension, MapRefWrapper,
+  PathSegment,
+};
 use tokio::sync::broadcast;
 use tracing::warn;
 
@@ -17,15 +20,29 @@ pub(crate) fn subscribe_field_change(
   field_map: &mut MapRefWrapper,
   change_tx: FieldChangeSender,
 ) -> DeepEventsSubscription {
+  let root = field_map.clone().into_inner();
   field_map.observe_deep(move |txn, events| {
+    // A transaction that updates several properties of a field, for example its type and type
+    // options, emits one event for each property. Collect the ids of the updated fields so that
+    // a single [FieldChange::DidUpdateField] is sent for each field.
+    let mut updated_field_ids: Vec<String> = vec![];
     for deep_event in events.iter() {
       match deep_event {
         Event::Text(_) => {},
         Event::Array(_) => {},
         Event::Map(event) => {
+          // Changes made inside a field are reported with a non-empty path whose first segment
+          // is the id of the field.
+          if let Some(PathSegment::Key(field_id)) = event.path().front() {
+            let field_id = field_id.to_string();
+            if !updated_field_ids.contains(&field_id) {
+              updated_field_ids.push(field_id);
+            }
+            continue;
+          }
+
           let keys = event.keys(txn);
           for (key, value) in keys.iter() {
-            let _change_tx = change_tx.clone();
             match value {
               EntryChange::Inserted(value) => {
                 // tracing::trace!("field observer: Inserted: {}:{}", key, value);
@@ -35,8 +52,9 @@ pub(crate) fn subscribe_field_change(
               },
               EntryChange::Updated(_, _value) => {
                 // tracing::trace!("field observer: update: {}:{}", key, value);
-                if let Some(field) = field_from_map_ref(event.target(), txn) {
-                  let _ = change_tx.send(FieldChange::DidUpdateField { field });
+                let field_id = (**key).to_string();
+                if !updated_field_ids.contains(&field_id) {
+                  updated_field_ids.push(field_id);
                 }
               },
               EntryChange::Removed(_value) => {
@@ -54,5 +72,14 @@ pub(crate) fn subscribe_field_change(
         Event::XmlText(_) => {},
       }
     }
+
+    for field_id in updated_field_ids {
+      if let Some(field) = root
+        .get_map_with_txn(txn, &field_id)
+        .and_then(|map_ref| field_from_map_ref(&map_ref, txn))
+      {
+        let _ = change_tx.send(FieldChange::DidUpdateField { field });
+      }
+    }
   })
 }
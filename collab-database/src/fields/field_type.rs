@@ -0,0 +1,85 @@
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+use crate::error::DatabaseError;
+
+/// The field types that the database knows how to interpret. The value is stored in
+/// [Field::field_type](crate::fields::Field) and is used as the key of the field's type options.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum FieldType {
+  RichText = 0,
+  Number = 1,
+  DateTime = 2,
+  SingleSelect = 3,
+  MultiSelect = 4,
+  Checkbox = 5,
+  URL = 6,
+  Checklist = 7,
+  LastEditedTime = 8,
+  CreatedTime = 9,
+  Relation = 10,
+  Media = 11,
+}
+
+impl FieldType {
+  pub fn value(&self) -> i64 {
+    *self as i64
+  }
+
+  pub fn default_name(&self) -> &'static str {
+    match self {
+      FieldType::RichText => "Text",
+      FieldType::Number => "Number",
+      FieldType::DateTime => "Date",
+      FieldType::SingleSelect => "Single Select",
+      FieldType::MultiSelect => "Multi Select",
+      FieldType::Checkbox => "Checkbox",
+      FieldType::URL => "URL",
+      FieldType::Checklist => "Checklist",
+      FieldType::LastEditedTime => "Last modified",
+      FieldType::CreatedTime => "Created time",
+      FieldType::Relation => "Relation",
+      FieldType::Media => "Files & media",
+    }
+  }
+
+  pub fn is_select_option(&self) -> bool {
+    matches!(self, FieldType::SingleSelect | FieldType::MultiSelect)
+  }
+
+  pub fn is_date(&self) -> bool {
+    matches!(
+      self,
+      FieldType::DateTime | FieldType::LastEditedTime | FieldType::CreatedTime
+    )
+  }
+}
+
+impl From<FieldType> for i64 {
+  fn from(field_type: FieldType) -> Self {
+    field_type.value()
+  }
+}
+
+impl TryFrom<i64> for FieldType {
+  type Error = DatabaseError;
+
+  fn try_from(value: i64) -> Result<Self, Self::Error> {
+    match value {
+      0 => Ok(FieldType::RichText),
+      1 => Ok(FieldType::Number),
+      2 => Ok(FieldType::DateTime),
+      3 => Ok(FieldType::SingleSelect),
+      4 => Ok(FieldType::MultiSelect),
+      5 => Ok(FieldType::Checkbox),
+      6 => Ok(FieldType::URL),
+      7 => Ok(FieldType::Checklist),
+      8 => Ok(FieldType::LastEditedTime),
+      9 => Ok(FieldType::CreatedTime),
+      10 => Ok(FieldType::Relation),
+      11 => Ok(FieldType::Media),
+      _ => Err(DatabaseError::UnknownFieldType(value)),
+    }
+  }
+}
@@ -0,0 +1,164 @@
+use crate::fields::{
+  DateTypeOption, Field, FieldType, SelectOption, SelectTypeOption, TypeOptionData,
+  SELECT_OPTION_SEPARATOR,
+};
+use crate::rows::{
+  parse_checkbox, parse_date, parse_number, Cell, CellValue, ChecklistCellValue, DateCellValue,
+};
+
+/// Converts the cells of a field from one [FieldType] to another.
+///
+/// Every cell is converted through its typed [CellValue]. The rules are:
+/// - Any type to text or url: the text representation of the value, see [CellValue::to_text].
+/// - Text to number: the text is parsed as a number. The cell is cleared if it's not a number.
+/// - Text to date: the text is parsed as a date. The cell is cleared if it's not a date.
+/// - Text to checkbox: "Yes" and "true" are checked, everything else is unchecked. Numbers are
+///   checked if they are not zero.
+/// - Any type to select option: the comma separated names of the text representation become the
+///   selected options. Missing options are added to the type option.
+/// - Any type to checklist: the comma separated names become unselected checklist options.
+/// - The relation, media, last edited time and created time fields can't be converted to, their
+///   cells are cleared.
+pub(crate) struct FieldTypeConverter<'a> {
+  field: &'a Field,
+  from: FieldType,
+  to: FieldType,
+  /// The type option of the select option field that the field is converted to.
+  select_type_option: SelectTypeOption,
+}
+
+impl<'a> FieldTypeConverter<'a> {
+  pub(crate) fn new(field: &'a Field, from: FieldType, to: FieldType) -> Self {
+    // Reuse the type option of the target type if the field was of this type before. Otherwise,
+    // start from the options of the current select option field.
+    let select_type_option = field
+      .get_type_option::<SelectTypeOption>(to.value())
+      .or_else(|| {
+        if from.is_select_option() {
+          field.get_type_option::<SelectTypeOption>(from.value())
+        } else {
+          None
+        }
+      })
+      .unwrap_or_default();
+    Self {
+      field,
+      from,
+      to,
+      select_type_option,
+    }
+  }
+
+  /// Collect the options that the cell needs in the type option of the target select option
+  /// field. It must be called for every cell before calling [FieldTypeConverter::type_option].
+  pub(crate) fn prepare(&mut self, cell: &Cell) {
+    if !self.to.is_select_option() {
+      return;
+    }
+    if let Some(text) = self.decode_text(cell) {
+      for name in split_names(&text) {
+        self.select_type_option.get_or_insert_option(name);
+      }
+    }
+  }
+
+  /// Return the type option of the target field type. Return None if the field already has a
+  /// type option for the target type or the target type doesn't need one.
+  pub(crate) fn type_option(&self) -> Option<TypeOptionData> {
+    if self.to.is_select_option() {
+      return Some(self.select_type_option.clone().into());
+    }
+    let has_type_option = self
+      .field
+      .type_options
+      .contains_key(&self.to.value().to_string());
+    match self.to {
+      FieldType::DateTime | FieldType::LastEditedTime | FieldType::CreatedTime
+        if !has_type_option =>
+      {
+        // Keep the date and time format of the date field that is converted from.
+        let type_option = self
+          .field
+          .get_type_option::<DateTypeOption>(self.from.value())
+          .filter(|_| self.from.is_date())
+          .unwrap_or_default();
+        Some(type_option.into())
+      },
+      _ => None,
+    }
+  }
+
+  /// Convert the cell. Return None if the cell should be cleared.
+  pub(crate) fn convert(&self, cell: &Cell) -> Option<Cell> {
+    let value = self.convert_value(cell)?;
+    Some(value.to_cell(self.to))
+  }
+
+  fn convert_value(&self, cell: &Cell) -> Option<CellValue> {
+    let value = CellValue::from_cell(self.from, cell).ok()??;
+    let text = value.to_text(self.field);
+    let value = match self.to {
+      FieldType::RichText => CellValue::Text(text),
+      FieldType::URL => CellValue::Url(text),
+      FieldType::Number => match value {
+        CellValue::Checkbox(is_checked) => CellValue::Number(if is_checked { 1.0 } else { 0.0 }),
+        _ => CellValue::Number(parse_number(text.trim())?),
+      },
+      FieldType::Checkbox => match value {
+        CellValue::Number(number) => CellValue::Checkbox(number != 0.0),
+        _ => CellValue::Checkbox(parse_checkbox(text.trim()).unwrap_or(false)),
+      },
+      FieldType::DateTime => match value {
+        CellValue::Timestamp(timestamp) => CellValue::Date(DateCellValue {
+          timestamp,
+          include_time: true,
+          ..Default::default()
+        }),
+        _ => {
+          let (timestamp, include_time) = parse_date(text.trim())?;
+          CellValue::Date(DateCellValue {
+            timestamp,
+            include_time,
+            ..Default::default()
+          })
+        },
+      },
+      FieldType::SingleSelect | FieldType::MultiSelect => {
+        let mut ids = split_names(&text)
+          .into_iter()
+          .flat_map(|name| self.select_type_option.get_option_by_name(name))
+          .map(|option| option.id.clone())
+          .collect::<Vec<_>>();
+        if self.to == FieldType::SingleSelect {
+          ids.truncate(1);
+        }
+        CellValue::SelectOption(ids)
+      },
+      FieldType::Checklist => CellValue::Checklist(ChecklistCellValue {
+        options: split_names(&text)
+          .into_iter()
+          .map(SelectOption::new)
+          .collect(),
+        selected_option_ids: vec![],
+      }),
+      FieldType::Relation
+      | FieldType::Media
+      | FieldType::LastEditedTime
+      | FieldType::CreatedTime => return None,
+    };
+    Some(value)
+  }
+
+  fn decode_text(&self, cell: &Cell) -> Option<String> {
+    let value = CellValue::from_cell(self.from, cell).ok()??;
+    Some(value.to_text(self.field))
+  }
+}
+
+fn split_names(text: &str) -> Vec<&str> {
+  text
+    .split(SELECT_OPTION_SEPARATOR)
+    .map(|name| name.trim())
+    .filter(|name| !name.is_empty())
+    .collect()
+}
@@ -1,11 +1,21 @@
+mod date_type_option;
 mod field;
+mod field_constraint;
 mod field_id;
 mod field_map;
 mod field_observer;
+mod field_type;
+mod field_type_convert;
+mod select_type_option;
 mod type_option;
 
+pub use date_type_option::*;
 pub use field::*;
+pub use field_constraint::*;
 pub use field_id::*;
 pub use field_map::*;
 pub use field_observer::*;
+pub use field_type::*;
+pub(crate) use field_type_convert::*;
+pub use select_type_option::*;
 pub use type_option::*;
@@ -0,0 +1,134 @@
+use collab::core::any_map::AnyMapExtension;
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+use crate::database::gen_option_id;
+use crate::fields::{TypeOptionData, TypeOptionDataBuilder};
+
+/// The select option ids of a select cell are stored as a single string joined by this separator.
+pub const SELECT_OPTION_SEPARATOR: &str = ",";
+
+const SELECT_OPTION_CONTENT: &str = "content";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelectOption {
+  pub id: String,
+  pub name: String,
+  #[serde(default)]
+  pub color: SelectOptionColor,
+}
+
+impl SelectOption {
+  pub fn new(name: &str) -> Self {
+    Self::with_color(name, SelectOptionColor::default())
+  }
+
+  pub fn with_color(name: &str, color: SelectOptionColor) -> Self {
+    Self {
+      id: gen_option_id(),
+      name: name.to_string(),
+      color,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum SelectOptionColor {
+  #[default]
+  Purple = 0,
+  Pink = 1,
+  LightPink = 2,
+  Orange = 3,
+  Yellow = 4,
+  Lime = 5,
+  Green = 6,
+  Aqua = 7,
+  Blue = 8,
+}
+
+impl SelectOptionColor {
+  /// Return the color at the given index, wrapping around when the index is out of range. It's
+  /// used to give consecutive options different colors.
+  pub fn from_index(index: usize) -> Self {
+    match index % 9 {
+      0 => SelectOptionColor::Purple,
+      1 => SelectOptionColor::Pink,
+      2 => SelectOptionColor::LightPink,
+      3 => SelectOptionColor::Orange,
+      4 => SelectOptionColor::Yellow,
+      5 => SelectOptionColor::Lime,
+      6 => SelectOptionColor::Green,
+      7 => SelectOptionColor::Aqua,
+      _ => SelectOptionColor::Blue,
+    }
+  }
+}
+
+/// The type option of the single select and multi select fields. The options are stored as a
+/// json string.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelectTypeOption {
+  pub options: Vec<SelectOption>,
+  #[serde(default)]
+  pub disable_color: bool,
+}
+
+impl SelectTypeOption {
+  pub fn get_option_by_name(&self, name: &str) -> Option<&SelectOption> {
+    self.options.iter().find(|option| option.name == name)
+  }
+
+  /// Return the id of the option with the given name. A new option is added if there is no
+  /// option with the name.
+  pub fn get_or_insert_option(&mut self, name: &str) -> String {
+    if let Some(option) = self.get_option_by_name(name) {
+      return option.id.clone();
+    }
+    let option = SelectOption::with_color(name, SelectOptionColor::from_index(self.options.len()));
+    let id = option.id.clone();
+    self.options.push(option);
+    id
+  }
+
+  /// Return the names of the options in the cell data. Unknown option ids are skipped.
+  pub fn get_option_names(&self, cell_data: &str) -> Vec<String> {
+    split_select_option_ids(cell_data)
+      .iter()
+      .flat_map(|id| {
+        self
+          .options
+          .iter()
+          .find(|option| &option.id == id)
+          .map(|option| option.name.clone())
+      })
+      .collect()
+  }
+}
+
+impl From<TypeOptionData> for SelectTypeOption {
+  fn from(data: TypeOptionData) -> Self {
+    data
+      .get_str_value(SELECT_OPTION_CONTENT)
+      .and_then(|content| serde_json::from_str(&content).ok())
+      .unwrap_or_default()
+  }
+}
+
+impl From<SelectTypeOption> for TypeOptionData {
+  fn from(data: SelectTypeOption) -> Self {
+    let content = serde_json::to_string(&data).unwrap_or_default();
+    TypeOptionDataBuilder::new()
+      .insert_str_value(SELECT_OPTION_CONTENT, content)
+      .build()
+  }
+}
+
+pub fn split_select_option_ids(cell_data: &str) -> Vec<String> {
+  cell_data
+    .split(SELECT_OPTION_SEPARATOR)
+    .map(|id| id.trim())
+    .filter(|id| !id.is_empty())
+    .map(|id| id.to_string())
+    .collect()
+}
@@ -1,4 +1,6 @@
+pub mod csv;
 pub mod database;
+pub mod database_awareness;
 pub mod fields;
 pub mod id_gen;
 pub mod meta;
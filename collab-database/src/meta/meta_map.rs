@@ -3,6 +3,7 @@ use std::ops::Deref;
 use collab::preclude::{MapRef, MapRefExtension, MapRefWrapper, ReadTxn, TransactionMut};
 
 const DATABASE_INLINE_VIEW: &str = "iid";
+const DATABASE_VERSION: &str = "version";
 
 pub struct MetaMap {
   container: MapRefWrapper,
@@ -24,6 +25,22 @@ impl MetaMap {
   pub fn get_inline_view_id_with_txn<T: ReadTxn>(&self, txn: &T) -> Option<String> {
     self.container.get_str_with_txn(txn, DATABASE_INLINE_VIEW)
   }
+
+  /// Set the version of the database's structure
+  pub fn set_version_with_txn(&self, txn: &mut TransactionMut, version: i64) {
+    self
+      .container
+      .insert_i64_with_txn(txn, DATABASE_VERSION, version);
+  }
+
+  /// Get the version of the database's structure. The databases that were created before the
+  /// version was introduced are version 0.
+  pub fn get_version_with_txn<T: ReadTxn>(&self, txn: &T) -> i64 {
+    self
+      .container
+      .get_i64_with_txn(txn, DATABASE_VERSION)
+      .unwrap_or_default()
+  }
 }
 
 impl Deref for MetaMap {
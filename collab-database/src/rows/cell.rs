@@ -108,6 +108,13 @@ pub type Cell = AnyMap;
 pub type CellBuilder = AnyMapBuilder;
 pub type CellUpdate<'a, 'b> = AnyMapUpdate<'a, 'b>;
 
+/// The key of the cell's value. Cells of all the [FieldType](crate::fields::FieldType)s store
+/// their value under this key.
+pub const CELL_DATA: &str = "data";
+/// The values of the checkbox cell.
+pub const CHECKBOX_CHECKED: &str = "Yes";
+pub const CHECKBOX_UNCHECKED: &str = "No";
+
 pub fn get_field_type_from_cell<T: From<i64>>(cell: &Cell) -> Option<T> {
   cell.get_i64_value("field_type").map(|value| T::from(value))
 }
@@ -118,6 +125,7 @@ pub fn new_cell_builder(field_type: impl Into<i64>) -> CellBuilder {
   inner.insert_i64_value("field_type", field_type.into())
 }
 
+#[derive(Debug, Clone)]
 pub struct RowCell {
   pub row_id: RowId,
   /// The cell might be empty if no value is written before
@@ -0,0 +1,67 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use collab::preclude::Any;
+
+const DATE_TIME_FORMATS: [&str; 4] = [
+  "%Y-%m-%d %H:%M:%S",
+  "%Y-%m-%d %H:%M",
+  "%Y/%m/%d %H:%M",
+  "%m/%d/%Y %H:%M",
+];
+const DATE_FORMATS: [&str; 5] = ["%Y-%m-%d", "%Y/%m/%d", "%m/%d/%Y", "%b %d, %Y", "%d.%m.%Y"];
+
+/// Return the text of a scalar cell value. The values of a list are joined with commas. Return
+/// None if the value has no text representation, like a map.
+pub(crate) fn any_to_string(value: &Any) -> Option<String> {
+  match value {
+    Any::String(s) => Some(s.to_string()),
+    Any::BigInt(value) => Some(value.to_string()),
+    Any::Number(value) => Some(value.to_string()),
+    Any::Bool(value) => Some(value.to_string()),
+    Any::Array(values) => Some(
+      values
+        .iter()
+        .flat_map(any_to_string)
+        .collect::<Vec<_>>()
+        .join(","),
+    ),
+    _ => None,
+  }
+}
+
+pub(crate) fn parse_checkbox(value: &str) -> Option<bool> {
+  match value.to_lowercase().as_str() {
+    "yes" | "true" => Some(true),
+    "no" | "false" => Some(false),
+    _ => None,
+  }
+}
+
+pub(crate) fn parse_number(value: &str) -> Option<f64> {
+  value
+    .parse::<f64>()
+    .ok()
+    .filter(|number| number.is_finite())
+}
+
+/// Return the timestamp in seconds and whether the value contains the time.
+pub(crate) fn parse_date(value: &str) -> Option<(i64, bool)> {
+  if let Ok(date_time) = DateTime::parse_from_rfc3339(value) {
+    return Some((date_time.timestamp(), true));
+  }
+  for format in DATE_TIME_FORMATS {
+    if let Ok(date_time) = NaiveDateTime::parse_from_str(value, format) {
+      return Some((date_time.and_utc().timestamp(), true));
+    }
+  }
+  for format in DATE_FORMATS {
+    if let Ok(date) = NaiveDate::parse_from_str(value, format) {
+      let date_time = date.and_hms_opt(0, 0, 0)?;
+      return Some((date_time.and_utc().timestamp(), false));
+    }
+  }
+  None
+}
+
+pub(crate) fn is_url(value: &str) -> bool {
+  (value.starts_with("http://") || value.starts_with("https://")) && !value.contains(' ')
+}
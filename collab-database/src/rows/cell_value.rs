@@ -0,0 +1,429 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use chrono_tz::Tz;
+use collab::core::any_map::AnyMapExtension;
+use collab::preclude::Any;
+use serde::{Deserialize, Serialize};
+
+use crate::error::DatabaseError;
+use crate::fields::{
+  split_select_option_ids, DateTypeOption, Field, FieldType, SelectOption, SelectTypeOption,
+  DATE_CELL_END_TIMESTAMP, DATE_CELL_INCLUDE_TIME, DATE_CELL_IS_RANGE, DATE_CELL_TIMEZONE_ID,
+  SELECT_OPTION_SEPARATOR,
+};
+use crate::rows::{
+  any_to_string, is_url, new_cell_builder, Cell, RowId, CELL_DATA, CHECKBOX_CHECKED,
+  CHECKBOX_UNCHECKED,
+};
+
+/// The typed value of a [Cell]. A [Cell] is an untyped map, the [CellValue] decodes its content
+/// according to the [FieldType] of the field that the cell belongs to.
+///
+/// Use [CellValue::validate] to check the value against the field's type option before writing
+/// it into the cell.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CellValue {
+  Text(String),
+  Number(f64),
+  Date(DateCellValue),
+  /// The ids of the selected options. It's used by both single and multi select fields.
+  SelectOption(Vec<String>),
+  Checklist(ChecklistCellValue),
+  Url(String),
+  Checkbox(bool),
+  /// The ids of the rows in the related database.
+  Relation(Vec<RowId>),
+  Media(Vec<MediaFile>),
+  /// The timestamp in seconds of the last edited time and created time fields. These values are
+  /// generated from the row, so they can't be written.
+  Timestamp(i64),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DateCellValue {
+  /// The timestamp in seconds.
+  pub timestamp: i64,
+  /// The end of the date range. None if the date is not a range.
+  pub end_timestamp: Option<i64>,
+  pub include_time: bool,
+  /// The IANA timezone id, for example "Asia/Shanghai". Empty means the timezone of the field's
+  /// type option.
+  pub timezone_id: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChecklistCellValue {
+  pub options: Vec<SelectOption>,
+  #[serde(default)]
+  pub selected_option_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MediaFile {
+  pub id: String,
+  pub name: String,
+  pub url: String,
+}
+
+impl CellValue {
+  /// Decode the [Cell] of a field with the given [FieldType]. Return None if the cell is empty.
+  pub fn from_cell(field_type: FieldType, cell: &Cell) -> Result<Option<Self>, DatabaseError> {
+    let data = match cell.get(CELL_DATA) {
+      None => return Ok(None),
+      Some(data) => data,
+    };
+    let value = match field_type {
+      FieldType::RichText => CellValue::Text(any_to_text(data)?),
+      FieldType::URL => CellValue::Url(any_to_text(data)?),
+      FieldType::Number => match any_to_f64(data)? {
+        None => return Ok(None),
+        Some(number) => CellValue::Number(number),
+      },
+      FieldType::DateTime => {
+        let timestamp = match any_to_i64(data)? {
+          None => return Ok(None),
+          Some(timestamp) => timestamp,
+        };
+        let is_range = cell.get_bool_value(DATE_CELL_IS_RANGE).unwrap_or(false);
+        let end_timestamp = match cell.get(DATE_CELL_END_TIMESTAMP) {
+          Some(end) if is_range => any_to_i64(end)?,
+          _ => None,
+        };
+        CellValue::Date(DateCellValue {
+          timestamp,
+          end_timestamp,
+          include_time: cell.get_bool_value(DATE_CELL_INCLUDE_TIME).unwrap_or(false),
+          timezone_id: cell
+            .get_str_value(DATE_CELL_TIMEZONE_ID)
+            .unwrap_or_default(),
+        })
+      },
+      FieldType::SingleSelect | FieldType::MultiSelect => {
+        CellValue::SelectOption(split_select_option_ids(&any_to_text(data)?))
+      },
+      FieldType::Checklist => {
+        let content = any_to_text(data)?;
+        if content.is_empty() {
+          CellValue::Checklist(ChecklistCellValue::default())
+        } else {
+          CellValue::Checklist(serde_json::from_str(&content)?)
+        }
+      },
+      FieldType::Checkbox => match data {
+        Any::Bool(value) => CellValue::Checkbox(*value),
+        Any::String(value) => CellValue::Checkbox(matches!(
+          value.to_lowercase().as_str(),
+          "yes" | "true" | "1"
+        )),
+        _ => return Err(invalid_data(field_type, data)),
+      },
+      FieldType::Relation => {
+        let row_ids = any_to_strings(data)?.into_iter().map(RowId::from).collect();
+        CellValue::Relation(row_ids)
+      },
+      FieldType::Media => {
+        let files = any_to_strings(data)?
+          .iter()
+          .map(|file| serde_json::from_str::<MediaFile>(file))
+          .collect::<Result<Vec<_>, _>>()?;
+        CellValue::Media(files)
+      },
+      FieldType::LastEditedTime | FieldType::CreatedTime => match any_to_i64(data)? {
+        None => return Ok(None),
+        Some(timestamp) => CellValue::Timestamp(timestamp),
+      },
+    };
+    Ok(Some(value))
+  }
+
+  /// Encode the value into a [Cell] of a field with the given [FieldType].
+  pub fn to_cell(&self, field_type: FieldType) -> Cell {
+    let builder = new_cell_builder(field_type);
+    let builder = match self {
+      CellValue::Text(s) | CellValue::Url(s) => builder.insert_str_value(CELL_DATA, s),
+      CellValue::Number(number) => builder.insert_str_value(CELL_DATA, number),
+      CellValue::Date(date) => builder
+        .insert_str_value(CELL_DATA, date.timestamp)
+        .insert_str_value(
+          DATE_CELL_END_TIMESTAMP,
+          date
+            .end_timestamp
+            .map(|end| end.to_string())
+            .unwrap_or_default(),
+        )
+        .insert_bool_value(DATE_CELL_IS_RANGE, date.end_timestamp.is_some())
+        .insert_bool_value(DATE_CELL_INCLUDE_TIME, date.include_time)
+        .insert_str_value(DATE_CELL_TIMEZONE_ID, &date.timezone_id),
+      CellValue::SelectOption(ids) => {
+        builder.insert_str_value(CELL_DATA, ids.join(SELECT_OPTION_SEPARATOR))
+      },
+      CellValue::Checklist(checklist) => builder.insert_str_value(
+        CELL_DATA,
+        serde_json::to_string(checklist).unwrap_or_default(),
+      ),
+      CellValue::Checkbox(is_checked) => {
+        let data = if *is_checked {
+          CHECKBOX_CHECKED
+        } else {
+          CHECKBOX_UNCHECKED
+        };
+        builder.insert_str_value(CELL_DATA, data)
+      },
+      CellValue::Relation(row_ids) => builder.insert_any(
+        CELL_DATA,
+        strings_to_any(row_ids.iter().map(|row_id| row_id.to_string())),
+      ),
+      CellValue::Media(files) => builder.insert_any(
+        CELL_DATA,
+        strings_to_any(
+          files
+            .iter()
+            .map(|file| serde_json::to_string(file).unwrap_or_default()),
+        ),
+      ),
+      CellValue::Timestamp(timestamp) => builder.insert_str_value(CELL_DATA, timestamp),
+    };
+    builder.build()
+  }
+
+  /// Return the text representation of the value. The type option of the field is used to
+  /// format dates and to look up the names of the selected options.
+  pub fn to_text(&self, field: &Field) -> String {
+    match self {
+      CellValue::Text(s) | CellValue::Url(s) => s.clone(),
+      CellValue::Number(number) => number.to_string(),
+      CellValue::Date(date) => {
+        let type_option = field
+          .get_type_option::<DateTypeOption>(field.field_type)
+          .unwrap_or_default();
+        let start = type_option.format_timestamp(date.timestamp, date.include_time);
+        match date.end_timestamp {
+          None => start,
+          Some(end) => format!(
+            "{} - {}",
+            start,
+            type_option.format_timestamp(end, date.include_time)
+          ),
+        }
+      },
+      CellValue::SelectOption(ids) => field
+        .get_type_option::<SelectTypeOption>(field.field_type)
+        .unwrap_or_default()
+        .get_option_names(&ids.join(SELECT_OPTION_SEPARATOR))
+        .join(", "),
+      CellValue::Checklist(checklist) => checklist
+        .options
+        .iter()
+        .map(|option| option.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", "),
+      CellValue::Checkbox(is_checked) => {
+        if *is_checked {
+          CHECKBOX_CHECKED.to_string()
+        } else {
+          CHECKBOX_UNCHECKED.to_string()
+        }
+      },
+      CellValue::Relation(row_ids) => row_ids
+        .iter()
+        .map(|row_id| row_id.as_str())
+        .collect::<Vec<_>>()
+        .join(", "),
+      CellValue::Media(files) => files
+        .iter()
+        .map(|file| file.url.as_str())
+        .collect::<Vec<_>>()
+        .join(", "),
+      CellValue::Timestamp(timestamp) => field
+        .get_type_option::<DateTypeOption>(field.field_type)
+        .unwrap_or_default()
+        .format_timestamp(*timestamp, true),
+    }
+  }
+
+  /// Return true if the value can be stored in the cell of the given [FieldType].
+  pub fn is_value_of(&self, field_type: FieldType) -> bool {
+    matches!(
+      (self, field_type),
+      (CellValue::Text(_), FieldType::RichText)
+        | (CellValue::Number(_), FieldType::Number)
+        | (CellValue::Date(_), FieldType::DateTime)
+        | (
+          CellValue::SelectOption(_),
+          FieldType::SingleSelect | FieldType::MultiSelect
+        )
+        | (CellValue::Checklist(_), FieldType::Checklist)
+        | (CellValue::Url(_), FieldType::URL)
+        | (CellValue::Checkbox(_), FieldType::Checkbox)
+        | (CellValue::Relation(_), FieldType::Relation)
+        | (CellValue::Media(_), FieldType::Media)
+        | (
+          CellValue::Timestamp(_),
+          FieldType::LastEditedTime | FieldType::CreatedTime
+        )
+    )
+  }
+
+  /// Check the value against the field's type and type option. Return an error if the value
+  /// can't be written into the cell of the field.
+  pub fn validate(&self, field: &Field) -> Result<(), DatabaseError> {
+    let field_type = FieldType::try_from(field.field_type)?;
+    if !self.is_value_of(field_type) {
+      return Err(DatabaseError::InvalidCellValue(format!(
+        "the value can't be stored in a {} field",
+        field_type.default_name()
+      )));
+    }
+
+    match self {
+      CellValue::Number(number) if !number.is_finite() => Err(DatabaseError::InvalidCellValue(
+        format!("{} is not a finite number", number),
+      )),
+      CellValue::Date(date) => {
+        if matches!(date.end_timestamp, Some(end) if end < date.timestamp) {
+          return Err(DatabaseError::InvalidCellValue(
+            "the end of the date range is before its start".to_string(),
+          ));
+        }
+        if !date.timezone_id.is_empty() && date.timezone_id.parse::<Tz>().is_err() {
+          return Err(DatabaseError::InvalidCellValue(format!(
+            "\"{}\" is not a known timezone",
+            date.timezone_id
+          )));
+        }
+        Ok(())
+      },
+      CellValue::Url(url) if !url.is_empty() && !is_url(url) => Err(
+        DatabaseError::InvalidCellValue(format!("\"{}\" is not a valid url", url)),
+      ),
+      CellValue::SelectOption(ids) => {
+        if field_type == FieldType::SingleSelect && ids.len() > 1 {
+          return Err(DatabaseError::InvalidCellValue(
+            "a single select cell can only select one option".to_string(),
+          ));
+        }
+        let type_option = field
+          .get_type_option::<SelectTypeOption>(field.field_type)
+          .unwrap_or_default();
+        let option_ids = type_option
+          .options
+          .iter()
+          .map(|option| option.id.as_str())
+          .collect::<Vec<_>>();
+        check_unknown_ids(ids, &option_ids, "select option")
+      },
+      CellValue::Checklist(checklist) => {
+        let option_ids = checklist
+          .options
+          .iter()
+          .map(|option| option.id.as_str())
+          .collect::<Vec<_>>();
+        check_unknown_ids(
+          &checklist.selected_option_ids,
+          &option_ids,
+          "checklist option",
+        )
+      },
+      CellValue::Relation(row_ids) => {
+        let mut unique_ids = HashSet::new();
+        match row_ids
+          .iter()
+          .find(|row_id| row_id.is_empty() || !unique_ids.insert(row_id.as_str()))
+        {
+          None => Ok(()),
+          Some(row_id) => Err(DatabaseError::InvalidCellValue(format!(
+            "the related row id \"{}\" is empty or duplicated",
+            row_id
+          ))),
+        }
+      },
+      CellValue::Timestamp(_) => Err(DatabaseError::InvalidCellValue(format!(
+        "the value of a {} field is generated from the row",
+        field_type.default_name()
+      ))),
+      _ => Ok(()),
+    }
+  }
+}
+
+fn check_unknown_ids(ids: &[String], known_ids: &[&str], kind: &str) -> Result<(), DatabaseError> {
+  match ids.iter().find(|id| !known_ids.contains(&id.as_str())) {
+    None => Ok(()),
+    Some(id) => Err(DatabaseError::InvalidCellValue(format!(
+      "the {} \"{}\" does not exist",
+      kind, id
+    ))),
+  }
+}
+
+fn invalid_data(field_type: FieldType, data: &Any) -> DatabaseError {
+  DatabaseError::InvalidCellValue(format!(
+    "{:?} is not a valid value of a {} field",
+    data,
+    field_type.default_name()
+  ))
+}
+
+fn any_to_text(data: &Any) -> Result<String, DatabaseError> {
+  // The lists are not a text, even though they can be joined into one
+  let text = match data {
+    Any::String(_) | Any::BigInt(_) | Any::Number(_) => any_to_string(data),
+    _ => None,
+  };
+  text.ok_or_else(|| DatabaseError::InvalidCellValue(format!("{:?} is not a string", data)))
+}
+
+/// Return None if the data is an empty string.
+fn any_to_f64(data: &Any) -> Result<Option<f64>, DatabaseError> {
+  match data {
+    Any::String(s) if s.trim().is_empty() => Ok(None),
+    Any::String(s) => s
+      .trim()
+      .parse::<f64>()
+      .map(Some)
+      .map_err(|_| DatabaseError::InvalidCellValue(format!("{} is not a number", s))),
+    Any::BigInt(value) => Ok(Some(*value as f64)),
+    Any::Number(value) => Ok(Some(*value)),
+    _ => Err(DatabaseError::InvalidCellValue(format!(
+      "{:?} is not a number",
+      data
+    ))),
+  }
+}
+
+/// Return None if the data is an empty string.
+fn any_to_i64(data: &Any) -> Result<Option<i64>, DatabaseError> {
+  match data {
+    Any::String(s) if s.trim().is_empty() => Ok(None),
+    Any::String(s) => s
+      .trim()
+      .parse::<i64>()
+      .map(Some)
+      .map_err(|_| DatabaseError::InvalidCellValue(format!("{} is not a timestamp", s))),
+    Any::BigInt(value) => Ok(Some(*value)),
+    Any::Number(value) => Ok(Some(*value as i64)),
+    _ => Err(DatabaseError::InvalidCellValue(format!(
+      "{:?} is not a timestamp",
+      data
+    ))),
+  }
+}
+
+fn any_to_strings(data: &Any) -> Result<Vec<String>, DatabaseError> {
+  match data {
+    Any::Array(values) => values.iter().map(any_to_text).collect(),
+    Any::String(s) if s.is_empty() => Ok(vec![]),
+    _ => Err(DatabaseError::InvalidCellValue(format!(
+      "{:?} is not a list",
+      data
+    ))),
+  }
+}
+
+fn strings_to_any(values: impl Iterator<Item = String>) -> Any {
+  let values = values
+    .map(|value| Any::String(Arc::from(value)))
+    .collect::<Vec<_>>();
+  Any::Array(Arc::from(values))
+}
@@ -1,13 +1,179 @@
-use collab::preclude::Any;
+use std::collections::HashMap;
+
+use collab::core::array_wrapper::ArrayRefExtension;
+use collab::core::value::YrsValueExtension;
+use collab::preclude::{
+  Any, Array, ArrayRef, Map, MapRef, MapRefExtension, ReadTxn, TransactionMut, YrsValue,
+};
 use collab::util::deserialize_i64_from_numeric;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::database::timestamp;
+
+const COMMENT_ID: &str = "id";
+const COMMENT_UID: &str = "uid";
+const COMMENT_CONTENT: &str = "content";
+const COMMENT_CREATED_AT: &str = "created_at";
+const COMMENT_EDITED_AT: &str = "edited_at";
+const COMMENT_PARENT_ID: &str = "parent_id";
+const COMMENT_IS_RESOLVED: &str = "is_resolved";
+const COMMENT_RESOLVED_BY: &str = "resolved_by";
+const COMMENT_REACTIONS: &str = "reactions";
 
-#[derive(Debug, Serialize, Deserialize)]
+/// A comment of a row. A comment with a `parent_id` is a reply to the comment with that id.
+/// Replies are always attached to the first comment of the thread, so a thread is one level deep.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RowComment {
-  uid: i64,
-  content: String,
+  #[serde(default)]
+  pub id: String,
+  pub uid: i64,
+  pub content: String,
   #[serde(deserialize_with = "deserialize_i64_from_numeric")]
-  created_at: i64,
+  pub created_at: i64,
+  /// The last time the content was edited. None if the comment was never edited.
+  #[serde(default)]
+  pub edited_at: Option<i64>,
+  #[serde(default)]
+  pub parent_id: Option<String>,
+  /// The reactions of the users with each emoji, ordered by the time of the reaction.
+  #[serde(default)]
+  pub reactions: HashMap<String, Vec<RowCommentReaction>>,
+  #[serde(default)]
+  pub is_resolved: bool,
+  #[serde(default)]
+  pub resolved_by: Option<i64>,
+}
+
+impl RowComment {
+  pub fn new(uid: i64, content: String) -> Self {
+    Self {
+      id: Uuid::new_v4().to_string(),
+      uid,
+      content,
+      created_at: timestamp(),
+      edited_at: None,
+      parent_id: None,
+      reactions: HashMap::new(),
+      is_resolved: false,
+      resolved_by: None,
+    }
+  }
+
+  pub fn with_parent(mut self, parent_id: String) -> Self {
+    self.parent_id = Some(parent_id);
+    self
+  }
+
+  pub fn is_reply(&self) -> bool {
+    self.parent_id.is_some()
+  }
+
+  /// Return the uids of the users that reacted with the emoji.
+  pub fn reaction_uids(&self, emoji: &str) -> Vec<i64> {
+    self
+      .reactions
+      .get(emoji)
+      .map(|reactions| reactions.iter().map(|reaction| reaction.uid).collect())
+      .unwrap_or_default()
+  }
+
+  pub(crate) fn from_map_ref<T: ReadTxn>(txn: &T, map_ref: &MapRef) -> Option<Self> {
+    let id = map_ref.get_str_with_txn(txn, COMMENT_ID)?;
+    let mut reactions = HashMap::new();
+    if let Some(reactions_map) = map_ref.get_map_with_txn(txn, COMMENT_REACTIONS) {
+      for (emoji, value) in reactions_map.iter(txn) {
+        let uids_map = match value.to_ymap() {
+          Some(uids_map) => uids_map.clone(),
+          None => continue,
+        };
+        // Each uid is stored with the time of the reaction
+        let mut emoji_reactions = uids_map
+          .iter(txn)
+          .filter_map(|(uid, value)| {
+            let reacted_at = match value.to_json(txn) {
+              Any::BigInt(reacted_at) => reacted_at,
+              Any::Number(reacted_at) => reacted_at as i64,
+              _ => return None,
+            };
+            Some(RowCommentReaction {
+              uid: uid.parse::<i64>().ok()?,
+              reacted_at,
+            })
+          })
+          .collect::<Vec<_>>();
+        if emoji_reactions.is_empty() {
+          continue;
+        }
+        emoji_reactions.sort_by_key(|reaction| (reaction.reacted_at, reaction.uid));
+        reactions.insert(emoji.to_string(), emoji_reactions);
+      }
+    }
+
+    Some(Self {
+      id,
+      uid: map_ref
+        .get_i64_with_txn(txn, COMMENT_UID)
+        .unwrap_or_default(),
+      content: map_ref
+        .get_str_with_txn(txn, COMMENT_CONTENT)
+        .unwrap_or_default(),
+      created_at: map_ref
+        .get_i64_with_txn(txn, COMMENT_CREATED_AT)
+        .unwrap_or_default(),
+      edited_at: map_ref.get_i64_with_txn(txn, COMMENT_EDITED_AT),
+      parent_id: map_ref
+        .get_str_with_txn(txn, COMMENT_PARENT_ID)
+        .filter(|parent_id| !parent_id.is_empty()),
+      reactions,
+      is_resolved: map_ref
+        .get_bool_with_txn(txn, COMMENT_IS_RESOLVED)
+        .unwrap_or(false),
+      resolved_by: map_ref.get_i64_with_txn(txn, COMMENT_RESOLVED_BY),
+    })
+  }
+
+  pub(crate) fn fill_map_ref(self, txn: &mut TransactionMut, map_ref: &MapRef) {
+    map_ref.insert_str_with_txn(txn, COMMENT_ID, self.id);
+    map_ref.insert_i64_with_txn(txn, COMMENT_UID, self.uid);
+    map_ref.insert_str_with_txn(txn, COMMENT_CONTENT, self.content);
+    map_ref.insert_i64_with_txn(txn, COMMENT_CREATED_AT, self.created_at);
+    if let Some(edited_at) = self.edited_at {
+      map_ref.insert_i64_with_txn(txn, COMMENT_EDITED_AT, edited_at);
+    }
+    if let Some(parent_id) = self.parent_id {
+      map_ref.insert_str_with_txn(txn, COMMENT_PARENT_ID, parent_id);
+    }
+    map_ref.insert_bool_with_txn(txn, COMMENT_IS_RESOLVED, self.is_resolved);
+    if let Some(resolved_by) = self.resolved_by {
+      map_ref.insert_i64_with_txn(txn, COMMENT_RESOLVED_BY, resolved_by);
+    }
+    let reactions_map = map_ref.get_or_create_map_with_txn(txn, COMMENT_REACTIONS);
+    for (emoji, emoji_reactions) in self.reactions {
+      let uids_map = reactions_map.get_or_create_map_with_txn(txn, &emoji);
+      for reaction in emoji_reactions {
+        uids_map.insert_i64_with_txn(txn, &reaction.uid.to_string(), reaction.reacted_at);
+      }
+    }
+  }
+}
+
+/// The reaction of a user to a [RowComment].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RowCommentReaction {
+  pub uid: i64,
+  /// The time of the reaction in seconds.
+  #[serde(deserialize_with = "deserialize_i64_from_numeric")]
+  pub reacted_at: i64,
+}
+
+impl RowCommentReaction {
+  pub fn new(uid: i64) -> Self {
+    Self {
+      uid,
+      reacted_at: timestamp(),
+    }
+  }
 }
 
 impl TryFrom<Any> for RowComment {
@@ -27,3 +193,116 @@ impl From<RowComment> for Any {
     Any::from_json(&json).unwrap()
   }
 }
+
+/// A comment with its replies.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowCommentThread {
+  pub comment: RowComment,
+  /// The replies in the order that they were added.
+  pub replies: Vec<RowComment>,
+}
+
+impl RowCommentThread {
+  /// Group the comments into threads. The threads are in the order that their first comments
+  /// were added. Replies whose comment doesn't exist anymore are dropped.
+  pub fn from_comments(comments: Vec<RowComment>) -> Vec<Self> {
+    let (comments, replies): (Vec<_>, Vec<_>) = comments
+      .into_iter()
+      .partition(|comment| !comment.is_reply());
+    let mut threads = comments
+      .into_iter()
+      .map(|comment| Self {
+        comment,
+        replies: vec![],
+      })
+      .collect::<Vec<_>>();
+    for reply in replies {
+      if let Some(thread) = threads
+        .iter_mut()
+        .find(|thread| Some(&thread.comment.id) == reply.parent_id.as_ref())
+      {
+        thread.replies.push(reply);
+      }
+    }
+    threads
+  }
+}
+
+/// Updates a [RowComment] that is stored in a [MapRef].
+pub struct RowCommentUpdate<'a, 'b> {
+  map_ref: MapRef,
+  txn: &'a mut TransactionMut<'b>,
+}
+
+impl<'a, 'b> RowCommentUpdate<'a, 'b> {
+  pub(crate) fn new(txn: &'a mut TransactionMut<'b>, map_ref: MapRef) -> Self {
+    Self { map_ref, txn }
+  }
+
+  /// Replace the content and set the edit timestamp.
+  pub fn set_content(self, content: String) -> Self {
+    self
+      .map_ref
+      .insert_str_with_txn(self.txn, COMMENT_CONTENT, content);
+    self
+      .map_ref
+      .insert_i64_with_txn(self.txn, COMMENT_EDITED_AT, timestamp());
+    self
+  }
+
+  /// Mark the comment as resolved by the user or reopen it.
+  pub fn set_resolved(self, uid: i64, is_resolved: bool) -> Self {
+    self
+      .map_ref
+      .insert_bool_with_txn(self.txn, COMMENT_IS_RESOLVED, is_resolved);
+    if is_resolved {
+      self
+        .map_ref
+        .insert_i64_with_txn(self.txn, COMMENT_RESOLVED_BY, uid);
+    } else {
+      self.map_ref.delete_with_txn(self.txn, COMMENT_RESOLVED_BY);
+    }
+    self
+  }
+
+  /// Add the user's reaction. Each user reacts at most once with the same emoji. Each user writes
+  /// its own key under the emoji, so reactions of different users don't overwrite each other.
+  pub fn add_reaction(self, uid: i64, emoji: &str) -> Self {
+    let reactions_map = self
+      .map_ref
+      .get_or_create_map_with_txn(self.txn, COMMENT_REACTIONS);
+    let uids_map = reactions_map.get_or_create_map_with_txn(self.txn, emoji);
+    let key = uid.to_string();
+    if uids_map.get(self.txn, &key).is_none() {
+      uids_map.insert_i64_with_txn(self.txn, &key, timestamp());
+    }
+    self
+  }
+
+  pub fn remove_reaction(self, uid: i64, emoji: &str) -> Self {
+    let uids_map = self
+      .map_ref
+      .get_map_with_txn(self.txn, COMMENT_REACTIONS)
+      .and_then(|reactions_map| reactions_map.get_map_with_txn(self.txn, emoji));
+    if let Some(uids_map) = uids_map {
+      uids_map.delete_with_txn(self.txn, &uid.to_string());
+    }
+    self
+  }
+}
+
+/// Return the index and the [MapRef] of the comment in the comments array.
+pub(crate) fn comment_map_ref<T: ReadTxn>(
+  txn: &T,
+  comments: &ArrayRef,
+  comment_id: &str,
+) -> Option<(u32, MapRef)> {
+  let index = comments.position_with_txn(txn, comment_id, COMMENT_ID)?;
+  let map_ref = comments.get(txn, index)?.to_ymap()?.clone();
+  Some((index, map_ref))
+}
+
+pub(crate) fn comment_from_value<T: ReadTxn>(txn: &T, value: YrsValue) -> Option<RowComment> {
+  let map_ref = value.to_ymap()?;
+  RowComment::from_map_ref(txn, map_ref)
+}
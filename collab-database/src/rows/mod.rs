@@ -1,14 +1,24 @@
 pub use cell::*;
 pub use cell_builder::*;
+pub(crate) use cell_text::*;
+pub use cell_value::*;
 pub use comment::*;
 pub use row::*;
+pub use row_history::*;
 pub use row_id::*;
 pub use row_meta::*;
 pub use row_observer::*;
+pub use row_page::*;
+pub use row_template::*;
 mod cell;
 mod cell_builder;
+mod cell_text;
+mod cell_value;
 mod comment;
 mod row;
+mod row_history;
 mod row_id;
 mod row_meta;
 mod row_observer;
+mod row_page;
+mod row_template;
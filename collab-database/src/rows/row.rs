@@ -1,13 +1,18 @@
 use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Weak};
 
-use collab::core::collab::MutexCollab;
+use collab::core::collab::{MutexCollab, TransactionMutExt, DATA_SECTION};
+use collab::preclude::updates::decoder::Decode;
 use collab::preclude::{
-  Any, ArrayRefWrapper, Collab, DeepEventsSubscription, Map, MapPrelim, MapRef, MapRefExtension,
-  MapRefWrapper, ReadTxn, Transaction, TransactionMut, YrsValue,
+  Any, Array, ArrayRefWrapper, Collab, DeepEventsSubscription, Doc, Map, MapPrelim, MapRef,
+  MapRefExtension, MapRefWrapper, OffsetKind, Options, ReadTxn, StateVector, Transact, Transaction,
+  TransactionMut, Update, YrsValue,
 };
 use parking_lot::Mutex;
 
+use collab::core::array_wrapper::ArrayRefExtension;
+use collab::core::origin::CollabOrigin;
 use collab::core::value::YrsValueExtension;
 use collab::error::CollabError;
 use collab_entity::define::DATABASE_ROW_DATA;
@@ -22,8 +27,9 @@ use uuid::Uuid;
 use crate::database::timestamp;
 use crate::error::DatabaseError;
 use crate::rows::{
-  subscribe_row_data_change, Cell, Cells, CellsUpdate, RowChangeSender, RowId, RowMeta,
-  RowMetaUpdate,
+  comment_from_value, comment_map_ref, subscribe_row_comment_change, subscribe_row_data_change,
+  Cell, CellChangeRecord, Cells, CellsUpdate, RowChangeSender, RowComment, RowCommentUpdate, RowId,
+  RowMeta, RowMetaUpdate,
 };
 use crate::views::{OrderObjectPosition, RowOrder};
 use crate::{impl_bool_update, impl_i32_update, impl_i64_update};
@@ -32,6 +38,9 @@ pub type BlockId = i64;
 
 const META: &str = "meta";
 const COMMENT: &str = "comment";
+const HISTORY: &str = "history";
+/// The maximum number of [CellChangeRecord]s that are kept in the history of a row.
+pub const MAX_HISTORY_LEN: u32 = 200;
 pub const LAST_MODIFIED: &str = "last_modified";
 pub const CREATED_AT: &str = "created_at";
 
@@ -42,11 +51,14 @@ pub struct DatabaseRow {
   collab: Arc<MutexCollab>,
   data: MapRefWrapper,
   meta: MapRefWrapper,
-  #[allow(dead_code)]
   comments: ArrayRefWrapper,
   collab_db: Weak<CollabKVDB>,
+  /// Mutes the [RowChange](crate::rows::RowChange)s of the row's data while it's true.
+  change_muted: Arc<AtomicBool>,
   #[allow(dead_code)]
   subscription: DeepEventsSubscription,
+  #[allow(dead_code)]
+  comment_subscription: DeepEventsSubscription,
 }
 
 impl DatabaseRow {
@@ -58,12 +70,13 @@ impl DatabaseRow {
     collab: Arc<MutexCollab>,
     change_tx: RowChangeSender,
   ) -> Self {
-    let (mut data, meta, comments) = {
+    let (mut data, meta, mut comments) = {
       let collab_guard = collab.lock();
       collab_guard.with_origin_transact_mut(|txn| {
         let data = collab_guard.insert_map_with_txn_if_not_exist(txn, DATABASE_ROW_DATA);
         let meta = collab_guard.insert_map_with_txn_if_not_exist(txn, META);
         let comments = collab_guard.create_array_with_txn::<MapPrelim<Any>>(txn, COMMENT, vec![]);
+        collab_guard.create_array_with_txn::<Any>(txn, HISTORY, vec![]);
         if let Some(row) = row {
           RowBuilder::new(txn, data.clone().into_inner(), meta.clone().into_inner())
             .update(|update| {
@@ -81,7 +94,15 @@ impl DatabaseRow {
         (data, meta, comments)
       })
     };
-    let subscription = subscribe_row_data_change(row_id.clone(), &mut data, change_tx);
+    let change_muted = Arc::new(AtomicBool::new(false));
+    let subscription = subscribe_row_data_change(
+      row_id.clone(),
+      &mut data,
+      change_tx.clone(),
+      change_muted.clone(),
+    );
+    let comment_subscription =
+      subscribe_row_comment_change(row_id.clone(), &mut comments, change_tx);
     Self {
       uid,
       row_id,
@@ -90,7 +111,9 @@ impl DatabaseRow {
       meta,
       comments,
       collab_db,
+      change_muted,
       subscription,
+      comment_subscription,
     }
   }
 
@@ -102,8 +125,16 @@ impl DatabaseRow {
     change_tx: RowChangeSender,
   ) -> Result<Self, CollabError> {
     match Self::create_row_struct(&collab)? {
-      Some((mut data, meta, comments)) => {
-        let subscription = subscribe_row_data_change(row_id.clone(), &mut data, change_tx);
+      Some((mut data, meta, mut comments)) => {
+        let change_muted = Arc::new(AtomicBool::new(false));
+        let subscription = subscribe_row_data_change(
+          row_id.clone(),
+          &mut data,
+          change_tx.clone(),
+          change_muted.clone(),
+        );
+        let comment_subscription =
+          subscribe_row_comment_change(row_id.clone(), &mut comments, change_tx);
         Ok(Self {
           uid,
           row_id,
@@ -112,7 +143,9 @@ impl DatabaseRow {
           meta,
           comments,
           collab_db,
+          change_muted,
           subscription,
+          comment_subscription,
         })
       },
       None => Ok(Self::create(
@@ -186,16 +219,236 @@ impl DatabaseRow {
       Some(guard) => {
         trace!("updating row: {}", self.row_id);
         guard.with_origin_transact_mut(|txn| {
+          let old_cells = cells_from_map_ref(&self.data, txn);
           let mut update = RowUpdate::new(txn, &self.data, &self.meta);
 
           // Update the last modified timestamp before we call the update function.
-          update = update.set_last_modified(timestamp());
-          f(update)
+          let timestamp = timestamp();
+          update = update.set_last_modified(timestamp);
+          f(update);
+
+          let new_cells = cells_from_map_ref(&self.data, txn);
+          self.record_history(&guard, txn, &old_cells, &new_cells, timestamp);
         });
       },
     }
   }
 
+  /// Update the row without sending the [RowChange](crate::rows::RowChange)s of the update. The
+  /// batch operations of the database use it and send one aggregated change instead.
+  pub(crate) fn update_silently<F>(&self, f: F)
+  where
+    F: FnOnce(RowUpdate),
+  {
+    self.change_muted.store(true, Ordering::Release);
+    self.update(f);
+    self.change_muted.store(false, Ordering::Release);
+  }
+
+  /// Update the row if its cells pass the validation, which gets the cells before and after the
+  /// update. The update is applied to a copy of the row's document first, and it's only applied
+  /// to the row if the validation passes. Otherwise, the row is not touched and the error is
+  /// returned.
+  pub fn update_with_validation<F, V>(&self, f: F, validate: V) -> Result<(), DatabaseError>
+  where
+    F: FnOnce(RowUpdate),
+    V: FnOnce(&Cells, &Cells) -> Result<(), DatabaseError>,
+  {
+    let guard = self.collab.try_lock().ok_or_else(|| {
+      DatabaseError::Internal(anyhow::anyhow!("failed to acquire lock for updating row"))
+    })?;
+    trace!("updating row with validation: {}", self.row_id);
+    let (state_vector, doc_state, old_cells) = {
+      let txn = guard.transact();
+      (
+        txn.state_vector(),
+        txn.encode_state_as_update_v1(&StateVector::default()),
+        cells_from_map_ref(&self.data, &txn),
+      )
+    };
+
+    // The copy has the client id of the row's document, so the update creates the same items in
+    // both of them.
+    let copy = Doc::with_options(Options {
+      client_id: guard.get_doc().client_id(),
+      offset_kind: OffsetKind::Utf16,
+      skip_gc: true,
+      ..Options::default()
+    });
+    let copy_root = copy.get_or_insert_map(DATA_SECTION);
+    let timestamp = timestamp();
+    let new_cells = {
+      let mut txn = copy.transact_mut();
+      txn
+        .try_apply_update(decode_update(&doc_state)?)
+        .map_err(|err| DatabaseError::Internal(anyhow::anyhow!("{}", err)))?;
+      let data = copy_root.get(&txn, DATABASE_ROW_DATA);
+      let meta = copy_root.get(&txn, META);
+      let (data, meta) = match (data, meta) {
+        (Some(YrsValue::YMap(data)), Some(YrsValue::YMap(meta))) => (data, meta),
+        _ => return Err(DatabaseError::NoRequiredData),
+      };
+      let mut update = RowUpdate::new(&mut txn, &data, &meta);
+      update = update.set_last_modified(timestamp);
+      f(update);
+      cells_from_map_ref(&data, &txn)
+    };
+    validate(&old_cells, &new_cells)?;
+
+    let update = copy.transact().encode_state_as_update_v1(&state_vector);
+    guard.with_origin_transact_mut(|txn| {
+      txn
+        .try_apply_update(decode_update(&update)?)
+        .map_err(|err| DatabaseError::Internal(anyhow::anyhow!("{}", err)))?;
+      self.record_history(&guard, txn, &old_cells, &new_cells, timestamp);
+      Ok(())
+    })
+  }
+
+  /// Record the changed cells in the row's history. The oldest records are removed when there
+  /// are more than [MAX_HISTORY_LEN] of them.
+  fn record_history(
+    &self,
+    collab: &Collab,
+    txn: &mut TransactionMut,
+    old_cells: &Cells,
+    new_cells: &Cells,
+    timestamp: i64,
+  ) {
+    let uid = CollabOrigin::from(&*txn).client_user_id();
+    let records = CellChangeRecord::diff(old_cells, new_cells, uid, timestamp);
+    if records.is_empty() {
+      return;
+    }
+    // The history is created with the row. The rows that were created before the history was
+    // introduced don't have one, and it's not created here, because the arrays created by two
+    // clients at the same time would overwrite each other.
+    let history = match collab.get_array_with_txn(txn, vec![HISTORY]) {
+      None => return,
+      Some(history) => history,
+    };
+    for record in records {
+      history.push_back(txn, Any::from(record));
+    }
+    let len = history.len(txn);
+    if len > MAX_HISTORY_LEN {
+      history.remove_range(txn, 0, len - MAX_HISTORY_LEN);
+    }
+  }
+
+  /// Return the changes of the row's cells, from the oldest to the newest.
+  pub fn get_history(&self) -> Vec<CellChangeRecord> {
+    let collab = self.collab.lock();
+    let txn = collab.transact();
+    match collab.get_array_with_txn(&txn, vec![HISTORY]) {
+      None => vec![],
+      Some(history) => history
+        .iter(&txn)
+        .flat_map(|value| CellChangeRecord::from_value(&txn, value))
+        .collect(),
+    }
+  }
+
+  /// Return the comments of the row in the order that they were added.
+  pub fn get_comments(&self) -> Vec<RowComment> {
+    let collab = self.collab.lock();
+    let txn = collab.transact();
+    self
+      .comments
+      .iter(&txn)
+      .flat_map(|value| comment_from_value(&txn, value))
+      .collect()
+  }
+
+  /// Add the comment as the current user. A reply to a reply is attached to the first comment of
+  /// the thread.
+  pub fn add_comment(&self, mut comment: RowComment) -> Result<(), DatabaseError> {
+    comment.uid = self.uid;
+    let collab = self.collab.lock();
+    collab.with_origin_transact_mut(|txn| {
+      if let Some(parent_id) = comment.parent_id.take() {
+        let parent = comment_map_ref(txn, &self.comments, &parent_id)
+          .and_then(|(_, map_ref)| RowComment::from_map_ref(txn, &map_ref))
+          .ok_or(DatabaseError::CommentNotExist)?;
+        comment.parent_id = Some(parent.parent_id.unwrap_or(parent.id));
+      }
+      let map_ref = self.comments.insert_map_with_txn(txn, None);
+      comment.fill_map_ref(txn, &map_ref);
+      Ok(())
+    })
+  }
+
+  pub fn update_comment<F>(&self, comment_id: &str, f: F) -> Result<(), DatabaseError>
+  where
+    F: FnOnce(RowCommentUpdate),
+  {
+    let collab = self.collab.lock();
+    collab.with_origin_transact_mut(|txn| {
+      let (_, map_ref) =
+        comment_map_ref(txn, &self.comments, comment_id).ok_or(DatabaseError::CommentNotExist)?;
+      f(RowCommentUpdate::new(txn, map_ref));
+      Ok(())
+    })
+  }
+
+  pub fn edit_comment(&self, comment_id: &str, content: String) -> Result<(), DatabaseError> {
+    self.update_comment(comment_id, |update| {
+      update.set_content(content);
+    })
+  }
+
+  /// Add the current user's reaction to the comment.
+  pub fn add_comment_reaction(&self, comment_id: &str, emoji: &str) -> Result<(), DatabaseError> {
+    self.update_comment(comment_id, |update| {
+      update.add_reaction(self.uid, emoji);
+    })
+  }
+
+  /// Remove the current user's reaction from the comment.
+  pub fn remove_comment_reaction(
+    &self,
+    comment_id: &str,
+    emoji: &str,
+  ) -> Result<(), DatabaseError> {
+    self.update_comment(comment_id, |update| {
+      update.remove_reaction(self.uid, emoji);
+    })
+  }
+
+  /// Resolve the comment as the current user or reopen it.
+  pub fn resolve_comment(&self, comment_id: &str, is_resolved: bool) -> Result<(), DatabaseError> {
+    self.update_comment(comment_id, |update| {
+      update.set_resolved(self.uid, is_resolved);
+    })
+  }
+
+  /// Delete the comment and its replies.
+  pub fn delete_comment(&self, comment_id: &str) -> Result<(), DatabaseError> {
+    let collab = self.collab.lock();
+    collab.with_origin_transact_mut(|txn| {
+      let (index, _) =
+        comment_map_ref(txn, &self.comments, comment_id).ok_or(DatabaseError::CommentNotExist)?;
+      let mut indexes = self
+        .comments
+        .iter(txn)
+        .enumerate()
+        .filter(|(_, value)| {
+          comment_from_value(txn, value.clone())
+            .map(|comment| comment.parent_id.as_deref() == Some(comment_id))
+            .unwrap_or(false)
+        })
+        .map(|(index, _)| index as u32)
+        .collect::<Vec<_>>();
+      indexes.push(index);
+      // Remove from the end so that the remaining indexes stay valid
+      indexes.sort_unstable_by(|a, b| b.cmp(a));
+      for index in indexes {
+        self.comments.remove(txn, index);
+      }
+      Ok(())
+    })
+  }
+
   pub fn update_meta<F>(&self, f: F)
   where
     F: FnOnce(RowMetaUpdate),
@@ -212,6 +465,26 @@ impl DatabaseRow {
       })
   }
 
+  /// Save the pending changes of the row to the local disk.
+  pub fn flush(&self) {
+    self.collab.lock().flush();
+  }
+
+  /// Return the estimated size of the row: the number of items that were inserted into its
+  /// document. It grows with the edits of the row like the size of the document, but unlike the
+  /// size, it's read from the state vector without encoding the document. None if the row is in
+  /// use.
+  pub fn estimated_size(&self) -> Option<usize> {
+    let collab = self.collab.try_lock()?;
+    let txn = collab.try_transaction().ok()?;
+    let size = txn
+      .state_vector()
+      .iter()
+      .map(|(_, clock)| *clock as usize)
+      .sum();
+    Some(size)
+  }
+
   pub fn delete(&self) {
     match self.collab_db.upgrade() {
       None => {
@@ -507,6 +780,24 @@ pub fn row_id_from_map_ref<T: ReadTxn>(txn: &T, map_ref: &MapRef) -> Option<RowI
   map_ref.get_str_with_txn(txn, ROW_ID).map(RowId::from)
 }
 
+fn decode_update(update: &[u8]) -> Result<Update, DatabaseError> {
+  Update::decode_v1(update).map_err(|err| DatabaseError::Internal(anyhow::anyhow!("{}", err)))
+}
+
+fn cells_from_map_ref<T: ReadTxn>(map_ref: &MapRef, txn: &T) -> Cells {
+  map_ref
+    .get_map_with_txn(txn, ROW_CELLS)
+    .map(|map_ref| (txn, &map_ref).into())
+    .unwrap_or_default()
+}
+
+/// Return the [Row] of the row's collab.
+pub(crate) fn row_from_collab<T: ReadTxn>(collab: &Collab, txn: &T) -> Option<Row> {
+  let data = collab.get_map_with_txn(txn, vec![DATABASE_ROW_DATA])?;
+  let meta = collab.get_map_with_txn(txn, vec![META])?;
+  row_from_map_ref(&data, &meta, txn)
+}
+
 /// Return a [Row] from a [MapRef]
 pub fn row_from_map_ref<T: ReadTxn>(map_ref: &MapRef, _meta_ref: &MapRef, txn: &T) -> Option<Row> {
   let id = RowId::from(map_ref.get_str_with_txn(txn, ROW_ID)?);
@@ -528,10 +819,7 @@ pub fn row_from_map_ref<T: ReadTxn>(map_ref: &MapRef, _meta_ref: &MapRef, txn: &
     .get_i64_with_txn(txn, LAST_MODIFIED)
     .unwrap_or_else(|| chrono::Utc::now().timestamp());
 
-  let cells = map_ref
-    .get_map_with_txn(txn, ROW_CELLS)
-    .map(|map_ref| (txn, &map_ref).into())
-    .unwrap_or_default();
+  let cells = cells_from_map_ref(map_ref, txn);
 
   Some(Row {
     id,
@@ -553,6 +841,14 @@ pub struct CreateRowParams {
   pub visibility: bool,
   #[serde(skip)]
   pub row_position: OrderObjectPosition,
+  /// The id of the group that the row is created in. The row gets the value of the group in the
+  /// grouping field when it is created in a view.
+  #[serde(skip)]
+  pub group_id: Option<String>,
+  /// Fill the cells that the filters of the view imply when the row is created in a view, so the
+  /// row is visible in the view. The cells of the params take precedence.
+  #[serde(skip)]
+  pub fill_filter_cells: bool,
   pub created_at: i64,
   pub modified_at: i64,
 }
@@ -587,6 +883,8 @@ impl CreateRowParams {
       height: 60,
       visibility: true,
       row_position: OrderObjectPosition::default(),
+      group_id: None,
+      fill_filter_cells: false,
       created_at: timestamp,
       modified_at: timestamp,
     }
@@ -610,6 +908,16 @@ impl CreateRowParams {
     self.row_position = row_position;
     self
   }
+
+  pub fn with_group_id(mut self, group_id: String) -> Self {
+    self.group_id = Some(group_id);
+    self
+  }
+
+  pub fn with_filter_cells(mut self, fill_filter_cells: bool) -> Self {
+    self.fill_filter_cells = fill_filter_cells;
+    self
+  }
 }
 
 impl From<CreateRowParams> for Row {
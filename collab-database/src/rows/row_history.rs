@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use collab::preclude::{Any, ReadTxn, YrsValue};
+
+use crate::rows::{Cell, Cells, CREATED_AT, LAST_MODIFIED};
+
+const HISTORY_FIELD_ID: &str = "field_id";
+const HISTORY_UID: &str = "uid";
+const HISTORY_TIMESTAMP: &str = "timestamp";
+const HISTORY_OLD_CELL: &str = "old_cell";
+const HISTORY_NEW_CELL: &str = "new_cell";
+
+/// A change of a [Cell] in a row. The records are stored in the row's collab, so they are synced
+/// to every client along with the row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellChangeRecord {
+  pub field_id: String,
+  /// The uid of the user that made the change. None if the change was not made by a client,
+  /// for example, by the server.
+  pub uid: Option<i64>,
+  /// The time of the change in seconds.
+  pub timestamp: i64,
+  /// The cell before the change. None if the cell didn't exist.
+  pub old_cell: Option<Cell>,
+  /// The cell after the change. None if the cell was removed.
+  pub new_cell: Option<Cell>,
+}
+
+impl CellChangeRecord {
+  /// Return a record for each cell that is different between the old and the new cells. The
+  /// records are sorted by field id.
+  pub(crate) fn diff(
+    old_cells: &Cells,
+    new_cells: &Cells,
+    uid: Option<i64>,
+    timestamp: i64,
+  ) -> Vec<Self> {
+    let mut field_ids = old_cells.keys().chain(new_cells.keys()).collect::<Vec<_>>();
+    field_ids.sort();
+    field_ids.dedup();
+
+    field_ids
+      .into_iter()
+      .filter_map(|field_id| {
+        // A cleared cell is an empty map
+        let old_cell = old_cells.get(field_id).filter(|cell| !cell.is_empty());
+        let new_cell = new_cells.get(field_id).filter(|cell| !cell.is_empty());
+        if is_same_cell(old_cell, new_cell) {
+          return None;
+        }
+        Some(Self {
+          field_id: field_id.clone(),
+          uid,
+          timestamp,
+          old_cell: old_cell.map(cell_content),
+          new_cell: new_cell.map(cell_content),
+        })
+      })
+      .collect()
+  }
+
+  pub(crate) fn from_value<T: ReadTxn>(txn: &T, value: YrsValue) -> Option<Self> {
+    match value.to_json(txn) {
+      Any::Map(map) => Self::try_from(Any::Map(map)).ok(),
+      _ => None,
+    }
+  }
+}
+
+/// Return true if the cells have the same content.
+fn is_same_cell(a: Option<&Cell>, b: Option<&Cell>) -> bool {
+  match (a, b) {
+    (None, None) => true,
+    (Some(a), Some(b)) => cell_content(a) == cell_content(b),
+    _ => false,
+  }
+}
+
+/// Return the cell without its timestamps. They are updated whenever a cell is written, even if
+/// its content doesn't change, so they are neither compared nor stored in the history.
+fn cell_content(cell: &Cell) -> Cell {
+  let content = cell
+    .iter()
+    .filter(|(key, _)| key.as_str() != CREATED_AT && key.as_str() != LAST_MODIFIED)
+    .map(|(key, value)| (key.clone(), value.clone()))
+    .collect::<HashMap<_, _>>();
+  Cell::from(Any::Map(Arc::new(content)))
+}
+
+impl TryFrom<Any> for CellChangeRecord {
+  type Error = anyhow::Error;
+
+  fn try_from(value: Any) -> Result<Self, Self::Error> {
+    let map = match value {
+      Any::Map(map) => map,
+      _ => return Err(anyhow::anyhow!("the cell change record is not a map")),
+    };
+    let field_id = match map.get(HISTORY_FIELD_ID) {
+      Some(Any::String(field_id)) => field_id.to_string(),
+      _ => return Err(anyhow::anyhow!("the cell change record has no field id")),
+    };
+    let get_i64 = |key: &str| match map.get(key) {
+      Some(Any::BigInt(value)) => Some(*value),
+      Some(Any::Number(value)) => Some(*value as i64),
+      _ => None,
+    };
+    let get_cell = |key: &str| match map.get(key) {
+      Some(value @ Any::Map(_)) => Some(Cell::from(value)),
+      _ => None,
+    };
+    Ok(Self {
+      field_id,
+      uid: get_i64(HISTORY_UID),
+      timestamp: get_i64(HISTORY_TIMESTAMP).unwrap_or_default(),
+      old_cell: get_cell(HISTORY_OLD_CELL),
+      new_cell: get_cell(HISTORY_NEW_CELL),
+    })
+  }
+}
+
+impl From<CellChangeRecord> for Any {
+  fn from(record: CellChangeRecord) -> Self {
+    let mut map = HashMap::new();
+    map.insert(
+      HISTORY_FIELD_ID.to_string(),
+      Any::String(Arc::from(record.field_id)),
+    );
+    map.insert(
+      HISTORY_UID.to_string(),
+      record.uid.map(Any::BigInt).unwrap_or(Any::Null),
+    );
+    map.insert(HISTORY_TIMESTAMP.to_string(), Any::BigInt(record.timestamp));
+    map.insert(
+      HISTORY_OLD_CELL.to_string(),
+      record.old_cell.map(Any::from).unwrap_or(Any::Null),
+    );
+    map.insert(
+      HISTORY_NEW_CELL.to_string(),
+      record.new_cell.map(Any::from).unwrap_or(Any::Null),
+    );
+    Any::Map(Arc::new(map))
+  }
+}
@@ -1,13 +1,21 @@
-use crate::rows::{Cell, Row, RowId, ROW_CELLS, ROW_HEIGHT, ROW_VISIBILITY};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::rows::{
+  comment_from_value, Cell, Row, RowComment, RowId, ROW_CELLS, ROW_HEIGHT, ROW_VISIBILITY,
+};
 use collab::core::value::YrsValueExtension;
 
 use collab::preclude::{
-  DeepEventsSubscription, DeepObservable, EntryChange, Event, MapRefWrapper, TransactionMut,
+  Array, ArrayRef, ArrayRefWrapper, DeepEventsSubscription, DeepObservable, EntryChange, Event,
+  MapRefWrapper, ReadTxn, TransactionMut,
 };
 use collab::preclude::{PathSegment, ToJson};
 use std::ops::Deref;
 
 use collab::preclude::map::MapEvent;
+use parking_lot::Mutex;
 use tokio::sync::broadcast;
 use tracing::trace;
 
@@ -32,14 +40,56 @@ pub enum RowChange {
   DidUpdateRowComment {
     row: Row,
   },
+  /// A comment of the row was added, updated or deleted.
+  DidChangeRowComment {
+    row_id: RowId,
+    change: RowCommentChange,
+  },
+  /// The rows were changed by a batch operation of the database. The changes of each row are not
+  /// sent separately.
+  DidBatchUpdate(RowBatchChange),
 }
 
+/// The aggregated change of a batch operation, see
+/// [Database::batch_create_rows](crate::database::Database::batch_create_rows) and its siblings.
+#[derive(Debug, Clone)]
+pub enum RowBatchChange {
+  DidCreateRows {
+    view_id: String,
+    row_ids: Vec<RowId>,
+  },
+  /// The cells of the rows were updated. Each row comes with the ids of its updated fields.
+  DidUpdateCells {
+    cells: Vec<(RowId, Vec<String>)>,
+  },
+  DidDeleteRows {
+    row_ids: Vec<RowId>,
+  },
+  DidMoveRows {
+    view_id: String,
+    row_ids: Vec<RowId>,
+  },
+}
+
+/// A change of a row's comments. The changes are sent for both local and remote updates.
+#[derive(Debug, Clone)]
+pub enum RowCommentChange {
+  DidAddComment { comment: RowComment },
+  DidUpdateComment { comment: RowComment },
+  DidDeleteComment { comment_id: String },
+}
+
+/// Observe the data of the row. No change is sent while `muted` is true.
 pub(crate) fn subscribe_row_data_change(
   row_id: RowId,
   row_data_map: &mut MapRefWrapper,
   change_tx: RowChangeSender,
+  muted: Arc<AtomicBool>,
 ) -> DeepEventsSubscription {
   row_data_map.observe_deep(move |txn, events| {
+    if muted.load(Ordering::Acquire) {
+      return;
+    }
     for event in events.iter() {
       // trace!(
       //   "row observe event: {:?}, {:?}",
@@ -59,6 +109,71 @@ pub(crate) fn subscribe_row_data_change(
   })
 }
 
+/// Observe the comments of the row. Yrs doesn't tell which elements were removed from an array,
+/// so the comments are compared with the ones from the last event to find out what changed.
+pub(crate) fn subscribe_row_comment_change(
+  row_id: RowId,
+  comments: &mut ArrayRefWrapper,
+  change_tx: RowChangeSender,
+) -> DeepEventsSubscription {
+  let comments_ref = comments.clone().into_inner();
+  let last_comments = Arc::new(Mutex::new(read_comments(
+    &comments_ref,
+    &comments.transact(),
+  )));
+  comments.observe_deep(move |txn, _events| {
+    let new_comments = read_comments(&comments_ref, txn);
+    let mut last_comments = last_comments.lock();
+    for change in diff_comments(&last_comments, &new_comments) {
+      let _ = change_tx.send(RowChange::DidChangeRowComment {
+        row_id: row_id.clone(),
+        change,
+      });
+    }
+    *last_comments = new_comments;
+  })
+}
+
+fn read_comments<T: ReadTxn>(comments: &ArrayRef, txn: &T) -> Vec<RowComment> {
+  comments
+    .iter(txn)
+    .flat_map(|value| comment_from_value(txn, value))
+    .collect()
+}
+
+fn diff_comments(old: &[RowComment], new: &[RowComment]) -> Vec<RowCommentChange> {
+  let old_by_id = old
+    .iter()
+    .map(|comment| (comment.id.as_str(), comment))
+    .collect::<HashMap<_, _>>();
+  let new_by_id = new
+    .iter()
+    .map(|comment| (comment.id.as_str(), comment))
+    .collect::<HashMap<_, _>>();
+
+  let mut changes = old
+    .iter()
+    .filter(|comment| !new_by_id.contains_key(comment.id.as_str()))
+    .map(|comment| RowCommentChange::DidDeleteComment {
+      comment_id: comment.id.clone(),
+    })
+    .collect::<Vec<_>>();
+  for comment in new {
+    match old_by_id.get(comment.id.as_str()) {
+      None => changes.push(RowCommentChange::DidAddComment {
+        comment: comment.clone(),
+      }),
+      Some(old_comment) if *old_comment != comment => {
+        changes.push(RowCommentChange::DidUpdateComment {
+          comment: comment.clone(),
+        })
+      },
+      Some(_) => {},
+    }
+  }
+  changes
+}
+
 fn handle_map_event(
   row_id: &RowId,
   change_tx: &RowChangeSender,
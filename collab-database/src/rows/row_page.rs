@@ -0,0 +1,37 @@
+use crate::rows::{Row, RowId};
+
+/// The position in a view's rows where a [RowPage] starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RowCursor {
+  /// Start at the row with the given index.
+  Offset(usize),
+  /// Start after the row with the given id. Unlike [RowCursor::Offset], the page doesn't shift
+  /// when rows are inserted or removed before the cursor.
+  After(RowId),
+}
+
+impl Default for RowCursor {
+  fn default() -> Self {
+    Self::Offset(0)
+  }
+}
+
+/// A window of the rows of a view.
+#[derive(Debug, Clone)]
+pub struct RowPage {
+  /// The rows in the order of the view. A row that is still being fetched from the remote is
+  /// returned as an empty row, see [Row::empty].
+  pub rows: Vec<Row>,
+  /// The index of the first row of the page in the view.
+  pub offset: usize,
+  /// The number of rows in the view.
+  pub total: usize,
+  /// The cursor of the next page. None if this is the last page.
+  pub next_cursor: Option<RowCursor>,
+}
+
+impl RowPage {
+  pub fn has_more(&self) -> bool {
+    self.next_cursor.is_some()
+  }
+}
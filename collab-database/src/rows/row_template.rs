@@ -0,0 +1,287 @@
+use collab::core::value::YrsValueExtension;
+use collab::preclude::{Map, MapRef, MapRefExtension, MapRefWrapper, ReadTxn, TransactionMut};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::database::timestamp;
+use crate::rows::{Cells, RowId};
+use crate::views::RowOrder;
+
+const TEMPLATES: &str = "templates";
+const TEMPLATE_ID: &str = "id";
+const TEMPLATE_NAME: &str = "name";
+const TEMPLATE_CELLS: &str = "cells";
+const TEMPLATE_DOCUMENT: &str = "document";
+const TEMPLATE_ICON_URL: &str = "icon_url";
+const TEMPLATE_COVER_URL: &str = "cover_url";
+const TEMPLATE_CREATED_AT: &str = "created_at";
+const TEMPLATE_LAST_MODIFIED: &str = "last_modified";
+
+/// A template for the rows of a database. A row that is created from a template starts with the
+/// template's cells, icon and cover.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RowTemplate {
+  pub id: String,
+  pub name: String,
+  /// The default cells of the row, keyed by field id.
+  pub cells: Cells,
+  /// The body of the row's document. The database doesn't interpret it, the client that creates
+  /// the row from the template uses it to fill the row's document.
+  pub document: Option<String>,
+  pub icon_url: Option<String>,
+  pub cover_url: Option<String>,
+  pub created_at: i64,
+  pub modified_at: i64,
+}
+
+impl RowTemplate {
+  pub fn new(name: String) -> Self {
+    let timestamp = timestamp();
+    Self {
+      id: Uuid::new_v4().to_string(),
+      name,
+      created_at: timestamp,
+      modified_at: timestamp,
+      ..Default::default()
+    }
+  }
+
+  pub fn with_cells(mut self, cells: Cells) -> Self {
+    self.cells = cells;
+    self
+  }
+
+  pub fn with_document(mut self, document: String) -> Self {
+    self.document = Some(document);
+    self
+  }
+
+  pub fn with_icon_url(mut self, icon_url: String) -> Self {
+    self.icon_url = Some(icon_url);
+    self
+  }
+
+  pub fn with_cover_url(mut self, cover_url: String) -> Self {
+    self.cover_url = Some(cover_url);
+    self
+  }
+}
+
+/// The row that was created from a [RowTemplate].
+#[derive(Debug, Clone)]
+pub struct RowFromTemplate {
+  /// The index of the row in the view that it was created in.
+  pub index: usize,
+  pub row_order: RowOrder,
+  /// The id of the row's document and the template's document body that it should be filled
+  /// with. The document is None if the template doesn't have one.
+  pub document_id: String,
+  pub document: Option<String>,
+}
+
+impl RowFromTemplate {
+  pub fn row_id(&self) -> &RowId {
+    &self.row_order.id
+  }
+}
+
+/// A map of the row templates of a database, keyed by template id.
+/// The row templates of a database. They are stored in the `templates` map of the database,
+/// which is created with the database. The databases that were created before the templates were
+/// introduced don't have the map until they are migrated, see [RowTemplateMap::migrate_with_txn].
+/// A missing map has no templates.
+pub struct RowTemplateMap {
+  /// The map of the database that contains the templates map.
+  database: MapRefWrapper,
+}
+
+impl RowTemplateMap {
+  pub fn new(database: MapRefWrapper) -> Self {
+    Self { database }
+  }
+
+  /// Create the templates map of a new database.
+  pub(crate) fn create_with_txn(&self, txn: &mut TransactionMut) {
+    self.database.create_map_with_txn(txn, TEMPLATES);
+  }
+
+  /// Create the templates map of a database that was created before the templates were
+  /// introduced. It's a no-op if the map exists.
+  pub(crate) fn migrate_with_txn(&self, txn: &mut TransactionMut) {
+    if self.templates_with_txn(txn).is_none() {
+      self.create_with_txn(txn);
+    }
+  }
+
+  fn templates_with_txn<T: ReadTxn>(&self, txn: &T) -> Option<MapRefWrapper> {
+    self.database.get_map_with_txn(txn, TEMPLATES)
+  }
+
+  pub fn insert_template(&self, template: RowTemplate) {
+    self.database.with_transact_mut(|txn| {
+      self.insert_template_with_txn(txn, template);
+    });
+  }
+
+  /// Insert the template. The template is dropped if the database doesn't have the templates
+  /// map yet.
+  pub fn insert_template_with_txn(&self, txn: &mut TransactionMut, template: RowTemplate) {
+    let templates = match self.templates_with_txn(txn) {
+      None => {
+        warn!("The database doesn't have the templates map, it needs to be migrated");
+        return;
+      },
+      Some(templates) => templates,
+    };
+    let map_ref = templates.create_map_with_txn(txn, &template.id);
+    map_ref.insert_str_with_txn(txn, TEMPLATE_ID, &template.id);
+    map_ref.insert_i64_with_txn(txn, TEMPLATE_CREATED_AT, template.created_at);
+    RowTemplateUpdate::new(txn, &map_ref)
+      .set_name(template.name)
+      .set_cells(template.cells)
+      .set_document(template.document)
+      .set_icon_url(template.icon_url)
+      .set_cover_url(template.cover_url)
+      .set_last_modified(template.modified_at);
+  }
+
+  /// Return all templates sorted by their creation time.
+  pub fn get_all_templates(&self) -> Vec<RowTemplate> {
+    let txn = self.database.transact();
+    let templates = match self.templates_with_txn(&txn) {
+      None => return vec![],
+      Some(templates) => templates,
+    };
+    let mut templates = templates
+      .iter(&txn)
+      .flat_map(|(_, value)| {
+        let map_ref = value.to_ymap()?;
+        template_from_map_ref(&txn, map_ref)
+      })
+      .collect::<Vec<_>>();
+    templates.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+    templates
+  }
+
+  pub fn get_template(&self, template_id: &str) -> Option<RowTemplate> {
+    let txn = self.database.transact();
+    self.get_template_with_txn(&txn, template_id)
+  }
+
+  pub fn get_template_with_txn<T: ReadTxn>(
+    &self,
+    txn: &T,
+    template_id: &str,
+  ) -> Option<RowTemplate> {
+    let map_ref = self
+      .templates_with_txn(txn)?
+      .get_map_with_txn(txn, template_id)?;
+    template_from_map_ref(txn, &map_ref)
+  }
+
+  /// Update the template. Return false if the template doesn't exist.
+  pub fn update_template<F>(&self, template_id: &str, f: F) -> bool
+  where
+    F: FnOnce(RowTemplateUpdate),
+  {
+    self.database.with_transact_mut(|txn| {
+      let map_ref = self
+        .templates_with_txn(txn)
+        .and_then(|templates| templates.get_map_with_txn(txn, template_id));
+      match map_ref {
+        None => false,
+        Some(map_ref) => {
+          let update = RowTemplateUpdate::new(txn, &map_ref).set_last_modified(timestamp());
+          f(update);
+          true
+        },
+      }
+    })
+  }
+
+  pub fn delete_template(&self, template_id: &str) {
+    self.database.with_transact_mut(|txn| {
+      if let Some(templates) = self.templates_with_txn(txn) {
+        templates.remove(txn, template_id);
+      }
+    });
+  }
+}
+
+/// Updates a [RowTemplate] that is stored in a [MapRef].
+pub struct RowTemplateUpdate<'a, 'b, 'c> {
+  map_ref: &'c MapRef,
+  txn: &'a mut TransactionMut<'b>,
+}
+
+impl<'a, 'b, 'c> RowTemplateUpdate<'a, 'b, 'c> {
+  pub fn new(txn: &'a mut TransactionMut<'b>, map_ref: &'c MapRef) -> Self {
+    Self { map_ref, txn }
+  }
+
+  pub fn set_name(self, name: String) -> Self {
+    self
+      .map_ref
+      .insert_str_with_txn(self.txn, TEMPLATE_NAME, name);
+    self
+  }
+
+  /// Replace the default cells of the template.
+  pub fn set_cells(self, cells: Cells) -> Self {
+    let cells_map = self.map_ref.create_map_with_txn(self.txn, TEMPLATE_CELLS);
+    cells.fill_map_ref(self.txn, &cells_map);
+    self
+  }
+
+  pub fn set_document(self, document: Option<String>) -> Self {
+    self.set_optional_str(TEMPLATE_DOCUMENT, document)
+  }
+
+  pub fn set_icon_url(self, icon_url: Option<String>) -> Self {
+    self.set_optional_str(TEMPLATE_ICON_URL, icon_url)
+  }
+
+  pub fn set_cover_url(self, cover_url: Option<String>) -> Self {
+    self.set_optional_str(TEMPLATE_COVER_URL, cover_url)
+  }
+
+  fn set_last_modified(self, timestamp: i64) -> Self {
+    self
+      .map_ref
+      .insert_i64_with_txn(self.txn, TEMPLATE_LAST_MODIFIED, timestamp);
+    self
+  }
+
+  fn set_optional_str(self, key: &str, value: Option<String>) -> Self {
+    match value {
+      None => self.map_ref.delete_with_txn(self.txn, key),
+      Some(value) => self.map_ref.insert_str_with_txn(self.txn, key, value),
+    }
+    self
+  }
+}
+
+fn template_from_map_ref<T: ReadTxn>(txn: &T, map_ref: &MapRef) -> Option<RowTemplate> {
+  let id = map_ref.get_str_with_txn(txn, TEMPLATE_ID)?;
+  let cells = map_ref
+    .get_map_with_txn(txn, TEMPLATE_CELLS)
+    .map(|map_ref| (txn, &map_ref).into())
+    .unwrap_or_default();
+  Some(RowTemplate {
+    id,
+    name: map_ref
+      .get_str_with_txn(txn, TEMPLATE_NAME)
+      .unwrap_or_default(),
+    cells,
+    document: map_ref.get_str_with_txn(txn, TEMPLATE_DOCUMENT),
+    icon_url: map_ref.get_str_with_txn(txn, TEMPLATE_ICON_URL),
+    cover_url: map_ref.get_str_with_txn(txn, TEMPLATE_COVER_URL),
+    created_at: map_ref
+      .get_i64_with_txn(txn, TEMPLATE_CREATED_AT)
+      .unwrap_or_default(),
+    modified_at: map_ref
+      .get_i64_with_txn(txn, TEMPLATE_LAST_MODIFIED)
+      .unwrap_or_default(),
+  })
+}
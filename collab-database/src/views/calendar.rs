@@ -0,0 +1,381 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use collab::core::any_map::AnyMapExtension;
+
+use crate::error::DatabaseError;
+use crate::fields::{DateTypeOption, Field, FieldType};
+use crate::rows::{CellValue, DateCellValue, Row, RowId};
+use crate::views::{LayoutSetting, LayoutSettingBuilder};
+
+const CALENDAR_LAYOUT_TY: &str = "layout_ty";
+const CALENDAR_FIRST_DAY_OF_WEEK: &str = "first_day_of_week";
+const CALENDAR_SHOW_WEEKENDS: &str = "show_weekends";
+const CALENDAR_SHOW_WEEK_NUMBERS: &str = "show_week_numbers";
+const CALENDAR_FIELD_ID: &str = "field_id";
+
+pub const DEFAULT_FIRST_DAY_OF_WEEK: i32 = 0;
+pub const DEFAULT_SHOW_WEEKENDS: bool = true;
+pub const DEFAULT_SHOW_WEEK_NUMBERS: bool = true;
+
+/// The layout setting of the calendar view. It's stored in the view's [LayoutSetting] of
+/// [DatabaseLayout::Calendar](crate::views::DatabaseLayout::Calendar).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalendarLayoutSetting {
+  pub layout_ty: CalendarLayout,
+  /// The first day of the week. 0 is Sunday, 1 is Monday and so on.
+  pub first_day_of_week: i32,
+  pub show_weekends: bool,
+  pub show_week_numbers: bool,
+  /// The id of the date field that the events are resolved from.
+  pub field_id: String,
+}
+
+impl CalendarLayoutSetting {
+  pub fn new(field_id: String) -> Self {
+    Self {
+      layout_ty: CalendarLayout::default(),
+      first_day_of_week: DEFAULT_FIRST_DAY_OF_WEEK,
+      show_weekends: DEFAULT_SHOW_WEEKENDS,
+      show_week_numbers: DEFAULT_SHOW_WEEK_NUMBERS,
+      field_id,
+    }
+  }
+
+  pub fn first_weekday(&self) -> Weekday {
+    match self.first_day_of_week.rem_euclid(7) {
+      0 => Weekday::Sun,
+      1 => Weekday::Mon,
+      2 => Weekday::Tue,
+      3 => Weekday::Wed,
+      4 => Weekday::Thu,
+      5 => Weekday::Fri,
+      _ => Weekday::Sat,
+    }
+  }
+
+  /// Return the dates that the calendar shows for the given date as a half-open range. The month
+  /// layout shows the whole weeks that cover the month of the date.
+  pub fn visible_range(&self, date: NaiveDate) -> (NaiveDate, NaiveDate) {
+    match self.layout_ty {
+      CalendarLayout::Day => (date, date + Duration::days(1)),
+      CalendarLayout::Week => {
+        let start = self.start_of_week(date);
+        (start, start + Duration::days(7))
+      },
+      CalendarLayout::Month => {
+        let first_day = date.with_day(1).unwrap_or(date);
+        let next_month = if first_day.month() == 12 {
+          NaiveDate::from_ymd_opt(first_day.year() + 1, 1, 1)
+        } else {
+          NaiveDate::from_ymd_opt(first_day.year(), first_day.month() + 1, 1)
+        }
+        .unwrap_or(first_day);
+        let start = self.start_of_week(first_day);
+        let mut end = self.start_of_week(next_month);
+        if end < next_month {
+          end += Duration::days(7);
+        }
+        (start, end)
+      },
+    }
+  }
+
+  fn start_of_week(&self, date: NaiveDate) -> NaiveDate {
+    let days = (7 + date.weekday().num_days_from_sunday() as i64
+      - self.first_weekday().num_days_from_sunday() as i64)
+      % 7;
+    date - Duration::days(days)
+  }
+}
+
+impl From<LayoutSetting> for CalendarLayoutSetting {
+  fn from(setting: LayoutSetting) -> Self {
+    let layout_ty = setting
+      .get_i64_value(CALENDAR_LAYOUT_TY)
+      .map(CalendarLayout::from)
+      .unwrap_or_default();
+    let first_day_of_week = setting
+      .get_i64_value(CALENDAR_FIRST_DAY_OF_WEEK)
+      .unwrap_or(DEFAULT_FIRST_DAY_OF_WEEK as i64) as i32;
+    let show_weekends = setting
+      .get_bool_value(CALENDAR_SHOW_WEEKENDS)
+      .unwrap_or(DEFAULT_SHOW_WEEKENDS);
+    let show_week_numbers = setting
+      .get_bool_value(CALENDAR_SHOW_WEEK_NUMBERS)
+      .unwrap_or(DEFAULT_SHOW_WEEK_NUMBERS);
+    let field_id = setting.get_str_value(CALENDAR_FIELD_ID).unwrap_or_default();
+    Self {
+      layout_ty,
+      first_day_of_week,
+      show_weekends,
+      show_week_numbers,
+      field_id,
+    }
+  }
+}
+
+impl From<CalendarLayoutSetting> for LayoutSetting {
+  fn from(setting: CalendarLayoutSetting) -> Self {
+    LayoutSettingBuilder::new()
+      .insert_i64_value(CALENDAR_LAYOUT_TY, setting.layout_ty.value())
+      .insert_i64_value(CALENDAR_FIRST_DAY_OF_WEEK, setting.first_day_of_week as i64)
+      .insert_bool_value(CALENDAR_SHOW_WEEK_NUMBERS, setting.show_week_numbers)
+      .insert_bool_value(CALENDAR_SHOW_WEEKENDS, setting.show_weekends)
+      .insert_str_value(CALENDAR_FIELD_ID, setting.field_id)
+      .build()
+  }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+#[repr(u8)]
+pub enum CalendarLayout {
+  #[default]
+  Month = 0,
+  Week = 1,
+  Day = 2,
+}
+
+impl CalendarLayout {
+  pub fn value(&self) -> i64 {
+    *self as i64
+  }
+}
+
+impl From<i64> for CalendarLayout {
+  fn from(value: i64) -> Self {
+    match value {
+      1 => CalendarLayout::Week,
+      2 => CalendarLayout::Day,
+      _ => CalendarLayout::Month,
+    }
+  }
+}
+
+/// An event of the calendar view. Each row that has a date in the calendar's date field is an
+/// event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalendarEvent {
+  pub row_id: RowId,
+  /// The text of the row's primary field.
+  pub title: String,
+  /// The start of the event in the timezone that the calendar is displayed in.
+  pub start: DateTime<Tz>,
+  /// The exclusive end of the event. An all-day event ends at the start of the day after its
+  /// last day. A timed event without an end date ends at its start.
+  pub end: DateTime<Tz>,
+  pub is_all_day: bool,
+  /// The timezone of the event's date. It's the timezone of the date cell, or the timezone of
+  /// the date field if the cell doesn't have one.
+  pub timezone: Tz,
+}
+
+impl CalendarEvent {
+  /// Return true if the event overlaps the half-open range.
+  pub fn overlaps(&self, start: &DateTime<Tz>, end: &DateTime<Tz>) -> bool {
+    if self.start == self.end {
+      return &self.start >= start && &self.start < end;
+    }
+    &self.start < end && &self.end > start
+  }
+
+  /// Return true if every day of the event is a Saturday or a Sunday.
+  pub fn is_on_weekend(&self) -> bool {
+    let last = if self.end > self.start {
+      self.end - Duration::seconds(1)
+    } else {
+      self.start
+    };
+    let mut date = self.start.date_naive();
+    while date <= last.date_naive() {
+      if !matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+        return false;
+      }
+      date += Duration::days(1);
+    }
+    true
+  }
+}
+
+/// Resolves the [CalendarEvent]s from the cells of the calendar's date field.
+pub(crate) struct CalendarEventResolver {
+  field: Field,
+  primary_field: Option<Field>,
+  /// The timezone of the date field. It's used when the date cell doesn't have a timezone.
+  field_timezone: Tz,
+  /// The timezone that the calendar is displayed in.
+  timezone: Tz,
+}
+
+impl CalendarEventResolver {
+  pub(crate) fn new(
+    field: Field,
+    primary_field: Option<Field>,
+    timezone: Tz,
+  ) -> Result<Self, DatabaseError> {
+    if FieldType::try_from(field.field_type)? != FieldType::DateTime {
+      return Err(DatabaseError::InvalidCalendarLayoutSetting(
+        "the calendar's field is not a date field",
+      ));
+    }
+    let field_timezone = field_timezone(&field);
+    Ok(Self {
+      field,
+      primary_field,
+      field_timezone,
+      timezone,
+    })
+  }
+
+  pub(crate) fn field_id(&self) -> &str {
+    &self.field.id
+  }
+
+  /// Return the date of the row's cell in the calendar's date field.
+  pub(crate) fn date_of_row(&self, row: &Row) -> Option<DateCellValue> {
+    let cell = row.cells.get(&self.field.id)?;
+    match CellValue::from_cell(FieldType::DateTime, cell).ok()?? {
+      CellValue::Date(date) => Some(date),
+      _ => None,
+    }
+  }
+
+  /// Return the timezone of the date. All-day dates are stored as the start of the day in this
+  /// timezone.
+  pub(crate) fn timezone_of_date(&self, date: &DateCellValue) -> Tz {
+    parse_timezone(&date.timezone_id).unwrap_or(self.field_timezone)
+  }
+
+  pub(crate) fn resolve(&self, row: &Row) -> Option<CalendarEvent> {
+    let date = self.date_of_row(row)?;
+    let timezone = self.timezone_of_date(&date);
+    let (start, end) = date_range_in_timezone(&date, &timezone, &self.timezone)?;
+    let title = row_title(self.primary_field.as_ref(), row);
+
+    Some(CalendarEvent {
+      row_id: row.id.clone(),
+      title,
+      start,
+      end,
+      is_all_day: !date.include_time,
+      timezone,
+    })
+  }
+
+  /// Return the date that moves the event of the given date to the new start. The duration of
+  /// the event is kept. All-day events are moved by whole days, so an event that lasts two days
+  /// still lasts two days after crossing a daylight saving time transition.
+  pub(crate) fn move_date(
+    &self,
+    date: DateCellValue,
+    start: &DateTime<Tz>,
+  ) -> Option<DateCellValue> {
+    if date.include_time {
+      let offset = start.timestamp() - date.timestamp;
+      return Some(DateCellValue {
+        timestamp: start.timestamp(),
+        end_timestamp: date.end_timestamp.map(|end| end + offset),
+        ..date
+      });
+    }
+
+    let timezone = self.timezone_of_date(&date);
+    let old_start_date = from_timestamp(date.timestamp)?
+      .with_timezone(&timezone)
+      .date_naive();
+    let new_start_date = start.date_naive();
+    let days = new_start_date - old_start_date;
+    let end_timestamp = match date.end_timestamp {
+      None => None,
+      Some(end) => {
+        let end_date = from_timestamp(end)?.with_timezone(&timezone).date_naive() + days;
+        Some(start_of_day(&timezone, end_date)?.timestamp())
+      },
+    };
+    Some(DateCellValue {
+      timestamp: start_of_day(&timezone, new_start_date)?.timestamp(),
+      end_timestamp,
+      ..date
+    })
+  }
+}
+
+/// Return the start and the exclusive end of the date in the timezone that it's displayed in.
+/// An all-day date covers the same days in every timezone, so its days are resolved in the
+/// timezone of the date and then placed in the displayed timezone.
+pub(crate) fn date_range_in_timezone(
+  date: &DateCellValue,
+  date_timezone: &Tz,
+  timezone: &Tz,
+) -> Option<(DateTime<Tz>, DateTime<Tz>)> {
+  if date.include_time {
+    let start = from_timestamp(date.timestamp)?.with_timezone(timezone);
+    let end = date
+      .end_timestamp
+      .and_then(from_timestamp)
+      .map(|end| end.with_timezone(timezone))
+      .filter(|end| end >= &start)
+      .unwrap_or(start);
+    return Some((start, end));
+  }
+
+  let start_date = from_timestamp(date.timestamp)?
+    .with_timezone(date_timezone)
+    .date_naive();
+  let end_date = date
+    .end_timestamp
+    .and_then(from_timestamp)
+    .map(|end| end.with_timezone(date_timezone).date_naive())
+    .filter(|end_date| end_date >= &start_date)
+    .unwrap_or(start_date);
+  Some((
+    start_of_day(timezone, start_date)?,
+    start_of_day(timezone, end_date + Duration::days(1))?,
+  ))
+}
+
+/// Return the text of the row's primary field.
+pub(crate) fn row_title(primary_field: Option<&Field>, row: &Row) -> String {
+  primary_field
+    .and_then(|field| {
+      let field_type = FieldType::try_from(field.field_type).ok()?;
+      let cell = row.cells.get(&field.id)?;
+      let value = CellValue::from_cell(field_type, cell).ok()??;
+      Some(value.to_text(field))
+    })
+    .unwrap_or_default()
+}
+
+/// Return the timezone of the date field. Dates without a timezone are in this timezone.
+pub(crate) fn field_timezone(field: &Field) -> Tz {
+  field
+    .get_type_option::<DateTypeOption>(field.field_type)
+    .and_then(|type_option| parse_timezone(&type_option.timezone_id))
+    .unwrap_or(Tz::UTC)
+}
+
+/// Return the start of the day in the timezone. The start of the day is usually midnight, but
+/// midnight doesn't exist in the timezones that start the daylight saving time at midnight. In
+/// that case, the first valid time of the day is returned.
+pub fn start_of_day(timezone: &Tz, date: NaiveDate) -> Option<DateTime<Tz>> {
+  let midnight = date.and_hms_opt(0, 0, 0)?;
+  (0..=2)
+    .map(|hours| midnight + Duration::hours(hours))
+    .find_map(|local| timezone.from_local_datetime(&local).earliest())
+}
+
+fn from_timestamp(timestamp: i64) -> Option<DateTime<Utc>> {
+  Utc.timestamp_opt(timestamp, 0).single()
+}
+
+pub(crate) fn parse_timezone(timezone_id: &str) -> Option<Tz> {
+  if timezone_id.is_empty() {
+    return None;
+  }
+  match timezone_id.parse::<Tz>() {
+    Ok(timezone) => Some(timezone),
+    Err(_) => {
+      tracing::warn!("Unknown timezone id: {}", timezone_id);
+      None
+    },
+  }
+}
@@ -1,6 +1,99 @@
 use collab::core::any_array::ArrayMap;
-use collab::core::any_map::{AnyMap, AnyMapBuilder};
+use collab::core::any_map::{AnyMap, AnyMapBuilder, AnyMapExtension};
+
+use crate::fields::{Field, FieldType, SELECT_OPTION_SEPARATOR};
+use crate::rows::{parse_number, Cell, CellValue, Cells, DateCellValue};
 
 pub type FilterArray = ArrayMap;
 pub type FilterMap = AnyMap;
 pub type FilterMapBuilder = AnyMapBuilder;
+
+pub const FILTER_FIELD_ID: &str = "field_id";
+pub const FILTER_CONDITION: &str = "condition";
+pub const FILTER_CONTENT: &str = "content";
+
+/// Return the cells that a new row needs to pass the filters. Only the filters that imply a
+/// value are used, for example, `status is "Done"` or `checkbox is checked`. Filters like
+/// `name is not empty` don't imply a value and are ignored.
+///
+/// The conditions are the ones that the clients store in the filter's `condition`:
+/// - Text and url: is (0), contains (2), starts with (4), ends with (5).
+/// - Number: equal (0), greater than or equal to (4), less than or equal to (5).
+/// - Checkbox: is checked (0), is unchecked (1).
+/// - Select option: option is (0), option contains (2). The `content` is the comma separated
+///   option ids.
+/// - Date: date is (0), on or before (3), on or after (4). The `content` is the timestamp, either
+///   as it is or in the `timestamp` key of a JSON object.
+pub(crate) fn default_cells_from_filters(
+  filters: &[FilterMap],
+  get_field: impl Fn(&str) -> Option<Field>,
+) -> Cells {
+  let mut cells = Cells::new();
+  for filter in filters {
+    let field = match filter
+      .get_str_value(FILTER_FIELD_ID)
+      .and_then(|field_id| get_field(&field_id))
+    {
+      Some(field) => field,
+      None => continue,
+    };
+    if let Some(cell) = default_cell_from_filter(filter, &field) {
+      cells.insert(field.id, cell);
+    }
+  }
+  cells
+}
+
+fn default_cell_from_filter(filter: &FilterMap, field: &Field) -> Option<Cell> {
+  let field_type = FieldType::try_from(field.field_type).ok()?;
+  let condition = filter.get_i64_value(FILTER_CONDITION)?;
+  let content = filter.get_str_value(FILTER_CONTENT).unwrap_or_default();
+  let content = content.trim();
+  let value = match field_type {
+    FieldType::RichText if matches!(condition, 0 | 2 | 4 | 5) && !content.is_empty() => {
+      CellValue::Text(content.to_string())
+    },
+    FieldType::URL if matches!(condition, 0 | 2 | 4 | 5) && !content.is_empty() => {
+      CellValue::Url(content.to_string())
+    },
+    FieldType::Number if matches!(condition, 0 | 4 | 5) => {
+      CellValue::Number(parse_number(content)?)
+    },
+    FieldType::Checkbox => match condition {
+      0 => CellValue::Checkbox(true),
+      1 => CellValue::Checkbox(false),
+      _ => return None,
+    },
+    FieldType::SingleSelect | FieldType::MultiSelect if matches!(condition, 0 | 2) => {
+      let mut option_ids = content
+        .split(SELECT_OPTION_SEPARATOR)
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+        .collect::<Vec<_>>();
+      if field_type == FieldType::SingleSelect {
+        option_ids.truncate(1);
+      }
+      if option_ids.is_empty() {
+        return None;
+      }
+      CellValue::SelectOption(option_ids)
+    },
+    FieldType::DateTime if matches!(condition, 0 | 3 | 4) => CellValue::Date(DateCellValue {
+      timestamp: parse_filter_timestamp(content)?,
+      ..Default::default()
+    }),
+    _ => return None,
+  };
+  Some(value.to_cell(field_type))
+}
+
+fn parse_filter_timestamp(content: &str) -> Option<i64> {
+  if let Ok(timestamp) = content.parse::<i64>() {
+    return Some(timestamp);
+  }
+  let json = serde_json::from_str::<serde_json::Value>(content).ok()?;
+  let timestamp = json.get("timestamp")?;
+  timestamp
+    .as_i64()
+    .or_else(|| timestamp.as_str().and_then(|s| s.parse().ok()))
+}
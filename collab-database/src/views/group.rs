@@ -1,5 +1,8 @@
 use collab::core::any_array::{ArrayMap, ArrayMapUpdate};
-use collab::core::any_map::{AnyMap, AnyMapBuilder};
+use collab::core::any_map::{AnyMap, AnyMapBuilder, AnyMapExtension};
+
+use crate::fields::{Field, FieldType};
+use crate::rows::{parse_checkbox, parse_number, Cell, CellValue};
 
 /// [GroupSettingArray] contains list of [GroupSettingMap]
 pub type GroupSettingArray = ArrayMap;
@@ -14,3 +17,49 @@ pub type GroupSettingBuilder = AnyMapBuilder;
 pub type GroupMap = AnyMap;
 /// [GroupMapBuilder] is the builder for [GroupMap]
 pub type GroupMapBuilder = AnyMapBuilder;
+
+pub const GROUP_SETTING_FIELD_ID: &str = "field_id";
+pub const GROUP_SETTING_GROUPS: &str = "groups";
+pub const GROUP_ID: &str = "id";
+
+/// Return the field id and the cell that a new row needs to be in the group. The group is looked
+/// up in the given group settings.
+///
+/// The id of a group is the value of the grouping field that the rows in the group have:
+/// - Single and multi select: the option id.
+/// - Checkbox: "Yes" or "No".
+/// - Text, url and number: the value itself.
+///
+/// Return None if the group doesn't exist, the field can't be grouped by value or the group is the
+/// default group, whose id is the field id and that holds the rows without a value.
+pub(crate) fn default_cell_from_group(
+  group_settings: &[GroupSettingMap],
+  group_id: &str,
+  get_field: impl Fn(&str) -> Option<Field>,
+) -> Option<(String, Cell)> {
+  let field_id = group_settings.iter().find_map(|setting| {
+    let field_id = setting.get_str_value(GROUP_SETTING_FIELD_ID)?;
+    setting
+      .get_array::<_, GroupMap>(GROUP_SETTING_GROUPS)
+      .iter()
+      .any(|group| group.get_str_value(GROUP_ID).as_deref() == Some(group_id))
+      .then_some(field_id)
+  })?;
+  if field_id == group_id {
+    return None;
+  }
+
+  let field = get_field(&field_id)?;
+  let field_type = FieldType::try_from(field.field_type).ok()?;
+  let value = match field_type {
+    FieldType::SingleSelect | FieldType::MultiSelect => {
+      CellValue::SelectOption(vec![group_id.to_string()])
+    },
+    FieldType::Checkbox => CellValue::Checkbox(parse_checkbox(group_id)?),
+    FieldType::RichText => CellValue::Text(group_id.to_string()),
+    FieldType::URL => CellValue::Url(group_id.to_string()),
+    FieldType::Number => CellValue::Number(parse_number(group_id)?),
+    _ => return None,
+  };
+  Some((field.id, value.to_cell(field_type)))
+}
@@ -17,12 +17,17 @@ pub enum DatabaseLayout {
   Grid = 0,
   Board = 1,
   Calendar = 2,
+  Timeline = 3,
 }
 
 impl DatabaseLayout {
   pub fn is_board(&self) -> bool {
     matches!(self, DatabaseLayout::Board)
   }
+
+  pub fn is_timeline(&self) -> bool {
+    matches!(self, DatabaseLayout::Timeline)
+  }
 }
 
 impl AsRef<str> for DatabaseLayout {
@@ -31,6 +36,7 @@ impl AsRef<str> for DatabaseLayout {
       DatabaseLayout::Grid => "0",
       DatabaseLayout::Board => "1",
       DatabaseLayout::Calendar => "2",
+      DatabaseLayout::Timeline => "3",
     }
   }
 }
@@ -43,6 +49,7 @@ impl FromStr for DatabaseLayout {
       "0" => Ok(DatabaseLayout::Grid),
       "1" => Ok(DatabaseLayout::Board),
       "2" => Ok(DatabaseLayout::Calendar),
+      "3" => Ok(DatabaseLayout::Timeline),
       _ => bail!("Invalid layout type"),
     }
   }
@@ -60,6 +67,7 @@ impl From<i64> for DatabaseLayout {
       0 => DatabaseLayout::Grid,
       1 => DatabaseLayout::Board,
       2 => DatabaseLayout::Calendar,
+      3 => DatabaseLayout::Timeline,
       _ => Self::default(),
     }
   }
@@ -1,4 +1,5 @@
 mod calculation;
+mod calendar;
 pub mod define;
 mod field_order;
 mod field_settings;
@@ -7,11 +8,13 @@ mod group;
 mod layout;
 mod row_order;
 mod sort;
+mod timeline;
 mod view;
 mod view_map;
 mod view_observer;
 
 pub use calculation::*;
+pub use calendar::*;
 pub use field_order::*;
 pub use field_settings::*;
 pub use filter::*;
@@ -19,6 +22,7 @@ pub use group::*;
 pub use layout::*;
 pub use row_order::*;
 pub use sort::*;
+pub use timeline::*;
 pub use view::*;
 pub use view_map::*;
 pub use view_observer::*;
@@ -0,0 +1,321 @@
+use chrono::DateTime;
+use chrono_tz::Tz;
+use collab::core::any_map::AnyMapExtension;
+
+use crate::error::DatabaseError;
+use crate::fields::{Field, FieldType, SelectTypeOption};
+use crate::rows::{CellValue, DateCellValue, Row, RowId};
+use crate::views::{
+  date_range_in_timezone, field_timezone, parse_timezone, row_title, LayoutSetting,
+  LayoutSettingBuilder,
+};
+
+const TIMELINE_START_FIELD_ID: &str = "start_field_id";
+const TIMELINE_END_FIELD_ID: &str = "end_field_id";
+const TIMELINE_DEPENDENCY_FIELD_ID: &str = "dependency_field_id";
+const TIMELINE_GROUP_FIELD_ID: &str = "group_field_id";
+
+/// The layout setting of the timeline view. It's stored in the view's [LayoutSetting] of
+/// [DatabaseLayout::Timeline](crate::views::DatabaseLayout::Timeline).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TimelineLayoutSetting {
+  /// The id of the date field that the bars start at.
+  pub start_field_id: String,
+  /// The id of the date field that the bars end at. If it's None, the bars end at the end of the
+  /// start field's date, which is the end of its date range if it has one.
+  pub end_field_id: Option<String>,
+  /// The id of the relation field that links a row to the rows that it depends on.
+  pub dependency_field_id: Option<String>,
+  /// The id of the field that the bars are grouped by.
+  pub group_field_id: Option<String>,
+}
+
+impl TimelineLayoutSetting {
+  pub fn new(start_field_id: String) -> Self {
+    Self {
+      start_field_id,
+      ..Default::default()
+    }
+  }
+
+  pub fn with_end_field(mut self, field_id: String) -> Self {
+    self.end_field_id = Some(field_id);
+    self
+  }
+
+  pub fn with_dependency_field(mut self, field_id: String) -> Self {
+    self.dependency_field_id = Some(field_id);
+    self
+  }
+
+  pub fn with_group_field(mut self, field_id: String) -> Self {
+    self.group_field_id = Some(field_id);
+    self
+  }
+}
+
+impl From<LayoutSetting> for TimelineLayoutSetting {
+  fn from(setting: LayoutSetting) -> Self {
+    let optional_field_id = |key: &str| setting.get_str_value(key).filter(|id| !id.is_empty());
+    Self {
+      start_field_id: setting
+        .get_str_value(TIMELINE_START_FIELD_ID)
+        .unwrap_or_default(),
+      end_field_id: optional_field_id(TIMELINE_END_FIELD_ID),
+      dependency_field_id: optional_field_id(TIMELINE_DEPENDENCY_FIELD_ID),
+      group_field_id: optional_field_id(TIMELINE_GROUP_FIELD_ID),
+    }
+  }
+}
+
+impl From<TimelineLayoutSetting> for LayoutSetting {
+  fn from(setting: TimelineLayoutSetting) -> Self {
+    LayoutSettingBuilder::new()
+      .insert_str_value(TIMELINE_START_FIELD_ID, setting.start_field_id)
+      .insert_str_value(
+        TIMELINE_END_FIELD_ID,
+        setting.end_field_id.unwrap_or_default(),
+      )
+      .insert_str_value(
+        TIMELINE_DEPENDENCY_FIELD_ID,
+        setting.dependency_field_id.unwrap_or_default(),
+      )
+      .insert_str_value(
+        TIMELINE_GROUP_FIELD_ID,
+        setting.group_field_id.unwrap_or_default(),
+      )
+      .build()
+  }
+}
+
+/// A bar of the timeline view. Each row that has a date in the timeline's start field is a bar.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineBar {
+  pub row_id: RowId,
+  /// The text of the row's primary field.
+  pub title: String,
+  /// The start of the bar in the timezone that the timeline is displayed in.
+  pub start: DateTime<Tz>,
+  /// The exclusive end of the bar. It's never before the start.
+  pub end: DateTime<Tz>,
+  pub is_all_day: bool,
+  /// The ids of the rows that the row depends on.
+  pub dependencies: Vec<RowId>,
+  /// The group of the bar. For select option fields, it's the id of the first selected option.
+  /// For other fields, it's the text of the cell. None if the timeline is not grouped or the
+  /// cell is empty.
+  pub group_id: Option<String>,
+}
+
+/// The bars of the timeline view that belong to the same group.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineGroup {
+  /// The [TimelineBar::group_id] of the bars. None for the bars that don't belong to a group.
+  pub group_id: Option<String>,
+  pub bars: Vec<TimelineBar>,
+}
+
+/// Resolves the [TimelineBar]s of the timeline view.
+pub(crate) struct TimelineBarResolver {
+  start_field: Field,
+  end_field: Option<Field>,
+  dependency_field: Option<Field>,
+  group_field: Option<Field>,
+  primary_field: Option<Field>,
+  /// The timezone that the timeline is displayed in.
+  timezone: Tz,
+}
+
+impl TimelineBarResolver {
+  pub(crate) fn new(
+    setting: &TimelineLayoutSetting,
+    get_field: impl Fn(&str) -> Option<Field>,
+    primary_field: Option<Field>,
+    timezone: Tz,
+  ) -> Result<Self, DatabaseError> {
+    let start_field = get_field(&setting.start_field_id).ok_or(
+      DatabaseError::InvalidTimelineLayoutSetting("the start field doesn't exist"),
+    )?;
+    if !is_date_field(&start_field) {
+      return Err(DatabaseError::InvalidTimelineLayoutSetting(
+        "the start field is not a date field",
+      ));
+    }
+    let end_field = existing_field(
+      &get_field,
+      setting.end_field_id.as_deref(),
+      "the end field doesn't exist",
+    )?;
+    if end_field.as_ref().map(is_date_field) == Some(false) {
+      return Err(DatabaseError::InvalidTimelineLayoutSetting(
+        "the end field is not a date field",
+      ));
+    }
+    let dependency_field = existing_field(
+      &get_field,
+      setting.dependency_field_id.as_deref(),
+      "the dependency field doesn't exist",
+    )?;
+    if let Some(field) = &dependency_field {
+      if FieldType::try_from(field.field_type).ok() != Some(FieldType::Relation) {
+        return Err(DatabaseError::InvalidTimelineLayoutSetting(
+          "the dependency field is not a relation field",
+        ));
+      }
+    }
+    let group_field = existing_field(
+      &get_field,
+      setting.group_field_id.as_deref(),
+      "the group field doesn't exist",
+    )?;
+    Ok(Self {
+      start_field,
+      end_field,
+      dependency_field,
+      group_field,
+      primary_field,
+      timezone,
+    })
+  }
+
+  pub(crate) fn resolve(&self, row: &Row) -> Option<TimelineBar> {
+    let start_date = date_of_row(&self.start_field, row)?;
+    let (start, mut end) = self.date_range(&self.start_field, &start_date)?;
+    if let Some(end_field) = &self.end_field {
+      if let Some((_, end_of_end_date)) =
+        date_of_row(end_field, row).and_then(|end_date| self.date_range(end_field, &end_date))
+      {
+        end = end_of_end_date.max(start);
+      }
+    }
+
+    let dependencies = self
+      .dependency_field
+      .as_ref()
+      .and_then(|field| {
+        let cell = row.cells.get(&field.id)?;
+        match CellValue::from_cell(FieldType::Relation, cell).ok()?? {
+          CellValue::Relation(row_ids) => Some(row_ids),
+          _ => None,
+        }
+      })
+      .unwrap_or_default();
+
+    let group_id = self.group_field.as_ref().and_then(|field| {
+      let field_type = FieldType::try_from(field.field_type).ok()?;
+      let cell = row.cells.get(&field.id)?;
+      let group_id = match CellValue::from_cell(field_type, cell).ok()?? {
+        CellValue::SelectOption(ids) => ids.into_iter().next()?,
+        value => value.to_text(field),
+      };
+      if group_id.is_empty() {
+        None
+      } else {
+        Some(group_id)
+      }
+    });
+
+    Some(TimelineBar {
+      row_id: row.id.clone(),
+      title: row_title(self.primary_field.as_ref(), row),
+      start,
+      end,
+      is_all_day: !start_date.include_time,
+      dependencies,
+      group_id,
+    })
+  }
+
+  /// Split the bars into the groups of the group field. The groups of a select option field
+  /// follow the order of its options, the other groups follow the order of their first bar. The
+  /// bars without a group come last. All the bars are in one group if the timeline is not
+  /// grouped.
+  pub(crate) fn group(&self, bars: Vec<TimelineBar>) -> Vec<TimelineGroup> {
+    let mut groups = self
+      .group_field
+      .as_ref()
+      .filter(|field| {
+        FieldType::try_from(field.field_type)
+          .map(|field_type| field_type.is_select_option())
+          .unwrap_or(false)
+      })
+      .and_then(|field| field.get_type_option::<SelectTypeOption>(field.field_type))
+      .map(|type_option| {
+        type_option
+          .options
+          .into_iter()
+          .map(|option| TimelineGroup {
+            group_id: Some(option.id),
+            bars: vec![],
+          })
+          .collect::<Vec<_>>()
+      })
+      .unwrap_or_default();
+    let mut ungrouped_bars = vec![];
+    for bar in bars {
+      let group_id = match &bar.group_id {
+        None => {
+          ungrouped_bars.push(bar);
+          continue;
+        },
+        Some(group_id) => group_id,
+      };
+      match groups
+        .iter_mut()
+        .find(|group| group.group_id.as_ref() == Some(group_id))
+      {
+        Some(group) => group.bars.push(bar),
+        None => groups.push(TimelineGroup {
+          group_id: Some(group_id.clone()),
+          bars: vec![bar],
+        }),
+      }
+    }
+    groups.push(TimelineGroup {
+      group_id: None,
+      bars: ungrouped_bars,
+    });
+    groups.retain(|group| !group.bars.is_empty());
+    groups
+  }
+
+  fn date_range(
+    &self,
+    field: &Field,
+    date: &DateCellValue,
+  ) -> Option<(DateTime<Tz>, DateTime<Tz>)> {
+    let date_timezone = parse_timezone(&date.timezone_id).unwrap_or_else(|| field_timezone(field));
+    date_range_in_timezone(date, &date_timezone, &self.timezone)
+  }
+}
+
+/// Return the field of the layout setting. Return an error if the setting has a field id but the
+/// field doesn't exist.
+fn existing_field(
+  get_field: impl Fn(&str) -> Option<Field>,
+  field_id: Option<&str>,
+  missing_field_error: &'static str,
+) -> Result<Option<Field>, DatabaseError> {
+  let field_id = match field_id {
+    None => return Ok(None),
+    Some(field_id) => field_id,
+  };
+  match get_field(field_id) {
+    Some(field) => Ok(Some(field)),
+    None => Err(DatabaseError::InvalidTimelineLayoutSetting(
+      missing_field_error,
+    )),
+  }
+}
+
+fn is_date_field(field: &Field) -> bool {
+  FieldType::try_from(field.field_type).ok() == Some(FieldType::DateTime)
+}
+
+fn date_of_row(field: &Field, row: &Row) -> Option<DateCellValue> {
+  let cell = row.cells.get(&field.id)?;
+  match CellValue::from_cell(FieldType::DateTime, cell).ok()?? {
+    CellValue::Date(date) => Some(date),
+    _ => None,
+  }
+}
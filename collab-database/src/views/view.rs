@@ -19,6 +19,7 @@ use crate::views::layout::{DatabaseLayout, LayoutSettings};
 use crate::views::{
   FieldOrder, FieldOrderArray, FieldSettingsByFieldIdMap, FieldSettingsMap, FilterArray, FilterMap,
   GroupSettingArray, GroupSettingMap, LayoutSetting, RowOrder, RowOrderArray, SortArray, SortMap,
+  TimelineLayoutSetting,
 };
 use crate::{impl_any_update, impl_i64_update, impl_order_update, impl_str_update};
 
@@ -153,6 +154,20 @@ impl CreateViewParamsValidator {
       return Err(DatabaseError::InvalidViewID("view_id is empty"));
     }
 
+    if params.layout.is_timeline() {
+      let start_field_id = params
+        .layout_settings
+        .get(&DatabaseLayout::Timeline)
+        .cloned()
+        .map(|setting| TimelineLayoutSetting::from(setting).start_field_id)
+        .unwrap_or_default();
+      if start_field_id.is_empty() {
+        return Err(DatabaseError::InvalidTimelineLayoutSetting(
+          "the timeline doesn't have a start date field",
+        ));
+      }
+    }
+
     Ok(params)
   }
 }
@@ -187,6 +202,8 @@ impl CreateDatabaseParams {
         height: row.height,
         visibility: row.visibility,
         row_position: OrderObjectPosition::End,
+        group_id: None,
+        fill_filter_cells: false,
       })
       .collect();
 
@@ -1,8 +1,17 @@
-use crate::database::{Database, DatabaseContext, DatabaseData, MutexDatabase};
+use crate::csv::import_csv;
+use crate::database::{gen_row_id, Database, DatabaseContext, DatabaseData, MutexDatabase};
 use crate::database_state::DatabaseNotify;
 use crate::error::DatabaseError;
-use crate::views::{CreateDatabaseParams, CreateViewParams, CreateViewParamsValidator};
+use crate::fields::Field;
+use crate::rows::{Cells, CreateRowParams, Row, RowCell, RowId};
+use crate::views::{
+  CreateDatabaseParams, CreateViewParams, CreateViewParamsValidator, OrderObjectPosition, RowOrder,
+};
 use crate::workspace_database::database_meta::{DatabaseMeta, DatabaseMetaList};
+use crate::workspace_database::relation::{
+  DanglingLink, DatabaseRelation, LookupCellData, LookupTypeOption, RelationCellCache,
+  RelationCellChangeReceiver, RollupCellData, RollupTypeOption, RowRelationMap,
+};
 use async_trait::async_trait;
 use collab::core::collab::{DataSource, MutexCollab};
 use collab::preclude::{Any, Collab, MapPrelim};
@@ -15,6 +24,7 @@ use collab_plugins::CollabKVDB;
 use parking_lot::Mutex;
 use std::collections::{HashMap, HashSet};
 use std::future::Future;
+use std::io::Read;
 
 use std::pin::Pin;
 use std::sync::{Arc, Weak};
@@ -74,6 +84,10 @@ pub struct WorkspaceDatabase {
   /// and the handler will be removed when the database is deleted or closed.
   databases: Arc<Mutex<HashMap<String, Arc<MutexDatabase>>>>,
   removing_databases: Arc<Mutex<HashMap<String, Arc<MutexDatabase>>>>,
+  /// The row relations between the databases. It's stored in the same collab as the database
+  /// metas.
+  relation: Arc<DatabaseRelation>,
+  relation_cell_cache: Arc<RelationCellCache>,
 }
 
 impl WorkspaceDatabase {
@@ -107,6 +121,7 @@ impl WorkspaceDatabase {
       }
     }
 
+    let relation = Arc::new(DatabaseRelation::new(collab.clone()));
     Self {
       uid,
       collab_db,
@@ -115,6 +130,8 @@ impl WorkspaceDatabase {
       config,
       collab_service,
       removing_databases,
+      relation,
+      relation_cell_cache: Arc::new(RelationCellCache::new()),
     }
   }
 
@@ -192,7 +209,8 @@ impl WorkspaceDatabase {
           collab_service: self.collab_service.clone(),
           notifier,
         };
-        let database = Database::get_or_create(database_id, context).ok()?;
+        let mut database = Database::get_or_create(database_id, context).ok()?;
+        database.set_row_relations(Arc::downgrade(&self.relation));
         // The database is not exist in local disk, which means the rows of the database are not
         // loaded yet.
         if !is_exist {
@@ -261,12 +279,133 @@ impl WorkspaceDatabase {
       .database_meta_list()
       .add_database(&params.database_id, linked_views.into_iter().collect());
     let database_id = params.database_id.clone();
-    let mutex_database = MutexDatabase::new(Database::create_with_inline_view(params, context)?);
-    let database = Arc::new(mutex_database);
+    let mut database = Database::create_with_inline_view(params, context)?;
+    database.set_row_relations(Arc::downgrade(&self.relation));
+    let database = Arc::new(MutexDatabase::new(database));
     self.databases.lock().insert(database_id, database.clone());
     Ok(database)
   }
 
+  /// Create a grid database from the csv content. The field types are inferred from the values
+  /// of each column. See [import_csv] for more details.
+  ///
+  /// The rows are added with [Database::batch_create_rows] after the database is created, so they
+  /// are created in one transaction.
+  pub fn import_csv<R: Read>(
+    &self,
+    view_id: &str,
+    view_name: &str,
+    reader: R,
+  ) -> Result<Arc<MutexDatabase>, DatabaseError> {
+    let mut params = import_csv(view_id, view_name, reader)?;
+    let rows = std::mem::take(&mut params.rows);
+    let database = self.create_database(params)?;
+    database
+      .lock()
+      .batch_create_rows(view_id, rows, OrderObjectPosition::End)?;
+    Ok(database)
+  }
+
+  /// Move the rows from the view to the position in another view. The rows are moved within the
+  /// database if both views belong to the same one, see [Database::batch_move_rows].
+  ///
+  /// Otherwise the rows are created in the database of the other view with
+  /// [Database::batch_create_rows] and then removed from their database. A cell is kept if the
+  /// other database has a field with the same name and type, the other cells are dropped. Return
+  /// the rows of the other database.
+  ///
+  /// Moving the rows between databases is not atomic, because each database is a separate
+  /// collab. The rows are only removed after they are created in the other database. If they
+  /// can't be removed, for example because one of them was removed from the view in the meantime,
+  /// the created rows are removed again and the error is returned.
+  pub async fn move_rows_between_views(
+    &self,
+    from_view_id: &str,
+    row_ids: &[RowId],
+    to_view_id: &str,
+    position: OrderObjectPosition,
+  ) -> Result<Vec<RowOrder>, DatabaseError> {
+    let from_database = self
+      .get_database_with_view_id(from_view_id)
+      .await
+      .ok_or(DatabaseError::DatabaseViewNotExist)?;
+    let to_database = self
+      .get_database_with_view_id(to_view_id)
+      .await
+      .ok_or(DatabaseError::DatabaseViewNotExist)?;
+    if Arc::ptr_eq(&from_database, &to_database) {
+      let database = to_database.lock();
+      database.batch_move_rows(to_view_id, row_ids, position)?;
+      let row_ids = row_ids.iter().collect::<HashSet<_>>();
+      let row_orders = database
+        .get_row_orders_for_view(to_view_id)
+        .into_iter()
+        .filter(|row_order| row_ids.contains(&row_order.id))
+        .collect();
+      return Ok(row_orders);
+    }
+
+    let (rows, from_fields) = {
+      let database = from_database.lock();
+      check_rows_in_view(&database, from_view_id, row_ids)?;
+      let rows = row_ids
+        .iter()
+        .map(|row_id| {
+          let row = database.get_row(row_id);
+          if row.is_empty() {
+            return Err(DatabaseError::RowNotExist);
+          }
+          Ok(row)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+      (rows, database.get_fields(None))
+    };
+
+    let to_database_id = to_database.lock().get_database_id();
+    let to_fields = to_database.lock().get_fields(None);
+    let field_id_map = from_fields
+      .iter()
+      .filter_map(|from_field| {
+        let to_field = to_fields.iter().find(|to_field| {
+          to_field.name == from_field.name && to_field.field_type == from_field.field_type
+        })?;
+        Some((from_field.id.clone(), to_field.id.clone()))
+      })
+      .collect::<HashMap<_, _>>();
+    let params = rows
+      .into_iter()
+      .map(|row| {
+        let mut cells = Cells::new();
+        for (field_id, cell) in row.cells.into_inner() {
+          if let Some(to_field_id) = field_id_map.get(&field_id) {
+            cells.insert(to_field_id.clone(), cell);
+          }
+        }
+        CreateRowParams::new(gen_row_id(), to_database_id.clone())
+          .with_cells(cells)
+          .with_height(row.height)
+          .with_visibility(row.visibility)
+      })
+      .collect();
+
+    let row_orders = to_database
+      .lock()
+      .batch_create_rows(to_view_id, params, position)?;
+    let removed = {
+      let database = from_database.lock();
+      check_rows_in_view(&database, from_view_id, row_ids).map(|_| database.remove_rows(row_ids))
+    };
+    if let Err(err) = removed {
+      let created_row_ids = row_orders
+        .into_iter()
+        .map(|row_order| row_order.id)
+        .collect::<Vec<_>>();
+      to_database.lock().remove_rows(&created_row_ids);
+      return Err(err);
+    }
+    Ok(row_orders)
+  }
+
   pub fn track_database(&self, database_id: &str, database_view_ids: Vec<String>) {
     self
       .database_meta_list()
@@ -298,8 +437,10 @@ impl WorkspaceDatabase {
   }
 
   /// Delete the database with the given database id.
+  /// The relations of the database are removed too, so no row keeps linking to its rows.
   pub fn delete_database(&self, database_id: &str) {
     self.database_meta_list().delete_database(database_id);
+    self.relations().remove_database_relations(database_id);
     if let Some(collab_db) = self.collab_db.upgrade() {
       let _ = collab_db.with_write_txn(|w_db_txn| {
         if let Err(err) = w_db_txn.delete_doc(self.uid, database_id) {
@@ -391,6 +532,221 @@ impl WorkspaceDatabase {
     }
   }
 
+  pub fn relations(&self) -> &RowRelationMap {
+    self.relation.row_relations()
+  }
+
+  /// Remove the rows from the database. Like [Database::remove_rows], the links from or to the
+  /// rows are removed from all the relations of the database.
+  pub async fn remove_rows(
+    &self,
+    database_id: &str,
+    row_ids: &[RowId],
+  ) -> Result<Vec<Row>, DatabaseError> {
+    let database = self
+      .get_database(database_id)
+      .await
+      .ok_or(DatabaseError::DatabaseNotExist)?;
+    let rows = database.lock().remove_rows(row_ids);
+    Ok(rows)
+  }
+
+  /// Return the links that only exist on one side of their relation, and the links that refer
+  /// to a database or a row that doesn't exist.
+  pub async fn find_dangling_links(&self) -> Vec<DanglingLink> {
+    let mut links = self.relations().find_dangling_links();
+    for relation in self.relations().get_relations() {
+      let relation_id = relation.id();
+      // The rows of each database referenced by the relation.
+      let mut row_ids_by_database: HashMap<String, HashSet<String>> = HashMap::new();
+      for connection in relation.row_connections.values() {
+        row_ids_by_database
+          .entry(connection.database_id().to_string())
+          .or_default()
+          .insert(connection.row_id().to_string());
+        row_ids_by_database
+          .entry(relation.linked_by_database_id.clone())
+          .or_default()
+          .extend(
+            connection
+              .linking_rows()
+              .iter()
+              .map(|row| row.row_id.clone()),
+          );
+        row_ids_by_database
+          .entry(relation.linking_database_id.clone())
+          .or_default()
+          .extend(
+            connection
+              .linked_by_rows()
+              .iter()
+              .map(|row| row.row_id.clone()),
+          );
+      }
+
+      for (database_id, row_ids) in row_ids_by_database {
+        match self.get_database(&database_id).await {
+          None => links.push(DanglingLink::DatabaseNotExist {
+            relation_id: relation_id.clone(),
+            database_id,
+          }),
+          Some(database) => {
+            let existing_row_ids = database
+              .lock()
+              .get_inline_row_orders()
+              .into_iter()
+              .map(|row_order| row_order.id.into_inner())
+              .collect::<HashSet<_>>();
+            for row_id in row_ids {
+              if !existing_row_ids.contains(&row_id) {
+                links.push(DanglingLink::RowNotExist {
+                  relation_id: relation_id.clone(),
+                  database_id: database_id.clone(),
+                  row_id,
+                });
+              }
+            }
+          },
+        }
+      }
+    }
+    links
+  }
+
+  /// Subscribe to the invalidation of the resolved lookup and rollup values. The values of the
+  /// database's rows should be resolved again when receiving a change.
+  pub fn subscribe_relation_cell_change(&self) -> RelationCellChangeReceiver {
+    self.relation_cell_cache.subscribe()
+  }
+
+  /// Resolve the lookup field of the row. The values are read from the target field of the
+  /// rows that the row links to in the relation database.
+  ///
+  /// The linked rows that are not available yet are returned in
+  /// [LookupCellData::pending_row_ids]. A [RelationCellChange] will be emitted once they are
+  /// fetched from the remote.
+  pub async fn get_lookup_cell(
+    &self,
+    database_id: &str,
+    row_id: &RowId,
+    field: &Field,
+  ) -> Result<LookupCellData, DatabaseError> {
+    let type_option = LookupTypeOption::from_field(field).ok_or(DatabaseError::NotRelationField)?;
+    let key = (database_id.to_string(), row_id.clone(), field.id.clone());
+    if let Some(data) = self.relation_cell_cache.get_lookup(&key, &type_option) {
+      return Ok(data);
+    }
+
+    let (values, pending_row_ids) = self
+      .resolve_linked_cells(
+        database_id,
+        row_id,
+        &type_option.relation_database_id,
+        &type_option.target_field_id,
+      )
+      .await;
+    let data = LookupCellData {
+      type_option,
+      values,
+      pending_row_ids,
+    };
+    self.relation_cell_cache.insert_lookup(key, data.clone());
+    Ok(data)
+  }
+
+  /// Resolve the rollup field of the row. Same as [WorkspaceDatabase::get_lookup_cell] but the
+  /// values are aggregated with the field's [RollupCalculation].
+  pub async fn get_rollup_cell(
+    &self,
+    database_id: &str,
+    row_id: &RowId,
+    field: &Field,
+  ) -> Result<RollupCellData, DatabaseError> {
+    let type_option = RollupTypeOption::from_field(field).ok_or(DatabaseError::NotRelationField)?;
+    let key = (database_id.to_string(), row_id.clone(), field.id.clone());
+    if let Some(data) = self.relation_cell_cache.get_rollup(&key, &type_option) {
+      return Ok(data);
+    }
+
+    let (values, pending_row_ids) = self
+      .resolve_linked_cells(
+        database_id,
+        row_id,
+        &type_option.relation_database_id,
+        &type_option.target_field_id,
+      )
+      .await;
+    let value = type_option
+      .calculation
+      .calculate(&values, values.len() + pending_row_ids.len());
+    let data = RollupCellData {
+      type_option,
+      value,
+      pending_row_ids,
+    };
+    self.relation_cell_cache.insert_rollup(key, data.clone());
+    Ok(data)
+  }
+
+  /// Return the target field's cells of the rows that the row links to, and the ids of the
+  /// linked rows that are not available yet.
+  async fn resolve_linked_cells(
+    &self,
+    database_id: &str,
+    row_id: &RowId,
+    relation_database_id: &str,
+    target_field_id: &str,
+  ) -> (Vec<RowCell>, Vec<RowId>) {
+    self
+      .relation_cell_cache
+      .observe_relations(self.relations().subscript_update());
+
+    let linked_row_ids = self
+      .relations()
+      .get_row_connection(database_id, relation_database_id, database_id, row_id)
+      .map(|connection| {
+        connection
+          .linking_rows()
+          .iter()
+          .map(|row| RowId::from(row.row_id.clone()))
+          .collect::<Vec<_>>()
+      })
+      .unwrap_or_default();
+    if linked_row_ids.is_empty() {
+      return (vec![], vec![]);
+    }
+
+    let relation_database = match self.get_database(relation_database_id).await {
+      None => {
+        trace!(
+          "relation database:{} is not available",
+          relation_database_id
+        );
+        return (vec![], linked_row_ids);
+      },
+      Some(database) => database,
+    };
+
+    let database = relation_database.lock();
+    self.relation_cell_cache.observe_database(
+      relation_database_id,
+      database.subscribe_row_change(),
+      database.block.subscribe_event(),
+    );
+    let mut values = vec![];
+    let mut pending_row_ids = vec![];
+    for linked_row_id in linked_row_ids {
+      match database.block.get_loaded_row(&linked_row_id) {
+        None => pending_row_ids.push(linked_row_id),
+        Some(row) => {
+          let cell = row.cells.get(target_field_id).cloned();
+          values.push(RowCell::new(row.id, cell));
+        },
+      }
+    }
+    (values, pending_row_ids)
+  }
+
   /// Create a new [Collab] instance for given database id.
   fn collab_for_database(
     &self,
@@ -415,3 +771,21 @@ impl WorkspaceDatabase {
 pub fn get_all_database_meta(collab: &Collab) -> Vec<DatabaseMeta> {
   DatabaseMetaList::from_collab(collab).get_all_database_meta()
 }
+
+/// Return [DatabaseError::InvalidRowID] if any of the rows is not in the view.
+fn check_rows_in_view(
+  database: &Database,
+  view_id: &str,
+  row_ids: &[RowId],
+) -> Result<(), DatabaseError> {
+  let view_row_ids = database
+    .get_row_orders_for_view(view_id)
+    .into_iter()
+    .map(|row_order| row_order.id)
+    .collect::<HashSet<_>>();
+  if row_ids.iter().all(|row_id| view_row_ids.contains(row_id)) {
+    Ok(())
+  } else {
+    Err(DatabaseError::InvalidRowID("the row is not in the view"))
+  }
+}
@@ -0,0 +1,472 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+
+use collab::core::any_map::AnyMapExtension;
+use collab::preclude::Any;
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use tokio::sync::broadcast;
+use tracing::trace;
+
+use crate::blocks::BlockEvent;
+use crate::fields::{Field, TypeOptionData, TypeOptionDataBuilder};
+use crate::rows::{any_to_string, Cell, RowCell, RowChangeReceiver, RowId, CELL_DATA};
+use crate::workspace_database::relation::{RowRelationChange, RowRelationUpdateReceiver};
+
+const RELATION_DATABASE_ID: &str = "relation_database_id";
+const TARGET_FIELD_ID: &str = "target_field_id";
+const CALCULATION: &str = "calculation";
+
+/// The type option of a lookup field. A lookup field shows the value of the target field of
+/// the rows that the row links to in the relation database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LookupTypeOption {
+  pub relation_database_id: String,
+  pub target_field_id: String,
+}
+
+impl LookupTypeOption {
+  pub fn new(relation_database_id: impl Into<String>, target_field_id: impl Into<String>) -> Self {
+    Self {
+      relation_database_id: relation_database_id.into(),
+      target_field_id: target_field_id.into(),
+    }
+  }
+
+  /// Return the lookup type option stored under the field's current field type.
+  pub fn from_field(field: &Field) -> Option<Self> {
+    let data = field.get_any_type_option(field.field_type)?;
+    Self::try_from(data).ok()
+  }
+}
+
+impl TryFrom<TypeOptionData> for LookupTypeOption {
+  type Error = anyhow::Error;
+
+  fn try_from(data: TypeOptionData) -> Result<Self, Self::Error> {
+    let relation_database_id = data
+      .get_str_value(RELATION_DATABASE_ID)
+      .ok_or_else(|| anyhow::anyhow!("Missing {}", RELATION_DATABASE_ID))?;
+    let target_field_id = data
+      .get_str_value(TARGET_FIELD_ID)
+      .ok_or_else(|| anyhow::anyhow!("Missing {}", TARGET_FIELD_ID))?;
+    Ok(Self {
+      relation_database_id,
+      target_field_id,
+    })
+  }
+}
+
+impl From<LookupTypeOption> for TypeOptionData {
+  fn from(data: LookupTypeOption) -> Self {
+    TypeOptionDataBuilder::new()
+      .insert_str_value(RELATION_DATABASE_ID, data.relation_database_id)
+      .insert_str_value(TARGET_FIELD_ID, data.target_field_id)
+      .build()
+  }
+}
+
+/// The type option of a rollup field. A rollup field aggregates the value of the target field
+/// of the rows that the row links to in the relation database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RollupTypeOption {
+  pub relation_database_id: String,
+  pub target_field_id: String,
+  pub calculation: RollupCalculation,
+}
+
+impl RollupTypeOption {
+  pub fn new(
+    relation_database_id: impl Into<String>,
+    target_field_id: impl Into<String>,
+    calculation: RollupCalculation,
+  ) -> Self {
+    Self {
+      relation_database_id: relation_database_id.into(),
+      target_field_id: target_field_id.into(),
+      calculation,
+    }
+  }
+
+  /// Return the rollup type option stored under the field's current field type.
+  pub fn from_field(field: &Field) -> Option<Self> {
+    let data = field.get_any_type_option(field.field_type)?;
+    Self::try_from(data).ok()
+  }
+}
+
+impl TryFrom<TypeOptionData> for RollupTypeOption {
+  type Error = anyhow::Error;
+
+  fn try_from(data: TypeOptionData) -> Result<Self, Self::Error> {
+    let calculation = data
+      .get_i64_value(CALCULATION)
+      .map(RollupCalculation::from)
+      .unwrap_or_default();
+    let lookup = LookupTypeOption::try_from(data)?;
+    Ok(Self {
+      relation_database_id: lookup.relation_database_id,
+      target_field_id: lookup.target_field_id,
+      calculation,
+    })
+  }
+}
+
+impl From<RollupTypeOption> for TypeOptionData {
+  fn from(data: RollupTypeOption) -> Self {
+    TypeOptionDataBuilder::new()
+      .insert_str_value(RELATION_DATABASE_ID, data.relation_database_id)
+      .insert_str_value(TARGET_FIELD_ID, data.target_field_id)
+      .insert_i64_value(CALCULATION, data.calculation as i64)
+      .build()
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum RollupCalculation {
+  #[default]
+  Count = 0,
+  Sum = 1,
+  Average = 2,
+  Min = 3,
+  Max = 4,
+  Concat = 5,
+}
+
+impl From<i64> for RollupCalculation {
+  fn from(value: i64) -> Self {
+    match value {
+      1 => RollupCalculation::Sum,
+      2 => RollupCalculation::Average,
+      3 => RollupCalculation::Min,
+      4 => RollupCalculation::Max,
+      5 => RollupCalculation::Concat,
+      _ => RollupCalculation::Count,
+    }
+  }
+}
+
+impl RollupCalculation {
+  /// Aggregate the given cells. The `linked_row_count` is the number of linked rows, including
+  /// the rows that are not loaded yet, and is only used by [RollupCalculation::Count].
+  pub fn calculate(&self, cells: &[RowCell], linked_row_count: usize) -> RollupValue {
+    let numbers = || {
+      cells
+        .iter()
+        .flat_map(|row_cell| row_cell.cell.as_ref().and_then(cell_to_f64))
+        .collect::<Vec<f64>>()
+    };
+    match self {
+      RollupCalculation::Count => RollupValue::Number(linked_row_count as f64),
+      RollupCalculation::Sum => RollupValue::Number(numbers().iter().sum()),
+      RollupCalculation::Average => {
+        let numbers = numbers();
+        if numbers.is_empty() {
+          RollupValue::Empty
+        } else {
+          RollupValue::Number(numbers.iter().sum::<f64>() / numbers.len() as f64)
+        }
+      },
+      RollupCalculation::Min => numbers()
+        .into_iter()
+        .reduce(f64::min)
+        .map(RollupValue::Number)
+        .unwrap_or(RollupValue::Empty),
+      RollupCalculation::Max => numbers()
+        .into_iter()
+        .reduce(f64::max)
+        .map(RollupValue::Number)
+        .unwrap_or(RollupValue::Empty),
+      RollupCalculation::Concat => {
+        let texts = cells
+          .iter()
+          .flat_map(|row_cell| row_cell.cell.as_ref().and_then(cell_to_string))
+          .collect::<Vec<String>>();
+        if texts.is_empty() {
+          RollupValue::Empty
+        } else {
+          RollupValue::Text(texts.join(", "))
+        }
+      },
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RollupValue {
+  Number(f64),
+  Text(String),
+  Empty,
+}
+
+/// The resolved value of a lookup field.
+#[derive(Debug, Clone)]
+pub struct LookupCellData {
+  pub type_option: LookupTypeOption,
+  /// The target field's cells of the linked rows that are available locally.
+  pub values: Vec<RowCell>,
+  /// The linked rows that are not available yet, either because the relation database is not
+  /// loaded or because the rows are still being fetched from the remote.
+  pub pending_row_ids: Vec<RowId>,
+}
+
+impl LookupCellData {
+  pub fn is_complete(&self) -> bool {
+    self.pending_row_ids.is_empty()
+  }
+}
+
+/// The resolved value of a rollup field.
+#[derive(Debug, Clone)]
+pub struct RollupCellData {
+  pub type_option: RollupTypeOption,
+  pub value: RollupValue,
+  /// Same as [LookupCellData::pending_row_ids]. The value only covers the loaded rows when
+  /// it's not empty.
+  pub pending_row_ids: Vec<RowId>,
+}
+
+impl RollupCellData {
+  pub fn is_complete(&self) -> bool {
+    self.pending_row_ids.is_empty()
+  }
+}
+
+/// Emitted when the cached lookup and rollup values of the database's rows become stale. The
+/// receiver should resolve the values again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RelationCellChange {
+  Invalidated {
+    database_id: String,
+    relation_database_id: String,
+  },
+}
+
+pub type RelationCellChangeSender = broadcast::Sender<RelationCellChange>;
+pub type RelationCellChangeReceiver = broadcast::Receiver<RelationCellChange>;
+
+/// The key is (database_id, row_id, field_id)
+type RelationCellKey = (String, RowId, String);
+
+/// Caches the resolved lookup and rollup values. Only complete values are cached. The cache is
+/// invalidated when the row relations change or when the rows of an observed relation database
+/// change.
+pub(crate) struct RelationCellCache {
+  lookups: DashMap<RelationCellKey, LookupCellData>,
+  rollups: DashMap<RelationCellKey, RollupCellData>,
+  is_observing_relations: AtomicBool,
+  observed_databases: Mutex<HashSet<String>>,
+  tx: RelationCellChangeSender,
+}
+
+impl RelationCellCache {
+  pub(crate) fn new() -> Self {
+    let (tx, _) = broadcast::channel(1000);
+    Self {
+      lookups: DashMap::new(),
+      rollups: DashMap::new(),
+      is_observing_relations: AtomicBool::new(false),
+      observed_databases: Mutex::new(HashSet::new()),
+      tx,
+    }
+  }
+
+  pub(crate) fn subscribe(&self) -> RelationCellChangeReceiver {
+    self.tx.subscribe()
+  }
+
+  pub(crate) fn get_lookup(
+    &self,
+    key: &RelationCellKey,
+    type_option: &LookupTypeOption,
+  ) -> Option<LookupCellData> {
+    let data = self.lookups.get(key)?;
+    (&data.type_option == type_option).then(|| data.clone())
+  }
+
+  pub(crate) fn insert_lookup(&self, key: RelationCellKey, data: LookupCellData) {
+    if data.is_complete() {
+      self.lookups.insert(key, data);
+    }
+  }
+
+  pub(crate) fn get_rollup(
+    &self,
+    key: &RelationCellKey,
+    type_option: &RollupTypeOption,
+  ) -> Option<RollupCellData> {
+    let data = self.rollups.get(key)?;
+    (&data.type_option == type_option).then(|| data.clone())
+  }
+
+  pub(crate) fn insert_rollup(&self, key: RelationCellKey, data: RollupCellData) {
+    if data.is_complete() {
+      self.rollups.insert(key, data);
+    }
+  }
+
+  /// Remove the cached values of the rows in `database_id` that resolve through
+  /// `relation_database_id`. If `database_id` is None, the values of all databases that
+  /// resolve through `relation_database_id` are removed.
+  pub(crate) fn invalidate(&self, database_id: Option<&str>, relation_database_id: &str) {
+    let mut database_ids = HashSet::new();
+    let mut is_stale = |key: &RelationCellKey, other_relation_database_id: &str| {
+      let is_stale = other_relation_database_id == relation_database_id
+        && database_id.map(|id| id == key.0).unwrap_or(true);
+      if is_stale {
+        database_ids.insert(key.0.clone());
+      }
+      is_stale
+    };
+    self
+      .lookups
+      .retain(|key, data| !is_stale(key, &data.type_option.relation_database_id));
+    self
+      .rollups
+      .retain(|key, data| !is_stale(key, &data.type_option.relation_database_id));
+
+    // Notify even if nothing was cached, the pending values also need to be resolved again.
+    if let Some(database_id) = database_id {
+      database_ids.insert(database_id.to_string());
+    }
+    for database_id in database_ids {
+      let _ = self.tx.send(RelationCellChange::Invalidated {
+        database_id,
+        relation_database_id: relation_database_id.to_string(),
+      });
+    }
+  }
+
+  /// Remove all the cached values. Used when some changes were missed and it's unknown which
+  /// values are stale.
+  pub(crate) fn invalidate_all(&self) {
+    let mut pairs = HashSet::new();
+    self.lookups.retain(|key, data| {
+      pairs.insert((key.0.clone(), data.type_option.relation_database_id.clone()));
+      false
+    });
+    self.rollups.retain(|key, data| {
+      pairs.insert((key.0.clone(), data.type_option.relation_database_id.clone()));
+      false
+    });
+    for (database_id, relation_database_id) in pairs {
+      let _ = self.tx.send(RelationCellChange::Invalidated {
+        database_id,
+        relation_database_id,
+      });
+    }
+  }
+
+  /// Start observing the row relations. It's a no-op if the relations are already observed.
+  pub(crate) fn observe_relations(self: &Arc<Self>, mut rx: RowRelationUpdateReceiver) {
+    if self.is_observing_relations.swap(true, Ordering::SeqCst) {
+      return;
+    }
+    let weak_cache = Arc::downgrade(self);
+    tokio::spawn(async move {
+      loop {
+        let change = rx.recv().await;
+        let cache = match weak_cache.upgrade() {
+          None => break,
+          Some(cache) => cache,
+        };
+        let relation = match change {
+          Ok(
+            RowRelationChange::NewRelation(relation)
+            | RowRelationChange::UpdateRelation(relation)
+            | RowRelationChange::DeleteRelation(relation),
+          ) => relation,
+          Err(broadcast::error::RecvError::Lagged(_)) => {
+            // Some relation changes were missed, so any cached value might be stale.
+            cache.invalidate_all();
+            continue;
+          },
+          Err(broadcast::error::RecvError::Closed) => break,
+        };
+        cache.invalidate(
+          Some(&relation.linking_database_id),
+          &relation.linked_by_database_id,
+        );
+        cache.invalidate(
+          Some(&relation.linked_by_database_id),
+          &relation.linking_database_id,
+        );
+      }
+
+      // Allow the relations to be observed again by a new receiver.
+      if let Some(cache) = weak_cache.upgrade() {
+        cache.is_observing_relations.store(false, Ordering::SeqCst);
+      }
+    });
+  }
+
+  /// Start observing the rows of the relation database, including the rows fetched from the
+  /// remote. It's a no-op if the database is already observed.
+  pub(crate) fn observe_database(
+    self: &Arc<Self>,
+    database_id: &str,
+    row_change_rx: RowChangeReceiver,
+    block_event_rx: broadcast::Receiver<BlockEvent>,
+  ) {
+    if !self
+      .observed_databases
+      .lock()
+      .insert(database_id.to_string())
+    {
+      return;
+    }
+    trace!("observe relation database: {}", database_id);
+    tokio::spawn(invalidate_on_change(
+      Arc::downgrade(self),
+      database_id.to_string(),
+      row_change_rx,
+    ));
+    tokio::spawn(invalidate_on_change(
+      Arc::downgrade(self),
+      database_id.to_string(),
+      block_event_rx,
+    ));
+  }
+}
+
+async fn invalidate_on_change<T: Clone>(
+  weak_cache: Weak<RelationCellCache>,
+  database_id: String,
+  mut rx: broadcast::Receiver<T>,
+) {
+  loop {
+    match rx.recv().await {
+      Ok(_) => match weak_cache.upgrade() {
+        None => break,
+        Some(cache) => cache.invalidate(None, &database_id),
+      },
+      Err(broadcast::error::RecvError::Lagged(_)) => continue,
+      Err(broadcast::error::RecvError::Closed) => break,
+    }
+  }
+
+  // The database was closed. Allow it to be observed again after it's reopened.
+  if let Some(cache) = weak_cache.upgrade() {
+    cache.observed_databases.lock().remove(&database_id);
+  }
+}
+
+fn cell_to_f64(cell: &Cell) -> Option<f64> {
+  match cell.get(CELL_DATA)? {
+    Any::String(s) => s.trim().parse::<f64>().ok(),
+    Any::BigInt(value) => Some(*value as f64),
+    Any::Number(value) => Some(*value),
+    _ => None,
+  }
+}
+
+fn cell_to_string(cell: &Cell) -> Option<String> {
+  let s = any_to_string(cell.get(CELL_DATA)?)?;
+  if s.is_empty() {
+    None
+  } else {
+    Some(s)
+  }
+}
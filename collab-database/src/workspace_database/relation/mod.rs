@@ -1,7 +1,9 @@
 mod db_relation;
+mod lookup;
 mod row_relation;
 mod row_relation_map;
 
 pub use db_relation::*;
+pub use lookup::*;
 pub use row_relation::*;
 pub use row_relation_map::*;
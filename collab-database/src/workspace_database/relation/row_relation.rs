@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use collab::core::array_wrapper::ArrayRefExtension;
 use collab::core::value::YrsValueExtension;
@@ -11,6 +11,8 @@ use collab::preclude::{
 pub struct RowRelation {
   pub linking_database_id: String,
   pub linked_by_database_id: String,
+  /// The connections of the rows of both databases. The key is made by [make_row_connection_id],
+  /// so the rows of different databases that have the same id don't share a connection.
   pub row_connections: HashMap<String, RowConnection>,
 }
 
@@ -20,11 +22,23 @@ const ROW_CONNECTIONS: &str = "row_connections";
 
 impl RowRelation {
   pub fn id(&self) -> String {
-    format!(
-      "{}-{}",
-      self.linking_database_id, self.linked_by_database_id
-    )
+    make_row_relation_id(&self.linking_database_id, &self.linked_by_database_id)
   }
+
+  /// Return the connection of the row of the given database.
+  pub fn get_row_connection(&self, database_id: &str, row_id: &str) -> Option<&RowConnection> {
+    self
+      .row_connections
+      .get(&make_row_connection_id(database_id, row_id))
+  }
+}
+
+pub fn make_row_relation_id(linking_database_id: &str, linked_by_database_id: &str) -> String {
+  format!("{}-{}", linking_database_id, linked_by_database_id)
+}
+
+pub fn make_row_connection_id(database_id: &str, row_id: &str) -> String {
+  format!("{}/{}", database_id, row_id)
 }
 
 pub struct RowRelationBuilder<'a, 'b> {
@@ -65,10 +79,16 @@ impl<'a, 'b> RowRelationUpdate<'a, 'b> {
     Self { map_ref, txn }
   }
 
+  /// Set the connections of the rows. Each connection is stored with the id that is made from its
+  /// database id and row id, the keys of the map are ignored.
   pub fn set_row_connections(self, connections: HashMap<String, RowConnection>) -> Self {
-    connections.into_iter().for_each(|(k, v)| {
-      let map_ref = self.map_ref.get_or_create_map_with_txn(self.txn, &k);
-      RowConnectionBuilder::new(&v.row_id, self.txn, map_ref).update(|update| {
+    let connections_map = self
+      .map_ref
+      .get_or_create_map_with_txn(self.txn, ROW_CONNECTIONS);
+    connections.into_values().for_each(|v| {
+      let connection_id = make_row_connection_id(&v.database_id, &v.row_id);
+      let map_ref = connections_map.get_or_create_map_with_txn(self.txn, &connection_id);
+      RowConnectionBuilder::new(&v.database_id, &v.row_id, self.txn, map_ref).update(|update| {
         update
           .set_linking_rows(v.linking_rows)
           .set_linked_by_rows(v.linked_by_rows);
@@ -77,11 +97,154 @@ impl<'a, 'b> RowRelationUpdate<'a, 'b> {
     self
   }
 
+  /// Link the row of the linking database to the given rows of the linked database. The reverse
+  /// [LinkedByRow] is added to the connection of each linked row. Rows that are already linked
+  /// are skipped.
+  pub fn link_rows(self, row_id: &str, rows: Vec<LinkingRow>) -> Self {
+    let (linking_database_id, linked_by_database_id) = match self.database_ids() {
+      None => return self,
+      Some(database_ids) => database_ids,
+    };
+    let connections = self
+      .map_ref
+      .get_or_create_map_with_txn(self.txn, ROW_CONNECTIONS);
+    for row in rows {
+      let linked_row_id = row.row_id.clone();
+      let connection =
+        get_or_create_row_connection(self.txn, &connections, &linking_database_id, row_id);
+      RowConnectionUpdate::new(self.txn, &connection).add_linking_rows(vec![row]);
+
+      let linked_connection = get_or_create_row_connection(
+        self.txn,
+        &connections,
+        &linked_by_database_id,
+        &linked_row_id,
+      );
+      RowConnectionUpdate::new(self.txn, &linked_connection).add_linked_by_rows(vec![
+        LinkedByRow {
+          row_id: row_id.to_string(),
+        },
+      ]);
+    }
+    self
+  }
+
+  /// Unlink the row of the linking database from the given rows of the linked database,
+  /// including the reverse [LinkedByRow]s.
+  pub fn unlink_rows(self, row_id: &str, linking_row_ids: &[String]) -> Self {
+    let (linking_database_id, linked_by_database_id) = match self.database_ids() {
+      None => return self,
+      Some(database_ids) => database_ids,
+    };
+    let connections = match self.map_ref.get_map_with_txn(self.txn, ROW_CONNECTIONS) {
+      None => return self,
+      Some(connections) => connections,
+    };
+    let connection_id = make_row_connection_id(&linking_database_id, row_id);
+    if let Some(connection) = connections.get_map_with_txn(self.txn, &connection_id) {
+      RowConnectionUpdate::new(self.txn, &connection).remove_linking_rows(linking_row_ids);
+    }
+    for linked_row_id in linking_row_ids {
+      let linked_connection_id = make_row_connection_id(&linked_by_database_id, linked_row_id);
+      if let Some(connection) = connections.get_map_with_txn(self.txn, &linked_connection_id) {
+        RowConnectionUpdate::new(self.txn, &connection)
+          .remove_linked_by_rows(&[row_id.to_string()]);
+      }
+      remove_row_connection_if_empty(self.txn, &connections, &linked_connection_id);
+    }
+    remove_row_connection_if_empty(self.txn, &connections, &connection_id);
+    self
+  }
+
+  /// Remove the row of the given database from the relation. All the links from or to the row
+  /// are removed from both sides of the relation.
+  pub fn remove_row(self, database_id: &str, row_id: &str) -> Self {
+    let (linking_database_id, linked_by_database_id) = match self.database_ids() {
+      None => return self,
+      Some(database_ids) => database_ids,
+    };
+    let connections = match self.map_ref.get_map_with_txn(self.txn, ROW_CONNECTIONS) {
+      None => return self,
+      Some(connections) => connections,
+    };
+    let connection_id = make_row_connection_id(database_id, row_id);
+    let connection = match connections
+      .get_map_with_txn(self.txn, &connection_id)
+      .and_then(|map_ref| row_connection_from_map_ref(self.txn, &map_ref))
+    {
+      None => return self,
+      Some(connection) => connection,
+    };
+
+    // The row links to the rows of the linked database, and is linked by the rows of the linking
+    // database.
+    let removed_row_ids = [row_id.to_string()];
+    for linking_row in connection.linking_rows {
+      let id = make_row_connection_id(&linked_by_database_id, &linking_row.row_id);
+      if let Some(map_ref) = connections.get_map_with_txn(self.txn, &id) {
+        RowConnectionUpdate::new(self.txn, &map_ref).remove_linked_by_rows(&removed_row_ids);
+      }
+      remove_row_connection_if_empty(self.txn, &connections, &id);
+    }
+    for linked_by_row in connection.linked_by_rows {
+      let id = make_row_connection_id(&linking_database_id, &linked_by_row.row_id);
+      if let Some(map_ref) = connections.get_map_with_txn(self.txn, &id) {
+        RowConnectionUpdate::new(self.txn, &map_ref).remove_linking_rows(&removed_row_ids);
+      }
+      remove_row_connection_if_empty(self.txn, &connections, &id);
+    }
+    connections.remove(self.txn, &connection_id);
+    self
+  }
+
+  /// Return the ids of the linking database and the linked database.
+  fn database_ids(&self) -> Option<(String, String)> {
+    let linking_database_id = self.map_ref.get_str_with_txn(self.txn, LINKING_DB_ID)?;
+    let linked_by_database_id = self.map_ref.get_str_with_txn(self.txn, LINKED_BY_DB_ID)?;
+    Some((linking_database_id, linked_by_database_id))
+  }
+
   pub fn done(self) -> Option<RowRelation> {
     row_relation_from_map_ref(self.txn, self.map_ref)
   }
 }
 
+fn get_or_create_row_connection(
+  txn: &mut TransactionMut,
+  connections: &MapRef,
+  database_id: &str,
+  row_id: &str,
+) -> MapRef {
+  let connection_id = make_row_connection_id(database_id, row_id);
+  if let Some(map_ref) = connections.get_map_with_txn(txn, &connection_id) {
+    return map_ref;
+  }
+  let map_ref = connections.create_map_with_txn(txn, &connection_id);
+  RowConnectionBuilder::new(database_id, row_id, txn, map_ref.clone()).done();
+  map_ref
+}
+
+fn remove_row_connection_if_empty(
+  txn: &mut TransactionMut,
+  connections: &MapRef,
+  connection_id: &str,
+) {
+  let is_empty = connections
+    .get_map_with_txn(txn, connection_id)
+    .map(|map_ref| {
+      [LINKING_ROWS, LINKED_BY_ROWS].iter().all(|key| {
+        map_ref
+          .get_array_ref_with_txn(txn, key)
+          .map(|array_ref| array_ref.len(txn) == 0)
+          .unwrap_or(true)
+      })
+    })
+    .unwrap_or(false);
+  if is_empty {
+    connections.remove(txn, connection_id);
+  }
+}
+
 pub fn row_relation_from_map_ref<T: ReadTxn>(txn: &T, map_ref: &MapRef) -> Option<RowRelation> {
   let linking_database_id = map_ref.get_str_with_txn(txn, LINKING_DB_ID)?;
   let linked_by_database_id = map_ref.get_str_with_txn(txn, LINKED_BY_DB_ID)?;
@@ -108,11 +271,48 @@ pub fn row_relation_from_map_ref<T: ReadTxn>(txn: &T, map_ref: &MapRef) -> Optio
 
 #[derive(Debug, Clone)]
 pub struct RowConnection {
+  database_id: String,
   row_id: String,
   linking_rows: Vec<LinkingRow>,
   linked_by_rows: Vec<LinkedByRow>,
 }
 
+impl RowConnection {
+  pub fn new(
+    database_id: impl Into<String>,
+    row_id: impl Into<String>,
+    linking_rows: Vec<LinkingRow>,
+    linked_by_rows: Vec<LinkedByRow>,
+  ) -> Self {
+    Self {
+      database_id: database_id.into(),
+      row_id: row_id.into(),
+      linking_rows,
+      linked_by_rows,
+    }
+  }
+
+  /// The database that the row belongs to.
+  pub fn database_id(&self) -> &str {
+    &self.database_id
+  }
+
+  pub fn row_id(&self) -> &str {
+    &self.row_id
+  }
+
+  /// The rows of the linked database that this row links to.
+  pub fn linking_rows(&self) -> &[LinkingRow] {
+    &self.linking_rows
+  }
+
+  /// The rows of the linked database that link to this row.
+  pub fn linked_by_rows(&self) -> &[LinkedByRow] {
+    &self.linked_by_rows
+  }
+}
+
+const DATABASE_ID: &str = "database_id";
 const ROW_ID: &str = "row_id";
 const LINKING_ROWS: &str = "linking_rows";
 const LINKED_BY_ROWS: &str = "linked_by_rows";
@@ -123,8 +323,16 @@ pub struct RowConnectionBuilder<'a, 'b> {
 }
 
 impl<'a, 'b> RowConnectionBuilder<'a, 'b> {
-  pub fn new(id: &'a str, txn: &'a mut TransactionMut<'b>, map_ref: MapRef) -> Self {
+  pub fn new(
+    database_id: &'a str,
+    id: &'a str,
+    txn: &'a mut TransactionMut<'b>,
+    map_ref: MapRef,
+  ) -> Self {
+    map_ref.insert_str_with_txn(txn, DATABASE_ID, database_id);
     map_ref.insert_str_with_txn(txn, ROW_ID, id);
+    map_ref.get_or_create_array_with_txn::<MapPrelim<Any>>(txn, LINKING_ROWS);
+    map_ref.get_or_create_array_with_txn::<MapPrelim<Any>>(txn, LINKED_BY_ROWS);
     Self { map_ref, txn }
   }
 
@@ -172,12 +380,78 @@ impl<'a, 'b> RowConnectionUpdate<'a, 'b> {
     self
   }
 
+  /// Same as [RowConnectionUpdate::set_linking_rows] but skips the rows that already exist.
+  pub fn add_linking_rows(self, rows: Vec<LinkingRow>) -> Self {
+    let existing_row_ids = self.row_ids(LINKING_ROWS);
+    let rows = rows
+      .into_iter()
+      .filter(|row| !existing_row_ids.contains(&row.row_id))
+      .collect();
+    self.set_linking_rows(rows)
+  }
+
+  /// Same as [RowConnectionUpdate::set_linked_by_rows] but skips the rows that already exist.
+  pub fn add_linked_by_rows(self, rows: Vec<LinkedByRow>) -> Self {
+    let existing_row_ids = self.row_ids(LINKED_BY_ROWS);
+    let rows = rows
+      .into_iter()
+      .filter(|row| !existing_row_ids.contains(&row.row_id))
+      .collect();
+    self.set_linked_by_rows(rows)
+  }
+
+  pub fn remove_linking_rows(mut self, row_ids: &[String]) -> Self {
+    self.remove_rows(LINKING_ROWS, row_ids);
+    self
+  }
+
+  pub fn remove_linked_by_rows(mut self, row_ids: &[String]) -> Self {
+    self.remove_rows(LINKED_BY_ROWS, row_ids);
+    self
+  }
+
   pub fn done(self) -> Option<RowConnection> {
     row_connection_from_map_ref(self.txn, self.map_ref)
   }
+
+  fn row_ids(&self, key: &str) -> HashSet<String> {
+    self
+      .map_ref
+      .get_array_ref_with_txn(self.txn, key)
+      .map(|array_ref| {
+        array_ref
+          .iter(self.txn)
+          .flat_map(|value| value.to_ymap()?.get_str_with_txn(self.txn, ROW_ID))
+          .collect()
+      })
+      .unwrap_or_default()
+  }
+
+  fn remove_rows(&mut self, key: &str, row_ids: &[String]) {
+    if let Some(array_ref) = self.map_ref.get_array_ref_with_txn(self.txn, key) {
+      let indexes = array_ref
+        .iter(self.txn)
+        .enumerate()
+        .filter(|(_, value)| {
+          value
+            .clone()
+            .to_ymap()
+            .and_then(|map_ref| map_ref.get_str_with_txn(self.txn, ROW_ID))
+            .map(|row_id| row_ids.contains(&row_id))
+            .unwrap_or(false)
+        })
+        .map(|(index, _)| index as u32)
+        .collect::<Vec<_>>();
+      // Remove from the back so the remaining indexes stay valid.
+      for index in indexes.into_iter().rev() {
+        array_ref.remove(self.txn, index);
+      }
+    }
+  }
 }
 
 pub fn row_connection_from_map_ref<T: ReadTxn>(txn: &T, map_ref: &MapRef) -> Option<RowConnection> {
+  let database_id = map_ref.get_str_with_txn(txn, DATABASE_ID)?;
   let row_id = map_ref.get_str_with_txn(txn, ROW_ID)?;
   let linking_rows = map_ref
     .get_array_ref_with_txn(txn, LINKING_ROWS)?
@@ -190,6 +464,7 @@ pub fn row_connection_from_map_ref<T: ReadTxn>(txn: &T, map_ref: &MapRef) -> Opt
     .flat_map(|value| LinkedByRow::from_yrs_value(txn, value))
     .collect::<Vec<_>>();
   Some(RowConnection {
+    database_id,
     row_id,
     linking_rows,
     linked_by_rows,
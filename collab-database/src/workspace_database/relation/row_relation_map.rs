@@ -1,20 +1,52 @@
 use std::ops::Deref;
 
 use collab::preclude::{
-  DeepEventsSubscription, DeepObservable, EntryChange, Event, Map, MapRefWrapper, ToJson,
-  TransactionMut, YrsValue,
+  DeepEventsSubscription, DeepObservable, EntryChange, Event, Map, MapRef, MapRefWrapper,
+  PathSegment, ReadTxn, TransactionMut, YrsValue,
 };
 use tokio::sync::broadcast;
 
-use crate::workspace_database::relation::{RowRelation, RowRelationBuilder};
+use crate::workspace_database::relation::{
+  make_row_connection_id, make_row_relation_id, LinkingRow, RowConnection, RowRelation,
+  RowRelationBuilder, RowRelationUpdate,
+};
 use crate::workspace_database::row_relation_from_map_ref;
 
 #[derive(Debug, Clone)]
 pub enum RowRelationChange {
   NewRelation(RowRelation),
+  /// The row connections of an existing relation were changed.
+  UpdateRelation(RowRelation),
   DeleteRelation(RowRelation),
 }
 
+/// A link that only exists on one side of a relation, or that refers to a database or row that
+/// doesn't exist anymore.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DanglingLink {
+  /// The row links to `linking_row_id` but the reverse [LinkedByRow] doesn't exist.
+  MissingLinkedByRow {
+    relation_id: String,
+    row_id: String,
+    linking_row_id: String,
+  },
+  /// The row is linked by `linked_by_row_id` but the reverse [LinkingRow] doesn't exist.
+  MissingLinkingRow {
+    relation_id: String,
+    row_id: String,
+    linked_by_row_id: String,
+  },
+  DatabaseNotExist {
+    relation_id: String,
+    database_id: String,
+  },
+  RowNotExist {
+    relation_id: String,
+    database_id: String,
+    row_id: String,
+  },
+}
+
 pub type RowRelationUpdateSender = broadcast::Sender<RowRelationChange>;
 pub type RowRelationUpdateReceiver = broadcast::Receiver<RowRelationChange>;
 
@@ -59,6 +91,226 @@ impl RowRelationMap {
     });
   }
 
+  pub fn get_relation(
+    &self,
+    linking_database_id: &str,
+    linked_by_database_id: &str,
+  ) -> Option<RowRelation> {
+    let txn = self.container.transact();
+    self.get_relation_with_txn(&txn, linking_database_id, linked_by_database_id)
+  }
+
+  pub fn get_relation_with_txn<T: ReadTxn>(
+    &self,
+    txn: &T,
+    linking_database_id: &str,
+    linked_by_database_id: &str,
+  ) -> Option<RowRelation> {
+    let relation_id = make_row_relation_id(linking_database_id, linked_by_database_id);
+    let map_ref = self.container.get_map_with_txn(txn, &relation_id)?;
+    row_relation_from_map_ref(txn, &map_ref)
+  }
+
+  pub fn get_relations(&self) -> Vec<RowRelation> {
+    let txn = self.container.transact();
+    self
+      .container
+      .iter(&txn)
+      .flat_map(|(_, v)| row_relation_from_map_ref(&txn, &v.to_ymap()?))
+      .collect()
+  }
+
+  /// Return the connection of the row of the given database in the relation between the two
+  /// databases. The database is either the linking database or the linked database.
+  pub fn get_row_connection(
+    &self,
+    linking_database_id: &str,
+    linked_by_database_id: &str,
+    database_id: &str,
+    row_id: &str,
+  ) -> Option<RowConnection> {
+    self
+      .get_relation(linking_database_id, linked_by_database_id)?
+      .row_connections
+      .remove(&make_row_connection_id(database_id, row_id))
+  }
+
+  /// Link the row of the linking database to the rows of the linked database. The relation is
+  /// created if it doesn't exist.
+  pub fn link_rows(
+    &self,
+    linking_database_id: &str,
+    linked_by_database_id: &str,
+    row_id: &str,
+    rows: Vec<LinkingRow>,
+  ) {
+    self.container.with_transact_mut(|txn| {
+      self.link_rows_with_txn(
+        txn,
+        linking_database_id,
+        linked_by_database_id,
+        row_id,
+        rows,
+      )
+    })
+  }
+
+  pub fn link_rows_with_txn(
+    &self,
+    txn: &mut TransactionMut,
+    linking_database_id: &str,
+    linked_by_database_id: &str,
+    row_id: &str,
+    rows: Vec<LinkingRow>,
+  ) {
+    let relation_id = make_row_relation_id(linking_database_id, linked_by_database_id);
+    let map_ref = match self.container.get_map_with_txn(txn, &relation_id) {
+      Some(map_ref) => map_ref,
+      None => {
+        let map_ref = self.container.create_map_with_txn(txn, &relation_id);
+        RowRelationBuilder::new(
+          linking_database_id,
+          linked_by_database_id,
+          txn,
+          map_ref.clone(),
+        )
+        .done();
+        map_ref
+      },
+    };
+    RowRelationUpdate::new(txn, &map_ref).link_rows(row_id, rows);
+  }
+
+  pub fn unlink_rows(
+    &self,
+    linking_database_id: &str,
+    linked_by_database_id: &str,
+    row_id: &str,
+    linking_row_ids: &[String],
+  ) {
+    self.container.with_transact_mut(|txn| {
+      self.unlink_rows_with_txn(
+        txn,
+        linking_database_id,
+        linked_by_database_id,
+        row_id,
+        linking_row_ids,
+      )
+    })
+  }
+
+  pub fn unlink_rows_with_txn(
+    &self,
+    txn: &mut TransactionMut,
+    linking_database_id: &str,
+    linked_by_database_id: &str,
+    row_id: &str,
+    linking_row_ids: &[String],
+  ) {
+    let relation_id = make_row_relation_id(linking_database_id, linked_by_database_id);
+    if let Some(map_ref) = self.container.get_map_with_txn(txn, &relation_id) {
+      RowRelationUpdate::new(txn, &map_ref).unlink_rows(row_id, linking_row_ids);
+    }
+  }
+
+  /// Remove the links from or to the rows in all the relations of the database.
+  pub fn remove_rows(&self, database_id: &str, row_ids: &[String]) {
+    self
+      .container
+      .with_transact_mut(|txn| self.remove_rows_with_txn(txn, database_id, row_ids))
+  }
+
+  pub fn remove_rows_with_txn(
+    &self,
+    txn: &mut TransactionMut,
+    database_id: &str,
+    row_ids: &[String],
+  ) {
+    for relation_id in self.get_relation_ids_with_txn(txn, database_id) {
+      if let Some(map_ref) = self.container.get_map_with_txn(txn, &relation_id) {
+        let mut update = RowRelationUpdate::new(txn, &map_ref);
+        for row_id in row_ids {
+          update = update.remove_row(database_id, row_id);
+        }
+      }
+    }
+  }
+
+  /// Remove all the relations that the database is a part of.
+  pub fn remove_database_relations(&self, database_id: &str) {
+    self
+      .container
+      .with_transact_mut(|txn| self.remove_database_relations_with_txn(txn, database_id))
+  }
+
+  pub fn remove_database_relations_with_txn(&self, txn: &mut TransactionMut, database_id: &str) {
+    for relation_id in self.get_relation_ids_with_txn(txn, database_id) {
+      self.remove_relation_with_txn(txn, &relation_id);
+    }
+  }
+
+  /// Return the links that only exist on one side of their relation.
+  pub fn find_dangling_links(&self) -> Vec<DanglingLink> {
+    let mut links = vec![];
+    for relation in self.get_relations() {
+      let relation_id = relation.id();
+      for connection in relation.row_connections.values() {
+        let row_id = connection.row_id();
+        for linking_row in connection.linking_rows() {
+          let has_reverse = relation
+            .get_row_connection(&relation.linked_by_database_id, &linking_row.row_id)
+            .map(|other| {
+              other
+                .linked_by_rows()
+                .iter()
+                .any(|row| row.row_id == row_id)
+            })
+            .unwrap_or(false);
+          if !has_reverse {
+            links.push(DanglingLink::MissingLinkedByRow {
+              relation_id: relation_id.clone(),
+              row_id: row_id.to_string(),
+              linking_row_id: linking_row.row_id.clone(),
+            });
+          }
+        }
+
+        for linked_by_row in connection.linked_by_rows() {
+          let has_reverse = relation
+            .get_row_connection(&relation.linking_database_id, &linked_by_row.row_id)
+            .map(|other| other.linking_rows().iter().any(|row| row.row_id == row_id))
+            .unwrap_or(false);
+          if !has_reverse {
+            links.push(DanglingLink::MissingLinkingRow {
+              relation_id: relation_id.clone(),
+              row_id: row_id.to_string(),
+              linked_by_row_id: linked_by_row.row_id.clone(),
+            });
+          }
+        }
+      }
+    }
+    links
+  }
+
+  /// Return the ids of the relations that the database is a part of.
+  fn get_relation_ids_with_txn<T: ReadTxn>(&self, txn: &T, database_id: &str) -> Vec<String> {
+    self
+      .container
+      .iter(txn)
+      .flat_map(|(relation_id, value)| {
+        let relation = row_relation_from_map_ref(txn, &value.to_ymap()?)?;
+        if relation.linking_database_id == database_id
+          || relation.linked_by_database_id == database_id
+        {
+          Some(relation_id.to_string())
+        } else {
+          None
+        }
+      })
+      .collect()
+  }
+
   pub fn remove_relation(&self, relation_id: &str) {
     self.container.with_transact_mut(|txn| {
       self.remove_relation_with_txn(txn, relation_id);
@@ -74,8 +326,19 @@ fn subscription_changes(
   tx: RowRelationUpdateSender,
   container: &mut MapRefWrapper,
 ) -> DeepEventsSubscription {
+  let root = container.clone().into_inner();
   container.observe_deep(move |txn, events| {
     for deep_event in events.iter() {
+      // Changes made inside a relation, for example adding a row connection, are reported
+      // with a non-empty path whose first segment is the id of the relation.
+      if let Some(PathSegment::Key(relation_id)) = deep_event.path().front() {
+        if let Some(row_relation) = relation_from_root(txn, &root, relation_id) {
+          tracing::trace!("update: {:?}", row_relation);
+          let _ = tx.send(RowRelationChange::UpdateRelation(row_relation));
+        }
+        continue;
+      }
+
       match deep_event {
         Event::Text(_) => {},
         Event::Array(_) => {},
@@ -90,11 +353,15 @@ fn subscription_changes(
                   }
                 }
               },
-              EntryChange::Updated(_k, _v) => {
-                println!("update: {}", event.target().to_json(txn));
+              EntryChange::Updated(_, v) => {
+                if let YrsValue::YMap(map_ref) = v {
+                  if let Some(row_relation) = row_relation_from_map_ref(txn, map_ref) {
+                    tracing::trace!("update: {:?}", row_relation);
+                    let _ = tx.send(RowRelationChange::UpdateRelation(row_relation));
+                  }
+                }
               },
               EntryChange::Removed(v) => {
-                println!("remove: {}", event.target().to_json(txn));
                 if let YrsValue::YMap(map_ref) = v {
                   if let Some(row_relation) = row_relation_from_map_ref(txn, map_ref) {
                     tracing::trace!("delete: {:?}", row_relation);
@@ -112,6 +379,15 @@ fn subscription_changes(
   })
 }
 
+fn relation_from_root<T: ReadTxn>(
+  txn: &T,
+  root: &MapRef,
+  relation_id: &str,
+) -> Option<RowRelation> {
+  let map_ref = root.get(txn, relation_id)?.to_ymap()?;
+  row_relation_from_map_ref(txn, &map_ref)
+}
+
 impl Deref for RowRelationMap {
   type Target = MapRefWrapper;
 
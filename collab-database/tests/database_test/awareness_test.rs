@@ -0,0 +1,59 @@
+use std::sync::mpsc;
+
+use collab::core::origin::CollabOrigin;
+use collab::preclude::Collab;
+use collab_database::database_awareness::{
+  DatabaseAwarenessCell, DatabaseAwarenessState, DatabaseAwarenessUser,
+};
+
+use crate::database_test::helper::create_database_with_default_data;
+
+fn selected_cell_state(uid: i64, row_id: i64) -> DatabaseAwarenessState {
+  let mut state = DatabaseAwarenessState::new(DatabaseAwarenessUser {
+    uid,
+    device_id: uid.to_string(),
+  });
+  state.selected_cell = Some(DatabaseAwarenessCell {
+    view_id: "v1".to_string(),
+    row_id: row_id.into(),
+    field_id: "f1".to_string(),
+  });
+  state
+}
+
+#[tokio::test]
+async fn set_selected_cell_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  let state = selected_cell_state(1, 1);
+  database_test.set_awareness_local_state(state.clone());
+  assert_eq!(database_test.get_awareness_local_state(), Some(state));
+
+  database_test.clean_awareness_local_state();
+  assert_eq!(database_test.get_awareness_local_state(), None);
+}
+
+#[tokio::test]
+async fn subscribe_selected_cell_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  let (tx, rx) = mpsc::channel();
+  database_test.subscribe_awareness_state(move |states| {
+    tx.send(states).unwrap();
+  });
+
+  let mut client = Collab::new(2, "1", "2", vec![], true);
+  let state = selected_cell_state(2, 2);
+  client
+    .get_mut_awareness()
+    .set_local_typed_state(&state)
+    .unwrap();
+  let update = client.get_awareness().update().unwrap();
+  database_test
+    .get_collab()
+    .lock()
+    .get_mut_awareness()
+    .apply_update(update, &CollabOrigin::Empty)
+    .unwrap();
+
+  let states = rx.try_iter().last().unwrap();
+  assert_eq!(states.get(&client.get_doc().client_id()), Some(&state));
+}
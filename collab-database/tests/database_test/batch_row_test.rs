@@ -0,0 +1,219 @@
+use collab_database::error::DatabaseError;
+use collab_database::fields::FieldConstraints;
+use collab_database::rows::{
+  CellsBuilder, CreateRowParams, RowBatchChange, RowChange, RowChangeReceiver, RowId,
+};
+use collab_database::views::OrderObjectPosition;
+
+use crate::database_test::helper::{create_database_with_default_data, DatabaseTest};
+use crate::helper::TestTextCell;
+
+#[tokio::test]
+async fn batch_create_rows_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  let mut row_change_rx = database_test.subscribe_row_change();
+  let params = (4..7)
+    .map(|i| {
+      CreateRowParams::new(i, "1".to_string()).with_cells(
+        CellsBuilder::new()
+          .insert_cell("f1", TestTextCell::from(format!("{}f1cell", i).as_str()))
+          .build(),
+      )
+    })
+    .collect();
+  let row_orders = database_test
+    .batch_create_rows("v1", params, OrderObjectPosition::After("1".to_string()))
+    .unwrap();
+  assert_eq!(row_orders.len(), 3);
+
+  // The rows keep their order at the position
+  assert_eq!(view_row_ids(&database_test), vec![1, 4, 5, 6, 2, 3]);
+  assert_eq!(text_cell(&database_test, 5), "5f1cell");
+
+  let changes = drain_changes(&mut row_change_rx);
+  assert_eq!(changes.len(), 1);
+  assert!(matches!(
+    &changes[0],
+    RowBatchChange::DidCreateRows { view_id, row_ids } if view_id == "v1" && row_ids.len() == 3
+  ));
+}
+
+#[tokio::test]
+async fn batch_create_rows_violating_constraint_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  database_test
+    .update_field_constraints("f1", FieldConstraints::new().with_unique(true))
+    .unwrap();
+
+  // The second row duplicates the first one, so none of the rows is created
+  let params = ["4f1cell", "4f1cell"]
+    .iter()
+    .enumerate()
+    .map(|(i, text)| {
+      CreateRowParams::new(i as i64 + 4, "1".to_string()).with_cells(
+        CellsBuilder::new()
+          .insert_cell("f1", TestTextCell::from(*text))
+          .build(),
+      )
+    })
+    .collect();
+  let result = database_test.batch_create_rows("v1", params, OrderObjectPosition::End);
+  assert!(matches!(result, Err(DatabaseError::ConstraintViolation(_))));
+  assert_eq!(database_test.get_database_rows().len(), 3);
+
+  let result = database_test.batch_create_rows("v10", vec![], OrderObjectPosition::End);
+  assert!(matches!(result, Err(DatabaseError::DatabaseViewNotExist)));
+}
+
+#[tokio::test]
+async fn batch_update_cells_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  let mut row_change_rx = database_test.subscribe_row_change();
+  let cells = (1..4)
+    .map(|i| {
+      (
+        RowId::from(i),
+        CellsBuilder::new()
+          .insert_cell("f1", TestTextCell::from(format!("pasted {}", i).as_str()))
+          .insert_cell("f2", TestTextCell::from("pasted"))
+          .build(),
+      )
+    })
+    .collect();
+  database_test.batch_update_cells(cells).unwrap();
+  for i in 1..4 {
+    assert_eq!(text_cell(&database_test, i), format!("pasted {}", i));
+  }
+
+  // The changes of each row are not sent
+  let changes = drain_changes(&mut row_change_rx);
+  assert_eq!(changes.len(), 1);
+  match &changes[0] {
+    RowBatchChange::DidUpdateCells { cells } => {
+      assert_eq!(cells.len(), 3);
+      let mut field_ids = cells[0].1.clone();
+      field_ids.sort();
+      assert_eq!(field_ids, vec!["f1", "f2"]);
+    },
+    change => panic!("unexpected change: {:?}", change),
+  }
+
+  // The single row updates still send their changes
+  database_test
+    .update_row(&1.into(), |row_update| {
+      row_update.update_cells(|cells_update| {
+        cells_update.insert("f1", TestTextCell::from("typed"));
+      });
+    })
+    .unwrap();
+  assert!(matches!(
+    row_change_rx.try_recv(),
+    Ok(RowChange::DidUpdateCell { .. })
+  ));
+}
+
+#[tokio::test]
+async fn batch_update_field_cells_violating_constraint_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  database_test
+    .update_field_constraints("f1", FieldConstraints::new().with_unique(true))
+    .unwrap();
+
+  // Row 1 and row 2 would get the same value, so neither of them is updated
+  let result = database_test.batch_update_field_cells(
+    "f1",
+    vec![
+      (1.into(), TestTextCell::from("same").into()),
+      (2.into(), TestTextCell::from("same").into()),
+    ],
+  );
+  assert!(matches!(result, Err(DatabaseError::ConstraintViolation(_))));
+  assert_eq!(text_cell(&database_test, 1), "1f1cell");
+  assert_eq!(text_cell(&database_test, 2), "2f1cell");
+
+  // Swapping the values of two rows is allowed
+  database_test
+    .batch_update_field_cells(
+      "f1",
+      vec![
+        (1.into(), TestTextCell::from("3f1cell").into()),
+        (3.into(), TestTextCell::from("1f1cell").into()),
+      ],
+    )
+    .unwrap();
+  assert_eq!(text_cell(&database_test, 1), "3f1cell");
+  assert_eq!(text_cell(&database_test, 3), "1f1cell");
+
+  let result =
+    database_test.batch_update_field_cells("f1", vec![(10.into(), TestTextCell::from("").into())]);
+  assert!(matches!(result, Err(DatabaseError::InvalidRowID(_))));
+}
+
+#[tokio::test]
+async fn batch_move_rows_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  let mut row_change_rx = database_test.subscribe_row_change();
+  database_test
+    .batch_move_rows("v1", &[1.into(), 2.into()], OrderObjectPosition::End)
+    .unwrap();
+  assert_eq!(view_row_ids(&database_test), vec![3, 1, 2]);
+
+  database_test
+    .batch_move_rows("v1", &[2.into(), 1.into()], OrderObjectPosition::Start)
+    .unwrap();
+  assert_eq!(view_row_ids(&database_test), vec![2, 1, 3]);
+
+  let changes = drain_changes(&mut row_change_rx);
+  assert_eq!(changes.len(), 2);
+  assert!(matches!(&changes[0], RowBatchChange::DidMoveRows { .. }));
+
+  let result = database_test.batch_move_rows(
+    "v1",
+    &[1.into()],
+    OrderObjectPosition::After("1".to_string()),
+  );
+  assert!(matches!(result, Err(DatabaseError::InvalidRowID(_))));
+  let result = database_test.batch_move_rows("v1", &[10.into()], OrderObjectPosition::End);
+  assert!(matches!(result, Err(DatabaseError::InvalidRowID(_))));
+  assert_eq!(view_row_ids(&database_test), vec![2, 1, 3]);
+}
+
+#[tokio::test]
+async fn remove_rows_sends_one_change_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  let mut row_change_rx = database_test.subscribe_row_change();
+  database_test.remove_rows(&[1.into(), 3.into()]);
+  assert_eq!(view_row_ids(&database_test), vec![2]);
+
+  let changes = drain_changes(&mut row_change_rx);
+  assert_eq!(changes.len(), 1);
+  assert!(matches!(
+    &changes[0],
+    RowBatchChange::DidDeleteRows { row_ids } if row_ids.len() == 2
+  ));
+}
+
+/// Return the batch changes that were sent. Panic if any other change was sent.
+fn drain_changes(rx: &mut RowChangeReceiver) -> Vec<RowBatchChange> {
+  let mut changes = vec![];
+  while let Ok(change) = rx.try_recv() {
+    match change {
+      RowChange::DidBatchUpdate(change) => changes.push(change),
+      change => panic!("unexpected change: {:?}", change),
+    }
+  }
+  changes
+}
+
+fn view_row_ids(database_test: &DatabaseTest) -> Vec<i64> {
+  database_test
+    .get_row_orders_for_view("v1")
+    .iter()
+    .map(|row_order| row_order.id.parse::<i64>().unwrap())
+    .collect()
+}
+
+fn text_cell(database_test: &DatabaseTest, row_id: i64) -> String {
+  let cell = database_test.get_cell("f1", &row_id.into()).cell.unwrap();
+  TestTextCell::from(cell).0
+}
@@ -0,0 +1,249 @@
+use chrono::{Duration, NaiveDate, TimeZone};
+use chrono_tz::America::{New_York, Sao_Paulo};
+use chrono_tz::Tz;
+use collab_database::error::DatabaseError;
+use collab_database::fields::{Field, FieldType};
+use collab_database::rows::{CellValue, CellsBuilder, CreateRowParams, DateCellValue, RowId};
+use collab_database::views::{
+  start_of_day, CalendarLayout, CalendarLayoutSetting, DatabaseLayout, OrderObjectPosition,
+};
+
+use crate::database_test::helper::{
+  create_database, default_field_settings_by_layout, DatabaseTest,
+};
+
+#[test]
+fn calendar_visible_range_test() {
+  let mut setting = CalendarLayoutSetting::new("date".to_string());
+  setting.first_day_of_week = 1;
+  let day = date(2024, 3, 15);
+
+  // March 2024 starts on a Friday and April 1 is a Monday
+  assert_eq!(
+    setting.visible_range(day),
+    (date(2024, 2, 26), date(2024, 4, 1))
+  );
+
+  setting.layout_ty = CalendarLayout::Week;
+  assert_eq!(
+    setting.visible_range(day),
+    (date(2024, 3, 11), date(2024, 3, 18))
+  );
+
+  setting.first_day_of_week = 0;
+  assert_eq!(
+    setting.visible_range(day),
+    (date(2024, 3, 10), date(2024, 3, 17))
+  );
+
+  setting.layout_ty = CalendarLayout::Day;
+  assert_eq!(
+    setting.visible_range(day),
+    (date(2024, 3, 15), date(2024, 3, 16))
+  );
+}
+
+#[tokio::test]
+async fn get_calendar_events_test() {
+  let database_test = create_calendar_database().await;
+  let events = database_test
+    .get_calendar_events("v1", date(2024, 3, 1), date(2024, 4, 1), New_York)
+    .unwrap();
+  assert_eq!(events.len(), 2);
+
+  // The all-day event on the day that the daylight saving time starts only lasts 23 hours
+  let event = &events[0];
+  assert_eq!(event.row_id, row_id("all_day"));
+  assert_eq!(event.title, "Daylight saving");
+  assert!(event.is_all_day);
+  assert_eq!(event.start, local(2024, 3, 10, 0));
+  assert_eq!(event.end, local(2024, 3, 11, 0));
+  assert_eq!(event.end - event.start, Duration::hours(23));
+
+  let event = &events[1];
+  assert_eq!(event.row_id, row_id("meeting"));
+  assert!(!event.is_all_day);
+  assert_eq!(event.start, local(2024, 3, 9, 10));
+  assert_eq!(event.end, local(2024, 3, 9, 11));
+}
+
+#[tokio::test]
+async fn get_calendar_events_without_weekends_test() {
+  let database_test = create_calendar_database().await;
+  let mut setting = CalendarLayoutSetting::new("date".to_string());
+  setting.show_weekends = false;
+  database_test.insert_layout_setting("v1", &DatabaseLayout::Calendar, setting);
+
+  // Both events are on the weekend
+  let events = database_test
+    .get_calendar_events("v1", date(2024, 3, 1), date(2024, 4, 1), New_York)
+    .unwrap();
+  assert!(events.is_empty());
+}
+
+#[tokio::test]
+async fn move_timed_event_across_dst_test() {
+  let database_test = create_calendar_database().await;
+
+  // Move the meeting from 10:00 on Saturday (EST) to 10:00 on Sunday (EDT)
+  let event = database_test
+    .move_calendar_event("v1", &row_id("meeting"), local(2024, 3, 10, 10))
+    .unwrap();
+  assert_eq!(event.start, local(2024, 3, 10, 10));
+  assert_eq!(event.end - event.start, Duration::hours(1));
+
+  let value = database_test
+    .get_typed_cell("date", &row_id("meeting"))
+    .unwrap();
+  assert_eq!(
+    value,
+    Some(CellValue::Date(DateCellValue {
+      timestamp: 1710079200,
+      end_timestamp: Some(1710082800),
+      include_time: true,
+      timezone_id: "".to_string(),
+    }))
+  );
+}
+
+#[tokio::test]
+async fn move_all_day_event_across_dst_test() {
+  let database_test = create_calendar_database().await;
+  database_test
+    .update_typed_cell(
+      "date",
+      &row_id("all_day"),
+      CellValue::Date(DateCellValue {
+        timestamp: 1730505600,
+        end_timestamp: Some(1730592000),
+        ..Default::default()
+      }),
+    )
+    .unwrap();
+
+  // Move the two-day event from November 2 to November 3, the day that the daylight saving time
+  // ends. It still lasts two days, which is 49 hours in New York.
+  let event = database_test
+    .move_calendar_event("v1", &row_id("all_day"), local(2024, 11, 3, 0))
+    .unwrap();
+  assert_eq!(event.start, local(2024, 11, 3, 0));
+  assert_eq!(event.end, local(2024, 11, 5, 0));
+  assert_eq!(event.end - event.start, Duration::hours(49));
+
+  let value = database_test
+    .get_typed_cell("date", &row_id("all_day"))
+    .unwrap();
+  assert_eq!(
+    value,
+    Some(CellValue::Date(DateCellValue {
+      timestamp: 1730592000,
+      end_timestamp: Some(1730678400),
+      ..Default::default()
+    }))
+  );
+}
+
+#[tokio::test]
+async fn calendar_events_without_layout_setting_test() {
+  let database_test = create_database(1, "1").await;
+  let result = database_test.get_calendar_events("v1", date(2024, 3, 1), date(2024, 4, 1), Tz::UTC);
+  assert!(matches!(
+    result,
+    Err(DatabaseError::InvalidCalendarLayoutSetting(_))
+  ));
+}
+
+#[test]
+fn start_of_day_without_midnight_test() {
+  // The daylight saving time started at midnight in Sao Paulo on November 4, 2018
+  let start = start_of_day(&Sao_Paulo, date(2018, 11, 4)).unwrap();
+  assert_eq!(
+    start,
+    Sao_Paulo
+      .from_local_datetime(&date(2018, 11, 4).and_hms_opt(1, 0, 0).unwrap())
+      .unwrap()
+  );
+}
+
+async fn create_calendar_database() -> DatabaseTest {
+  let database_test = create_database(1, "1").await;
+  for (field_id, field_type, is_primary) in [
+    ("title", FieldType::RichText, true),
+    ("date", FieldType::DateTime, false),
+  ] {
+    database_test.create_field(
+      None,
+      Field::new(
+        field_id.to_string(),
+        field_type.default_name().to_string(),
+        field_type.value(),
+        is_primary,
+      ),
+      &OrderObjectPosition::default(),
+      default_field_settings_by_layout(),
+    );
+  }
+
+  let rows = [
+    (
+      "all_day",
+      "Daylight saving",
+      Some(DateCellValue {
+        timestamp: 1710028800,
+        ..Default::default()
+      }),
+    ),
+    (
+      "meeting",
+      "Meeting",
+      Some(DateCellValue {
+        timestamp: 1709996400,
+        end_timestamp: Some(1710000000),
+        include_time: true,
+        ..Default::default()
+      }),
+    ),
+    (
+      "later",
+      "Later",
+      Some(DateCellValue {
+        timestamp: 1713571200,
+        ..Default::default()
+      }),
+    ),
+    ("no_date", "No date", None),
+  ];
+  for (id, title, date) in rows {
+    let mut cells = CellsBuilder::new().insert_cell(
+      "title",
+      CellValue::Text(title.to_string()).to_cell(FieldType::RichText),
+    );
+    if let Some(date) = date {
+      cells = cells.insert_cell("date", CellValue::Date(date).to_cell(FieldType::DateTime));
+    }
+    database_test
+      .create_row(CreateRowParams::new(row_id(id), "1".to_string()).with_cells(cells.build()))
+      .unwrap();
+  }
+
+  database_test.insert_layout_setting(
+    "v1",
+    &DatabaseLayout::Calendar,
+    CalendarLayoutSetting::new("date".to_string()),
+  );
+  database_test
+}
+
+fn row_id(id: &str) -> RowId {
+  RowId::from(id.to_string())
+}
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+  NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+fn local(year: i32, month: u32, day: u32, hour: u32) -> chrono::DateTime<Tz> {
+  New_York
+    .from_local_datetime(&date(year, month, day).and_hms_opt(hour, 0, 0).unwrap())
+    .unwrap()
+}
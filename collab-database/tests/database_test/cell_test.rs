@@ -32,11 +32,13 @@ async fn update_cell_for_field_test() {
   let cells = database_test.get_cells_for_field("v1", "f1");
   assert_eq!(cells.len(), 3);
 
-  database_test.update_row(&1.into(), |row_update| {
-    row_update.update_cells(|cells_update| {
-      cells_update.insert("f1", TestTextCell("hello world".to_string()));
-    });
-  });
+  database_test
+    .update_row(&1.into(), |row_update| {
+      row_update.update_cells(|cells_update| {
+        cells_update.insert("f1", TestTextCell("hello world".to_string()));
+      });
+    })
+    .unwrap();
 
   let cells = database_test.get_cells_for_field("v1", "f1");
   assert_eq!(
@@ -56,11 +58,13 @@ async fn update_empty_cell_for_field_test() {
   let cells = database_test.get_cells_for_field("v1", "f2");
   assert_eq!(cells.len(), 3);
 
-  database_test.update_row(&3.into(), |row_update| {
-    row_update.update_cells(|cells_update| {
-      cells_update.insert("f2", TestTextCell("hello world".to_string()));
-    });
-  });
+  database_test
+    .update_row(&3.into(), |row_update| {
+      row_update.update_cells(|cells_update| {
+        cells_update.insert("f2", TestTextCell("hello world".to_string()));
+      });
+    })
+    .unwrap();
 
   let cells = database_test.get_cells_for_field("v1", "f2");
   assert_eq!(cells.len(), 3);
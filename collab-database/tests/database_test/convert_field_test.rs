@@ -0,0 +1,275 @@
+use collab_database::error::DatabaseError;
+use collab_database::fields::{
+  ConstraintViolationKind, Field, FieldChange, FieldConstraints, FieldType, SelectTypeOption,
+};
+use collab_database::rows::{CellValue, DateCellValue};
+use collab_database::views::OrderObjectPosition;
+
+use crate::database_test::helper::{
+  create_database_with_default_data, default_field_settings_by_layout, DatabaseTest,
+};
+
+#[tokio::test]
+async fn convert_text_to_number_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  create_field(&database_test, "f4", FieldType::RichText, None);
+  update_cell(&database_test, "f4", 1, CellValue::Text("12.5".to_string()));
+  update_cell(&database_test, "f4", 2, CellValue::Text("abc".to_string()));
+
+  let field = database_test
+    .convert_field_type("f4", FieldType::Number)
+    .unwrap();
+  assert_eq!(field.field_type, FieldType::Number.value());
+  assert_eq!(
+    database_test.get_typed_cell("f4", &1.into()).unwrap(),
+    Some(CellValue::Number(12.5))
+  );
+  // The text is not a number, so the cell is cleared
+  assert_eq!(database_test.get_typed_cell("f4", &2.into()).unwrap(), None);
+}
+
+#[tokio::test]
+async fn convert_select_option_to_text_and_back_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  let mut type_option = SelectTypeOption::default();
+  let todo_id = type_option.get_or_insert_option("Todo");
+  let done_id = type_option.get_or_insert_option("Done");
+  create_field(
+    &database_test,
+    "f4",
+    FieldType::MultiSelect,
+    Some(type_option),
+  );
+  update_cell(
+    &database_test,
+    "f4",
+    1,
+    CellValue::SelectOption(vec![todo_id.clone(), done_id.clone()]),
+  );
+
+  let field = database_test
+    .convert_field_type("f4", FieldType::RichText)
+    .unwrap();
+  assert_eq!(
+    database_test.get_typed_cell("f4", &1.into()).unwrap(),
+    Some(CellValue::Text("Todo, Done".to_string()))
+  );
+  // The type option of the select option field is kept
+  let type_option = field
+    .get_type_option::<SelectTypeOption>(FieldType::MultiSelect.value())
+    .unwrap();
+  assert_eq!(type_option.options.len(), 2);
+
+  // Converting back reuses the options
+  database_test
+    .convert_field_type("f4", FieldType::MultiSelect)
+    .unwrap();
+  assert_eq!(
+    database_test.get_typed_cell("f4", &1.into()).unwrap(),
+    Some(CellValue::SelectOption(vec![todo_id, done_id]))
+  );
+}
+
+#[tokio::test]
+async fn convert_text_to_select_option_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  create_field(&database_test, "f4", FieldType::RichText, None);
+  update_cell(&database_test, "f4", 1, CellValue::Text("a, b".to_string()));
+  update_cell(&database_test, "f4", 2, CellValue::Text("b".to_string()));
+
+  let field = database_test
+    .convert_field_type("f4", FieldType::SingleSelect)
+    .unwrap();
+  let type_option = field
+    .get_type_option::<SelectTypeOption>(FieldType::SingleSelect.value())
+    .unwrap();
+  assert_eq!(type_option.options.len(), 2);
+  let a_id = type_option.get_option_by_name("a").unwrap().id.clone();
+  let b_id = type_option.get_option_by_name("b").unwrap().id.clone();
+
+  // A single select cell only keeps the first option
+  assert_eq!(
+    database_test.get_typed_cell("f4", &1.into()).unwrap(),
+    Some(CellValue::SelectOption(vec![a_id]))
+  );
+  assert_eq!(
+    database_test.get_typed_cell("f4", &2.into()).unwrap(),
+    Some(CellValue::SelectOption(vec![b_id]))
+  );
+}
+
+#[tokio::test]
+async fn convert_date_to_text_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  create_field(&database_test, "f4", FieldType::DateTime, None);
+  update_cell(
+    &database_test,
+    "f4",
+    1,
+    CellValue::Date(DateCellValue {
+      timestamp: 1704067200,
+      ..Default::default()
+    }),
+  );
+
+  database_test
+    .convert_field_type("f4", FieldType::RichText)
+    .unwrap();
+  assert_eq!(
+    database_test.get_typed_cell("f4", &1.into()).unwrap(),
+    Some(CellValue::Text("Jan 01, 2024".to_string()))
+  );
+}
+
+#[tokio::test]
+async fn convert_checkbox_to_text_and_back_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  create_field(&database_test, "f4", FieldType::Checkbox, None);
+  update_cell(&database_test, "f4", 1, CellValue::Checkbox(true));
+  update_cell(&database_test, "f4", 2, CellValue::Checkbox(false));
+
+  database_test
+    .convert_field_type("f4", FieldType::RichText)
+    .unwrap();
+  assert_eq!(
+    database_test.get_typed_cell("f4", &1.into()).unwrap(),
+    Some(CellValue::Text("Yes".to_string()))
+  );
+  assert_eq!(
+    database_test.get_typed_cell("f4", &2.into()).unwrap(),
+    Some(CellValue::Text("No".to_string()))
+  );
+
+  database_test
+    .convert_field_type("f4", FieldType::Checkbox)
+    .unwrap();
+  assert_eq!(
+    database_test.get_typed_cell("f4", &1.into()).unwrap(),
+    Some(CellValue::Checkbox(true))
+  );
+  assert_eq!(
+    database_test.get_typed_cell("f4", &2.into()).unwrap(),
+    Some(CellValue::Checkbox(false))
+  );
+}
+
+#[tokio::test]
+async fn convert_field_type_emits_single_field_change_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  create_field(&database_test, "f4", FieldType::RichText, None);
+
+  let mut field_change_rx = database_test.subscribe_field_change();
+  database_test
+    .convert_field_type("f4", FieldType::SingleSelect)
+    .unwrap();
+
+  let mut changes = vec![];
+  while let Ok(change) = field_change_rx.try_recv() {
+    changes.push(change);
+  }
+  assert_eq!(changes.len(), 1);
+  match &changes[0] {
+    FieldChange::DidUpdateField { field } => {
+      assert_eq!(field.id, "f4");
+      assert_eq!(field.field_type, FieldType::SingleSelect.value());
+    },
+    change => panic!("unexpected field change: {:?}", change),
+  }
+}
+
+#[tokio::test]
+async fn convert_field_type_checks_constraints_of_new_type_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  create_field(&database_test, "f4", FieldType::Number, None);
+  database_test
+    .update_field_constraints("f4", FieldConstraints::new().with_max(10.0))
+    .unwrap();
+  database_test
+    .convert_field_type("f4", FieldType::RichText)
+    .unwrap();
+  update_cell(&database_test, "f4", 1, CellValue::Text("5".to_string()));
+  update_cell(&database_test, "f4", 2, CellValue::Text("20".to_string()));
+
+  // The constraints of the number type are checked before any cell is converted
+  let err = database_test
+    .convert_field_type("f4", FieldType::Number)
+    .unwrap_err();
+  match err {
+    DatabaseError::ConstraintViolation(violation) => {
+      assert_eq!(violation.row_id, 2.into());
+      assert_eq!(
+        violation.kind,
+        ConstraintViolationKind::GreaterThanMax(10.0)
+      );
+    },
+    err => panic!("unexpected error: {:?}", err),
+  }
+  let field = database_test.fields.get_field("f4").unwrap();
+  assert_eq!(field.field_type, FieldType::RichText.value());
+  assert_eq!(
+    database_test.get_typed_cell("f4", &1.into()).unwrap(),
+    Some(CellValue::Text("5".to_string()))
+  );
+}
+
+#[tokio::test]
+async fn convert_field_type_checks_required_for_rows_without_cell_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  create_field(&database_test, "f4", FieldType::Number, None);
+  database_test
+    .update_field_constraints("f4", FieldConstraints::new().with_required(true))
+    .unwrap();
+  database_test
+    .convert_field_type("f4", FieldType::RichText)
+    .unwrap();
+  update_cell(&database_test, "f4", 1, CellValue::Text("1".to_string()));
+  update_cell(&database_test, "f4", 3, CellValue::Text("3".to_string()));
+
+  // The second row has no cell in the field, so it violates the required constraint of the
+  // number type
+  let err = database_test
+    .convert_field_type("f4", FieldType::Number)
+    .unwrap_err();
+  match err {
+    DatabaseError::ConstraintViolation(violation) => {
+      assert_eq!(violation.row_id, 2.into());
+      assert_eq!(violation.kind, ConstraintViolationKind::Required);
+    },
+    err => panic!("unexpected error: {:?}", err),
+  }
+  let field = database_test.fields.get_field("f4").unwrap();
+  assert_eq!(field.field_type, FieldType::RichText.value());
+  assert_eq!(
+    database_test.get_typed_cell("f4", &1.into()).unwrap(),
+    Some(CellValue::Text("1".to_string()))
+  );
+}
+
+fn create_field(
+  database_test: &DatabaseTest,
+  field_id: &str,
+  field_type: FieldType,
+  type_option: Option<SelectTypeOption>,
+) {
+  let mut field = Field::new(
+    field_id.to_string(),
+    field_type.default_name().to_string(),
+    field_type.value(),
+    false,
+  );
+  if let Some(type_option) = type_option {
+    field = field.with_type_option_data(field_type.value(), type_option.into());
+  }
+  database_test.create_field(
+    None,
+    field,
+    &OrderObjectPosition::default(),
+    default_field_settings_by_layout(),
+  );
+}
+
+fn update_cell(database_test: &DatabaseTest, field_id: &str, row_id: i64, value: CellValue) {
+  database_test
+    .update_typed_cell(field_id, &row_id.into(), value)
+    .unwrap();
+}
@@ -0,0 +1,165 @@
+use collab::core::any_map::AnyMapExtension;
+use collab_database::csv::{export_csv, infer_field_type};
+use collab_database::fields::{DateTypeOption, FieldType, SelectTypeOption};
+use collab_database::rows::{CELL_DATA, CHECKBOX_CHECKED};
+use collab_database::views::FieldSettingsMapBuilder;
+
+use crate::user_test::helper::workspace_database_test;
+
+const TASKS_CSV: &str = r#"Name,Status,Done,Estimate,Due,Link,Tags
+Task 1,Todo,Yes,3,2024-01-02,https://appflowy.io,"a, b"
+Task 2,Done,No,1.5,2024-01-03,https://github.com,a
+Task 3,Todo,yes,2,2024-01-04,,b
+"#;
+
+#[test]
+fn infer_field_type_test() {
+  assert_eq!(infer_field_type(&["1", "2.5", "-3"]), FieldType::Number);
+  assert_eq!(infer_field_type(&["Yes", "no", ""]), FieldType::Checkbox);
+  assert_eq!(
+    infer_field_type(&["2024-01-02", "2024/01/03"]),
+    FieldType::DateTime
+  );
+  assert_eq!(
+    infer_field_type(&["https://appflowy.io", "http://github.com"]),
+    FieldType::URL
+  );
+  assert_eq!(infer_field_type(&["a", "b", "a"]), FieldType::SingleSelect);
+  assert_eq!(
+    infer_field_type(&["a, b", "b", "a"]),
+    FieldType::MultiSelect
+  );
+  assert_eq!(infer_field_type(&["a", "b", "c"]), FieldType::RichText);
+  assert_eq!(infer_field_type(&["", ""]), FieldType::RichText);
+}
+
+#[tokio::test]
+async fn import_csv_test() {
+  let test = workspace_database_test(1).await;
+  let database = test
+    .import_csv("v1", "tasks", TASKS_CSV.as_bytes())
+    .unwrap();
+  let database = database.lock();
+
+  let fields = database.get_fields_in_view("v1", None);
+  let field_types = fields
+    .iter()
+    .map(|field| FieldType::try_from(field.field_type).unwrap())
+    .collect::<Vec<_>>();
+  assert_eq!(
+    field_types,
+    vec![
+      FieldType::RichText,
+      FieldType::SingleSelect,
+      FieldType::Checkbox,
+      FieldType::Number,
+      FieldType::DateTime,
+      FieldType::URL,
+      FieldType::MultiSelect,
+    ]
+  );
+  assert!(fields[0].is_primary);
+
+  let rows = database.get_rows_for_view("v1");
+  assert_eq!(rows.len(), 3);
+  let name = |index: usize| {
+    rows[index]
+      .cells
+      .get(&fields[0].id)
+      .unwrap()
+      .get_str_value(CELL_DATA)
+      .unwrap()
+  };
+  assert_eq!(name(0), "Task 1");
+  assert_eq!(name(2), "Task 3");
+
+  let done = rows[2].cells.get(&fields[2].id).unwrap();
+  assert_eq!(done.get_str_value(CELL_DATA).unwrap(), CHECKBOX_CHECKED);
+  assert!(rows[2].cells.get(&fields[5].id).is_none());
+
+  let tags = fields[6]
+    .get_type_option::<SelectTypeOption>(fields[6].field_type)
+    .unwrap();
+  assert_eq!(tags.options.len(), 2);
+  let tag_ids = rows[0]
+    .cells
+    .get(&fields[6].id)
+    .unwrap()
+    .get_str_value(CELL_DATA)
+    .unwrap();
+  assert_eq!(tags.get_option_names(&tag_ids), vec!["a", "b"]);
+}
+
+#[tokio::test]
+async fn export_csv_test() {
+  let test = workspace_database_test(1).await;
+  let database = test
+    .import_csv("v1", "tasks", TASKS_CSV.as_bytes())
+    .unwrap();
+  let database = database.lock();
+
+  let content = export_csv(&database, "v1").unwrap();
+  let lines = content.lines().collect::<Vec<_>>();
+  assert_eq!(lines.len(), 4);
+  assert_eq!(lines[0], "Name,Status,Done,Estimate,Due,Link,Tags");
+  assert_eq!(
+    lines[1],
+    r#"Task 1,Todo,Yes,3,"Jan 02, 2024",https://appflowy.io,"a, b""#
+  );
+  assert_eq!(lines[3], r#"Task 3,Todo,Yes,2,"Jan 04, 2024",,b"#);
+}
+
+#[tokio::test]
+async fn export_csv_in_field_timezone_test() {
+  let test = workspace_database_test(1).await;
+  let database = test
+    .import_csv("v1", "tasks", TASKS_CSV.as_bytes())
+    .unwrap();
+  let database = database.lock();
+  let due_field = database
+    .get_fields_in_view("v1", None)
+    .into_iter()
+    .find(|field| field.name == "Due")
+    .unwrap();
+  let mut type_option = due_field
+    .get_type_option::<DateTypeOption>(due_field.field_type)
+    .unwrap_or_default();
+  type_option.timezone_id = "America/New_York".to_string();
+  database.fields.update_field(&due_field.id, |update| {
+    update.set_type_option(due_field.field_type, Some(type_option.into()));
+  });
+
+  // The dates are imported at midnight UTC, which is the day before in New York.
+  let content = export_csv(&database, "v1").unwrap();
+  let lines = content.lines().collect::<Vec<_>>();
+  assert_eq!(
+    lines[1],
+    r#"Task 1,Todo,Yes,3,"Jan 01, 2024",https://appflowy.io,"a, b""#
+  );
+}
+
+#[tokio::test]
+async fn export_csv_skip_hidden_field_test() {
+  let test = workspace_database_test(1).await;
+  let database = test
+    .import_csv("v1", "tasks", TASKS_CSV.as_bytes())
+    .unwrap();
+  let database = database.lock();
+  let link_field = database
+    .get_fields_in_view("v1", None)
+    .into_iter()
+    .find(|field| field.name == "Link")
+    .unwrap();
+  database.update_field_settings(
+    "v1",
+    Some(vec![link_field.id]),
+    FieldSettingsMapBuilder::new()
+      .insert_i64_value("visibility", 2)
+      .build(),
+  );
+
+  let content = export_csv(&database, "v1").unwrap();
+  let lines = content.lines().collect::<Vec<_>>();
+  assert_eq!(lines[0], "Name,Status,Done,Estimate,Due,Tags");
+  assert_eq!(lines[2], r#"Task 2,Done,No,1.5,"Jan 03, 2024",a"#);
+}
@@ -0,0 +1,267 @@
+use collab::core::any_map::AnyMapExtension;
+use collab_database::error::DatabaseError;
+use collab_database::fields::{
+  ConstraintViolation, ConstraintViolationKind, FieldConstraints, FieldType,
+};
+use collab_database::rows::{CellValue, CellsBuilder, CreateRowParams, RowId, CELL_DATA};
+
+use crate::database_test::helper::{create_database_with_default_data, DatabaseTest};
+use crate::helper::TestTextCell;
+
+#[tokio::test]
+async fn update_and_get_field_constraints_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  assert!(database_test
+    .get_field_constraints("f1")
+    .unwrap()
+    .is_empty());
+
+  let constraints = FieldConstraints::new()
+    .with_required(true)
+    .with_unique(true)
+    .with_pattern("[0-9]f1cell".to_string())
+    .with_max_length(10);
+  database_test
+    .update_field_constraints("f1", constraints.clone())
+    .unwrap();
+  assert_eq!(
+    database_test.get_field_constraints("f1").unwrap(),
+    constraints
+  );
+
+  // Removing the constraints deletes them from the type option
+  database_test
+    .update_field_constraints("f1", FieldConstraints::new().with_required(true))
+    .unwrap();
+  assert_eq!(
+    database_test.get_field_constraints("f1").unwrap(),
+    FieldConstraints::new().with_required(true)
+  );
+
+  let result = database_test
+    .update_field_constraints("f1", FieldConstraints::new().with_pattern("(".to_string()));
+  assert!(matches!(
+    result,
+    Err(DatabaseError::InvalidFieldConstraint(_))
+  ));
+  let result = database_test
+    .update_field_constraints("f3", FieldConstraints::new().with_min(10.0).with_max(1.0));
+  assert!(matches!(
+    result,
+    Err(DatabaseError::InvalidFieldConstraint(_))
+  ));
+  let result = database_test.update_field_constraints("f10", FieldConstraints::new());
+  assert!(matches!(result, Err(DatabaseError::FieldNotExist)));
+}
+
+#[tokio::test]
+async fn update_row_violating_text_constraints_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  database_test
+    .update_field_constraints(
+      "f1",
+      FieldConstraints::new()
+        .with_required(true)
+        .with_pattern("[0-9]f1cell".to_string())
+        .with_max_length(7),
+    )
+    .unwrap();
+
+  let violation = update_text_cell(&database_test, 1, None).unwrap_err();
+  assert_eq!(violation.kind, ConstraintViolationKind::Required);
+  let violation = update_text_cell(&database_test, 1, Some("10f1cell")).unwrap_err();
+  assert_eq!(violation.kind, ConstraintViolationKind::TooLong(7));
+  let violation = update_text_cell(&database_test, 1, Some("af1cell")).unwrap_err();
+  assert_eq!(
+    violation.kind,
+    ConstraintViolationKind::PatternMismatch("[0-9]f1cell".to_string())
+  );
+  assert_eq!(violation.row_id, RowId::from(1));
+  assert_eq!(violation.field_id, "f1");
+
+  // The rejected updates are reverted and not recorded in the history
+  assert_eq!(text_cell(&database_test, 1), Some("1f1cell".to_string()));
+  assert!(database_test.get_row_history(&1.into()).is_empty());
+
+  update_text_cell(&database_test, 1, Some("9f1cell")).unwrap();
+  assert_eq!(text_cell(&database_test, 1), Some("9f1cell".to_string()));
+}
+
+#[tokio::test]
+async fn create_and_update_row_violating_unique_constraint_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  database_test
+    .update_field_constraints("f1", FieldConstraints::new().with_unique(true))
+    .unwrap();
+
+  let violation = update_text_cell(&database_test, 2, Some("1f1cell")).unwrap_err();
+  assert_eq!(
+    violation.kind,
+    ConstraintViolationKind::Unique {
+      other_row_id: 1.into()
+    }
+  );
+  assert_eq!(text_cell(&database_test, 2), Some("2f1cell".to_string()));
+
+  // Writing the same value into the same row is not a violation
+  update_text_cell(&database_test, 2, Some("2f1cell")).unwrap();
+
+  let params = CreateRowParams::new(4, "1".to_string()).with_cells(
+    CellsBuilder::new()
+      .insert_cell("f1", TestTextCell::from("3f1cell"))
+      .build(),
+  );
+  let result = database_test.create_row(params);
+  assert!(matches!(
+    result,
+    Err(DatabaseError::ConstraintViolation(ConstraintViolation {
+      kind: ConstraintViolationKind::Unique { .. },
+      ..
+    }))
+  ));
+  assert_eq!(database_test.get_database_rows().len(), 3);
+}
+
+#[tokio::test]
+async fn unique_constraint_after_row_update_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  database_test
+    .update_field_constraints("f1", FieldConstraints::new().with_unique(true))
+    .unwrap();
+  update_text_cell(&database_test, 2, Some("a")).unwrap();
+
+  // The values written by the other updates are taken into account by the later checks
+  database_test
+    .update_row(&3.into(), |row_update| {
+      row_update.update_cells(|cells_update| {
+        cells_update.insert("f1", TestTextCell::from("b"));
+      });
+    })
+    .unwrap();
+  let violation = update_text_cell(&database_test, 2, Some("b")).unwrap_err();
+  assert_eq!(
+    violation.kind,
+    ConstraintViolationKind::Unique {
+      other_row_id: 3.into()
+    }
+  );
+
+  // The value of a removed row can be used again
+  database_test.remove_row(&3.into());
+  update_text_cell(&database_test, 2, Some("b")).unwrap();
+  update_text_cell(&database_test, 1, Some("a")).unwrap();
+}
+
+#[tokio::test]
+async fn update_missing_row_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  database_test
+    .update_field_constraints("f1", FieldConstraints::new().with_required(true))
+    .unwrap();
+  let result = database_test.update_row(&100.into(), |row_update| {
+    row_update.update_cells(|cells_update| {
+      cells_update.insert("f1", TestTextCell::from("hello"));
+    });
+  });
+  assert!(matches!(result, Err(DatabaseError::RowNotExist)));
+}
+
+#[tokio::test]
+async fn create_and_update_row_violating_number_constraints_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  database_test
+    .update_field_constraints("f3", FieldConstraints::new().with_min(0.0).with_max(100.0))
+    .unwrap();
+
+  let params = CreateRowParams::new(4, "1".to_string()).with_cells(
+    CellsBuilder::new()
+      .insert_cell("f3", CellValue::Number(150.0).to_cell(FieldType::Number))
+      .build(),
+  );
+  let result = database_test.create_row(params);
+  assert!(matches!(
+    result,
+    Err(DatabaseError::ConstraintViolation(ConstraintViolation {
+      kind: ConstraintViolationKind::GreaterThanMax(_),
+      ..
+    }))
+  ));
+
+  let result = database_test.update_typed_cell("f3", &1.into(), CellValue::Number(-1.0));
+  assert!(matches!(
+    result,
+    Err(DatabaseError::ConstraintViolation(ConstraintViolation {
+      kind: ConstraintViolationKind::LessThanMin(_),
+      ..
+    }))
+  ));
+  database_test
+    .update_typed_cell("f3", &1.into(), CellValue::Number(42.0))
+    .unwrap();
+}
+
+#[tokio::test]
+async fn find_constraint_violations_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  assert!(database_test.find_constraint_violations().is_empty());
+
+  update_text_cell(&database_test, 2, Some("1f1cell")).unwrap();
+  update_text_cell(&database_test, 3, None).unwrap();
+  database_test
+    .update_field_constraints(
+      "f1",
+      FieldConstraints::new()
+        .with_required(true)
+        .with_unique(true),
+    )
+    .unwrap();
+
+  let violations = database_test.find_constraint_violations();
+  assert_eq!(
+    violations,
+    vec![
+      ConstraintViolation {
+        row_id: 2.into(),
+        field_id: "f1".to_string(),
+        kind: ConstraintViolationKind::Unique {
+          other_row_id: 1.into()
+        },
+      },
+      ConstraintViolation {
+        row_id: 3.into(),
+        field_id: "f1".to_string(),
+        kind: ConstraintViolationKind::Required,
+      },
+    ]
+  );
+}
+
+/// Write the text into the f1 cell of the row, or clear the cell if the text is None.
+fn update_text_cell(
+  database_test: &DatabaseTest,
+  row_id: i64,
+  text: Option<&str>,
+) -> Result<(), ConstraintViolation> {
+  let result = database_test.update_row(&row_id.into(), |row_update| {
+    row_update.update_cells(|cells_update| match text {
+      None => {
+        cells_update.clear("f1");
+      },
+      Some(text) => {
+        cells_update.insert("f1", TestTextCell::from(text));
+      },
+    });
+  });
+  match result {
+    Ok(()) => Ok(()),
+    Err(DatabaseError::ConstraintViolation(violation)) => Err(violation),
+    Err(err) => panic!("unexpected error: {}", err),
+  }
+}
+
+fn text_cell(database_test: &DatabaseTest, row_id: i64) -> Option<String> {
+  let cell = database_test.get_cell("f1", &row_id.into()).cell?;
+  cell
+    .get_str_value(CELL_DATA)
+    .filter(|text| !text.is_empty())
+}
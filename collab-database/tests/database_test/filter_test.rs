@@ -1,7 +1,8 @@
 use collab::core::any_map::AnyMapExtension;
+use collab_database::rows::{CellsBuilder, CreateRowParams};
 
 use crate::database_test::helper::{create_database_with_default_data, DatabaseTest};
-use crate::helper::{TestFieldType, TestFilter, FILTER_CONTENT};
+use crate::helper::{TestFieldType, TestFilter, TestTextCell, FILTER_CONTENT};
 
 #[tokio::test]
 async fn create_database_view_with_filter_test() {
@@ -78,6 +79,37 @@ async fn remove_database_view_filter_test() {
   assert!(filter_1.is_none());
 }
 
+#[tokio::test]
+async fn create_row_in_filtered_view_test() {
+  let database_test = create_database_with_two_filters().await;
+  // The cells of the filters are only filled in on request
+  let (_, row_order) = database_test
+    .create_row_in_view("v1", CreateRowParams::new(6, "1".to_string()))
+    .unwrap();
+  assert!(database_test.get_row(&row_order.id).cells.is_empty());
+
+  let params = CreateRowParams::new(4, "1".to_string()).with_filter_cells(true);
+  let (_, row_order) = database_test.create_row_in_view("v1", params).unwrap();
+
+  // The text filter implies the cell. The date filter has no date, so it doesn't imply any cell
+  let row = database_test.get_row(&row_order.id);
+  let cell = row.cells.get("f1").cloned().unwrap();
+  assert_eq!(TestTextCell::from(cell).0, "hello filter");
+  assert!(row.cells.get("f2").is_none());
+
+  // The cells of the params take precedence over the filters
+  let params = CreateRowParams::new(5, "1".to_string())
+    .with_cells(
+      CellsBuilder::new()
+        .insert_cell("f1", TestTextCell::from("my cell"))
+        .build(),
+    )
+    .with_filter_cells(true);
+  let (_, row_order) = database_test.create_row_in_view("v1", params).unwrap();
+  let cell = database_test.get_cell("f1", &row_order.id).cell.unwrap();
+  assert_eq!(TestTextCell::from(cell).0, "my cell");
+}
+
 async fn create_database_with_two_filters() -> DatabaseTest {
   let database_test = create_database_with_default_data(1, "1").await;
   let filter_1 = TestFilter {
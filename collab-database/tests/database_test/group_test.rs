@@ -1,5 +1,7 @@
 use collab::core::any_map::AnyMapExtension;
-use collab_database::views::{CreateViewParams, DatabaseLayout};
+use collab_database::fields::{Field, FieldType};
+use collab_database::rows::{CellValue, CreateRowParams};
+use collab_database::views::{CreateViewParams, DatabaseLayout, OrderObjectPosition};
 
 use crate::database_test::helper::{create_database_with_default_data, DatabaseTest};
 use crate::helper::{TestGroup, TestGroupSetting, CONTENT, GROUPS};
@@ -177,6 +179,62 @@ async fn remove_database_view_group_test() {
   assert_eq!(group_settings[0].groups[0].id, "group_item2");
 }
 
+#[tokio::test]
+async fn create_row_in_group_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  let status_field = Field::new(
+    "f4".to_string(),
+    "status".to_string(),
+    FieldType::SingleSelect.into(),
+    false,
+  );
+  database_test.create_field(
+    None,
+    status_field,
+    &OrderObjectPosition::default(),
+    Default::default(),
+  );
+  // The group whose id is the field id holds the rows without a status
+  database_test.insert_group_setting(
+    "v1",
+    TestGroupSetting {
+      id: "g1".to_string(),
+      field_id: "f4".to_string(),
+      field_type: FieldType::SingleSelect.into(),
+      groups: vec![
+        TestGroup {
+          id: "f4".to_string(),
+          name: "No status".to_string(),
+          visible: true,
+        },
+        TestGroup {
+          id: "done".to_string(),
+          name: "Done".to_string(),
+          visible: true,
+        },
+      ],
+      content: "".to_string(),
+    },
+  );
+
+  let params = CreateRowParams::new(4, "1".to_string()).with_group_id("done".to_string());
+  let (_, row_order) = database_test.create_row_in_view("v1", params).unwrap();
+  let cell = database_test.get_cell("f4", &row_order.id).cell.unwrap();
+  assert_eq!(
+    CellValue::from_cell(FieldType::SingleSelect, &cell).unwrap(),
+    Some(CellValue::SelectOption(vec!["done".to_string()]))
+  );
+
+  let params = CreateRowParams::new(5, "1".to_string()).with_group_id("f4".to_string());
+  let (_, row_order) = database_test.create_row_in_view("v1", params).unwrap();
+  assert!(database_test.get_cell("f4", &row_order.id).cell.is_none());
+
+  // A group that doesn't exist doesn't imply any cell
+  let params = CreateRowParams::new(6, "1".to_string()).with_group_id("archived".to_string());
+  let (_, row_order) = database_test.create_row_in_view("v1", params).unwrap();
+  assert!(database_test.get_cell("f4", &row_order.id).cell.is_none());
+}
+
 async fn create_database_with_two_groups() -> DatabaseTest {
   let database_test = create_database_with_default_data(1, "1").await;
   let group_1 = TestGroupSetting {
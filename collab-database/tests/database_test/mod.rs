@@ -1,5 +1,11 @@
+mod awareness_test;
+mod batch_row_test;
 mod block_test;
+mod calendar_test;
 mod cell_test;
+mod convert_field_test;
+mod csv_test;
+mod field_constraint_test;
 mod field_observe_test;
 mod field_setting_test;
 mod field_test;
@@ -8,9 +14,17 @@ mod group_test;
 pub mod helper;
 mod layout_test;
 mod restore_test;
+mod row_cache_test;
+mod row_comment_test;
+mod row_history_test;
 mod row_observe_test;
+mod row_page_test;
+mod row_template_test;
 mod row_test;
 mod sort_test;
+mod sync_sim_test;
+mod timeline_test;
 mod type_option_test;
+mod typed_cell_test;
 mod view_observe_test;
 mod view_test;
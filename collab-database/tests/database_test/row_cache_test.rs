@@ -0,0 +1,139 @@
+use collab_database::blocks::RowCacheConfig;
+use collab_database::rows::{CellsBuilder, CreateRowParams, RowId};
+
+use crate::database_test::helper::{create_database, DatabaseTest};
+use crate::helper::TestTextCell;
+
+#[tokio::test]
+async fn evict_least_recently_used_rows_test() {
+  let database_test = create_database(1, "1").await;
+  database_test.set_row_cache_config(RowCacheConfig::unbounded().with_max_rows(5));
+  create_rows(&database_test, 10);
+
+  let metrics = database_test.get_row_cache_metrics();
+  assert_eq!(metrics.rows, 5);
+  assert_eq!(metrics.evictions, 5);
+  for i in 0..5 {
+    assert!(!database_test.block.is_row_loaded(&RowId::from(i)));
+  }
+  for i in 5..10 {
+    assert!(database_test.block.is_row_loaded(&RowId::from(i)));
+  }
+
+  // Accessing a row makes it the most recently used one, so the next row is evicted instead
+  database_test.get_row(&5.into());
+  database_test.get_row(&0.into());
+  assert!(database_test.block.is_row_loaded(&5.into()));
+  assert!(!database_test.block.is_row_loaded(&6.into()));
+}
+
+#[tokio::test]
+async fn reload_evicted_row_from_disk_test() {
+  let database_test = create_database(1, "1").await;
+  database_test.set_row_cache_config(RowCacheConfig::unbounded().with_max_rows(2));
+  create_rows(&database_test, 4);
+  assert!(!database_test.block.is_row_loaded(&0.into()));
+
+  let row = database_test.get_row(&0.into());
+  let cell = row.cells.get("f1").cloned().unwrap();
+  assert_eq!(TestTextCell::from(cell).0, "row 0");
+  assert!(database_test.block.is_row_loaded(&0.into()));
+
+  // The changes that are made after the reload are kept when the row is evicted again
+  database_test
+    .update_row(&0.into(), |row_update| {
+      row_update.update_cells(|cells_update| {
+        cells_update.insert("f1", TestTextCell::from("updated row 0"));
+      });
+    })
+    .unwrap();
+  database_test.block.evict_rows(&[0.into()]);
+  assert!(!database_test.block.is_row_loaded(&0.into()));
+
+  let cell = database_test.get_cell("f1", &0.into()).cell.unwrap();
+  assert_eq!(TestTextCell::from(cell).0, "updated row 0");
+}
+
+#[tokio::test]
+async fn update_evicted_row_test() {
+  let database_test = create_database(1, "1").await;
+  database_test.set_row_cache_config(RowCacheConfig::unbounded().with_max_rows(2));
+  create_rows(&database_test, 4);
+  assert!(!database_test.block.is_row_loaded(&1.into()));
+
+  database_test
+    .update_row(&1.into(), |row_update| {
+      row_update.update_cells(|cells_update| {
+        cells_update.insert("f1", TestTextCell::from("updated row 1"));
+      });
+    })
+    .unwrap();
+  let cell = database_test.get_cell("f1", &1.into()).cell.unwrap();
+  assert_eq!(TestTextCell::from(cell).0, "updated row 1");
+}
+
+#[tokio::test]
+async fn row_cache_metrics_test() {
+  let database_test = create_database(1, "1").await;
+  database_test.set_row_cache_config(RowCacheConfig::unbounded().with_max_rows(2));
+  create_rows(&database_test, 3);
+  let before = database_test.get_row_cache_metrics();
+  // The size of the rows is only estimated when it's limited
+  assert_eq!(before.estimated_bytes, 0);
+
+  database_test.get_row(&2.into());
+  let metrics = database_test.get_row_cache_metrics();
+  assert_eq!(metrics.hits, before.hits + 1);
+  assert_eq!(metrics.misses, before.misses);
+
+  database_test.get_row(&0.into());
+  let metrics = database_test.get_row_cache_metrics();
+  assert_eq!(metrics.misses, before.misses + 1);
+  assert_eq!(metrics.evictions, before.evictions + 1);
+  assert_eq!(metrics.rows, 2);
+}
+
+#[tokio::test]
+async fn shrink_row_cache_by_memory_limit_test() {
+  let database_test = create_database(1, "1").await;
+  create_rows(&database_test, 5);
+  assert_eq!(database_test.get_row_cache_metrics().rows, 5);
+
+  // Every row is larger than the limit, so all of them are evicted
+  database_test.set_row_cache_config(RowCacheConfig::unbounded().with_max_bytes(1));
+  let metrics = database_test.get_row_cache_metrics();
+  assert_eq!(metrics.rows, 0);
+  assert_eq!(metrics.estimated_bytes, 0);
+  assert_eq!(database_test.get_database_rows().len(), 5);
+}
+
+#[tokio::test]
+async fn estimate_row_size_again_after_update_test() {
+  let database_test = create_database(1, "1").await;
+  database_test.set_row_cache_config(RowCacheConfig::unbounded().with_max_bytes(usize::MAX));
+  create_rows(&database_test, 1);
+  let before = database_test.get_row_cache_metrics();
+  assert!(before.estimated_bytes > 0);
+
+  database_test
+    .update_row(&0.into(), |row_update| {
+      row_update.update_cells(|cells_update| {
+        cells_update.insert("f1", TestTextCell::from("a longer text than before"));
+      });
+    })
+    .unwrap();
+  database_test.get_row(&0.into());
+  let metrics = database_test.get_row_cache_metrics();
+  assert!(metrics.estimated_bytes > before.estimated_bytes);
+}
+
+fn create_rows(database_test: &DatabaseTest, count: i64) {
+  for i in 0..count {
+    let params = CreateRowParams::new(i, "1".to_string()).with_cells(
+      CellsBuilder::new()
+        .insert_cell("f1", TestTextCell::from(format!("row {}", i).as_str()))
+        .build(),
+    );
+    database_test.create_row(params).unwrap();
+  }
+}
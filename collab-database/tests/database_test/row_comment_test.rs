@@ -0,0 +1,191 @@
+use collab::preclude::Any;
+use collab_database::error::DatabaseError;
+use collab_database::rows::{RowChange, RowComment, RowCommentChange, RowCommentReaction, RowId};
+
+use crate::database_test::helper::create_database_with_default_data;
+
+#[tokio::test]
+async fn add_and_reply_to_comment_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  let comment = RowComment::new(1, "Is this done?".to_string());
+  let comment_id = comment.id.clone();
+  database_test
+    .add_row_comment(&1.into(), comment.clone())
+    .unwrap();
+
+  let reply = RowComment::new(2, "Yes".to_string()).with_parent(comment_id.clone());
+  let reply_id = reply.id.clone();
+  database_test.add_row_comment(&1.into(), reply).unwrap();
+
+  // A reply to a reply is attached to the first comment of the thread
+  let nested_reply = RowComment::new(1, "Thanks".to_string()).with_parent(reply_id);
+  database_test
+    .add_row_comment(&1.into(), nested_reply)
+    .unwrap();
+
+  let comments = database_test.get_row_comments(&1.into());
+  assert_eq!(comments.len(), 3);
+  assert_eq!(comments[0], comment);
+  // The comments are added as the user of the database
+  assert!(comments.iter().all(|comment| comment.uid == 1));
+
+  let threads = database_test.get_row_comment_threads(&1.into());
+  assert_eq!(threads.len(), 1);
+  assert_eq!(threads[0].comment.id, comment_id);
+  let replies = threads[0]
+    .replies
+    .iter()
+    .map(|reply| (reply.content.as_str(), reply.parent_id.clone()))
+    .collect::<Vec<_>>();
+  assert_eq!(
+    replies,
+    vec![
+      ("Yes", Some(comment_id.clone())),
+      ("Thanks", Some(comment_id))
+    ]
+  );
+}
+
+#[tokio::test]
+async fn reply_to_missing_comment_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  let reply = RowComment::new(1, "Hello".to_string()).with_parent("missing".to_string());
+  let result = database_test.add_row_comment(&1.into(), reply);
+  assert!(matches!(result, Err(DatabaseError::CommentNotExist)));
+  assert!(database_test.get_row_comments(&1.into()).is_empty());
+}
+
+#[tokio::test]
+async fn edit_resolve_and_react_to_comment_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  let comment = RowComment::new(2, "Draft".to_string());
+  let comment_id = comment.id.clone();
+  database_test.add_row_comment(&2.into(), comment).unwrap();
+
+  database_test
+    .edit_row_comment(&2.into(), &comment_id, "Final".to_string())
+    .unwrap();
+  database_test
+    .resolve_row_comment(&2.into(), &comment_id, true)
+    .unwrap();
+  database_test
+    .add_row_comment_reaction(&2.into(), &comment_id, "👍")
+    .unwrap();
+  // Reacting twice with the same emoji only counts once
+  database_test
+    .add_row_comment_reaction(&2.into(), &comment_id, "👍")
+    .unwrap();
+  database_test
+    .add_row_comment_reaction(&2.into(), &comment_id, "🎉")
+    .unwrap();
+
+  let comment = database_test.get_row_comments(&2.into()).remove(0);
+  assert_eq!(comment.content, "Final");
+  assert!(comment.edited_at.is_some());
+  assert!(comment.is_resolved);
+  assert_eq!(comment.resolved_by, Some(1));
+  assert_eq!(comment.uid, 1);
+  assert_eq!(comment.reaction_uids("👍"), vec![1]);
+  assert_eq!(comment.reaction_uids("🎉"), vec![1]);
+  assert!(comment.reactions["👍"][0].reacted_at >= comment.created_at);
+
+  database_test
+    .remove_row_comment_reaction(&2.into(), &comment_id, "🎉")
+    .unwrap();
+  database_test
+    .resolve_row_comment(&2.into(), &comment_id, false)
+    .unwrap();
+  let comment = database_test.get_row_comments(&2.into()).remove(0);
+  assert!(!comment.reactions.contains_key("🎉"));
+  assert!(!comment.is_resolved);
+  assert_eq!(comment.resolved_by, None);
+
+  let result = database_test.edit_row_comment(&2.into(), "missing", "Hello".to_string());
+  assert!(matches!(result, Err(DatabaseError::CommentNotExist)));
+}
+
+#[tokio::test]
+async fn delete_comment_with_replies_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  let first = RowComment::new(1, "First".to_string());
+  let second = RowComment::new(1, "Second".to_string());
+  let reply = RowComment::new(1, "Reply".to_string()).with_parent(first.id.clone());
+  let first_id = first.id.clone();
+  let second_id = second.id.clone();
+  for comment in [first, second, reply] {
+    database_test.add_row_comment(&3.into(), comment).unwrap();
+  }
+
+  database_test
+    .delete_row_comment(&3.into(), &first_id)
+    .unwrap();
+  let comments = database_test.get_row_comments(&3.into());
+  assert_eq!(comments.len(), 1);
+  assert_eq!(comments[0].id, second_id);
+}
+
+#[test]
+fn comment_reaction_time_test() {
+  let mut comment = RowComment::new(1, "Hello".to_string());
+  comment.reactions.insert(
+    "👍".to_string(),
+    vec![RowCommentReaction {
+      uid: 2,
+      reacted_at: comment.created_at + 60,
+    }],
+  );
+  // The reactions keep their time when the comment is converted
+  let value = Any::from(comment.clone());
+  assert_eq!(RowComment::try_from(value).unwrap(), comment);
+}
+
+#[tokio::test]
+async fn add_comment_keeps_reaction_time_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  let mut comment = RowComment::new(1, "Hello".to_string());
+  let reaction = RowCommentReaction {
+    uid: 2,
+    reacted_at: comment.created_at + 60,
+  };
+  comment
+    .reactions
+    .insert("👍".to_string(), vec![reaction.clone()]);
+  database_test.add_row_comment(&1.into(), comment).unwrap();
+
+  let comment = database_test.get_row_comments(&1.into()).remove(0);
+  assert_eq!(comment.reactions["👍"], vec![reaction]);
+}
+
+#[tokio::test]
+async fn observe_comment_change_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  let mut row_change_rx = database_test.subscribe_row_change();
+
+  let comment = RowComment::new(1, "Hello".to_string());
+  let comment_id = comment.id.clone();
+  database_test.add_row_comment(&1.into(), comment).unwrap();
+  database_test
+    .edit_row_comment(&1.into(), &comment_id, "Hi".to_string())
+    .unwrap();
+  database_test
+    .delete_row_comment(&1.into(), &comment_id)
+    .unwrap();
+
+  let mut changes = vec![];
+  while let Ok(change) = row_change_rx.try_recv() {
+    if let RowChange::DidChangeRowComment { row_id, change } = change {
+      assert_eq!(row_id, RowId::from(1));
+      changes.push(change);
+    }
+  }
+  assert_eq!(changes.len(), 3);
+  assert!(
+    matches!(&changes[0], RowCommentChange::DidAddComment { comment } if comment.content == "Hello")
+  );
+  assert!(
+    matches!(&changes[1], RowCommentChange::DidUpdateComment { comment } if comment.content == "Hi")
+  );
+  assert!(
+    matches!(&changes[2], RowCommentChange::DidDeleteComment { comment_id: id } if *id == comment_id)
+  );
+}
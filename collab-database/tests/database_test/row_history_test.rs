@@ -0,0 +1,150 @@
+use collab_database::rows::{Cell, CreateRowParams, MAX_HISTORY_LEN};
+
+use crate::database_test::helper::create_database_with_default_data;
+use crate::helper::TestTextCell;
+
+#[tokio::test]
+async fn update_cell_records_history_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  // Creating the row doesn't record any change
+  assert!(database_test.get_row_history(&1.into()).is_empty());
+
+  database_test
+    .update_row(&1.into(), |row_update| {
+      row_update.update_cells(|cells_update| {
+        cells_update.insert("f1", TestTextCell::from("hello"));
+      });
+    })
+    .unwrap();
+
+  let history = database_test.get_row_history(&1.into());
+  assert_eq!(history.len(), 1);
+  let record = &history[0];
+  assert_eq!(record.field_id, "f1");
+  assert_eq!(record.uid, Some(1));
+  assert!(record.timestamp > 0);
+  assert_eq!(text(record.old_cell.clone()), Some("1f1cell".to_string()));
+  assert_eq!(text(record.new_cell.clone()), Some("hello".to_string()));
+}
+
+#[tokio::test]
+async fn history_keeps_changes_in_order_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  for content in ["a", "b"] {
+    database_test
+      .update_row(&2.into(), |row_update| {
+        row_update.update_cells(|cells_update| {
+          cells_update.insert("f1", TestTextCell::from(content));
+        });
+      })
+      .unwrap();
+  }
+  // Clearing a cell records its removal
+  database_test
+    .update_row(&2.into(), |row_update| {
+      row_update.update_cells(|cells_update| {
+        cells_update.clear("f2");
+      });
+    })
+    .unwrap();
+
+  let history = database_test.get_row_history(&2.into());
+  let changes = history
+    .into_iter()
+    .map(|record| {
+      (
+        record.field_id,
+        text(record.old_cell),
+        text(record.new_cell),
+      )
+    })
+    .collect::<Vec<_>>();
+  assert_eq!(
+    changes,
+    vec![
+      (
+        "f1".to_string(),
+        Some("2f1cell".to_string()),
+        Some("a".to_string())
+      ),
+      (
+        "f1".to_string(),
+        Some("a".to_string()),
+        Some("b".to_string())
+      ),
+      ("f2".to_string(), Some("2f2cell".to_string()), None),
+    ]
+  );
+}
+
+#[tokio::test]
+async fn update_without_cell_change_records_nothing_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  database_test
+    .update_row(&3.into(), |row_update| {
+      row_update.set_height(100);
+    })
+    .unwrap();
+  // Writing the same content again is not a change
+  database_test
+    .update_row(&3.into(), |row_update| {
+      row_update.update_cells(|cells_update| {
+        cells_update.insert("f1", TestTextCell::from("3f1cell"));
+      });
+    })
+    .unwrap();
+  assert!(database_test.get_row_history(&3.into()).is_empty());
+
+  // A new cell has no old cell
+  database_test
+    .update_row(&3.into(), |row_update| {
+      row_update.update_cells(|cells_update| {
+        cells_update.insert("f2", TestTextCell::from("3f2cell"));
+      });
+    })
+    .unwrap();
+  let history = database_test.get_row_history(&3.into());
+  assert_eq!(
+    history
+      .iter()
+      .map(|record| (record.old_cell.is_none(), record.new_cell.is_some()))
+      .collect::<Vec<_>>(),
+    vec![(true, true)]
+  );
+}
+
+#[tokio::test]
+async fn new_row_has_empty_history_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  database_test
+    .create_row(CreateRowParams::new(4, "1".to_string()))
+    .unwrap();
+  assert!(database_test.get_row_history(&4.into()).is_empty());
+}
+
+#[tokio::test]
+async fn history_keeps_the_newest_records_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  let update_count = MAX_HISTORY_LEN as usize + 5;
+  for i in 0..update_count {
+    database_test
+      .update_row(&1.into(), |row_update| {
+        row_update.update_cells(|cells_update| {
+          cells_update.insert("f1", TestTextCell::from(i.to_string().as_str()));
+        });
+      })
+      .unwrap();
+  }
+
+  let history = database_test.get_row_history(&1.into());
+  assert_eq!(history.len(), MAX_HISTORY_LEN as usize);
+  assert_eq!(text(history[0].old_cell.clone()), Some("4".to_string()));
+  assert_eq!(
+    text(history.last().unwrap().new_cell.clone()),
+    Some((update_count - 1).to_string())
+  );
+}
+
+fn text(cell: Option<Cell>) -> Option<String> {
+  cell.map(|cell| TestTextCell::from(cell).0)
+}
@@ -53,14 +53,16 @@ async fn observer_row_cell_test() {
       ))
       .unwrap();
 
-    cloned_database_test.update_row(&cloned_row_id, |row| {
-      row.update_cells(|cells| {
-        cells.insert_cell(
-          "f1",
-          new_cell_builder(1).insert_i64_value("level", 1).build(),
-        );
-      });
-    });
+    cloned_database_test
+      .update_row(&cloned_row_id, |row| {
+        row.update_cells(|cells| {
+          cells.insert_cell(
+            "f1",
+            new_cell_builder(1).insert_i64_value("level", 1).build(),
+          );
+        });
+      })
+      .unwrap();
   });
 
   wait_for_specific_event(row_change_rx, |event| match event {
@@ -80,14 +82,16 @@ async fn observer_row_cell_test() {
   tokio::spawn(async move {
     sleep(Duration::from_millis(300)).await;
 
-    cloned_database_test.update_row(&row_id, |row| {
-      row.update_cells(|cells| {
-        cells.insert_cell(
-          "f1",
-          new_cell_builder(1).insert_i64_value("level", 2).build(),
-        );
-      });
-    });
+    cloned_database_test
+      .update_row(&row_id, |row| {
+        row.update_cells(|cells| {
+          cells.insert_cell(
+            "f1",
+            new_cell_builder(1).insert_i64_value("level", 2).build(),
+          );
+        });
+      })
+      .unwrap();
   });
 
   wait_for_specific_event(row_change_rx, |event| match event {
@@ -116,9 +120,11 @@ async fn observer_update_row_test() {
       .create_row(CreateRowParams::new(row_id.clone(), database_id.clone()))
       .unwrap();
 
-    cloned_database_test.update_row(&row_id, |row| {
-      row.set_height(1000);
-    });
+    cloned_database_test
+      .update_row(&row_id, |row| {
+        row.set_height(1000);
+      })
+      .unwrap();
   });
 
   wait_for_specific_event(row_change_rx, |event| match event {
@@ -0,0 +1,116 @@
+use collab_database::error::DatabaseError;
+use collab_database::rows::{CellsBuilder, CreateRowParams, Row, RowCursor, RowId};
+
+use crate::database_test::helper::{create_database, DatabaseTest};
+use crate::helper::TestTextCell;
+
+#[tokio::test]
+async fn get_rows_page_by_offset_test() {
+  let database_test = create_database_with_rows(20).await;
+  let page = database_test
+    .get_rows_page("v1", RowCursor::Offset(5), 3)
+    .unwrap();
+  assert_eq!(page.offset, 5);
+  assert_eq!(page.total, 20);
+  assert_eq!(row_ids(&page.rows), vec![5, 6, 7]);
+  assert_eq!(page.next_cursor, Some(RowCursor::After(7.into())));
+
+  // The offset past the end returns an empty page
+  let page = database_test
+    .get_rows_page("v1", RowCursor::Offset(30), 3)
+    .unwrap();
+  assert!(page.rows.is_empty());
+  assert!(!page.has_more());
+}
+
+#[tokio::test]
+async fn iterate_rows_pages_by_cursor_test() {
+  let database_test = create_database_with_rows(20).await;
+  let mut cursor = RowCursor::default();
+  let mut page_sizes = vec![];
+  let mut all_row_ids = vec![];
+  loop {
+    let page = database_test
+      .get_database_rows_page(cursor.clone(), 6)
+      .unwrap();
+    page_sizes.push(page.rows.len());
+    all_row_ids.extend(row_ids(&page.rows));
+    match page.next_cursor {
+      None => break,
+      Some(next_cursor) => cursor = next_cursor,
+    }
+  }
+  assert_eq!(page_sizes, vec![6, 6, 6, 2]);
+  assert_eq!(all_row_ids, (0..20).collect::<Vec<_>>());
+
+  // The cursor keeps its position when the rows before it are removed
+  database_test.remove_row(&1.into());
+  let page = database_test
+    .get_rows_page("v1", RowCursor::After(5.into()), 2)
+    .unwrap();
+  assert_eq!(row_ids(&page.rows), vec![6, 7]);
+  assert_eq!(page.offset, 5);
+
+  let result = database_test.get_rows_page("v1", RowCursor::After(1.into()), 2);
+  assert!(matches!(result, Err(DatabaseError::InvalidRowID(_))));
+}
+
+#[tokio::test]
+async fn get_rows_page_evicts_far_away_rows_test() {
+  let database_test = create_database_with_rows(20).await;
+  let page = database_test
+    .get_rows_page("v1", RowCursor::Offset(16), 2)
+    .unwrap();
+  assert_eq!(row_ids(&page.rows), vec![16, 17]);
+
+  // The rows that are more than two pages away from the page are evicted
+  for i in 0..12 {
+    assert!(!database_test.block.is_row_loaded(&RowId::from(i)));
+  }
+  for i in 12..20 {
+    assert!(database_test.block.is_row_loaded(&RowId::from(i)));
+  }
+
+  // The evicted rows are loaded from the disk again
+  let row = database_test.get_row(&3.into());
+  let cell = row.cells.get("f1").cloned().unwrap();
+  assert_eq!(TestTextCell::from(cell).0, "row 3");
+  assert!(database_test.block.is_row_loaded(&3.into()));
+}
+
+#[tokio::test]
+async fn update_evicted_row_test() {
+  let database_test = create_database_with_rows(20).await;
+  database_test.block.evict_rows(&[4.into()]);
+  assert!(!database_test.block.is_row_loaded(&4.into()));
+
+  database_test
+    .update_row(&4.into(), |row_update| {
+      row_update.update_cells(|cells_update| {
+        cells_update.insert("f1", TestTextCell::from("updated row 4"));
+      });
+    })
+    .unwrap();
+  let cell = database_test.get_cell("f1", &4.into()).cell.unwrap();
+  assert_eq!(TestTextCell::from(cell).0, "updated row 4");
+}
+
+async fn create_database_with_rows(count: i64) -> DatabaseTest {
+  let database_test = create_database(1, "1").await;
+  for i in 0..count {
+    let params = CreateRowParams::new(i, "1".to_string()).with_cells(
+      CellsBuilder::new()
+        .insert_cell("f1", TestTextCell::from(format!("row {}", i).as_str()))
+        .build(),
+    );
+    database_test.create_row(params).unwrap();
+  }
+  database_test
+}
+
+fn row_ids(rows: &[Row]) -> Vec<i64> {
+  rows
+    .iter()
+    .map(|row| row.id.parse::<i64>().unwrap())
+    .collect()
+}
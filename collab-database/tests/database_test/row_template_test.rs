@@ -0,0 +1,167 @@
+use collab::preclude::Map;
+use collab_database::error::DatabaseError;
+use collab_database::fields::FieldType;
+use collab_database::rows::{CellValue, CellsBuilder, RowTemplate};
+use collab_database::views::OrderObjectPosition;
+
+use crate::database_test::helper::create_database_with_default_data;
+use crate::helper::{TestFieldType, TestFilter, TestTextCell};
+
+#[tokio::test]
+async fn create_and_update_row_template_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  let template = RowTemplate::new("Bug".to_string())
+    .with_cells(
+      CellsBuilder::new()
+        .insert_cell("f1", TestTextCell::from("Untitled bug"))
+        .build(),
+    )
+    .with_icon_url("🐞".to_string());
+  let template_id = template.id.clone();
+  database_test.create_row_template(template.clone());
+  database_test.create_row_template(RowTemplate::new("Task".to_string()));
+
+  let templates = database_test.get_row_templates();
+  assert_eq!(templates.len(), 2);
+  assert_eq!(
+    database_test.get_row_template(&template_id).unwrap(),
+    template
+  );
+
+  database_test
+    .update_row_template(&template_id, |update| {
+      update
+        .set_name("Crash".to_string())
+        .set_icon_url(None)
+        .set_document(Some("Steps to reproduce".to_string()));
+    })
+    .unwrap();
+  let template = database_test.get_row_template(&template_id).unwrap();
+  assert_eq!(template.name, "Crash");
+  assert_eq!(template.icon_url, None);
+  assert_eq!(template.document, Some("Steps to reproduce".to_string()));
+
+  database_test.delete_row_template(&template_id);
+  assert!(database_test.get_row_template(&template_id).is_none());
+  assert_eq!(database_test.get_row_templates().len(), 1);
+
+  let result = database_test.update_row_template(&template_id, |update| {
+    update.set_name("Missing".to_string());
+  });
+  assert!(matches!(result, Err(DatabaseError::RowTemplateNotExist)));
+}
+
+#[tokio::test]
+async fn create_row_from_template_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  let template = RowTemplate::new("Bug".to_string())
+    .with_cells(
+      CellsBuilder::new()
+        .insert_cell("f1", TestTextCell::from("Untitled bug"))
+        .build(),
+    )
+    .with_icon_url("🐞".to_string())
+    .with_cover_url("cover.png".to_string())
+    .with_document("Steps to reproduce".to_string());
+  let template_id = template.id.clone();
+  database_test.create_row_template(template);
+
+  let row = database_test
+    .create_row_from_template("v1", &template_id, OrderObjectPosition::Start)
+    .unwrap();
+  assert_eq!(row.index, 0);
+  assert_eq!(row.document, Some("Steps to reproduce".to_string()));
+
+  let row_id = row.row_id().clone();
+  let cell = database_test.get_cell("f1", &row_id).cell.unwrap();
+  assert_eq!(TestTextCell::from(cell).0, "Untitled bug");
+
+  let meta = database_test.get_row_meta(&row_id).unwrap();
+  assert_eq!(meta.icon_url, Some("🐞".to_string()));
+  assert_eq!(meta.cover_url, Some("cover.png".to_string()));
+  assert!(!meta.is_document_empty);
+  assert_eq!(
+    database_test.get_row_document_id(&row_id),
+    Some(row.document_id)
+  );
+
+  let result =
+    database_test.create_row_from_template("v1", "missing", OrderObjectPosition::default());
+  assert!(matches!(result, Err(DatabaseError::RowTemplateNotExist)));
+}
+
+#[tokio::test]
+async fn create_row_from_template_with_view_filters_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  // f1 is a text field and f3 is a number field
+  database_test.insert_filter(
+    "v1",
+    TestFilter {
+      id: "filter_1".to_string(),
+      field_id: "f1".to_string(),
+      field_type: TestFieldType::RichText,
+      condition: 0,
+      content: "Done".to_string(),
+    },
+  );
+  database_test.insert_filter(
+    "v1",
+    TestFilter {
+      id: "filter_2".to_string(),
+      field_id: "f3".to_string(),
+      field_type: TestFieldType::Number,
+      condition: 0,
+      content: "5".to_string(),
+    },
+  );
+
+  let template = RowTemplate::new("Task".to_string()).with_cells(
+    CellsBuilder::new()
+      .insert_cell("f1", TestTextCell::from("Todo"))
+      .build(),
+  );
+  let template_id = template.id.clone();
+  database_test.create_row_template(template);
+
+  let row = database_test
+    .create_row_from_template("v1", &template_id, OrderObjectPosition::default())
+    .unwrap();
+  let row_id = row.row_id().clone();
+
+  // The filters overwrite the template's cells
+  let cell = database_test.get_cell("f1", &row_id).cell.unwrap();
+  assert_eq!(TestTextCell::from(cell).0, "Done");
+  let cell = database_test.get_cell("f3", &row_id).cell.unwrap();
+  assert_eq!(
+    CellValue::from_cell(FieldType::Number, &cell).unwrap(),
+    Some(CellValue::Number(5.0))
+  );
+}
+
+#[tokio::test]
+async fn database_without_templates_map_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  // A database that was created before the templates were introduced
+  {
+    let collab = database_test.get_collab().lock();
+    collab.with_origin_transact_mut(|txn| {
+      let database = collab.get_map_with_txn(txn, vec!["database"]).unwrap();
+      database.remove(txn, "templates");
+      let metas = collab
+        .get_map_with_txn(txn, vec!["database", "metas"])
+        .unwrap();
+      metas.remove(txn, "version");
+    });
+  }
+  assert!(database_test.get_row_templates().is_empty());
+  assert!(database_test.get_row_template("missing").is_none());
+
+  // The database is migrated when the first template is created
+  let template = RowTemplate::new("Bug".to_string());
+  let template_id = template.id.clone();
+  database_test.create_row_template(template);
+  assert_eq!(
+    database_test.get_row_template(&template_id).unwrap().name,
+    "Bug"
+  );
+}
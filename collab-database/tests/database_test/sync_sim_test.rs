@@ -0,0 +1,126 @@
+use std::sync::Arc;
+
+use collab::sync_sim::{NetworkConfig, SyncSimulation};
+use collab_database::database::{gen_row_id, Database, DatabaseContext};
+use collab_database::database_state::DatabaseNotify;
+use collab_database::fields::Field;
+use collab_database::rows::{DatabaseRow, Row, RowId};
+use collab_database::views::{CreateDatabaseParams, CreateViewParams, OrderObjectPosition};
+use collab_plugins::CollabKVDB;
+
+use crate::database_test::helper::default_field_settings_by_layout;
+use crate::helper::{make_rocks_db, TestTextCell};
+use crate::user_test::helper::TestUserDatabaseCollabBuilderImpl;
+
+const DATABASE_ID: &str = "d1";
+
+fn database_context(
+  sim: &SyncSimulation,
+  peer_id: usize,
+  collab_db: &Arc<CollabKVDB>,
+) -> DatabaseContext {
+  DatabaseContext {
+    uid: peer_id as i64 + 1,
+    db: Arc::downgrade(collab_db),
+    collab: sim.peer(peer_id),
+    collab_service: Arc::new(TestUserDatabaseCollabBuilderImpl()),
+    notifier: DatabaseNotify::default(),
+  }
+}
+
+/// Create the database and the row on the first peer and open them on the other peers once
+/// they're synced. The database and the row are two objects of the simulation.
+fn open_databases(
+  sim: &mut SyncSimulation,
+  row_id: &RowId,
+  collab_db: &Arc<CollabKVDB>,
+) -> Vec<(Database, DatabaseRow)> {
+  let params = CreateDatabaseParams {
+    database_id: DATABASE_ID.to_string(),
+    inline_view_id: "v1".to_string(),
+    views: vec![CreateViewParams {
+      database_id: DATABASE_ID.to_string(),
+      view_id: "v1".to_string(),
+      ..Default::default()
+    }],
+    fields: vec![Field::new("f1".to_string(), "name".to_string(), 0, true)],
+    ..Default::default()
+  };
+  let database =
+    Database::create_with_inline_view(params, database_context(sim, 0, collab_db)).unwrap();
+  let row = DatabaseRow::create(
+    Some(Row::new(row_id.clone(), DATABASE_ID)),
+    1,
+    row_id.clone(),
+    Arc::downgrade(collab_db),
+    sim.peer_object(0, row_id),
+    tokio::sync::broadcast::channel(1).0,
+  );
+  sim.sync_all();
+
+  let mut databases = vec![(database, row)];
+  for peer_id in 1..sim.num_of_peers() {
+    let database =
+      Database::get_or_create(DATABASE_ID, database_context(sim, peer_id, collab_db)).unwrap();
+    let row = DatabaseRow::new(
+      peer_id as i64 + 1,
+      row_id.clone(),
+      Arc::downgrade(collab_db),
+      sim.peer_object(peer_id, row_id),
+      tokio::sync::broadcast::channel(1).0,
+    )
+    .unwrap();
+    databases.push((database, row));
+  }
+  databases
+}
+
+#[tokio::test]
+async fn concurrent_database_edits_converge_test() {
+  let collab_db = make_rocks_db();
+  let config = NetworkConfig::default()
+    .with_latency(1, 30)
+    .with_drop_rate(0.1);
+  let mut sim = SyncSimulation::new(DATABASE_ID, 3, 12, config);
+  let row_id = gen_row_id();
+  sim.add_object(&row_id);
+  let databases = open_databases(&mut sim, &row_id, &collab_db);
+
+  sim.partition(&[0], &[1, 2]);
+  for (peer_id, field_id) in [(0, "f2"), (1, "f3")] {
+    databases[peer_id].0.create_field(
+      None,
+      Field::new(field_id.to_string(), field_id.to_string(), 0, false),
+      &OrderObjectPosition::End,
+      default_field_settings_by_layout(),
+    );
+  }
+  for (peer_id, field_id) in [(0, "f1"), (2, "f2")] {
+    databases[peer_id].1.update(|row_update| {
+      row_update.update_cells(|cells_update| {
+        cells_update.insert(field_id, TestTextCell::from(field_id));
+      });
+    });
+  }
+  sim.run_until_quiet();
+  assert!(!sim.is_converged());
+
+  sim.heal();
+  sim.sync_all();
+  sim.assert_converged();
+  for (database, row) in &databases {
+    let mut field_ids = database
+      .get_fields(None)
+      .into_iter()
+      .map(|field| field.id)
+      .collect::<Vec<_>>();
+    field_ids.sort();
+    assert_eq!(field_ids, vec!["f1", "f2", "f3"]);
+
+    let cells = row.get_row().unwrap().cells;
+    for field_id in ["f1", "f2"] {
+      let cell = cells.get(field_id).cloned().unwrap();
+      assert_eq!(TestTextCell::from(cell).0, field_id);
+    }
+  }
+}
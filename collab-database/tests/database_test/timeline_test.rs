@@ -0,0 +1,263 @@
+use chrono::{DateTime, NaiveDate, TimeZone};
+use chrono_tz::Tz;
+use collab_database::error::DatabaseError;
+use collab_database::fields::{Field, FieldType, SelectTypeOption};
+use collab_database::rows::{CellValue, CellsBuilder, CreateRowParams, DateCellValue, RowId};
+use collab_database::views::{
+  CreateViewParams, DatabaseLayout, LayoutSetting, OrderObjectPosition, TimelineLayoutSetting,
+};
+
+use crate::database_test::helper::{
+  create_database, default_field_settings_by_layout, DatabaseTest,
+};
+
+#[test]
+fn timeline_layout_setting_test() {
+  let setting = TimelineLayoutSetting::new("start".to_string())
+    .with_end_field("end".to_string())
+    .with_group_field("status".to_string());
+  let layout_setting = LayoutSetting::from(setting.clone());
+  assert_eq!(TimelineLayoutSetting::from(layout_setting), setting);
+  assert_eq!(setting.dependency_field_id, None);
+}
+
+#[tokio::test]
+async fn get_timeline_bars_test() {
+  let (database_test, todo_id, done_id) = create_timeline_database().await;
+  let bars = database_test.get_timeline_bars("t1", Tz::UTC).unwrap();
+  let row_ids = bars
+    .iter()
+    .map(|bar| bar.row_id.clone())
+    .collect::<Vec<_>>();
+  // The bars are sorted by their start, then by their end. The row without a start is skipped.
+  assert_eq!(
+    row_ids,
+    vec![
+      row_id("review"),
+      row_id("design"),
+      row_id("build"),
+      row_id("backwards")
+    ]
+  );
+
+  // Without an end date, the bar ends at the end of the start date
+  let review = &bars[0];
+  assert_eq!(review.title, "Review");
+  assert!(review.is_all_day);
+  assert_eq!(review.start, utc(2024, 3, 4, 0));
+  assert_eq!(review.end, utc(2024, 3, 5, 0));
+  assert_eq!(review.group_id, Some(done_id));
+
+  // The bar ends at the end of the day of the end date
+  let design = &bars[1];
+  assert_eq!(design.start, utc(2024, 3, 4, 0));
+  assert_eq!(design.end, utc(2024, 3, 9, 0));
+  assert_eq!(design.group_id, Some(todo_id));
+
+  let build = &bars[2];
+  assert!(!build.is_all_day);
+  assert_eq!(build.start, utc(2024, 3, 11, 9));
+  assert_eq!(build.end, utc(2024, 3, 11, 12));
+  assert_eq!(build.dependencies, vec![row_id("design"), row_id("review")]);
+  assert_eq!(build.group_id, None);
+
+  // An end date before the start doesn't make the bar end before it starts
+  let backwards = &bars[3];
+  assert_eq!(backwards.start, utc(2024, 3, 20, 0));
+  assert_eq!(backwards.end, backwards.start);
+}
+
+#[tokio::test]
+async fn create_timeline_view_without_start_field_test() {
+  let database_test = create_database(1, "1").await;
+  let params = CreateViewParams::new(
+    "1".to_string(),
+    "t1".to_string(),
+    "Timeline".to_string(),
+    DatabaseLayout::Timeline,
+  );
+  let result = database_test.create_linked_view(params);
+  assert!(matches!(
+    result,
+    Err(DatabaseError::InvalidTimelineLayoutSetting(_))
+  ));
+  assert!(database_test.get_view("t1").is_none());
+}
+
+#[tokio::test]
+async fn timeline_start_field_is_not_date_test() {
+  let (database_test, _, _) = create_timeline_database().await;
+  database_test.insert_layout_setting(
+    "t1",
+    &DatabaseLayout::Timeline,
+    TimelineLayoutSetting::new("title".to_string()),
+  );
+  let result = database_test.get_timeline_bars("t1", Tz::UTC);
+  assert!(matches!(
+    result,
+    Err(DatabaseError::InvalidTimelineLayoutSetting(_))
+  ));
+}
+
+#[tokio::test]
+async fn get_timeline_groups_test() {
+  let (database_test, todo_id, done_id) = create_timeline_database().await;
+  let groups = database_test.get_timeline_groups("t1", Tz::UTC).unwrap();
+  let group_ids = groups
+    .iter()
+    .map(|group| group.group_id.clone())
+    .collect::<Vec<_>>();
+  // The groups follow the order of the options, the bars without a group come last
+  assert_eq!(group_ids, vec![Some(todo_id), Some(done_id), None]);
+  assert_eq!(groups[0].bars[0].row_id, row_id("design"));
+  assert_eq!(groups[1].bars[0].row_id, row_id("review"));
+  let row_ids = groups[2]
+    .bars
+    .iter()
+    .map(|bar| bar.row_id.clone())
+    .collect::<Vec<_>>();
+  assert_eq!(row_ids, vec![row_id("build"), row_id("backwards")]);
+}
+
+#[tokio::test]
+async fn timeline_missing_fields_test() {
+  let (database_test, _, _) = create_timeline_database().await;
+  for setting in [
+    TimelineLayoutSetting::new("start".to_string()).with_end_field("unknown".to_string()),
+    TimelineLayoutSetting::new("start".to_string()).with_dependency_field("unknown".to_string()),
+    TimelineLayoutSetting::new("start".to_string()).with_group_field("unknown".to_string()),
+  ] {
+    database_test.insert_layout_setting("t1", &DatabaseLayout::Timeline, setting);
+    let result = database_test.get_timeline_bars("t1", Tz::UTC);
+    assert!(matches!(
+      result,
+      Err(DatabaseError::InvalidTimelineLayoutSetting(_))
+    ));
+  }
+}
+
+async fn create_timeline_database() -> (DatabaseTest, String, String) {
+  let database_test = create_database(1, "1").await;
+  let mut type_option = SelectTypeOption::default();
+  let todo_id = type_option.get_or_insert_option("Todo");
+  let done_id = type_option.get_or_insert_option("Done");
+  for (field_id, field_type, is_primary) in [
+    ("title", FieldType::RichText, true),
+    ("start", FieldType::DateTime, false),
+    ("end", FieldType::DateTime, false),
+    ("depends_on", FieldType::Relation, false),
+    ("status", FieldType::SingleSelect, false),
+  ] {
+    let mut field = Field::new(
+      field_id.to_string(),
+      field_type.default_name().to_string(),
+      field_type.value(),
+      is_primary,
+    );
+    if field_type == FieldType::SingleSelect {
+      field = field.with_type_option_data(field_type.value(), type_option.clone().into());
+    }
+    database_test.create_field(
+      None,
+      field,
+      &OrderObjectPosition::default(),
+      default_field_settings_by_layout(),
+    );
+  }
+
+  let rows = [
+    (
+      "design",
+      "Design",
+      Some(all_day(1709510400)),
+      Some(all_day(1709856000)),
+    ),
+    (
+      "build",
+      "Build",
+      Some(DateCellValue {
+        timestamp: 1710147600,
+        end_timestamp: Some(1710158400),
+        include_time: true,
+        ..Default::default()
+      }),
+      None,
+    ),
+    ("review", "Review", Some(all_day(1709510400)), None),
+    ("no_date", "No date", None, Some(all_day(1709856000))),
+    (
+      "backwards",
+      "Backwards",
+      Some(all_day(1710892800)),
+      Some(all_day(1710720000)),
+    ),
+  ];
+  for (id, title, start, end) in rows {
+    let mut cells = CellsBuilder::new().insert_cell(
+      "title",
+      CellValue::Text(title.to_string()).to_cell(FieldType::RichText),
+    );
+    if let Some(start) = start {
+      cells = cells.insert_cell("start", CellValue::Date(start).to_cell(FieldType::DateTime));
+    }
+    if let Some(end) = end {
+      cells = cells.insert_cell("end", CellValue::Date(end).to_cell(FieldType::DateTime));
+    }
+    let status = match id {
+      "design" => Some(&todo_id),
+      "review" => Some(&done_id),
+      _ => None,
+    };
+    if let Some(status) = status {
+      cells = cells.insert_cell(
+        "status",
+        CellValue::SelectOption(vec![status.clone()]).to_cell(FieldType::SingleSelect),
+      );
+    }
+    if id == "build" {
+      cells = cells.insert_cell(
+        "depends_on",
+        CellValue::Relation(vec![row_id("design"), row_id("review")]).to_cell(FieldType::Relation),
+      );
+    }
+    database_test
+      .create_row(CreateRowParams::new(row_id(id), "1".to_string()).with_cells(cells.build()))
+      .unwrap();
+  }
+
+  let setting = TimelineLayoutSetting::new("start".to_string())
+    .with_end_field("end".to_string())
+    .with_dependency_field("depends_on".to_string())
+    .with_group_field("status".to_string());
+  let params = CreateViewParams::new(
+    "1".to_string(),
+    "t1".to_string(),
+    "Timeline".to_string(),
+    DatabaseLayout::Timeline,
+  )
+  .with_layout_setting(setting.into());
+  database_test.create_linked_view(params).unwrap();
+  (database_test, todo_id, done_id)
+}
+
+fn all_day(timestamp: i64) -> DateCellValue {
+  DateCellValue {
+    timestamp,
+    ..Default::default()
+  }
+}
+
+fn row_id(id: &str) -> RowId {
+  RowId::from(id.to_string())
+}
+
+fn utc(year: i32, month: u32, day: u32, hour: u32) -> DateTime<Tz> {
+  Tz::UTC
+    .from_local_datetime(
+      &NaiveDate::from_ymd_opt(year, month, day)
+        .unwrap()
+        .and_hms_opt(hour, 0, 0)
+        .unwrap(),
+    )
+    .unwrap()
+}
@@ -0,0 +1,255 @@
+use collab_database::error::DatabaseError;
+use collab_database::fields::{Field, FieldType, SelectOption, SelectTypeOption};
+use collab_database::rows::{CellValue, ChecklistCellValue, DateCellValue, RowId};
+use collab_database::views::OrderObjectPosition;
+
+use crate::database_test::helper::{
+  create_database_with_default_data, default_field_settings_by_layout, DatabaseTest,
+};
+
+#[tokio::test]
+async fn get_typed_text_cell_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  let value = database_test.get_typed_cell("f1", &1.into()).unwrap();
+  assert_eq!(value, Some(CellValue::Text("1f1cell".to_string())));
+
+  let value = database_test.get_typed_cell("f2", &3.into()).unwrap();
+  assert!(value.is_none());
+
+  let result = database_test.get_typed_cell("unknown", &1.into());
+  assert!(matches!(result, Err(DatabaseError::FieldNotExist)));
+}
+
+#[tokio::test]
+async fn update_typed_number_cell_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  create_typed_field(&database_test, "number", FieldType::Number, None);
+
+  database_test
+    .update_typed_cell("number", &1.into(), CellValue::Number(1.5))
+    .unwrap();
+  let value = database_test.get_typed_cell("number", &1.into()).unwrap();
+  assert_eq!(value, Some(CellValue::Number(1.5)));
+
+  let result = database_test.update_typed_cell("number", &1.into(), CellValue::Number(f64::NAN));
+  assert!(matches!(result, Err(DatabaseError::InvalidCellValue(_))));
+  let value = database_test.get_typed_cell("number", &1.into()).unwrap();
+  assert_eq!(value, Some(CellValue::Number(1.5)));
+}
+
+#[tokio::test]
+async fn update_typed_cell_with_mismatched_value_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  create_typed_field(&database_test, "number", FieldType::Number, None);
+
+  let result = database_test.update_typed_cell(
+    "number",
+    &1.into(),
+    CellValue::Text("not a number".to_string()),
+  );
+  assert!(matches!(result, Err(DatabaseError::InvalidCellValue(_))));
+  let value = database_test.get_typed_cell("number", &1.into()).unwrap();
+  assert!(value.is_none());
+}
+
+#[tokio::test]
+async fn update_typed_select_option_cell_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  let mut type_option = SelectTypeOption::default();
+  let todo_id = type_option.get_or_insert_option("Todo");
+  let done_id = type_option.get_or_insert_option("Done");
+  create_typed_field(
+    &database_test,
+    "status",
+    FieldType::SingleSelect,
+    Some(type_option),
+  );
+
+  database_test
+    .update_typed_cell(
+      "status",
+      &1.into(),
+      CellValue::SelectOption(vec![todo_id.clone()]),
+    )
+    .unwrap();
+  let value = database_test.get_typed_cell("status", &1.into()).unwrap();
+  assert_eq!(value, Some(CellValue::SelectOption(vec![todo_id.clone()])));
+
+  // The option doesn't exist in the field's type option
+  let result = database_test.update_typed_cell(
+    "status",
+    &1.into(),
+    CellValue::SelectOption(vec!["unknown".to_string()]),
+  );
+  assert!(matches!(result, Err(DatabaseError::InvalidCellValue(_))));
+
+  // A single select cell can't select more than one option
+  let result = database_test.update_typed_cell(
+    "status",
+    &1.into(),
+    CellValue::SelectOption(vec![todo_id.clone(), done_id]),
+  );
+  assert!(matches!(result, Err(DatabaseError::InvalidCellValue(_))));
+
+  let value = database_test.get_typed_cell("status", &1.into()).unwrap();
+  assert_eq!(value, Some(CellValue::SelectOption(vec![todo_id])));
+}
+
+#[tokio::test]
+async fn update_typed_date_cell_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  create_typed_field(&database_test, "due", FieldType::DateTime, None);
+
+  let date = DateCellValue {
+    timestamp: 1704067200,
+    end_timestamp: Some(1704153600),
+    include_time: true,
+    timezone_id: "Asia/Shanghai".to_string(),
+  };
+  database_test
+    .update_typed_cell("due", &1.into(), CellValue::Date(date.clone()))
+    .unwrap();
+  let value = database_test.get_typed_cell("due", &1.into()).unwrap();
+  assert_eq!(value, Some(CellValue::Date(date)));
+
+  let result = database_test.update_typed_cell(
+    "due",
+    &2.into(),
+    CellValue::Date(DateCellValue {
+      timestamp: 1704153600,
+      end_timestamp: Some(1704067200),
+      ..Default::default()
+    }),
+  );
+  assert!(matches!(result, Err(DatabaseError::InvalidCellValue(_))));
+
+  let result = database_test.update_typed_cell(
+    "due",
+    &2.into(),
+    CellValue::Date(DateCellValue {
+      timestamp: 1704067200,
+      timezone_id: "Mars/Olympus_Mons".to_string(),
+      ..Default::default()
+    }),
+  );
+  assert!(matches!(result, Err(DatabaseError::InvalidCellValue(_))));
+}
+
+#[tokio::test]
+async fn update_typed_url_cell_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  create_typed_field(&database_test, "link", FieldType::URL, None);
+
+  let url = CellValue::Url("https://appflowy.io".to_string());
+  database_test
+    .update_typed_cell("link", &1.into(), url.clone())
+    .unwrap();
+  let value = database_test.get_typed_cell("link", &1.into()).unwrap();
+  assert_eq!(value, Some(url));
+
+  let result =
+    database_test.update_typed_cell("link", &1.into(), CellValue::Url("not a url".to_string()));
+  assert!(matches!(result, Err(DatabaseError::InvalidCellValue(_))));
+}
+
+#[tokio::test]
+async fn update_typed_cell_of_missing_row_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  let result =
+    database_test.update_typed_cell("f1", &100.into(), CellValue::Text("hello".to_string()));
+  assert!(matches!(result, Err(DatabaseError::RowNotExist)));
+}
+
+#[tokio::test]
+async fn update_typed_checklist_cell_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  create_typed_field(&database_test, "checklist", FieldType::Checklist, None);
+
+  let option = SelectOption::new("write the doc");
+  let checklist = ChecklistCellValue {
+    selected_option_ids: vec![option.id.clone()],
+    options: vec![option],
+  };
+  database_test
+    .update_typed_cell(
+      "checklist",
+      &1.into(),
+      CellValue::Checklist(checklist.clone()),
+    )
+    .unwrap();
+  let value = database_test
+    .get_typed_cell("checklist", &1.into())
+    .unwrap();
+  assert_eq!(value, Some(CellValue::Checklist(checklist)));
+
+  let result = database_test.update_typed_cell(
+    "checklist",
+    &1.into(),
+    CellValue::Checklist(ChecklistCellValue {
+      options: vec![],
+      selected_option_ids: vec!["unknown".to_string()],
+    }),
+  );
+  assert!(matches!(result, Err(DatabaseError::InvalidCellValue(_))));
+}
+
+#[tokio::test]
+async fn update_typed_relation_cell_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  create_typed_field(&database_test, "relation", FieldType::Relation, None);
+
+  let row_ids = vec![RowId::from("r1".to_string()), RowId::from("r2".to_string())];
+  database_test
+    .update_typed_cell("relation", &1.into(), CellValue::Relation(row_ids.clone()))
+    .unwrap();
+  let value = database_test.get_typed_cell("relation", &1.into()).unwrap();
+  assert_eq!(value, Some(CellValue::Relation(row_ids)));
+
+  let result = database_test.update_typed_cell(
+    "relation",
+    &1.into(),
+    CellValue::Relation(vec![
+      RowId::from("r1".to_string()),
+      RowId::from("r1".to_string()),
+    ]),
+  );
+  assert!(matches!(result, Err(DatabaseError::InvalidCellValue(_))));
+}
+
+#[tokio::test]
+async fn typed_timestamp_cell_test() {
+  let database_test = create_database_with_default_data(1, "1").await;
+  create_typed_field(&database_test, "created_at", FieldType::CreatedTime, None);
+
+  let row = database_test.get_row(&1.into());
+  let value = database_test
+    .get_typed_cell("created_at", &1.into())
+    .unwrap();
+  assert_eq!(value, Some(CellValue::Timestamp(row.created_at)));
+
+  let result = database_test.update_typed_cell("created_at", &1.into(), CellValue::Timestamp(0));
+  assert!(matches!(result, Err(DatabaseError::InvalidCellValue(_))));
+}
+
+fn create_typed_field(
+  database_test: &DatabaseTest,
+  field_id: &str,
+  field_type: FieldType,
+  type_option: Option<SelectTypeOption>,
+) {
+  let mut field = Field::new(
+    field_id.to_string(),
+    field_type.default_name().to_string(),
+    field_type.value(),
+    false,
+  );
+  if let Some(type_option) = type_option {
+    field = field.with_type_option_data(field_type.value(), type_option.into());
+  }
+  database_test.create_field(
+    None,
+    field,
+    &OrderObjectPosition::default(),
+    default_field_settings_by_layout(),
+  );
+}
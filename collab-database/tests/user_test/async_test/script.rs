@@ -139,7 +139,8 @@ pub async fn run_script(
         .lock()
         .update_row(&row_id, |row| {
           row.set_cells(cells);
-        });
+        })
+        .unwrap();
     },
     DatabaseScript::AssertDatabaseInDisk {
       database_id,
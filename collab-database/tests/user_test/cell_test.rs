@@ -9,14 +9,17 @@ use crate::user_test::helper::{workspace_database_test, WorkspaceDatabaseTest};
 async fn insert_cell_test() {
   let test = user_database_with_default_row().await;
   let database = test.get_database("d1").await.unwrap();
-  database.lock().update_row(&1.into(), |row_update| {
-    row_update.update_cells(|cells_update| {
-      cells_update.insert_cell(
-        "f1",
-        new_cell_builder(1).insert_i64_value("level", 1).build(),
-      );
-    });
-  });
+  database
+    .lock()
+    .update_row(&1.into(), |row_update| {
+      row_update.update_cells(|cells_update| {
+        cells_update.insert_cell(
+          "f1",
+          new_cell_builder(1).insert_i64_value("level", 1).build(),
+        );
+      });
+    })
+    .unwrap();
 
   let row = database.lock().get_row(&1.into());
   let cell = row.cells.get("f1").unwrap();
@@ -27,26 +30,32 @@ async fn insert_cell_test() {
 async fn update_cell_test() {
   let test = user_database_with_default_row().await;
   let database = test.get_database("d1").await.unwrap();
-  database.lock().update_row(&1.into(), |row_update| {
-    row_update.update_cells(|cells_update| {
-      cells_update.insert_cell(
-        "f1",
-        new_cell_builder(1).insert_i64_value("level", 1).build(),
-      );
-    });
-  });
+  database
+    .lock()
+    .update_row(&1.into(), |row_update| {
+      row_update.update_cells(|cells_update| {
+        cells_update.insert_cell(
+          "f1",
+          new_cell_builder(1).insert_i64_value("level", 1).build(),
+        );
+      });
+    })
+    .unwrap();
 
-  database.lock().update_row(&1.into(), |row_update| {
-    row_update.update_cells(|cells_update| {
-      cells_update.insert(
-        "f1",
-        new_cell_builder(1)
-          .insert_i64_value("level", 2)
-          .insert_str_value("name", "appflowy")
-          .build(),
-      );
-    });
-  });
+  database
+    .lock()
+    .update_row(&1.into(), |row_update| {
+      row_update.update_cells(|cells_update| {
+        cells_update.insert(
+          "f1",
+          new_cell_builder(1)
+            .insert_i64_value("level", 2)
+            .insert_str_value("name", "appflowy")
+            .build(),
+        );
+      });
+    })
+    .unwrap();
 
   let row = database.lock().get_row(&1.into());
   let cell = row.cells.get("f1").unwrap();
@@ -74,7 +83,10 @@ async fn update_not_exist_row_test() {
     })
     .unwrap();
 
-  database.lock().update_row(&1.into(), |_row_update| {});
+  database
+    .lock()
+    .update_row(&1.into(), |_row_update| {})
+    .unwrap();
   let row = database.lock().get_row(&1.into());
   // If the row with the given id does not exist, the get_row method will return a empty Row
   assert!(row.is_empty())
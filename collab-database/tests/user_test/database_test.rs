@@ -1,7 +1,8 @@
-use collab_database::database::gen_database_view_id;
-use collab_database::rows::CreateRowParams;
-use collab_database::views::{CreateDatabaseParams, CreateViewParams};
+use collab_database::database::{gen_database_view_id, gen_row_id};
+use collab_database::rows::{CellsBuilder, CreateRowParams};
+use collab_database::views::{CreateDatabaseParams, CreateViewParams, OrderObjectPosition};
 
+use crate::helper::TestTextCell;
 use crate::user_test::helper::{
   make_default_grid, random_uid, user_database_test_with_db, user_database_test_with_default_data,
   workspace_database_test,
@@ -341,3 +342,43 @@ async fn reopen_database_test() {
   let database = test.get_database_with_view_id(&view_id).await;
   let _ = database.unwrap().lock().to_json_value();
 }
+
+#[tokio::test]
+async fn move_rows_between_databases_test() {
+  let uid = random_uid();
+  let test = workspace_database_test(uid).await;
+  let mut params = make_default_grid("v1", "first view");
+  let name_field_id = params.fields[0].id.clone();
+  let row_id = gen_row_id();
+  params.rows = vec![
+    CreateRowParams::new(row_id.clone(), params.database_id.clone()).with_cells(
+      CellsBuilder::new()
+        .insert_cell(&name_field_id, TestTextCell::from("moved row"))
+        .build(),
+    ),
+  ];
+  let from_database = test.create_database(params).unwrap();
+  let to_database = test
+    .create_database(make_default_grid("v2", "second view"))
+    .unwrap();
+
+  let row_orders = test
+    .move_rows_between_views("v1", &[row_id], "v2", OrderObjectPosition::Start)
+    .await
+    .unwrap();
+  assert_eq!(row_orders.len(), 1);
+  assert!(from_database.lock().get_rows_for_view("v1").is_empty());
+
+  let to_database = to_database.lock();
+  let rows = to_database.get_rows_for_view("v2");
+  assert_eq!(rows.len(), 4);
+  assert_eq!(rows[0].id, row_orders[0].id);
+  let to_name_field_id = to_database
+    .get_fields(None)
+    .into_iter()
+    .find(|field| field.is_primary)
+    .unwrap()
+    .id;
+  let cell = rows[0].cells.get(&to_name_field_id).cloned().unwrap();
+  assert_eq!(TestTextCell::from(cell).0, "moved row");
+}
@@ -1,6 +1,6 @@
 mod cell_test;
 mod database_test;
 pub mod helper;
-// mod relation_test;
+mod relation_test;
 // mod snapshot_test;
 mod type_option_test;
@@ -1,11 +1,21 @@
+use collab::core::any_map::AnyMapExtension;
 use collab::preclude::MapRefExtension;
-use collab_database::user::{RowRelation, RowRelationChange};
+use collab_database::fields::Field;
+use collab_database::rows::{new_cell_builder, CellsBuilder, CreateRowParams, RowId};
+use collab_database::views::{CreateDatabaseParams, CreateViewParams};
+use collab_database::workspace_database::{
+  DanglingLink, LinkingRow, LookupTypeOption, RelationCellChange, RollupCalculation,
+  RollupTypeOption, RollupValue, RowConnection, RowRelation, RowRelationChange,
+};
 
-use crate::user_test::helper::{poll_row_relation_rx, test_timeout, user_database_test};
+use crate::database_test::helper::field_settings_for_default_database;
+use crate::user_test::helper::{
+  poll_row_relation_rx, test_timeout, workspace_database_test, WorkspaceDatabaseTest,
+};
 
 #[tokio::test]
 async fn insert_relation_data_test() {
-  let test = user_database_test(1);
+  let test = workspace_database_test(1).await;
   let relations = test.relations();
   relations.with_transact_mut(|txn| {
     relations.insert_str_with_txn(txn, "version", "1.0");
@@ -17,7 +27,7 @@ async fn insert_relation_data_test() {
 
 #[tokio::test]
 async fn restore_relation_data_test() {
-  let test = user_database_test(1);
+  let test = workspace_database_test(1).await;
   let relations = test.relations();
   relations.with_transact_mut(|txn| {
     relations.insert_str_with_txn(txn, "version", "1.0");
@@ -36,7 +46,7 @@ async fn restore_relation_data_test() {
 
 #[tokio::test]
 async fn insert_row_relation_data_test() {
-  let test = user_database_test(1);
+  let test = workspace_database_test(1).await;
   let relations = test.relations();
   let mut rx = poll_row_relation_rx(relations.subscript_update());
 
@@ -53,13 +63,13 @@ async fn insert_row_relation_data_test() {
       assert_eq!(value.linking_database_id, "d1");
       assert_eq!(value.linked_by_database_id, "d2");
     },
-    RowRelationChange::DeleteRelation(_) => {},
+    _ => panic!("expected a new relation"),
   }
 }
 
 #[tokio::test]
 async fn remove_row_relation_data_test() {
-  let test = user_database_test(1);
+  let test = workspace_database_test(1).await;
   let relations = test.relations();
   let mut rx = poll_row_relation_rx(relations.subscript_update());
 
@@ -78,6 +88,478 @@ async fn remove_row_relation_data_test() {
       assert_eq!(value.linking_database_id, "d1");
       assert_eq!(value.linked_by_database_id, "d2");
     },
-    RowRelationChange::DeleteRelation(_) => {},
+    _ => panic!("expected a new relation"),
   }
+  assert!(relations.get_relation("d1", "d2").is_none());
+}
+
+#[tokio::test]
+async fn get_row_connection_test() {
+  let test = workspace_database_test(1).await;
+  link_rows(&test, "d1", "d2", "1", vec!["a", "b"]);
+
+  let connection = test
+    .relations()
+    .get_row_connection("d1", "d2", "d1", "1")
+    .unwrap();
+  assert_eq!(connection.row_id(), "1");
+  let linking_row_ids = connection
+    .linking_rows()
+    .iter()
+    .map(|row| row.row_id.as_str())
+    .collect::<Vec<_>>();
+  assert_eq!(linking_row_ids, vec!["a", "b"]);
+}
+
+#[tokio::test]
+async fn lookup_linked_rows_test() {
+  let test = user_database_with_linked_databases().await;
+  let field = lookup_field("lookup", LookupTypeOption::new("projects", "name"));
+
+  let data = test
+    .get_lookup_cell("tasks", &RowId::from("t1".to_string()), &field)
+    .await
+    .unwrap();
+  assert!(data.is_complete());
+  let names = data
+    .values
+    .iter()
+    .map(|row_cell| {
+      row_cell
+        .cell
+        .as_ref()
+        .unwrap()
+        .get_str_value("data")
+        .unwrap()
+    })
+    .collect::<Vec<_>>();
+  assert_eq!(names, vec!["Project A", "Project B"]);
+}
+
+#[tokio::test]
+async fn rollup_linked_rows_test() {
+  let test = user_database_with_linked_databases().await;
+  let expected = vec![
+    (RollupCalculation::Count, RollupValue::Number(2.0)),
+    (RollupCalculation::Sum, RollupValue::Number(15.0)),
+    (RollupCalculation::Average, RollupValue::Number(7.5)),
+    (RollupCalculation::Min, RollupValue::Number(5.0)),
+    (RollupCalculation::Max, RollupValue::Number(10.0)),
+  ];
+  for (calculation, value) in expected {
+    let field = rollup_field(
+      "rollup",
+      RollupTypeOption::new("projects", "budget", calculation),
+    );
+    let data = test
+      .get_rollup_cell("tasks", &RowId::from("t1".to_string()), &field)
+      .await
+      .unwrap();
+    assert_eq!(data.value, value);
+  }
+
+  let field = rollup_field(
+    "rollup",
+    RollupTypeOption::new("projects", "name", RollupCalculation::Concat),
+  );
+  let data = test
+    .get_rollup_cell("tasks", &RowId::from("t1".to_string()), &field)
+    .await
+    .unwrap();
+  assert_eq!(
+    data.value,
+    RollupValue::Text("Project A, Project B".to_string())
+  );
+}
+
+#[tokio::test]
+async fn rollup_recompute_after_linked_row_changed_test() {
+  let test = user_database_with_linked_databases().await;
+  let field = rollup_field(
+    "rollup",
+    RollupTypeOption::new("projects", "budget", RollupCalculation::Sum),
+  );
+  let data = test
+    .get_rollup_cell("tasks", &RowId::from("t1".to_string()), &field)
+    .await
+    .unwrap();
+  assert_eq!(data.value, RollupValue::Number(15.0));
+
+  let mut rx = test.subscribe_relation_cell_change();
+  let projects = test.get_database("projects").await.unwrap();
+  projects
+    .lock()
+    .update_row(&RowId::from("p1".to_string()), |row_update| {
+      row_update.update_cells(|cells_update| {
+        cells_update.insert(
+          "budget",
+          new_cell_builder(1).insert_i64_value("data", 20).build(),
+        );
+      });
+    })
+    .unwrap();
+
+  let change = test_timeout(rx.recv()).await.unwrap();
+  assert_eq!(
+    change,
+    RelationCellChange::Invalidated {
+      database_id: "tasks".to_string(),
+      relation_database_id: "projects".to_string(),
+    }
+  );
+  let data = test
+    .get_rollup_cell("tasks", &RowId::from("t1".to_string()), &field)
+    .await
+    .unwrap();
+  assert_eq!(data.value, RollupValue::Number(25.0));
+}
+
+#[tokio::test]
+async fn rollup_recompute_after_relation_changed_test() {
+  let test = user_database_with_linked_databases().await;
+  let field = rollup_field(
+    "rollup",
+    RollupTypeOption::new("projects", "budget", RollupCalculation::Count),
+  );
+  let data = test
+    .get_rollup_cell("tasks", &RowId::from("t1".to_string()), &field)
+    .await
+    .unwrap();
+  assert_eq!(data.value, RollupValue::Number(2.0));
+
+  let mut rx = test.subscribe_relation_cell_change();
+  test
+    .relations()
+    .unlink_rows("tasks", "projects", "t1", &["p2".to_string()]);
+  let _ = test_timeout(rx.recv()).await.unwrap();
+
+  let data = test
+    .get_rollup_cell("tasks", &RowId::from("t1".to_string()), &field)
+    .await
+    .unwrap();
+  assert_eq!(data.value, RollupValue::Number(1.0));
+}
+
+#[tokio::test]
+async fn lookup_unloaded_database_test() {
+  let test = user_database_with_linked_databases().await;
+  link_rows(&test, "tasks", "archived", "t1", vec!["x1", "x2"]);
+
+  let field = lookup_field("lookup", LookupTypeOption::new("archived", "name"));
+  let data = test
+    .get_lookup_cell("tasks", &RowId::from("t1".to_string()), &field)
+    .await
+    .unwrap();
+  assert!(!data.is_complete());
+  assert!(data.values.is_empty());
+  assert_eq!(
+    data.pending_row_ids,
+    vec![RowId::from("x1".to_string()), RowId::from("x2".to_string())]
+  );
+
+  let field = rollup_field(
+    "rollup",
+    RollupTypeOption::new("archived", "budget", RollupCalculation::Count),
+  );
+  let data = test
+    .get_rollup_cell("tasks", &RowId::from("t1".to_string()), &field)
+    .await
+    .unwrap();
+  assert_eq!(data.value, RollupValue::Number(2.0));
+  assert_eq!(data.pending_row_ids.len(), 2);
+}
+
+#[tokio::test]
+async fn lookup_with_non_relation_field_test() {
+  let test = user_database_with_linked_databases().await;
+  let field = Field::new("f1".to_string(), "text".to_string(), 0, false);
+  assert!(test
+    .get_lookup_cell("tasks", &RowId::from("t1".to_string()), &field)
+    .await
+    .is_err());
+}
+
+#[tokio::test]
+async fn link_rows_adds_linked_by_rows_test() {
+  let test = workspace_database_test(1).await;
+  link_rows(&test, "d1", "d2", "a1", vec!["b1", "b2"]);
+  link_rows(&test, "d1", "d2", "a2", vec!["b1"]);
+  // Linking the same rows again is a no-op.
+  link_rows(&test, "d1", "d2", "a1", vec!["b1"]);
+
+  let relations = test.relations();
+  let a1 = relations
+    .get_row_connection("d1", "d2", "d1", "a1")
+    .unwrap();
+  assert_eq!(a1.linking_rows().len(), 2);
+  let b1 = relations
+    .get_row_connection("d1", "d2", "d2", "b1")
+    .unwrap();
+  let linked_by_row_ids = b1
+    .linked_by_rows()
+    .iter()
+    .map(|row| row.row_id.as_str())
+    .collect::<Vec<_>>();
+  assert_eq!(linked_by_row_ids, vec!["a1", "a2"]);
+  assert!(relations.find_dangling_links().is_empty());
+}
+
+#[tokio::test]
+async fn unlink_rows_removes_linked_by_rows_test() {
+  let test = workspace_database_test(1).await;
+  link_rows(&test, "d1", "d2", "a1", vec!["b1", "b2"]);
+  let relations = test.relations();
+  relations.unlink_rows("d1", "d2", "a1", &["b1".to_string()]);
+
+  let a1 = relations
+    .get_row_connection("d1", "d2", "d1", "a1")
+    .unwrap();
+  assert_eq!(a1.linking_rows().len(), 1);
+  assert_eq!(a1.linking_rows()[0].row_id, "b2");
+  // The connection of b1 is removed because it has no links anymore.
+  assert!(relations
+    .get_row_connection("d1", "d2", "d2", "b1")
+    .is_none());
+  assert!(relations.find_dangling_links().is_empty());
+}
+
+#[tokio::test]
+async fn remove_rows_cleans_up_links_test() {
+  let test = user_database_with_linked_databases().await;
+  let rows = test
+    .remove_rows("projects", &[RowId::from("p1".to_string())])
+    .await
+    .unwrap();
+  assert_eq!(rows.len(), 1);
+
+  let relations = test.relations();
+  let t1 = relations
+    .get_row_connection("tasks", "projects", "tasks", "t1")
+    .unwrap();
+  assert_eq!(t1.linking_rows().len(), 1);
+  assert_eq!(t1.linking_rows()[0].row_id, "p2");
+  assert!(relations
+    .get_row_connection("tasks", "projects", "projects", "p1")
+    .is_none());
+  assert!(test.find_dangling_links().await.is_empty());
+
+  // Removing the linking row removes the reverse links too.
+  test
+    .remove_rows("tasks", &[RowId::from("t1".to_string())])
+    .await
+    .unwrap();
+  assert!(relations
+    .get_row_connection("tasks", "projects", "projects", "p2")
+    .is_none());
+}
+
+#[tokio::test]
+async fn remove_row_from_database_cleans_up_links_test() {
+  let test = user_database_with_linked_databases().await;
+  let database = test.get_database("projects").await.unwrap();
+  database.lock().remove_row(&RowId::from("p1".to_string()));
+
+  let relations = test.relations();
+  let t1 = relations
+    .get_row_connection("tasks", "projects", "tasks", "t1")
+    .unwrap();
+  assert_eq!(t1.linking_rows().len(), 1);
+  assert_eq!(t1.linking_rows()[0].row_id, "p2");
+  assert!(relations
+    .get_row_connection("tasks", "projects", "projects", "p1")
+    .is_none());
+  assert!(test.find_dangling_links().await.is_empty());
+}
+
+#[tokio::test]
+async fn row_connections_with_same_row_id_test() {
+  let test = workspace_database_test(1).await;
+  // The row "r1" exists in both databases.
+  link_rows(&test, "d1", "d2", "r1", vec!["r1", "r2"]);
+
+  let relations = test.relations();
+  let linking = relations
+    .get_row_connection("d1", "d2", "d1", "r1")
+    .unwrap();
+  assert_eq!(linking.linking_rows().len(), 2);
+  assert!(linking.linked_by_rows().is_empty());
+  let linked = relations
+    .get_row_connection("d1", "d2", "d2", "r1")
+    .unwrap();
+  assert!(linked.linking_rows().is_empty());
+  assert_eq!(linked.linked_by_rows()[0].row_id, "r1");
+
+  relations.remove_rows("d2", &["r1".to_string()]);
+  assert!(relations
+    .get_row_connection("d1", "d2", "d2", "r1")
+    .is_none());
+  let linking = relations
+    .get_row_connection("d1", "d2", "d1", "r1")
+    .unwrap();
+  assert_eq!(linking.linking_rows().len(), 1);
+  assert_eq!(linking.linking_rows()[0].row_id, "r2");
+}
+
+#[tokio::test]
+async fn delete_database_removes_relations_test() {
+  let test = user_database_with_linked_databases().await;
+  link_rows(&test, "projects", "tasks", "p1", vec!["t1"]);
+  test.delete_database("projects");
+
+  let relations = test.relations();
+  assert!(relations.get_relation("tasks", "projects").is_none());
+  assert!(relations.get_relation("projects", "tasks").is_none());
+}
+
+#[tokio::test]
+async fn find_dangling_links_test() {
+  let test = user_database_with_linked_databases().await;
+  // A one-sided link written without the reverse LinkedByRow.
+  let mut row_connections = std::collections::HashMap::new();
+  row_connections.insert(
+    "t1".to_string(),
+    RowConnection::new(
+      "tasks",
+      "t1",
+      vec![LinkingRow {
+        row_id: "x1".to_string(),
+        content: "".to_string(),
+      }],
+      vec![],
+    ),
+  );
+  test.relations().insert_relation(RowRelation {
+    linking_database_id: "tasks".to_string(),
+    linked_by_database_id: "archived".to_string(),
+    row_connections,
+  });
+  link_rows(&test, "tasks", "projects", "t1", vec!["p3"]);
+
+  let links = test.find_dangling_links().await;
+  assert_eq!(links.len(), 3);
+  assert!(links.contains(&DanglingLink::MissingLinkedByRow {
+    relation_id: "tasks-archived".to_string(),
+    row_id: "t1".to_string(),
+    linking_row_id: "x1".to_string(),
+  }));
+  assert!(links.contains(&DanglingLink::DatabaseNotExist {
+    relation_id: "tasks-archived".to_string(),
+    database_id: "archived".to_string(),
+  }));
+  assert!(links.contains(&DanglingLink::RowNotExist {
+    relation_id: "tasks-projects".to_string(),
+    database_id: "projects".to_string(),
+    row_id: "p3".to_string(),
+  }));
+}
+
+const LOOKUP_FIELD_TYPE: i64 = 100;
+const ROLLUP_FIELD_TYPE: i64 = 101;
+
+fn lookup_field(field_id: &str, type_option: LookupTypeOption) -> Field {
+  let mut field = Field::new(
+    field_id.to_string(),
+    "lookup".to_string(),
+    LOOKUP_FIELD_TYPE,
+    false,
+  );
+  field
+    .type_options
+    .insert(LOOKUP_FIELD_TYPE.to_string(), type_option.into());
+  field
+}
+
+fn rollup_field(field_id: &str, type_option: RollupTypeOption) -> Field {
+  let mut field = Field::new(
+    field_id.to_string(),
+    "rollup".to_string(),
+    ROLLUP_FIELD_TYPE,
+    false,
+  );
+  field
+    .type_options
+    .insert(ROLLUP_FIELD_TYPE.to_string(), type_option.into());
+  field
+}
+
+fn link_rows(
+  test: &WorkspaceDatabaseTest,
+  linking_database_id: &str,
+  linked_by_database_id: &str,
+  row_id: &str,
+  linking_row_ids: Vec<&str>,
+) {
+  let linking_rows = linking_row_ids
+    .into_iter()
+    .map(|row_id| LinkingRow {
+      row_id: row_id.to_string(),
+      content: "".to_string(),
+    })
+    .collect();
+  test.relations().link_rows(
+    linking_database_id,
+    linked_by_database_id,
+    row_id,
+    linking_rows,
+  );
+}
+
+/// Create the "tasks" database whose row "t1" links to the rows "p1" and "p2" of the
+/// "projects" database.
+async fn user_database_with_linked_databases() -> WorkspaceDatabaseTest {
+  let test = workspace_database_test(1).await;
+  let project_row = |row_id: &str, name: &str, budget: i64| {
+    CreateRowParams::new(row_id.to_string(), "projects".to_string()).with_cells(
+      CellsBuilder::new()
+        .insert_cell(
+          "name",
+          new_cell_builder(0)
+            .insert_str_value("data", name.to_string())
+            .build(),
+        )
+        .insert_cell(
+          "budget",
+          new_cell_builder(1).insert_i64_value("data", budget).build(),
+        )
+        .build(),
+    )
+  };
+  test
+    .create_database(CreateDatabaseParams {
+      database_id: "projects".to_string(),
+      inline_view_id: "v_projects".to_string(),
+      views: vec![CreateViewParams {
+        database_id: "projects".to_string(),
+        view_id: "v_projects".to_string(),
+        field_settings: field_settings_for_default_database(),
+        ..Default::default()
+      }],
+      rows: vec![
+        project_row("p1", "Project A", 10),
+        project_row("p2", "Project B", 5),
+      ],
+      fields: vec![
+        Field::new("name".to_string(), "Name".to_string(), 0, true),
+        Field::new("budget".to_string(), "Budget".to_string(), 1, false),
+      ],
+    })
+    .unwrap();
+
+  test
+    .create_database(CreateDatabaseParams {
+      database_id: "tasks".to_string(),
+      inline_view_id: "v_tasks".to_string(),
+      views: vec![CreateViewParams {
+        database_id: "tasks".to_string(),
+        view_id: "v_tasks".to_string(),
+        field_settings: field_settings_for_default_database(),
+        ..Default::default()
+      }],
+      rows: vec![CreateRowParams::new("t1".to_string(), "tasks".to_string())],
+      fields: vec![Field::new("f1".to_string(), "Name".to_string(), 0, true)],
+    })
+    .unwrap();
+
+  link_rows(&test, "tasks", "projects", "t1", vec!["p1", "p2"]);
+  test
 }
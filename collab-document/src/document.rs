@@ -2,7 +2,6 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::vec;
 
-use collab::core::awareness::AwarenessUpdateSubscription;
 use collab::core::collab::{DataSource, MutexCollab};
 use collab::core::collab_state::SyncState;
 use collab::core::origin::CollabOrigin;
@@ -11,7 +10,6 @@ use collab::preclude::block::ClientID;
 use collab::preclude::*;
 use collab_entity::define::DOCUMENT_ROOT;
 use collab_entity::CollabType;
-use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use tokio_stream::wrappers::WatchStream;
@@ -46,7 +44,6 @@ pub struct Document {
   children_operation: ChildrenOperation,
   block_operation: BlockOperation,
   text_operation: TextOperation,
-  awareness_subscription: RwLock<Option<AwarenessUpdateSubscription>>,
 }
 
 impl Document {
@@ -462,66 +459,28 @@ impl Document {
   // Set the local state of the awareness.
   // It will override the previous state.
   pub fn set_awareness_local_state(&self, state: DocumentAwarenessState) {
-    if let Ok(state) = serde_json::to_string(&state) {
-      self.inner.lock().get_mut_awareness().set_local_state(state);
-    } else {
-      tracing::error!(
-        "Failed to serialize DocumentAwarenessState, state: {:?}",
-        state
-      );
-    }
+    self.inner.set_awareness_state(&state)
   }
 
   pub fn get_awareness_local_state(&self) -> Option<DocumentAwarenessState> {
-    let mut collab = self.inner.lock();
-    let state = collab.get_mut_awareness().get_local_state();
-    state.and_then(|state| {
-      serde_json::from_value(state.clone()).ok().or_else(|| {
-        tracing::error!(
-          "Failed to deserialize DocumentAwarenessState, state: {:?}",
-          state
-        );
-        None
-      })
-    })
+    self.inner.get_awareness_state()
   }
 
   // Clean the local state of the awareness.
   // It should be called when the document is closed.
   pub fn clean_awareness_local_state(&self) {
-    self.inner.lock().get_mut_awareness().clean_local_state()
+    self.inner.clean_awareness_state()
   }
 
   // Subscribe to the awareness state change.
-  // This function only allowed to be called once for each document.
-  pub fn subscribe_awareness_state<F>(&mut self, f: F)
+  // The callback is called until the collab of the document is dropped, and subscribing again
+  // replaces the previous callback. The states that are not DocumentAwarenessState, or are
+  // published with an unsupported version, are skipped.
+  pub fn subscribe_awareness_state<F>(&self, f: F)
   where
     F: Fn(HashMap<ClientID, DocumentAwarenessState>) + 'static,
   {
-    let subscription = self
-      .inner
-      .lock()
-      .observe_awareness(move |awareness, _event, _| {
-        // convert the states to the hashmap and map/filter the invalid states
-        let result: HashMap<ClientID, DocumentAwarenessState> = awareness.get_states()
-          .iter()
-          .filter_map(|(id, state)| {
-            state
-              .as_str()
-              .and_then(|str| serde_json::from_str(str).ok().map(|state| (*id, state)))
-              .or_else(|| {
-                tracing::error!(
-                  "subscribe_awareness_state error: failed to parse state for id: {:?}, state: {:?}",
-                  id,
-                  state
-                );
-                None
-              })
-          })
-          .collect();
-        f(result);
-      });
-    *self.awareness_subscription.write() = Some(subscription);
+    self.inner.subscribe_awareness_states(f)
   }
 
   fn create_document(
@@ -582,7 +541,6 @@ impl Document {
       children_operation,
       text_operation,
       subscription: None,
-      awareness_subscription: Default::default(),
     };
     Ok(document)
   }
@@ -642,7 +600,6 @@ impl Document {
       children_operation: children_operation.unwrap(),
       text_operation: text_operation.unwrap(),
       subscription: None,
-      awareness_subscription: Default::default(),
     })
   }
 
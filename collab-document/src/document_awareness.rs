@@ -1,6 +1,13 @@
+use collab::core::awareness::{AwarenessState, AwarenessUser, Error};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-#[derive(Serialize, Deserialize, Debug)]
+/// The awareness state of a document. The version of the state is [AwarenessState::VERSION].
+///
+/// The older clients only decode the state that is published as a json string with a `version`
+/// field, so it's still published in that format, which is decoded as the version 0. Once all the
+/// clients decode the versioned state, the default [AwarenessState::encode] can be used instead.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct DocumentAwarenessState {
   // the fields supported in version 1 contain the user, selection, metadata, and timestamp fields
   pub version: i64,
@@ -24,19 +31,31 @@ impl DocumentAwarenessState {
   }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct DocumentAwarenessUser {
-  pub uid: i64,
-  pub device_id: String,
+impl AwarenessState for DocumentAwarenessState {
+  const VERSION: u32 = 1;
+
+  fn encode(&self) -> Result<Value, Error> {
+    Ok(Value::String(serde_json::to_string(self)?))
+  }
+
+  fn decode_version(version: u32, state: &Value) -> Result<Self, Error> {
+    match state {
+      // The json string that is published by this client and the older ones
+      Value::String(state) if version == 0 => Ok(serde_json::from_str(state)?),
+      _ => Err(Error::UnsupportedStateVersion(version)),
+    }
+  }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+pub type DocumentAwarenessUser = AwarenessUser;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct DocumentAwarenessSelection {
   pub start: DocumentAwarenessPosition,
   pub end: DocumentAwarenessPosition,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct DocumentAwarenessPosition {
   pub path: Vec<u64>,
   pub offset: u64,
@@ -0,0 +1,94 @@
+use std::sync::mpsc;
+
+use collab::core::origin::CollabOrigin;
+use collab::preclude::Collab;
+use collab_document::document_awareness::{
+  DocumentAwarenessPosition, DocumentAwarenessSelection, DocumentAwarenessState,
+  DocumentAwarenessUser,
+};
+use serde_json::{json, Value};
+
+use crate::util::DocumentTest;
+
+fn awareness_state(uid: i64) -> DocumentAwarenessState {
+  let mut state = DocumentAwarenessState::new(
+    1,
+    DocumentAwarenessUser {
+      uid,
+      device_id: uid.to_string(),
+    },
+  );
+  state.selection = Some(DocumentAwarenessSelection {
+    start: DocumentAwarenessPosition {
+      path: vec![0],
+      offset: 1,
+    },
+    end: DocumentAwarenessPosition {
+      path: vec![0],
+      offset: 3,
+    },
+  });
+  state
+}
+
+#[tokio::test]
+async fn set_awareness_local_state_test() {
+  let test = DocumentTest::new(1, "1").await;
+  let state = awareness_state(1);
+  test.set_awareness_local_state(state.clone());
+  assert_eq!(test.get_awareness_local_state(), Some(state));
+
+  // The state is still published as the json string that the older clients decode
+  let local_state = test
+    .get_collab()
+    .lock()
+    .get_awareness()
+    .get_local_state()
+    .cloned()
+    .unwrap();
+  let legacy_json: Value = serde_json::from_str(local_state.as_str().unwrap()).unwrap();
+  assert_eq!(legacy_json["version"], json!(1));
+  assert_eq!(legacy_json["user"]["uid"], json!(1));
+
+  test.clean_awareness_local_state();
+  assert_eq!(test.get_awareness_local_state(), None);
+}
+
+#[tokio::test]
+async fn subscribe_awareness_state_test() {
+  let test = DocumentTest::new(1, "1").await;
+  let (tx, rx) = mpsc::channel();
+  test.document.subscribe_awareness_state(move |states| {
+    tx.send(states).unwrap();
+  });
+
+  // The older clients publish the state as a json string with a version field
+  let mut legacy_client = Collab::new(2, "1", "2", vec![], true);
+  let legacy_state = awareness_state(2);
+  legacy_client
+    .get_mut_awareness()
+    .set_local_state(serde_json::to_string(&legacy_state).unwrap());
+  let mut client = Collab::new(3, "1", "3", vec![], true);
+  let state = awareness_state(3);
+  client
+    .get_mut_awareness()
+    .set_local_typed_state(&state)
+    .unwrap();
+
+  for client in [&legacy_client, &client] {
+    let update = client.get_awareness().update().unwrap();
+    test
+      .get_collab()
+      .lock()
+      .get_mut_awareness()
+      .apply_update(update, &CollabOrigin::Empty)
+      .unwrap();
+  }
+
+  let states = rx.try_iter().last().unwrap();
+  assert_eq!(
+    states.get(&legacy_client.get_doc().client_id()),
+    Some(&legacy_state)
+  );
+  assert_eq!(states.get(&client.get_doc().client_id()), Some(&state));
+}
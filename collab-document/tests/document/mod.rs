@@ -1,4 +1,6 @@
+mod awareness_test;
 mod document_data_test;
 mod document_test;
 mod redo_undo_test;
 mod restore_test;
+mod sync_sim_test;
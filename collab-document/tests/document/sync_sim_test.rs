@@ -0,0 +1,53 @@
+use collab::sync_sim::{NetworkConfig, SyncSimulation};
+use collab_document::document::Document;
+use collab_document::document_data::default_document_data;
+use serde_json::json;
+
+use crate::util::{get_document_data, insert_block_for_page};
+
+/// Create the document on the first peer and open it on the other peers once it's synced.
+fn open_documents(sim: &mut SyncSimulation) -> Vec<Document> {
+  let first = Document::create_with_data(sim.peer(0), default_document_data()).unwrap();
+  sim.sync_all();
+
+  let mut documents = vec![first];
+  for peer_id in 1..sim.num_of_peers() {
+    documents.push(Document::open(sim.peer(peer_id)).unwrap());
+  }
+  documents
+}
+
+fn first_text_id(document: &Document) -> String {
+  let data = document.get_document_data().unwrap();
+  let text_map = data.meta.text_map.unwrap();
+  text_map.keys().next().unwrap().clone()
+}
+
+#[test]
+fn concurrent_document_edits_converge_test() {
+  let config = NetworkConfig::default()
+    .with_latency(1, 30)
+    .with_duplicate_rate(0.2);
+  let mut sim = SyncSimulation::new("document", 3, 11, config);
+  let documents = open_documents(&mut sim);
+
+  sim.partition(&[0], &[1, 2]);
+  insert_block_for_page(&documents[0], "block_0".to_string());
+  insert_block_for_page(&documents[2], "block_2".to_string());
+  let text_id = first_text_id(&documents[1]);
+  documents[1].apply_text_delta(&text_id, json!([{"insert": "hello"}]).to_string());
+  sim.run_until_quiet();
+  assert!(!sim.is_converged());
+
+  sim.heal();
+  sim.sync_all();
+  sim.assert_converged();
+  for document in &documents {
+    let (_, blocks, _) = get_document_data(document);
+    assert!(blocks.contains_key("block_0"));
+    assert!(blocks.contains_key("block_2"));
+    let text_map = document.get_document_data().unwrap().meta.text_map.unwrap();
+    let delta: serde_json::Value = serde_json::from_str(&text_map[&text_id]).unwrap();
+    assert_eq!(delta, json!([{"insert": "hello"}]));
+  }
+}
@@ -6,6 +6,7 @@ use collab::core::collab::{DataSource, IndexContentReceiver, MutexCollab};
 use collab::core::collab_state::{SnapshotState, SyncState};
 pub use collab::core::origin::CollabOrigin;
 use collab::entity::EncodedCollab;
+use collab::preclude::block::ClientID;
 use collab::preclude::*;
 use collab_entity::define::{FOLDER, FOLDER_META, FOLDER_WORKSPACE_ID};
 use collab_entity::CollabType;
@@ -18,8 +19,8 @@ use crate::folder_observe::ViewChangeSender;
 use crate::section::{Section, SectionItem, SectionMap, SectionOperation};
 use crate::view::view_from_map_ref;
 use crate::{
-  impl_section_op, subscribe_folder_change, FolderData, SectionChangeSender, TrashInfo, View,
-  ViewRelations, ViewsMap, Workspace,
+  impl_section_op, subscribe_folder_change, FolderAwarenessState, FolderData, SectionChangeSender,
+  TrashInfo, View, ViewRelations, ViewsMap, Workspace,
 };
 
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
@@ -157,6 +158,30 @@ impl Folder {
     self.inner.lock().subscribe_index_content()
   }
 
+  /// Set the local awareness state, like the opened view. It overrides the previous state.
+  pub fn set_awareness_local_state(&self, state: FolderAwarenessState) {
+    self.inner.set_awareness_state(&state)
+  }
+
+  pub fn get_awareness_local_state(&self) -> Option<FolderAwarenessState> {
+    self.inner.get_awareness_state()
+  }
+
+  /// Clean the local awareness state. It should be called when the folder is closed.
+  pub fn clean_awareness_local_state(&self) {
+    self.inner.clean_awareness_state()
+  }
+
+  /// Subscribe to the awareness states of the clients. The callback is called until the collab of
+  /// the folder is dropped, and subscribing again replaces the previous callback. The states that
+  /// are not [FolderAwarenessState], or are published with an unsupported version, are skipped.
+  pub fn subscribe_awareness_state<F>(&self, f: F)
+  where
+    F: Fn(HashMap<ClientID, FolderAwarenessState>) + 'static,
+  {
+    self.inner.subscribe_awareness_states(f)
+  }
+
   /// Returns the doc state and the state vector.
   pub fn encode_collab_v1(&self) -> Result<EncodedCollab, FolderError> {
     self.inner.lock().encode_collab_v1(|collab| {
@@ -0,0 +1,30 @@
+use collab::core::awareness::{AwarenessState, AwarenessUser};
+use serde::{Deserialize, Serialize};
+
+/// The awareness state of a folder, like the view that the user opens.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FolderAwarenessState {
+  pub user: FolderAwarenessUser,
+  pub view_id: Option<String>,
+  // The `metadata` field is an optional field (json string) that can be used to store additional
+  // information.
+  pub metadata: Option<String>,
+  pub timestamp: i64,
+}
+
+impl FolderAwarenessState {
+  pub fn new(user: FolderAwarenessUser) -> Self {
+    Self {
+      user,
+      view_id: None,
+      metadata: None,
+      timestamp: 0,
+    }
+  }
+}
+
+impl AwarenessState for FolderAwarenessState {
+  const VERSION: u32 = 1;
+}
+
+pub type FolderAwarenessUser = AwarenessUser;
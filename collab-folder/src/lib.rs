@@ -1,5 +1,6 @@
 pub use entities::*;
 pub use folder::*;
+pub use folder_awareness::*;
 pub use folder_migration::*;
 pub use folder_observe::*;
 pub use relation::*;
@@ -10,6 +11,7 @@ pub use workspace::*;
 
 mod entities;
 mod folder;
+mod folder_awareness;
 mod relation;
 mod section;
 // mod trash;
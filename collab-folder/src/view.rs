@@ -793,6 +793,7 @@ pub enum ViewLayout {
   Grid = 1,
   Board = 2,
   Calendar = 3,
+  Timeline = 4,
 }
 
 impl ViewLayout {
@@ -803,7 +804,7 @@ impl ViewLayout {
   pub fn is_database(&self) -> bool {
     matches!(
       self,
-      ViewLayout::Grid | ViewLayout::Board | ViewLayout::Calendar
+      ViewLayout::Grid | ViewLayout::Board | ViewLayout::Calendar | ViewLayout::Timeline
     )
   }
 }
@@ -817,6 +818,7 @@ impl TryFrom<i64> for ViewLayout {
       1 => Ok(ViewLayout::Grid),
       2 => Ok(ViewLayout::Board),
       3 => Ok(ViewLayout::Calendar),
+      4 => Ok(ViewLayout::Timeline),
       _ => bail!("Unknown layout {}", value),
     }
   }
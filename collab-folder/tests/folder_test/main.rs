@@ -4,6 +4,7 @@ mod favorite_test;
 mod load_disk;
 mod recent_views_test;
 mod serde_test;
+mod sync_sim_test;
 mod trash_test;
 mod util;
 mod view_test;
@@ -0,0 +1,123 @@
+use collab::core::collab::DataSource;
+use collab::core::origin::CollabOrigin;
+use collab::preclude::{ReadTxn, StateVector};
+use collab::sync_sim::{NetworkConfig, SyncSimulation};
+use collab_folder::{Folder, FolderData, UserId, Workspace};
+
+use crate::util::make_test_view;
+
+/// Create the folder on the first peer and open it on the other peers once it's synced.
+fn open_folders(sim: &mut SyncSimulation, workspace_id: &str) -> Vec<Folder> {
+  let mut workspace = Workspace::new(workspace_id.to_string(), "".to_string(), 1);
+  workspace.created_at = 0;
+  let first = Folder::create(
+    UserId::from(1),
+    sim.peer(0),
+    None,
+    FolderData::new(workspace),
+  );
+  sim.sync_all();
+
+  let mut folders = vec![first];
+  for peer_id in 1..sim.num_of_peers() {
+    let uid = UserId::from(peer_id as i64 + 1);
+    folders.push(Folder::open(uid, sim.peer(peer_id), None).unwrap());
+  }
+  folders
+}
+
+fn child_view_ids(folder: &Folder, parent_view_id: &str) -> Vec<String> {
+  folder
+    .get_views_belong_to(parent_view_id)
+    .iter()
+    .map(|view| view.id.clone())
+    .collect()
+}
+
+/// Return the child views of the workspace, read from a folder that is reopened from the state
+/// of the peer, so nothing is served from the cache of the opened folder.
+fn reopened_child_view_ids(
+  sim: &SyncSimulation,
+  peer_id: usize,
+  workspace_id: &str,
+) -> Vec<String> {
+  let doc_state = sim
+    .peer(peer_id)
+    .lock()
+    .transact()
+    .encode_state_as_update_v1(&StateVector::default());
+  let folder = Folder::from_collab_doc_state(
+    peer_id as i64 + 1,
+    CollabOrigin::Empty,
+    DataSource::DocStateV1(doc_state),
+    workspace_id,
+    vec![],
+  )
+  .unwrap();
+  child_view_ids(&folder, workspace_id)
+}
+
+#[test]
+fn concurrent_create_views_converge_test() {
+  let config = NetworkConfig::default().with_latency(1, 30);
+  let mut sim = SyncSimulation::new("w1", 3, 11, config);
+  let folders = open_folders(&mut sim, "w1");
+
+  for (peer_id, folder) in folders.iter().enumerate() {
+    folder.insert_view(make_test_view(&format!("v{}", peer_id), "w1", vec![]), None);
+  }
+  sim.run_until_quiet();
+  sim.assert_converged();
+
+  let view_ids = reopened_child_view_ids(&sim, 0, "w1");
+  assert_eq!(view_ids.len(), 3);
+  for peer_id in 1..sim.num_of_peers() {
+    assert_eq!(reopened_child_view_ids(&sim, peer_id, "w1"), view_ids);
+  }
+}
+
+#[test]
+fn randomized_folder_edits_converge_test() {
+  for seed in 0..10 {
+    let config = NetworkConfig::default()
+      .with_latency(1, 50)
+      .with_drop_rate(0.1)
+      .with_duplicate_rate(0.1);
+    let mut sim = SyncSimulation::new("w1", 3, seed, config);
+    let folders = open_folders(&mut sim, "w1");
+
+    for i in 0..30 {
+      let peer_id = sim.rng().gen_index(folders.len());
+      let folder = &folders[peer_id];
+      let view_ids = child_view_ids(folder, "w1");
+      match sim.rng().gen_range(0..=3) {
+        0 if view_ids.len() > 1 => {
+          let from = sim.rng().gen_index(view_ids.len()) as u32;
+          let to = sim.rng().gen_index(view_ids.len()) as u32;
+          folder.move_view(&view_ids[from as usize], from, to);
+        },
+        1 if !view_ids.is_empty() => {
+          let view_id = &view_ids[sim.rng().gen_index(view_ids.len())];
+          folder.views.update_view(view_id, |update| {
+            update.set_name(format!("name {}", i)).done()
+          });
+        },
+        _ => {
+          let view_id = format!("v{}_{}", peer_id, i);
+          folder.insert_view(make_test_view(&view_id, "w1", vec![]), None);
+        },
+      }
+      let num_of_steps = sim.rng().gen_range(0..=2);
+      for _ in 0..num_of_steps {
+        sim.step();
+      }
+    }
+
+    sim.sync_all();
+    sim.assert_converged();
+    let view_ids = reopened_child_view_ids(&sim, 0, "w1");
+    for peer_id in 1..sim.num_of_peers() {
+      assert_eq!(reopened_child_view_ids(&sim, peer_id, "w1"), view_ids);
+    }
+  }
+}
@@ -0,0 +1,154 @@
+use std::io::{self, Read};
+
+/// The payloads that are smaller than this are not compressed, because the zstd frame header
+/// would make them bigger.
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 1024;
+/// The payloads are never decompressed to more than this, so a small crafted payload can't
+/// exhaust the memory.
+pub const MAX_DECOMPRESSED_PAYLOAD_SIZE: usize = 128 * 1024 * 1024;
+const ZSTD_LEVEL: i32 = 3;
+
+const RAW_TAG: u8 = 0;
+const ZSTD_TAG: u8 = 1;
+
+/// The compression of the payloads that are exchanged with the [RemoteCollabStorage].
+///
+/// The compression is negotiated per transport and per direction: the [RemoteCollab] only
+/// compresses the payloads it sends when the storage lists the compression in
+/// [RemoteCollabStorage::supported_compressions], and only asks for the compressed doc state and
+/// remote updates when the storage lists it in [RemoteCollabStorage::download_compressions]. Once
+/// a compression is negotiated, each payload starts with a tag byte, so the receiver must decode
+/// it with [decode_payload]. The payloads that are smaller than the threshold are tagged as raw.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PayloadCompression {
+  #[default]
+  None,
+  Zstd,
+}
+
+impl PayloadCompression {
+  /// Return the preferred compression if the remote storage supports it.
+  pub fn negotiate(preferred: PayloadCompression, supported: &[PayloadCompression]) -> Self {
+    if preferred != PayloadCompression::None && supported.contains(&preferred) {
+      preferred
+    } else {
+      PayloadCompression::None
+    }
+  }
+}
+
+/// Encode the payload with the negotiated compression. The payload is returned as is if no
+/// compression is negotiated.
+pub fn encode_payload(
+  compression: PayloadCompression,
+  threshold: usize,
+  payload: Vec<u8>,
+) -> Result<Vec<u8>, io::Error> {
+  match compression {
+    PayloadCompression::None => Ok(payload),
+    PayloadCompression::Zstd => {
+      if payload.len() >= threshold {
+        let compressed = zstd::bulk::compress(&payload, ZSTD_LEVEL)?;
+        // Keep the raw payload if it doesn't compress, like an update of random ids
+        if compressed.len() < payload.len() {
+          return Ok(tag_payload(ZSTD_TAG, &compressed));
+        }
+      }
+      Ok(tag_payload(RAW_TAG, &payload))
+    },
+  }
+}
+
+/// Decode the payload that is encoded by [encode_payload] with a negotiated compression. It fails
+/// if the payload is bigger than [MAX_DECOMPRESSED_PAYLOAD_SIZE] once decompressed.
+pub fn decode_payload(data: &[u8]) -> Result<Vec<u8>, io::Error> {
+  decode_payload_with_limit(data, MAX_DECOMPRESSED_PAYLOAD_SIZE)
+}
+
+/// Like [decode_payload], with the maximum size of the decompressed payload.
+pub fn decode_payload_with_limit(data: &[u8], max_size: usize) -> Result<Vec<u8>, io::Error> {
+  match data.split_first() {
+    Some((&RAW_TAG, payload)) => Ok(payload.to_vec()),
+    Some((&ZSTD_TAG, payload)) => decompress(payload, max_size),
+    Some((tag, _)) => Err(io::Error::new(
+      io::ErrorKind::InvalidData,
+      format!("unknown payload compression: {}", tag),
+    )),
+    None => Err(io::Error::new(
+      io::ErrorKind::UnexpectedEof,
+      "the payload is empty",
+    )),
+  }
+}
+
+/// Return true if the payload is encoded with a compression instead of being tagged as raw.
+pub(crate) fn is_compressed(compression: PayloadCompression, data: &[u8]) -> bool {
+  compression != PayloadCompression::None && data.first() == Some(&ZSTD_TAG)
+}
+
+/// Decompress the zstd frame without reading more than `max_size` bytes of it, so the size that
+/// the frame claims is never trusted.
+fn decompress(payload: &[u8], max_size: usize) -> Result<Vec<u8>, io::Error> {
+  let decoder = zstd::stream::read::Decoder::new(payload)?;
+  let mut data = vec![];
+  decoder.take(max_size as u64 + 1).read_to_end(&mut data)?;
+  if data.len() > max_size {
+    return Err(io::Error::new(
+      io::ErrorKind::InvalidData,
+      format!(
+        "the payload is bigger than {} bytes once decompressed",
+        max_size
+      ),
+    ));
+  }
+  Ok(data)
+}
+
+fn tag_payload(tag: u8, payload: &[u8]) -> Vec<u8> {
+  let mut data = Vec::with_capacity(payload.len() + 1);
+  data.push(tag);
+  data.extend_from_slice(payload);
+  data
+}
+
+/// The sizes of the payloads that are sent to the remote storage.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PayloadMetrics {
+  pub num_of_payloads: u64,
+  pub num_of_compressed_payloads: u64,
+  /// The size of the payloads before they are compressed.
+  pub raw_bytes: u64,
+  /// The size of the payloads that are sent, including the tag byte.
+  pub sent_bytes: u64,
+  /// The size of the biggest init sync payload before it's compressed.
+  pub max_init_sync_bytes: u64,
+}
+
+impl PayloadMetrics {
+  /// Return the ratio of the sent bytes to the raw bytes. It's 1.0 if nothing is sent.
+  pub fn compression_ratio(&self) -> f64 {
+    if self.raw_bytes == 0 {
+      1.0
+    } else {
+      self.sent_bytes as f64 / self.raw_bytes as f64
+    }
+  }
+
+  pub(crate) fn record(
+    &mut self,
+    is_init_msg: bool,
+    is_compressed: bool,
+    raw_len: usize,
+    sent_len: usize,
+  ) {
+    self.num_of_payloads += 1;
+    if is_compressed {
+      self.num_of_compressed_payloads += 1;
+    }
+    self.raw_bytes += raw_len as u64;
+    self.sent_bytes += sent_len as u64;
+    if is_init_msg {
+      self.max_init_sync_bytes = self.max_init_sync_bytes.max(raw_len as u64);
+    }
+  }
+}
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use parking_lot::RwLock;
+use rand::Rng;
+
+pub const WORKSPACE_KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+const BLOB_VERSION: u8 = 1;
+/// The version, the key id and the nonce.
+const BLOB_HEADER_LEN: usize = 1 + 4 + NONCE_LEN;
+
+#[derive(Debug, thiserror::Error)]
+pub enum EncryptionError {
+  #[error("the key {0} is not in the key ring")]
+  UnknownKey(u32),
+
+  #[error("the key {0} is already in the key ring")]
+  DuplicateKey(u32),
+
+  #[error("the current key {0} can't be removed")]
+  RemoveCurrentKey(u32),
+
+  #[error("invalid encrypted blob: {0}")]
+  InvalidBlob(String),
+
+  #[error("failed to encrypt the payload")]
+  Encrypt,
+
+  #[error("failed to decrypt the payload, it's corrupted or not encrypted for the object")]
+  Decrypt,
+}
+
+/// A symmetric key of a workspace. The `key_id` is stored in each blob that is encrypted with the
+/// key, so the key that decrypts the blob can be found after the keys are rotated.
+#[derive(Clone)]
+pub struct WorkspaceKey {
+  key_id: u32,
+  key: [u8; WORKSPACE_KEY_LEN],
+}
+
+impl WorkspaceKey {
+  pub fn new(key_id: u32, key: [u8; WORKSPACE_KEY_LEN]) -> Self {
+    Self { key_id, key }
+  }
+
+  /// Generate a random key.
+  pub fn generate(key_id: u32) -> Self {
+    Self::new(key_id, rand::thread_rng().gen())
+  }
+
+  pub fn key_id(&self) -> u32 {
+    self.key_id
+  }
+
+  pub fn as_bytes(&self) -> &[u8; WORKSPACE_KEY_LEN] {
+    &self.key
+  }
+}
+
+impl Debug for WorkspaceKey {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    // Never print the key itself
+    f.debug_struct("WorkspaceKey")
+      .field("key_id", &self.key_id)
+      .finish()
+  }
+}
+
+/// The keys of an end-to-end encrypted workspace. The payloads are encrypted with the current key
+/// and decrypted with the key that encrypted them.
+///
+/// The blobs are encrypted with AES-256-GCM. The header of the blob and the object id are used as
+/// the associated data, so the server can neither move a blob from one object to another nor
+/// tamper with the version or the key id. A blob is laid out as:
+///
+/// ```text
+/// | version (1) | key id (4, big endian) | nonce (12) | ciphertext and tag |
+/// ```
+pub struct WorkspaceKeyRing {
+  workspace_id: String,
+  state: RwLock<KeyRingState>,
+}
+
+struct KeyRingState {
+  current_key_id: u32,
+  keys: HashMap<u32, WorkspaceKey>,
+}
+
+impl WorkspaceKeyRing {
+  pub fn new(workspace_id: &str, key: WorkspaceKey) -> Self {
+    let current_key_id = key.key_id;
+    Self {
+      workspace_id: workspace_id.to_string(),
+      state: RwLock::new(KeyRingState {
+        current_key_id,
+        keys: HashMap::from([(current_key_id, key)]),
+      }),
+    }
+  }
+
+  pub fn workspace_id(&self) -> &str {
+    &self.workspace_id
+  }
+
+  pub fn current_key_id(&self) -> u32 {
+    self.state.read().current_key_id
+  }
+
+  /// Add a key that was used before, for example a key that is shared by another device, so the
+  /// blobs that are encrypted with it can be decrypted.
+  pub fn add_key(&self, key: WorkspaceKey) -> Result<(), EncryptionError> {
+    let mut state = self.state.write();
+    if state.keys.contains_key(&key.key_id) {
+      return Err(EncryptionError::DuplicateKey(key.key_id));
+    }
+    state.keys.insert(key.key_id, key);
+    Ok(())
+  }
+
+  /// Encrypt the payloads with the new key from now on. The previous keys are kept to decrypt the
+  /// blobs that were encrypted with them, until the blobs are compacted and the keys are removed
+  /// with [WorkspaceKeyRing::remove_key].
+  pub fn rotate(&self, key: WorkspaceKey) -> Result<(), EncryptionError> {
+    let key_id = key.key_id;
+    self.add_key(key)?;
+    self.state.write().current_key_id = key_id;
+    Ok(())
+  }
+
+  pub fn remove_key(&self, key_id: u32) -> Result<(), EncryptionError> {
+    let mut state = self.state.write();
+    if state.current_key_id == key_id {
+      return Err(EncryptionError::RemoveCurrentKey(key_id));
+    }
+    state
+      .keys
+      .remove(&key_id)
+      .map(|_| ())
+      .ok_or(EncryptionError::UnknownKey(key_id))
+  }
+
+  /// Encrypt the payload of the object with the current key.
+  pub fn encrypt(&self, object_id: &str, payload: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+    let state = self.state.read();
+    let key = state
+      .keys
+      .get(&state.current_key_id)
+      .ok_or(EncryptionError::UnknownKey(state.current_key_id))?;
+    let nonce: [u8; NONCE_LEN] = rand::thread_rng().gen();
+    let mut blob = Vec::with_capacity(BLOB_HEADER_LEN + payload.len() + TAG_LEN);
+    blob.push(BLOB_VERSION);
+    blob.extend_from_slice(&key.key_id.to_be_bytes());
+    blob.extend_from_slice(&nonce);
+    let ciphertext = cipher(key)
+      .encrypt(
+        Nonce::from_slice(&nonce),
+        Payload {
+          msg: payload,
+          aad: &associated_data(object_id, &blob),
+        },
+      )
+      .map_err(|_| EncryptionError::Encrypt)?;
+    blob.extend_from_slice(&ciphertext);
+    Ok(blob)
+  }
+
+  /// Decrypt the blob of the object with the key that encrypted it.
+  pub fn decrypt(&self, object_id: &str, blob: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+    let key_id = Self::blob_key_id(blob)?;
+    let state = self.state.read();
+    let key = state
+      .keys
+      .get(&key_id)
+      .ok_or(EncryptionError::UnknownKey(key_id))?;
+    let (header, ciphertext) = blob.split_at(BLOB_HEADER_LEN);
+    cipher(key)
+      .decrypt(
+        Nonce::from_slice(&header[5..]),
+        Payload {
+          msg: ciphertext,
+          aad: &associated_data(object_id, header),
+        },
+      )
+      .map_err(|_| EncryptionError::Decrypt)
+  }
+
+  /// Return the id of the key that encrypted the blob.
+  pub fn blob_key_id(blob: &[u8]) -> Result<u32, EncryptionError> {
+    if blob.len() < BLOB_HEADER_LEN {
+      return Err(EncryptionError::InvalidBlob(format!(
+        "the blob is too short: {}",
+        blob.len()
+      )));
+    }
+    if blob[0] != BLOB_VERSION {
+      return Err(EncryptionError::InvalidBlob(format!(
+        "unknown version: {}",
+        blob[0]
+      )));
+    }
+    let mut key_id = [0u8; 4];
+    key_id.copy_from_slice(&blob[1..5]);
+    Ok(u32::from_be_bytes(key_id))
+  }
+}
+
+fn cipher(key: &WorkspaceKey) -> Aes256Gcm {
+  Aes256Gcm::new(&key.key.into())
+}
+
+/// The header of the blob followed by the object id.
+fn associated_data(object_id: &str, header: &[u8]) -> Vec<u8> {
+  let mut aad = Vec::with_capacity(BLOB_HEADER_LEN + object_id.len());
+  aad.extend_from_slice(&header[..BLOB_HEADER_LEN]);
+  aad.extend_from_slice(object_id.as_bytes());
+  aad
+}
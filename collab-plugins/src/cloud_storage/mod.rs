@@ -1,7 +1,14 @@
+pub use compression::{
+  decode_payload, decode_payload_with_limit, encode_payload, PayloadCompression, PayloadMetrics,
+  DEFAULT_COMPRESSION_THRESHOLD, MAX_DECOMPRESSED_PAYLOAD_SIZE,
+};
+pub use encryption::*;
+pub use outbox::*;
 pub use remote_collab::{
-  RemoteCollabSnapshot, RemoteCollabState, RemoteCollabStorage, RemoteUpdateReceiver,
-  RemoteUpdateSender,
+  EncryptedDocState, EncryptedUpdate, RemoteCollabSnapshot, RemoteCollabState, RemoteCollabStorage,
+  RemoteUpdateReceiver, RemoteUpdateSender,
 };
+pub use sink::CollabSyncStatus;
 pub use yrs::merge_updates_v1;
 pub use yrs::updates::decoder::Decode;
 pub use yrs::Update as YrsUpdate;
@@ -9,7 +16,10 @@ pub use yrs::Update as YrsUpdate;
 pub mod postgres;
 
 mod channel;
+mod compression;
+mod encryption;
 mod error;
 mod msg;
+mod outbox;
 mod remote_collab;
 mod sink;
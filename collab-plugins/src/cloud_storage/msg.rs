@@ -65,7 +65,7 @@ pub(crate) struct PendingMessage<Msg> {
   msg: Msg,
   msg_id: MsgId,
   state: MessageState,
-  tx: Option<oneshot::Sender<MsgId>>,
+  tx: Option<oneshot::Sender<MessageState>>,
 }
 
 impl<Msg> PendingMessage<Msg>
@@ -91,12 +91,14 @@ where
 
   pub fn set_state(&mut self, new_state: MessageState) {
     self.state = new_state;
-    if self.state.is_done() && self.tx.is_some() {
-      self.tx.take().map(|tx| tx.send(self.msg_id));
+    if self.state.is_done() || self.state.is_failed() {
+      if let Some(tx) = self.tx.take() {
+        let _ = tx.send(self.state.clone());
+      }
     }
   }
 
-  pub fn set_ret(&mut self, tx: oneshot::Sender<MsgId>) {
+  pub fn set_ret(&mut self, tx: oneshot::Sender<MessageState>) {
     self.tx = Some(tx);
   }
 
@@ -157,6 +159,8 @@ pub(crate) enum MessageState {
   Processing,
   Done,
   Timeout,
+  /// The remote rejected the message or it couldn't be delivered. It will be sent again.
+  Failed,
 }
 
 impl MessageState {
@@ -166,4 +170,7 @@ impl MessageState {
   pub fn is_processing(&self) -> bool {
     matches!(self, MessageState::Processing)
   }
+  pub fn is_failed(&self) -> bool {
+    matches!(self, MessageState::Failed)
+  }
 }
@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Weak};
+
+use parking_lot::Mutex;
+use yrs::updates::decoder::Decode;
+use yrs::updates::encoder::Encode;
+use yrs::{ReadTxn, StateVector, Update};
+
+use crate::cloud_storage::msg::MsgId;
+use crate::local_storage::kv::doc::CollabKVAction;
+use crate::local_storage::kv::{KVTransactionDB, PersistenceError};
+use crate::CollabKVDB;
+
+/// The [CollabOutbox] keeps track of the updates of a collab that the remote has acknowledged.
+///
+/// Instead of keeping a copy of each pending update, it stores the state vector that the remote
+/// has acknowledged in the [CollabKVDB], next to the document itself. The pending updates are the
+/// difference between the local document, which is persisted by the disk plugin, and that state
+/// vector. So the changes that are made while offline survive a restart of the app, and the
+/// updates that were acknowledged before the restart are not sent again.
+///
+/// The state vector doesn't track the deletions, so the pending update always carries the whole
+/// delete set of the document. Applying the same deletions twice is a no-op for the remote.
+pub struct CollabOutbox {
+  uid: i64,
+  object_id: String,
+  collab_db: Weak<CollabKVDB>,
+  state: Mutex<OutboxState>,
+}
+
+#[derive(Default)]
+struct OutboxState {
+  /// The state vector that the remote has acknowledged. It's read from the disk on first use and
+  /// then kept in memory, so acknowledging an update doesn't read the disk.
+  acked_sv: Option<StateVector>,
+  /// The updates that are sent to the remote by message id. The message ids grow in the order the
+  /// messages are sent, so an update only counts as acknowledged once all the updates that were
+  /// sent before it are acknowledged too.
+  sent_updates: BTreeMap<MsgId, SentUpdate>,
+  /// The acknowledged state vector changed since it was saved.
+  is_dirty: bool,
+}
+
+struct SentUpdate {
+  state_vector: StateVector,
+  is_acked: bool,
+}
+
+impl CollabOutbox {
+  pub fn new(uid: i64, object_id: &str, collab_db: Weak<CollabKVDB>) -> Self {
+    Self {
+      uid,
+      object_id: object_id.to_string(),
+      collab_db,
+      state: Mutex::new(OutboxState::default()),
+    }
+  }
+
+  /// Return the state vector that the remote has acknowledged. It's empty if the remote hasn't
+  /// acknowledged any update yet.
+  pub fn acked_state_vector(&self) -> Result<StateVector, PersistenceError> {
+    let mut state = self.state.lock();
+    Ok(self.load_acked_sv(&mut state)?.clone())
+  }
+
+  /// Return the update that contains the changes of the document that the remote hasn't
+  /// acknowledged yet. Return None if there is nothing to send.
+  pub fn pending_update<T: ReadTxn>(&self, txn: &T) -> Result<Option<Vec<u8>>, PersistenceError> {
+    let acked_sv = self.acked_state_vector()?;
+    Self::pending_update_since(txn, &acked_sv)
+  }
+
+  /// Like [CollabOutbox::pending_update], with the state vector that was returned by
+  /// [CollabOutbox::acked_state_vector]. It doesn't read the disk, so it can be called while the
+  /// collab is locked.
+  pub fn pending_update_since<T: ReadTxn>(
+    txn: &T,
+    acked_sv: &StateVector,
+  ) -> Result<Option<Vec<u8>>, PersistenceError> {
+    let update = txn.encode_state_as_update_v1(acked_sv);
+    if Update::decode_v1(&update)?.is_empty() {
+      return Ok(None);
+    }
+    Ok(Some(update))
+  }
+
+  /// Remember the update that is sent in the message, so it's acknowledged by
+  /// [CollabOutbox::ack_msg]. A message that is sent again replaces the update it was sent with
+  /// before.
+  pub fn send_update(&self, msg_id: MsgId, update: &[u8]) -> Result<(), PersistenceError> {
+    let state_vector = Update::decode_v1(update)?.state_vector();
+    self.state.lock().sent_updates.insert(
+      msg_id,
+      SentUpdate {
+        state_vector,
+        is_acked: false,
+      },
+    );
+    Ok(())
+  }
+
+  /// Mark the update of the message as acknowledged by the remote. The acknowledged state vector
+  /// only moves forward over the updates whose earlier updates are all acknowledged, and it's
+  /// saved when it moves.
+  pub fn ack_msg(&self, msg_id: MsgId) -> Result<(), PersistenceError> {
+    let mut state = self.state.lock();
+    match state.sent_updates.get_mut(&msg_id) {
+      None => return Ok(()),
+      Some(sent_update) => sent_update.is_acked = true,
+    }
+    let mut acked_sv = self.load_acked_sv(&mut state)?.clone();
+    let mut is_changed = false;
+    while let Some(entry) = state.sent_updates.first_entry() {
+      if !entry.get().is_acked {
+        break;
+      }
+      acked_sv.merge(entry.remove().state_vector);
+      is_changed = true;
+    }
+    if is_changed {
+      state.acked_sv = Some(acked_sv);
+      state.is_dirty = true;
+      self.save_with_state(&mut state)?;
+    }
+    Ok(())
+  }
+
+  /// Mark the update that is received from the remote as acknowledged, so it's not sent back. Only
+  /// the part of the update that is integrated into the document counts: a change whose
+  /// dependencies are missing is not in the `doc_state_vector`, which is the state vector of the
+  /// document after the update is applied.
+  ///
+  /// The change is kept in memory and saved with the next acknowledged message or when the outbox
+  /// is dropped. Losing it only means that the update is sent back to the remote once.
+  pub fn ack_remote_update(
+    &self,
+    update_state_vector: &StateVector,
+    doc_state_vector: &StateVector,
+  ) {
+    let mut state = self.state.lock();
+    let acked_sv = match self.load_acked_sv(&mut state) {
+      Ok(acked_sv) => acked_sv,
+      Err(err) => {
+        tracing::error!(
+          "{}: failed to load the acked state vector: {:?}",
+          self.object_id,
+          err
+        );
+        return;
+      },
+    };
+    let mut is_changed = false;
+    for (client_id, clock) in update_state_vector.iter() {
+      let clock = (*clock).min(doc_state_vector.get(client_id));
+      if clock > acked_sv.get(client_id) {
+        acked_sv.set_max(*client_id, clock);
+        is_changed = true;
+      }
+    }
+    if is_changed {
+      state.is_dirty = true;
+    }
+  }
+
+  /// Save the acknowledged state vector if it changed since it was saved.
+  pub fn save(&self) -> Result<(), PersistenceError> {
+    self.save_with_state(&mut self.state.lock())
+  }
+
+  fn save_with_state(&self, state: &mut OutboxState) -> Result<(), PersistenceError> {
+    if !state.is_dirty {
+      return Ok(());
+    }
+    if let Some(acked_sv) = &state.acked_sv {
+      let encoded_sv = acked_sv.encode_v1();
+      self.collab_db()?.with_write_txn(|w_db_txn| {
+        w_db_txn.set_remote_state_vector(self.uid, &self.object_id, &encoded_sv)
+      })?;
+    }
+    state.is_dirty = false;
+    Ok(())
+  }
+
+  fn load_acked_sv<'a>(
+    &self,
+    state: &'a mut OutboxState,
+  ) -> Result<&'a mut StateVector, PersistenceError> {
+    if state.acked_sv.is_none() {
+      let encoded_sv = self
+        .collab_db()?
+        .read_txn()
+        .get_remote_state_vector(self.uid, &self.object_id)?;
+      let acked_sv = match encoded_sv {
+        None => StateVector::default(),
+        Some(encoded_sv) => StateVector::decode_v1(&encoded_sv)?,
+      };
+      state.acked_sv = Some(acked_sv);
+    }
+    Ok(state.acked_sv.get_or_insert_with(StateVector::default))
+  }
+
+  fn collab_db(&self) -> Result<Arc<CollabKVDB>, PersistenceError> {
+    self.collab_db.upgrade().ok_or_else(|| {
+      PersistenceError::Internal(anyhow::anyhow!(
+        "{}: the collab db is dropped",
+        self.object_id
+      ))
+    })
+  }
+}
+
+impl Drop for CollabOutbox {
+  fn drop(&mut self) {
+    if let Err(err) = self.save() {
+      tracing::warn!("{}: failed to save the outbox: {:?}", self.object_id, err);
+    }
+  }
+}
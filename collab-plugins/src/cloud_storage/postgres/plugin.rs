@@ -11,15 +11,22 @@ use collab::core::collab_state::SnapshotState;
 use collab::core::origin::CollabOrigin;
 use collab::preclude::{Collab, CollabPlugin};
 use collab_entity::CollabObject;
-use parking_lot::RwLock;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::watch;
 use tokio_retry::strategy::FibonacciBackoff;
 use tokio_retry::{Action, Retry};
 use tokio_stream::wrappers::WatchStream;
 use tokio_stream::StreamExt;
 use yrs::ReadTxn;
 
+use crate::cloud_storage::compression::{
+  PayloadCompression, PayloadMetrics, DEFAULT_COMPRESSION_THRESHOLD,
+};
+use crate::cloud_storage::encryption::WorkspaceKeyRing;
+use crate::cloud_storage::outbox::CollabOutbox;
 use crate::cloud_storage::remote_collab::{RemoteCollab, RemoteCollabStorage};
-use crate::cloud_storage::sink::{SinkConfig, SinkStrategy};
+use crate::cloud_storage::sink::{CollabSyncStatus, SinkConfig, SinkStrategy};
+use crate::connect_state::{CollabConnectReachability, CollabConnectState};
 use crate::local_storage::kv::doc::CollabKVAction;
 use crate::CollabKVDB;
 
@@ -30,7 +37,9 @@ pub struct SupabaseDBPlugin {
   local_collab_storage: Weak<CollabKVDB>,
   remote_collab: Arc<RemoteCollab>,
   remote_collab_storage: Arc<dyn RemoteCollabStorage>,
-  pending_updates: Arc<RwLock<Vec<Vec<u8>>>>,
+  /// The local updates that are not acked by the remote are tracked by the outbox, so they are
+  /// sent after the app restarts if the app quits while offline.
+  outbox: Arc<CollabOutbox>,
   is_first_sync_done: Arc<AtomicBool>,
 }
 
@@ -43,20 +52,89 @@ impl SupabaseDBPlugin {
     remote_collab_storage: Arc<dyn RemoteCollabStorage>,
     local_collab_storage: Weak<CollabKVDB>,
   ) -> Self {
-    let pending_updates = Arc::new(RwLock::new(Vec::new()));
-    let is_first_sync_done = Arc::new(AtomicBool::new(false));
+    let outbox = Self::outbox(uid, &object, &local_collab_storage);
+    let remote_collab = RemoteCollab::new_with_outbox(
+      object.clone(),
+      remote_collab_storage.clone(),
+      Self::sink_config(sync_per_secs),
+      local_collab.clone(),
+      outbox.clone(),
+    );
+    Self::new_with_remote_collab(
+      uid,
+      object,
+      local_collab,
+      remote_collab,
+      outbox,
+      remote_collab_storage,
+      local_collab_storage,
+    )
+  }
 
-    let config = SinkConfig::new()
-      .with_timeout(10)
-      .with_strategy(SinkStrategy::FixInterval(Duration::from_secs(
-        sync_per_secs,
-      )));
-    let remote_collab = Arc::new(RemoteCollab::new(
+  /// Create a plugin that encrypts the updates with the key ring of the workspace before they
+  /// leave the device. The remote storage only stores opaque blobs, so it must support the
+  /// end-to-end encryption. Otherwise, an error is returned.
+  pub fn new_with_encryption(
+    uid: i64,
+    object: CollabObject,
+    local_collab: Weak<MutexCollab>,
+    sync_per_secs: u64,
+    remote_collab_storage: Arc<dyn RemoteCollabStorage>,
+    local_collab_storage: Weak<CollabKVDB>,
+    key_ring: Arc<WorkspaceKeyRing>,
+  ) -> Result<Self, anyhow::Error> {
+    let outbox = Self::outbox(uid, &object, &local_collab_storage);
+    let remote_collab = RemoteCollab::new_encrypted(
       object.clone(),
       remote_collab_storage.clone(),
-      config,
+      Self::sink_config(sync_per_secs),
       local_collab.clone(),
-    ));
+      outbox.clone(),
+      key_ring,
+    )?;
+    Ok(Self::new_with_remote_collab(
+      uid,
+      object,
+      local_collab,
+      remote_collab,
+      outbox,
+      remote_collab_storage,
+      local_collab_storage,
+    ))
+  }
+
+  fn outbox(
+    uid: i64,
+    object: &CollabObject,
+    local_collab_storage: &Weak<CollabKVDB>,
+  ) -> Arc<CollabOutbox> {
+    Arc::new(CollabOutbox::new(
+      uid,
+      &object.object_id,
+      local_collab_storage.clone(),
+    ))
+  }
+
+  fn sink_config(sync_per_secs: u64) -> SinkConfig {
+    SinkConfig::new()
+      .with_timeout(10)
+      .with_strategy(SinkStrategy::FixInterval(Duration::from_secs(
+        sync_per_secs,
+      )))
+      .with_compression(PayloadCompression::Zstd, DEFAULT_COMPRESSION_THRESHOLD)
+  }
+
+  fn new_with_remote_collab(
+    uid: i64,
+    object: CollabObject,
+    local_collab: Weak<MutexCollab>,
+    remote_collab: RemoteCollab,
+    outbox: Arc<CollabOutbox>,
+    remote_collab_storage: Arc<dyn RemoteCollabStorage>,
+    local_collab_storage: Weak<CollabKVDB>,
+  ) -> Self {
+    let is_first_sync_done = Arc::new(AtomicBool::new(false));
+    let remote_collab = Arc::new(remote_collab);
 
     // Subscribe the sync state from the remote collab
     let remote_sync_state = remote_collab.subscribe_sync_state();
@@ -75,7 +153,7 @@ impl SupabaseDBPlugin {
       object,
       local_collab,
       remote_collab,
-      pending_updates,
+      outbox,
       is_first_sync_done,
       local_collab_storage,
       remote_collab_storage,
@@ -83,6 +161,63 @@ impl SupabaseDBPlugin {
   }
 }
 
+impl SupabaseDBPlugin {
+  /// Pause the syncing when the remote is not reachable. When it's reachable again, the syncing
+  /// resumes and an init sync is run to exchange the changes that were made in between.
+  pub fn with_reachability(self, reachability: &CollabConnectReachability) -> Self {
+    if !reachability.is_connected() {
+      self.remote_collab.pause();
+    }
+
+    let mut state_rx = reachability.subscribe();
+    let weak_remote_collab = Arc::downgrade(&self.remote_collab);
+    let weak_local_collab = self.local_collab.clone();
+    tokio::spawn(async move {
+      loop {
+        let state = match state_rx.recv().await {
+          Ok(state) => state,
+          Err(RecvError::Lagged(_)) => continue,
+          Err(RecvError::Closed) => break,
+        };
+        let remote_collab = match weak_remote_collab.upgrade() {
+          None => break,
+          Some(remote_collab) => remote_collab,
+        };
+        match state {
+          CollabConnectState::Disconnected => remote_collab.pause(),
+          CollabConnectState::Connected => {
+            remote_collab.resume();
+            if let Err(e) = remote_collab.sync(weak_local_collab.clone()).await {
+              tracing::error!("init sync after reconnecting failed: {:?}", e);
+            }
+          },
+        }
+      }
+    });
+    self
+  }
+
+  /// Subscribe the sync status of the object, for example to show whether the local changes are
+  /// synced to the remote.
+  pub fn subscribe_sync_status(&self) -> watch::Receiver<CollabSyncStatus> {
+    self.remote_collab.subscribe_sync_status()
+  }
+
+  pub fn sync_status(&self) -> CollabSyncStatus {
+    self.remote_collab.sync_status()
+  }
+
+  /// Return the sizes of the payloads that were sent to the remote, before and after they are
+  /// compressed.
+  pub fn payload_metrics(&self) -> PayloadMetrics {
+    self.remote_collab.payload_metrics()
+  }
+
+  pub fn subscribe_payload_metrics(&self) -> watch::Receiver<PayloadMetrics> {
+    self.remote_collab.subscribe_payload_metrics()
+  }
+}
+
 impl CollabPlugin for SupabaseDBPlugin {
   fn did_init(&self, _collab: &Collab, _object_id: &str, _last_sync_at: i64) {
     // TODO(nathan): retry action might take a long time even if the network is ready or enable of
@@ -95,7 +230,7 @@ impl CollabPlugin for SupabaseDBPlugin {
       local_collab: self.local_collab.clone(),
       local_collab_storage: self.local_collab_storage.clone(),
       remote_collab_storage: Arc::downgrade(&self.remote_collab_storage),
-      pending_updates: Arc::downgrade(&self.pending_updates),
+      outbox: Arc::downgrade(&self.outbox),
       is_first_sync_done: Arc::downgrade(&self.is_first_sync_done),
     };
 
@@ -105,10 +240,10 @@ impl CollabPlugin for SupabaseDBPlugin {
   }
 
   fn receive_local_update(&self, _origin: &CollabOrigin, _object_id: &str, update: &[u8]) {
+    // Before the first sync, the update is kept in the local document and is sent as part of the
+    // pending update of the outbox.
     if self.is_first_sync_done.load(Ordering::SeqCst) {
       self.remote_collab.push_update(update);
-    } else {
-      self.pending_updates.write().push(update.to_vec());
     }
   }
 
@@ -117,6 +252,9 @@ impl CollabPlugin for SupabaseDBPlugin {
   }
 }
 
+/// The interval to retry the first sync while the collab is locked.
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
 struct InitSyncAction {
   uid: i64,
   object: CollabObject,
@@ -124,7 +262,7 @@ struct InitSyncAction {
   local_collab: Weak<MutexCollab>,
   local_collab_storage: Weak<CollabKVDB>,
   remote_collab_storage: Weak<dyn RemoteCollabStorage>,
-  pending_updates: Weak<RwLock<Vec<Vec<u8>>>>,
+  outbox: Weak<CollabOutbox>,
   is_first_sync_done: Weak<AtomicBool>,
 }
 
@@ -135,23 +273,50 @@ impl Action for InitSyncAction {
 
   fn run(&mut self) -> Self::Future {
     let weak_remote_collab = self.remote_collab.clone();
-    let weak_pending_updates = self.pending_updates.clone();
+    let weak_local_collab = self.local_collab.clone();
+    let weak_outbox = self.outbox.clone();
     let weak_is_first_sync_done = self.is_first_sync_done.clone();
 
     Box::pin(async move {
-      if let (Some(remote_collab), Some(pending_updates), Some(is_first_sync_done)) = (
+      // The server can't send the changes that are made by the other devices in a way the local
+      // collab can read, so the encrypted update log is fetched and applied first.
+      if let Some(remote_collab) = weak_remote_collab.upgrade() {
+        if remote_collab.is_encrypted() {
+          remote_collab.sync(weak_local_collab.clone()).await?;
+        }
+      }
+
+      let (remote_collab, outbox, is_first_sync_done) = match (
         weak_remote_collab.upgrade(),
-        weak_pending_updates.upgrade(),
+        weak_outbox.upgrade(),
         weak_is_first_sync_done.upgrade(),
       ) {
-        for update in &*pending_updates.read() {
-          remote_collab.push_update(update);
+        (Some(remote_collab), Some(outbox), Some(is_first_sync_done)) => {
+          (remote_collab, outbox, is_first_sync_done)
+        },
+        _ => return Ok(()),
+      };
+      // The acknowledged state vector might be read from the disk, so it's loaded before the
+      // collab is locked.
+      let acked_sv = outbox.acked_state_vector()?;
+      loop {
+        let local_collab = match weak_local_collab.upgrade() {
+          None => return Ok(()),
+          Some(local_collab) => local_collab,
+        };
+        // Hold the lock of the collab until the first sync is marked as done. Otherwise, an
+        // update that is made in between would be neither in the pending update nor pushed.
+        // The collab might be locked for a long time, so the runtime is not blocked on it.
+        if let Some(collab) = local_collab.try_lock() {
+          let pending_update = CollabOutbox::pending_update_since(&collab.transact(), &acked_sv)?;
+          if let Some(pending_update) = pending_update {
+            remote_collab.push_update(&pending_update);
+          }
+          is_first_sync_done.store(true, Ordering::SeqCst);
+          return Ok(());
         }
-
-        is_first_sync_done.store(true, Ordering::SeqCst);
-        Ok(())
-      } else {
-        Ok(())
+        drop(local_collab);
+        tokio::time::sleep(LOCK_RETRY_INTERVAL).await;
       }
     })
   }
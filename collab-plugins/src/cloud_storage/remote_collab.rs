@@ -1,6 +1,6 @@
 use std::cmp::Ordering;
 use std::fmt::{Display, Formatter};
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, AtomicUsize};
 use std::sync::{Arc, Weak};
 use std::time::{Duration, SystemTime};
 
@@ -24,11 +24,20 @@ use yrs::updates::decoder::Decode;
 use yrs::{merge_updates_v1, ReadTxn, Transact, Update};
 
 use crate::cloud_storage::channel::TokioUnboundedSink;
+use crate::cloud_storage::compression::{
+  decode_payload, encode_payload, is_compressed, PayloadCompression, PayloadMetrics,
+};
+use crate::cloud_storage::encryption::WorkspaceKeyRing;
 use crate::cloud_storage::msg::{CollabSinkMessage, MsgId};
+use crate::cloud_storage::outbox::CollabOutbox;
 use crate::cloud_storage::sink::{
-  CollabSink, CollabSinkRunner, MsgIdCounter, SinkConfig, SinkState,
+  CollabSink, CollabSinkRunner, CollabSyncStatus, MsgIdCounter, SinkConfig, SinkState,
 };
 
+/// The number of the encrypted updates after which the update log is compacted, either by the init
+/// sync or once that many updates were appended to the log since.
+const COMPACT_ENCRYPTED_UPDATES_THRESHOLD: usize = 100;
+
 /// The [RemoteCollab] is used to sync the local collab to the remote.
 pub struct RemoteCollab {
   object: CollabObject,
@@ -40,6 +49,9 @@ pub struct RemoteCollab {
   sync_state: Arc<watch::Sender<SyncState>>,
   #[allow(dead_code)]
   is_init_sync_finish: Arc<AtomicBool>,
+  outbox: Option<Arc<CollabOutbox>>,
+  codec: Arc<PayloadCodec>,
+  payload_metrics: Arc<watch::Sender<PayloadMetrics>>,
 }
 
 impl Drop for RemoteCollab {
@@ -52,11 +64,63 @@ impl RemoteCollab {
   /// Create a new remote collab.
   /// `timeout` is the time to wait for the server to ack the message.
   /// If the server does not ack the message in time, the message will be sent again.
+  /// The payloads are compressed with the compression of the `config` if the storage supports it.
   pub fn new(
     object: CollabObject,
     storage: Arc<dyn RemoteCollabStorage>,
     config: SinkConfig,
     local_collab: Weak<MutexCollab>,
+  ) -> Self {
+    Self::new_with_opts(object, storage, config, local_collab, None, None)
+  }
+
+  /// Create a new remote collab like [RemoteCollab::new] that records in the `outbox` the updates
+  /// that are acked by the server and the updates that are received from the server.
+  pub fn new_with_outbox(
+    object: CollabObject,
+    storage: Arc<dyn RemoteCollabStorage>,
+    config: SinkConfig,
+    local_collab: Weak<MutexCollab>,
+    outbox: Arc<CollabOutbox>,
+  ) -> Self {
+    Self::new_with_opts(object, storage, config, local_collab, Some(outbox), None)
+  }
+
+  /// Create a new remote collab like [RemoteCollab::new_with_outbox] that encrypts the payloads
+  /// with the key ring before they are sent, so the storage only sees opaque blobs. Return an
+  /// error if the storage doesn't support the end-to-end encryption, because it couldn't keep
+  /// the blobs.
+  pub fn new_encrypted(
+    object: CollabObject,
+    storage: Arc<dyn RemoteCollabStorage>,
+    config: SinkConfig,
+    local_collab: Weak<MutexCollab>,
+    outbox: Arc<CollabOutbox>,
+    key_ring: Arc<WorkspaceKeyRing>,
+  ) -> Result<Self, Error> {
+    if !storage.supports_encryption() {
+      return Err(anyhow!(
+        "{} the remote storage doesn't support end-to-end encryption",
+        object.object_id
+      ));
+    }
+    Ok(Self::new_with_opts(
+      object,
+      storage,
+      config,
+      local_collab,
+      Some(outbox),
+      Some(key_ring),
+    ))
+  }
+
+  fn new_with_opts(
+    object: CollabObject,
+    storage: Arc<dyn RemoteCollabStorage>,
+    config: SinkConfig,
+    local_collab: Weak<MutexCollab>,
+    outbox: Option<Arc<CollabOutbox>>,
+    key_ring: Option<Arc<WorkspaceKeyRing>>,
   ) -> Self {
     let is_init_sync_finish = Arc::new(AtomicBool::new(false));
     let sync_state = Arc::new(watch::channel(SyncState::InitSyncBegin).0);
@@ -70,6 +134,8 @@ impl RemoteCollab {
     let weak_storage = Arc::downgrade(&storage);
     let (notifier, notifier_rx) = watch::channel(false);
     let (sync_state_tx, sink_state_rx) = watch::channel(SinkState::Init);
+    let codec = Arc::new(PayloadCodec::new(&config, key_ring, storage.as_ref()));
+    let payload_metrics = Arc::new(watch::channel(PayloadMetrics::default()).0);
     let collab_sink = Arc::new(CollabSink::new(
       object.uid,
       TokioUnboundedSink(sink),
@@ -82,19 +148,43 @@ impl RemoteCollab {
     // spawns an asynchronous task to continuously listen to the updates stream
     // and process them as they come in.
     let cloned_is_init_sync_finish = is_init_sync_finish.clone();
-    if let Some(mut collab_stream) = storage.subscribe_remote_updates(&object) {
+    let cloned_outbox = outbox.clone();
+    let cloned_codec = codec.clone();
+    let cloned_storage = weak_storage.clone();
+    let cloned_object = object.clone();
+    let object_id = object.object_id.clone();
+    let remote_updates = match codec.download_compression {
+      PayloadCompression::None => storage.subscribe_remote_updates(&object),
+      compression => storage.subscribe_compressed_remote_updates(&object, compression),
+    };
+    if let Some(mut collab_stream) = remote_updates {
       spawn(async move {
         while let Some(update) = collab_stream.recv().await {
           if !cloned_is_init_sync_finish.load(std::sync::atomic::Ordering::SeqCst) {
             continue;
           }
+          let update = match cloned_codec.decode(&object_id, update) {
+            Ok(update) => update,
+            Err(e) => {
+              tracing::error!("🔴Failed to decode remote payload: {:?}", e);
+              continue;
+            },
+          };
+          if cloned_codec.count_encrypted_update() {
+            spawn_encrypted_log_compaction(&cloned_storage, &cloned_codec, &cloned_object);
+          }
           if let Some(local_collab) = local_collab.upgrade() {
             if let Some(collab) = local_collab.try_lock_for(Duration::from_secs(1)) {
               if let Ok(mut txn) = collab.try_transaction_mut() {
                 match Update::decode_v1(&update) {
-                  Ok(update) => {
-                    if let Err(e) = txn.try_apply_update(update) {
+                  Ok(decoded_update) => {
+                    let update_state_vector = decoded_update.state_vector();
+                    if let Err(e) = txn.try_apply_update(decoded_update) {
                       tracing::error!("apply remote update failed: {:?}", e);
+                    } else if let Some(outbox) = &cloned_outbox {
+                      // The server already has the update, so it doesn't need to be sent back.
+                      // The outbox only records it in memory.
+                      outbox.ack_remote_update(&update_state_vector, &txn.state_vector());
                     }
                   },
                   Err(e) => tracing::error!("🔴Failed to decode remote update: {:?}", e),
@@ -131,6 +221,9 @@ impl RemoteCollab {
     // Spawn a task to receive updates from the [CollabSink] and send updates to
     // the remote storage.
     let cloned_is_init_sync_finish = is_init_sync_finish.clone();
+    let sink_outbox = outbox.clone();
+    let sink_payload_metrics = payload_metrics.clone();
+    let sink_codec = codec.clone();
     spawn(async move {
       while let Some(message) = stream.recv().await {
         if let Some(storage) = weak_storage.upgrade() {
@@ -144,11 +237,43 @@ impl RemoteCollab {
           trace!("send message: {}", message);
           match message.split() {
             Ok((object, msg_id, payload)) => {
+              // Remember what the payload contains before it's sent, so it can be marked as
+              // acked in the outbox.
+              if let Some(outbox) = &sink_outbox {
+                if let Err(e) = outbox.send_update(msg_id, &payload) {
+                  tracing::error!("🔴Failed to record the sent update: {:?}", e);
+                }
+              }
+              let raw_len = payload.len();
+              let (payload, is_compressed) = match sink_codec.encode(&object.object_id, payload) {
+                Ok(encoded) => encoded,
+                Err(e) => {
+                  tracing::error!("🔴Failed to encode {}:{}: {:?}", object, msg_id, e);
+                  if let Some(collab_sink) = weak_collab_sink.upgrade() {
+                    collab_sink.fail_msg(&object.object_id, msg_id, e.to_string());
+                  }
+                  continue;
+                },
+              };
+              trace!(
+                "{}:{} payload len: {} -> {}",
+                object,
+                msg_id,
+                raw_len,
+                payload.len()
+              );
+              sink_payload_metrics.send_modify(|metrics| {
+                metrics.record(is_init_msg, is_compressed, raw_len, payload.len());
+              });
               // If the message is init message, it will flush all the updates to the remote.
               if is_init_msg {
                 tracing::trace!("send init sync {}:{}", object, msg_id);
                 match storage.send_init_sync(&object, msg_id, payload).await {
                   Ok(_) => {
+                    ack_outbox(&sink_outbox, msg_id);
+                    if sink_codec.count_encrypted_update() {
+                      spawn_encrypted_log_compaction(&weak_storage, &sink_codec, &object);
+                    }
                     if let Some(collab_sink) = weak_collab_sink.upgrade() {
                       collab_sink.ack_msg(&object.object_id, msg_id).await;
                       cloned_is_init_sync_finish.store(true, std::sync::atomic::Ordering::SeqCst);
@@ -160,7 +285,10 @@ impl RemoteCollab {
                       object.object_id,
                       msg_id,
                       e
-                    )
+                    );
+                    if let Some(collab_sink) = weak_collab_sink.upgrade() {
+                      collab_sink.fail_msg(&object.object_id, msg_id, e.to_string());
+                    }
                   },
                 }
               } else {
@@ -168,16 +296,25 @@ impl RemoteCollab {
                 match storage.send_update(&object, msg_id, payload).await {
                   Ok(_) => {
                     tracing::debug!("ack update {}:{}", object, msg_id);
+                    ack_outbox(&sink_outbox, msg_id);
+                    if sink_codec.count_encrypted_update() {
+                      spawn_encrypted_log_compaction(&weak_storage, &sink_codec, &object);
+                    }
                     if let Some(collab_sink) = weak_collab_sink.upgrade() {
                       collab_sink.ack_msg(&object.object_id, msg_id).await;
                     }
                   },
-                  Err(e) => tracing::error!(
-                    "send {}:{} update failed: {:?}",
-                    object.object_id,
-                    msg_id,
-                    e
-                  ),
+                  Err(e) => {
+                    tracing::error!(
+                      "send {}:{} update failed: {:?}",
+                      object.object_id,
+                      msg_id,
+                      e
+                    );
+                    if let Some(collab_sink) = weak_collab_sink.upgrade() {
+                      collab_sink.fail_msg(&object.object_id, msg_id, e.to_string());
+                    }
+                  },
                 }
               }
             },
@@ -199,6 +336,9 @@ impl RemoteCollab {
       sink: collab_sink,
       sync_state,
       is_init_sync_finish,
+      outbox,
+      codec,
+      payload_metrics,
     }
   }
 
@@ -206,6 +346,53 @@ impl RemoteCollab {
     self.sync_state.subscribe()
   }
 
+  pub fn subscribe_sync_status(&self) -> watch::Receiver<CollabSyncStatus> {
+    self.sink.subscribe_sync_status()
+  }
+
+  pub fn sync_status(&self) -> CollabSyncStatus {
+    self.sink.sync_status()
+  }
+
+  /// Return the compression that is negotiated with the remote storage.
+  pub fn compression(&self) -> PayloadCompression {
+    self.codec.compression
+  }
+
+  /// Return the compression of the doc state and the remote updates that are received from the
+  /// remote storage.
+  pub fn download_compression(&self) -> PayloadCompression {
+    self.codec.download_compression
+  }
+
+  /// Return true if the payloads are end-to-end encrypted.
+  pub fn is_encrypted(&self) -> bool {
+    self.codec.encryption.is_some()
+  }
+
+  /// Return the sizes of the payloads that were sent to the remote, before and after they are
+  /// compressed.
+  pub fn payload_metrics(&self) -> PayloadMetrics {
+    self.payload_metrics.borrow().clone()
+  }
+
+  pub fn subscribe_payload_metrics(&self) -> watch::Receiver<PayloadMetrics> {
+    self.payload_metrics.subscribe()
+  }
+
+  /// Stop sending the updates to the remote, for example when the network is not reachable. The
+  /// updates are still queued.
+  pub fn pause(&self) {
+    tracing::trace!("{} pause syncing", self.object);
+    self.sink.pause();
+  }
+
+  /// Continue to send the queued updates to the remote.
+  pub fn resume(&self) {
+    tracing::trace!("{} resume syncing", self.object);
+    self.sink.resume();
+  }
+
   /// Return the update of the remote collab.
   /// If the remote collab contains any updates, it will return None.
   /// Otherwise, it will merge the updates into one and return the merged update.
@@ -216,7 +403,24 @@ impl RemoteCollab {
     // get all the updates from remote.
     // TODO(nathan): create a edge function to calculate the diff between the local and remote.
     tracing::trace!("Try init sync:{}", self.object);
-    let collab_doc_state = self.storage.get_doc_state(&self.object).await?;
+    let collab_doc_state = match &self.codec.encryption {
+      None => match self.codec.download_compression {
+        PayloadCompression::None => self.storage.get_doc_state(&self.object).await?,
+        compression => {
+          let doc_state = self
+            .storage
+            .get_compressed_doc_state(&self.object, compression)
+            .await?;
+          let doc_state = self.codec.decode(&self.object.object_id, doc_state)?;
+          if doc_state.is_empty() {
+            DataSource::Disk
+          } else {
+            DataSource::DocStateV1(doc_state)
+          }
+        },
+      },
+      Some(_) => get_encrypted_doc_state(self.storage.as_ref(), &self.codec, &self.object).await?,
+    };
     if !collab_doc_state.is_empty() {
       {
         let remote_collab = self.collab.lock();
@@ -232,6 +436,11 @@ impl RemoteCollab {
             } else {
               tracing::error!("🔴decode update failed");
             }
+            if let Some(outbox) = &self.outbox {
+              // The remote has every change of its document
+              let remote_sv = txn.state_vector();
+              outbox.ack_remote_update(&remote_sv, &remote_sv);
+            }
             remote_update = doc_state;
           },
           DataSource::DocStateV2(doc_state) => {
@@ -334,6 +543,167 @@ impl RemoteCollab {
   }
 }
 
+/// Fetch the encrypted update log of the object and decrypt it. The server can't merge the
+/// encrypted updates, so the log is compacted into an encrypted snapshot here when it's long or
+/// when it contains blobs that are encrypted with a previous key.
+async fn get_encrypted_doc_state(
+  storage: &dyn RemoteCollabStorage,
+  codec: &PayloadCodec,
+  object: &CollabObject,
+) -> Result<DataSource, Error> {
+  let key_ring = codec
+    .encryption
+    .as_ref()
+    .ok_or_else(|| anyhow!("{} is not encrypted", object.object_id))?;
+  let encrypted_doc_state = storage.get_encrypted_doc_state(object).await?;
+  let current_key_id = key_ring.current_key_id();
+  let mut is_compaction_needed =
+    encrypted_doc_state.updates.len() >= COMPACT_ENCRYPTED_UPDATES_THRESHOLD;
+  let mut updates = vec![];
+  for blob in encrypted_doc_state.blobs() {
+    if WorkspaceKeyRing::blob_key_id(blob)? != current_key_id {
+      is_compaction_needed = true;
+    }
+    updates.push(codec.decode(&object.object_id, blob.to_vec())?);
+  }
+  codec.num_of_encrypted_updates.store(
+    encrypted_doc_state.updates.len(),
+    std::sync::atomic::Ordering::SeqCst,
+  );
+  if updates.is_empty() {
+    return Ok(DataSource::Disk);
+  }
+
+  let updates = updates
+    .iter()
+    .map(|update| update.as_ref())
+    .collect::<Vec<&[u8]>>();
+  let doc_state = merge_updates_v1(&updates)?;
+  if is_compaction_needed {
+    trace!(
+      "{}: compact {} encrypted updates",
+      object,
+      encrypted_doc_state.updates.len()
+    );
+    let (snapshot, _) = codec.encode(&object.object_id, doc_state.clone())?;
+    match storage
+      .compact_encrypted_updates(object, snapshot, encrypted_doc_state.last_seq)
+      .await
+    {
+      Ok(_) => codec
+        .num_of_encrypted_updates
+        .store(0, std::sync::atomic::Ordering::SeqCst),
+      Err(e) => tracing::error!("{}: compact encrypted updates failed: {:?}", object, e),
+    }
+  }
+  Ok(DataSource::DocStateV1(doc_state))
+}
+
+/// Compact the encrypted update log of the object in the background. The log is fetched again,
+/// so it's left alone if another device compacted it in the meantime.
+fn spawn_encrypted_log_compaction(
+  storage: &Weak<dyn RemoteCollabStorage>,
+  codec: &Arc<PayloadCodec>,
+  object: &CollabObject,
+) {
+  let storage = match storage.upgrade() {
+    None => return,
+    Some(storage) => storage,
+  };
+  let codec = codec.clone();
+  let object = object.clone();
+  spawn(async move {
+    if let Err(e) = get_encrypted_doc_state(storage.as_ref(), &codec, &object).await {
+      tracing::error!("{}: compact encrypted updates failed: {:?}", object, e);
+    }
+  });
+}
+
+/// Encodes the payloads that are sent to the remote and decodes the ones that are received.
+struct PayloadCodec {
+  compression: PayloadCompression,
+  /// The compression of the payloads that are received from the remote storage.
+  download_compression: PayloadCompression,
+  compression_threshold: usize,
+  encryption: Option<Arc<WorkspaceKeyRing>>,
+  /// The number of the encrypted updates that were appended to the update log since it was
+  /// fetched, by this device or by the others.
+  num_of_encrypted_updates: AtomicUsize,
+}
+
+impl PayloadCodec {
+  fn new(
+    config: &SinkConfig,
+    encryption: Option<Arc<WorkspaceKeyRing>>,
+    storage: &dyn RemoteCollabStorage,
+  ) -> Self {
+    let (compression, download_compression) = match encryption {
+      None => (
+        PayloadCompression::negotiate(config.compression, &storage.supported_compressions()),
+        PayloadCompression::negotiate(config.compression, &storage.download_compressions()),
+      ),
+      // The encrypted payloads can't be compressed by the transport, so they are compressed
+      // before they are encrypted, whatever the transport supports.
+      Some(_) => (PayloadCompression::Zstd, PayloadCompression::None),
+    };
+    Self {
+      compression,
+      download_compression,
+      compression_threshold: config.compression_threshold,
+      encryption,
+      num_of_encrypted_updates: AtomicUsize::new(0),
+    }
+  }
+
+  /// Count an encrypted update that is appended to the update log. Return true when the log has
+  /// grown enough to be compacted, then the count starts over.
+  fn count_encrypted_update(&self) -> bool {
+    if self.encryption.is_none() {
+      return false;
+    }
+    let num_of_updates = self
+      .num_of_encrypted_updates
+      .fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+      + 1;
+    if num_of_updates < COMPACT_ENCRYPTED_UPDATES_THRESHOLD {
+      return false;
+    }
+    self
+      .num_of_encrypted_updates
+      .store(0, std::sync::atomic::Ordering::SeqCst);
+    true
+  }
+
+  /// Return the encoded payload and whether it's compressed.
+  fn encode(&self, object_id: &str, payload: Vec<u8>) -> Result<(Vec<u8>, bool), Error> {
+    let payload = encode_payload(self.compression, self.compression_threshold, payload)?;
+    let is_compressed = is_compressed(self.compression, &payload);
+    match &self.encryption {
+      None => Ok((payload, is_compressed)),
+      Some(key_ring) => Ok((key_ring.encrypt(object_id, &payload)?, is_compressed)),
+    }
+  }
+
+  /// Decode the payload that is received from the remote. The plain payloads are only encoded if
+  /// a download compression is negotiated, the encrypted ones are always compressed before they
+  /// are encrypted.
+  fn decode(&self, object_id: &str, data: Vec<u8>) -> Result<Vec<u8>, Error> {
+    match &self.encryption {
+      None if self.download_compression == PayloadCompression::None => Ok(data),
+      None => Ok(decode_payload(&data)?),
+      Some(key_ring) => Ok(decode_payload(&key_ring.decrypt(object_id, &data)?)?),
+    }
+  }
+}
+
+fn ack_outbox(outbox: &Option<Arc<CollabOutbox>>, msg_id: MsgId) {
+  if let Some(outbox) = outbox {
+    if let Err(e) = outbox.ack_msg(msg_id) {
+      tracing::error!("🔴Failed to ack the outbox: {:?}", e);
+    }
+  }
+}
+
 #[derive(Debug, Clone)]
 pub struct RemoteCollabState {
   /// The current edit count of the remote collab.
@@ -352,6 +722,35 @@ pub struct RemoteCollabSnapshot {
   pub created_at: i64,
 }
 
+/// The encrypted state of a collab that is stored by a [RemoteCollabStorage] that supports the
+/// end-to-end encryption. The server can't read the blobs, so it keeps the log of the encrypted
+/// updates instead of merging them, until a client compacts the log into an encrypted snapshot.
+#[derive(Debug, Clone, Default)]
+pub struct EncryptedDocState {
+  pub snapshot: Option<Vec<u8>>,
+  /// The encrypted updates after the snapshot, ordered by their seq.
+  pub updates: Vec<EncryptedUpdate>,
+  /// The seq of the last update that is in the state, including the compacted ones.
+  pub last_seq: i64,
+}
+
+impl EncryptedDocState {
+  /// Return the snapshot followed by the updates.
+  pub fn blobs(&self) -> impl Iterator<Item = &[u8]> {
+    self
+      .snapshot
+      .iter()
+      .map(|snapshot| snapshot.as_slice())
+      .chain(self.updates.iter().map(|update| update.blob.as_slice()))
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct EncryptedUpdate {
+  pub seq: i64,
+  pub blob: Vec<u8>,
+}
+
 /// The [RemoteCollabStorage] is used to store the updates of the remote collab. The [RemoteCollab]
 /// is the remote collab that maps to the local collab.
 /// Any storage that implements this trait can be used as the remote collab storage.
@@ -401,6 +800,76 @@ pub trait RemoteCollabStorage: Send + Sync + 'static {
 
   /// Subscribe the remote updates.
   fn subscribe_remote_updates(&self, object: &CollabObject) -> Option<RemoteUpdateReceiver>;
+
+  /// Return the compressions that the remote storage can decode. If the preferred compression of
+  /// the [RemoteCollab] is in the list, the payloads that are passed to `send_update` and
+  /// `send_init_sync` are encoded with it and must be decoded with
+  /// [decode_payload](crate::cloud_storage::decode_payload).
+  fn supported_compressions(&self) -> Vec<PayloadCompression> {
+    vec![]
+  }
+
+  /// Return the compressions that the remote storage can encode the doc state and the remote
+  /// updates with. If the preferred compression of the [RemoteCollab] is in the list, they are
+  /// received through `get_compressed_doc_state` and `subscribe_compressed_remote_updates`
+  /// instead, and decoded with [decode_payload](crate::cloud_storage::decode_payload).
+  fn download_compressions(&self) -> Vec<PayloadCompression> {
+    vec![]
+  }
+
+  /// Get all the updates of the remote collab merged into one update, which is encoded with the
+  /// compression. The decoded update is empty if the remote collab doesn't exist.
+  async fn get_compressed_doc_state(
+    &self,
+    object: &CollabObject,
+    _compression: PayloadCompression,
+  ) -> Result<Vec<u8>, anyhow::Error> {
+    Err(anyhow!(
+      "{} the storage doesn't compress the doc state",
+      object.object_id
+    ))
+  }
+
+  /// Subscribe the remote updates, each one encoded with the compression.
+  fn subscribe_compressed_remote_updates(
+    &self,
+    _object: &CollabObject,
+    _compression: PayloadCompression,
+  ) -> Option<RemoteUpdateReceiver> {
+    None
+  }
+
+  /// Return true if the storage keeps the encrypted update logs of the end-to-end encrypted
+  /// collabs. The payloads of such collabs are opaque blobs, which are returned by
+  /// `get_encrypted_doc_state` and `subscribe_remote_updates` as they were sent.
+  fn supports_encryption(&self) -> bool {
+    false
+  }
+
+  /// Get the encrypted update log of the remote collab.
+  async fn get_encrypted_doc_state(
+    &self,
+    object: &CollabObject,
+  ) -> Result<EncryptedDocState, anyhow::Error> {
+    Err(anyhow!(
+      "{} the remote storage doesn't support end-to-end encryption",
+      object.object_id
+    ))
+  }
+
+  /// Replace the snapshot and the updates up to `last_seq` with the encrypted snapshot, which is
+  /// the merge of all of them.
+  async fn compact_encrypted_updates(
+    &self,
+    object: &CollabObject,
+    _snapshot: Vec<u8>,
+    _last_seq: i64,
+  ) -> Result<(), anyhow::Error> {
+    Err(anyhow!(
+      "{} the remote storage doesn't support end-to-end encryption",
+      object.object_id
+    ))
+  }
 }
 
 pub type RemoteUpdateSender = tokio::sync::mpsc::UnboundedSender<Vec<u8>>;
@@ -452,6 +921,52 @@ where
   fn subscribe_remote_updates(&self, object: &CollabObject) -> Option<RemoteUpdateReceiver> {
     (**self).subscribe_remote_updates(object)
   }
+
+  fn supported_compressions(&self) -> Vec<PayloadCompression> {
+    (**self).supported_compressions()
+  }
+
+  fn download_compressions(&self) -> Vec<PayloadCompression> {
+    (**self).download_compressions()
+  }
+
+  async fn get_compressed_doc_state(
+    &self,
+    object: &CollabObject,
+    compression: PayloadCompression,
+  ) -> Result<Vec<u8>, Error> {
+    (**self).get_compressed_doc_state(object, compression).await
+  }
+
+  fn subscribe_compressed_remote_updates(
+    &self,
+    object: &CollabObject,
+    compression: PayloadCompression,
+  ) -> Option<RemoteUpdateReceiver> {
+    (**self).subscribe_compressed_remote_updates(object, compression)
+  }
+
+  fn supports_encryption(&self) -> bool {
+    (**self).supports_encryption()
+  }
+
+  async fn get_encrypted_doc_state(
+    &self,
+    object: &CollabObject,
+  ) -> Result<EncryptedDocState, Error> {
+    (**self).get_encrypted_doc_state(object).await
+  }
+
+  async fn compact_encrypted_updates(
+    &self,
+    object: &CollabObject,
+    snapshot: Vec<u8>,
+    last_seq: i64,
+  ) -> Result<(), Error> {
+    (**self)
+      .compact_encrypted_updates(object, snapshot, last_seq)
+      .await
+  }
 }
 
 #[derive(Clone, Debug)]
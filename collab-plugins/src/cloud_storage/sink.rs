@@ -1,18 +1,23 @@
 use std::marker::PhantomData;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Weak};
 use std::time::Duration;
 
 use futures_util::SinkExt;
+use rand::Rng;
 use tokio::spawn;
 use tokio::sync::{mpsc, oneshot, watch, Mutex};
 use tokio::time::{interval, Instant, Interval};
 use tracing::{debug, trace};
 
+use crate::cloud_storage::compression::{PayloadCompression, DEFAULT_COMPRESSION_THRESHOLD};
 use crate::cloud_storage::error::SyncError;
 use crate::cloud_storage::msg::{CollabSinkMessage, MessageState, PendingMsgQueue};
 
 pub const DEFAULT_SYNC_TIMEOUT: u64 = 2;
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(500);
+pub const DEFAULT_MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
 #[derive(Clone, Debug)]
 pub enum SinkState {
   Init,
@@ -54,6 +59,13 @@ pub struct CollabSink<Sink, Msg> {
   /// is [SinkStrategy::FixInterval].
   instant: Mutex<Instant>,
   state_notifier: Arc<watch::Sender<SinkState>>,
+
+  /// A paused sink keeps queueing the messages, but doesn't send them until it's resumed. It's
+  /// paused when the remote is not reachable.
+  is_paused: AtomicBool,
+  /// Delays the next attempt after the messages failed to be sent.
+  backoff: parking_lot::Mutex<RetryBackoff>,
+  sync_status: Arc<watch::Sender<CollabSyncStatus>>,
 }
 
 impl<Sink, Msg> Drop for CollabSink<Sink, Msg> {
@@ -104,9 +116,46 @@ where
       config,
       instant,
       interval_runner_stop_tx,
+      is_paused: AtomicBool::new(false),
+      backoff: parking_lot::Mutex::new(RetryBackoff::default()),
+      sync_status: Arc::new(watch::channel(CollabSyncStatus::default()).0),
     }
   }
 
+  /// Subscribe the [CollabSyncStatus] of the sink. The status is updated when a message is queued,
+  /// acked or failed, and when the sink is paused or resumed.
+  pub fn subscribe_sync_status(&self) -> watch::Receiver<CollabSyncStatus> {
+    self.sync_status.subscribe()
+  }
+
+  pub fn sync_status(&self) -> CollabSyncStatus {
+    self.sync_status.borrow().clone()
+  }
+
+  /// Stop sending the messages. The messages that are queued while the sink is paused are sent
+  /// after [CollabSink::resume] is called.
+  pub fn pause(&self) {
+    self.is_paused.store(true, Ordering::SeqCst);
+    self
+      .sync_status
+      .send_modify(|status| status.is_paused = true);
+  }
+
+  /// Resume sending the messages. The backoff is reset, so the pending messages are sent right
+  /// away.
+  pub fn resume(&self) {
+    self.is_paused.store(false, Ordering::SeqCst);
+    self.backoff.lock().reset();
+    self
+      .sync_status
+      .send_modify(|status| status.is_paused = false);
+    self.notify();
+  }
+
+  pub fn is_paused(&self) -> bool {
+    self.is_paused.load(Ordering::SeqCst)
+  }
+
   /// Put the message into the queue and notify the sink to process the next message.
   /// After the [Msg] was pushed into the [PendingMsgQueue]. The queue will pop the next msg base on
   /// its priority. And the message priority is determined by the [Msg] that implement the [Ord] and
@@ -121,11 +170,13 @@ where
       drop(pending_msgs);
     }
 
+    self.update_pending_bytes();
     self.notify();
   }
 
   pub fn remove_all_pending_msgs(&self) {
     self.pending_msg_queue.lock().clear();
+    self.update_pending_bytes();
   }
 
   /// Notify the sink to process the next message and mark the current message as done.
@@ -150,7 +201,24 @@ where
     }
   }
 
+  /// Mark the message as failed. The message will be sent again after the backoff delay.
+  pub fn fail_msg(&self, object_id: &str, msg_id: MsgId, error: String) {
+    trace!("{} message:{} failed: {}", object_id, msg_id, error);
+    self
+      .sync_status
+      .send_modify(|status| status.last_error = Some(error));
+    if let Some(mut pending_msg) = self.pending_msg_queue.lock().peek_mut() {
+      if pending_msg.msg_id() == msg_id {
+        pending_msg.set_state(MessageState::Failed);
+      }
+    }
+  }
+
   async fn process_next_msg(&self) -> Result<(), SyncError> {
+    if self.is_paused() || self.backoff.lock().is_waiting() {
+      return Ok(());
+    }
+
     // Check if the next message can be deferred. If not, try to send the message immediately. The
     // default value is true.
     let deferrable = self
@@ -251,7 +319,19 @@ where
     // Wait for the message to be acked.
     // If the message is not acked within the timeout, resend the message.
     match tokio::time::timeout(self.config.timeout, rx).await {
-      Ok(_) => {
+      Ok(Ok(MessageState::Failed)) => {
+        // The error was recorded by [CollabSink::fail_msg]
+        self.retry_later();
+      },
+      Ok(state) => {
+        if state.is_ok() {
+          self.backoff.lock().reset();
+          self.sync_status.send_modify(|status| {
+            status.last_ack_at = Some(chrono::Utc::now().timestamp());
+            status.last_error = None;
+            status.num_of_failures = 0;
+          });
+        }
         if let Some(mut pending_msgs) = self.pending_msg_queue.try_lock() {
           let pending_msg = pending_msgs.pop();
           trace!(
@@ -267,18 +347,65 @@ where
             }
           }
         }
+        self.update_pending_bytes();
         self.notify()
       },
       Err(_) => {
         if let Some(mut pending_msg) = self.pending_msg_queue.lock().peek_mut() {
           pending_msg.set_state(MessageState::Timeout);
         }
-        self.notify();
+        let timeout = self.config.timeout;
+        self.sync_status.send_modify(|status| {
+          status.last_error = Some(format!("the message is not acked in {:?}", timeout))
+        });
+        self.retry_later();
       },
     }
     None
   }
 
+  /// Wait for the backoff delay before sending the next message. The delay grows exponentially
+  /// with the number of consecutive failures.
+  fn retry_later(&self) {
+    let (delay, num_of_failures) = {
+      let mut backoff = self.backoff.lock();
+      let delay = backoff.fail(&self.config);
+      (delay, backoff.num_of_failures)
+    };
+    debug!(
+      "[Client {}]: retry after {:?}, failures: {}",
+      self.uid, delay, num_of_failures
+    );
+    self
+      .sync_status
+      .send_modify(|status| status.num_of_failures = num_of_failures);
+
+    let weak_notifier = Arc::downgrade(&self.notifier);
+    spawn(async move {
+      tokio::time::sleep(delay).await;
+      if let Some(notifier) = weak_notifier.upgrade() {
+        let _ = notifier.send(false);
+      }
+    });
+  }
+
+  fn update_pending_bytes(&self) {
+    let pending_bytes = self
+      .pending_msg_queue
+      .lock()
+      .iter()
+      .filter(|msg| !msg.state().is_done())
+      .map(|msg| msg.get_msg().length())
+      .sum();
+    self.sync_status.send_if_modified(|status| {
+      if status.pending_bytes == pending_bytes {
+        return false;
+      }
+      status.pending_bytes = pending_bytes;
+      true
+    });
+  }
+
   /// Notify the sink to process the next message.
   pub(crate) fn notify(&self) {
     let _ = self.notifier.send(false);
@@ -330,10 +457,19 @@ pub struct SinkConfig {
   /// `timeout` is the time to wait for the remote to ack the message. If the remote
   /// does not ack the message in time, the message will be sent again.
   pub timeout: Duration,
+  /// `retry_delay` is the delay before sending a message again after the first failure. The
+  /// delay is doubled after each consecutive failure, up to `max_retry_delay`.
+  pub retry_delay: Duration,
+  pub max_retry_delay: Duration,
   /// `max_zip_size` is the maximum size of the messages to be merged.
   pub max_merge_size: usize,
   /// `strategy` is the strategy to send the messages.
   pub strategy: SinkStrategy,
+  /// `compression` is the preferred compression of the payloads. It's only used if the remote
+  /// storage supports it.
+  pub compression: PayloadCompression,
+  /// `compression_threshold` is the minimum size of the payloads to be compressed.
+  pub compression_threshold: usize,
 }
 
 impl SinkConfig {
@@ -358,6 +494,18 @@ impl SinkConfig {
     self
   }
 
+  pub fn with_retry_delay(mut self, retry_delay: Duration, max_retry_delay: Duration) -> Self {
+    self.retry_delay = retry_delay;
+    self.max_retry_delay = max_retry_delay.max(retry_delay);
+    self
+  }
+
+  pub fn with_compression(mut self, compression: PayloadCompression, threshold: usize) -> Self {
+    self.compression = compression;
+    self.compression_threshold = threshold;
+    self
+  }
+
   pub fn with_strategy(mut self, strategy: SinkStrategy) -> Self {
     if let SinkStrategy::FixInterval(duration) = strategy {
       if self.timeout < duration {
@@ -373,8 +521,12 @@ impl Default for SinkConfig {
   fn default() -> Self {
     Self {
       timeout: Duration::from_secs(DEFAULT_SYNC_TIMEOUT),
+      retry_delay: DEFAULT_RETRY_DELAY,
+      max_retry_delay: DEFAULT_MAX_RETRY_DELAY,
       max_merge_size: 4096,
       strategy: SinkStrategy::Asap,
+      compression: PayloadCompression::None,
+      compression_threshold: DEFAULT_COMPRESSION_THRESHOLD,
     }
   }
 }
@@ -395,6 +547,66 @@ impl SinkStrategy {
   }
 }
 
+/// The sync status of the object that is synced by a [CollabSink]. It's used to show whether the
+/// local changes are synced to the remote.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CollabSyncStatus {
+  /// The size of the messages that are not acked by the remote yet.
+  pub pending_bytes: usize,
+  /// The timestamp, in seconds, of the last message that was acked by the remote.
+  pub last_ack_at: Option<i64>,
+  /// The error of the last message that failed. It's cleared when a message is acked.
+  pub last_error: Option<String>,
+  /// The number of consecutive failures.
+  pub num_of_failures: u32,
+  /// True if the sink is paused because the remote is not reachable.
+  pub is_paused: bool,
+}
+
+impl CollabSyncStatus {
+  /// Return true if all the local changes are acked by the remote.
+  pub fn is_synced(&self) -> bool {
+    self.pending_bytes == 0
+  }
+}
+
+/// Exponential backoff with jitter. The jitter avoids that all the sinks retry at the same time
+/// after the network is back.
+#[derive(Default)]
+struct RetryBackoff {
+  num_of_failures: u32,
+  retry_at: Option<Instant>,
+}
+
+impl RetryBackoff {
+  /// Record a failure and return the delay before the next attempt.
+  fn fail(&mut self, config: &SinkConfig) -> Duration {
+    self.num_of_failures = self.num_of_failures.saturating_add(1);
+    let exp = (self.num_of_failures - 1).min(16);
+    let delay = config
+      .retry_delay
+      .saturating_mul(1 << exp)
+      .min(config.max_retry_delay);
+    // Pick a delay in [delay / 2, delay]
+    let jitter = rand::thread_rng().gen_range(0.5..=1.0);
+    let delay = delay.mul_f64(jitter);
+    self.retry_at = Some(Instant::now() + delay);
+    delay
+  }
+
+  fn is_waiting(&self) -> bool {
+    self
+      .retry_at
+      .map(|retry_at| Instant::now() < retry_at)
+      .unwrap_or(false)
+  }
+
+  fn reset(&mut self) {
+    self.num_of_failures = 0;
+    self.retry_at = None;
+  }
+}
+
 pub type MsgId = u64;
 
 pub trait MsgIdCounter: Send + Sync + 'static {
@@ -1,7 +1,7 @@
 use parking_lot::Mutex;
 use tokio::sync::broadcast;
 
-#[derive(Clone, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum CollabConnectState {
   Connected,
   Disconnected,
@@ -36,6 +36,14 @@ impl CollabConnectReachability {
     }
   }
 
+  pub fn state(&self) -> CollabConnectState {
+    self.state.lock().clone()
+  }
+
+  pub fn is_connected(&self) -> bool {
+    *self.state.lock() == CollabConnectState::Connected
+  }
+
   pub fn subscribe(&self) -> broadcast::Receiver<CollabConnectState> {
     self.state_sender.subscribe()
   }
@@ -19,6 +19,8 @@ macro_rules! if_wasm {
 #[cfg(all(feature = "postgres_plugin", not(target_arch = "wasm32")))]
 pub mod cloud_storage;
 pub mod connect_state;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod sync_plugin;
 
 if_native! {
     pub type CollabKVDB = local_storage::rocksdb::kv_impl::KVTransactionDBRocksdbImpl;
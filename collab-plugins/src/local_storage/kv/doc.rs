@@ -56,6 +56,10 @@ where
     doc_state: Vec<u8>,
   ) -> Result<(), PersistenceError> {
     let doc_id = get_or_create_did(uid, self, object_id)?;
+    let remote_sv_key = make_remote_state_vector_key(doc_id);
+    let remote_sv = self
+      .get(remote_sv_key.as_ref())?
+      .map(|sv| sv.as_ref().to_vec());
 
     // Remove the updates
     let start = make_doc_start_key(doc_id);
@@ -69,6 +73,10 @@ where
     // Insert new doc state and state vector
     self.insert(doc_state_key, doc_state)?;
     self.insert(sv_key, state_vector)?;
+    // Flushing doesn't change what the remote has acknowledged
+    if let Some(remote_sv) = remote_sv {
+      self.insert(remote_sv_key, remote_sv)?;
+    }
     Ok(())
   }
 
@@ -204,6 +212,11 @@ where
     sv: &[u8],
   ) -> Result<(), PersistenceError> {
     let doc_id = get_or_create_did(uid, self, object_id)?;
+    let remote_sv_key = make_remote_state_vector_key(doc_id);
+    let remote_sv = self
+      .get(remote_sv_key.as_ref())?
+      .map(|sv| sv.as_ref().to_vec());
+
     let start = make_doc_start_key(doc_id);
     let end = make_doc_end_key(doc_id);
     self.remove_range(start.as_ref(), end.as_ref())?;
@@ -214,9 +227,52 @@ where
     // Insert new doc state and state vector
     self.insert(doc_state_key, doc_state)?;
     self.insert(sv_key, sv)?;
+    if let Some(remote_sv) = remote_sv {
+      self.insert(remote_sv_key, remote_sv)?;
+    }
     Ok(())
   }
 
+  /// Return the encoded state vector of the document that the remote has acknowledged, or None if
+  /// the remote hasn't acknowledged any update of the document yet.
+  fn get_remote_state_vector<K: AsRef<[u8]> + ?Sized + Debug>(
+    &self,
+    uid: i64,
+    object_id: &K,
+  ) -> Result<Option<Vec<u8>>, PersistenceError> {
+    match get_doc_id(uid, self, object_id) {
+      None => Ok(None),
+      Some(doc_id) => {
+        let remote_sv_key = make_remote_state_vector_key(doc_id);
+        Ok(
+          self
+            .get(remote_sv_key.as_ref())?
+            .map(|sv| sv.as_ref().to_vec()),
+        )
+      },
+    }
+  }
+
+  /// Save the encoded state vector of the document that the remote has acknowledged. The
+  /// document must exist.
+  fn set_remote_state_vector<K: AsRef<[u8]> + ?Sized + Debug>(
+    &self,
+    uid: i64,
+    object_id: &K,
+    state_vector: &[u8],
+  ) -> Result<(), PersistenceError> {
+    match get_doc_id(uid, self, object_id) {
+      None => Err(PersistenceError::RecordNotFound(format!(
+        "doc with given object id: {:?} is not found",
+        object_id
+      ))),
+      Some(doc_id) => {
+        self.insert(make_remote_state_vector_key(doc_id), state_vector)?;
+        Ok(())
+      },
+    }
+  }
+
   fn get_all_updates<K: AsRef<[u8]> + ?Sized + Debug>(
     &self,
     uid: i64,
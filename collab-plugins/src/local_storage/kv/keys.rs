@@ -17,10 +17,15 @@ use smallvec::{smallvec, SmallVec};
 //     DOC_SPACE_OBJECT_KEY     doc_id      TERMINATOR_HI_WATERMARK (state end)
 //     DOC_SPACE_OBJECT_KEY     doc_id      DOC_STATE_VEC (state vector)
 //     DOC_SPACE_OBJECT_KEY     doc_id      DOC_UPDATE clock TERMINATOR (update)
+//     DOC_SPACE_OBJECT_KEY     doc_id      REMOTE_DOC_STATE_VEC (acknowledged state vector)
 //
 // SNAPSHOT_SPACE
 //     SNAPSHOT_SPACE_OBJECT        object_id       TERMINATOR
 //     SNAPSHOT_SPACE_OBJECT_KEY    snapshot_id     SNAPSHOT_UPDATE(snapshot)
+//
+// ENCRYPTED_LOG_SPACE
+//     uid     object_id     TERMINATOR     ENCRYPTED_LOG_SNAPSHOT (snapshot)
+//     uid     object_id     TERMINATOR     ENCRYPTED_LOG_UPDATE seq (update)
 
 /// Prefix byte used for all of the yrs object entries.
 pub const DOC_SPACE: u8 = 1;
@@ -40,11 +45,14 @@ pub const DOC_STATE: u8 = 0;
 /// Tag byte within [DOC_SPACE_OBJECT_KEY] used to identify object's state vector entry.
 pub const DOC_STATE_VEC: u8 = 1;
 
-pub const REMOTE_DOC_STATE_VEC: u8 = 2;
-
 /// Tag byte within [DOC_SPACE_OBJECT_KEY] used to identify object's update entries.
 pub const DOC_UPDATE: u8 = 2;
 
+/// Tag byte within [DOC_SPACE_OBJECT_KEY] used to identify the state vector that the remote has
+/// acknowledged. It must not share the tag of [DOC_UPDATE], otherwise the key would be read as
+/// an update key.
+pub const REMOTE_DOC_STATE_VEC: u8 = 3;
+
 /// Prefix byte used for snapshot id -> [SnapshotID] mapping index key space.
 pub const SNAPSHOT_SPACE: u8 = 2;
 
@@ -57,6 +65,16 @@ pub const SNAPSHOT_UPDATE: u8 = 1;
 pub const COLLAB_SPACE: u8 = 3;
 pub const COLLAB_SPACE_OBJECT: u8 = 0;
 
+/// Prefix byte used for the encrypted update logs that a sync server keeps for the end-to-end
+/// encrypted documents. The server can't read the blobs, so they are not stored as yrs updates.
+pub const ENCRYPTED_LOG_SPACE: u8 = 4;
+
+/// Tag byte within [ENCRYPTED_LOG_SPACE] used to identify the encrypted snapshot of an object.
+pub const ENCRYPTED_LOG_SNAPSHOT: u8 = 0;
+
+/// Tag byte within [ENCRYPTED_LOG_SPACE] used to identify the encrypted updates of an object.
+pub const ENCRYPTED_LOG_UPDATE: u8 = 1;
+
 pub type DocID = u64;
 pub const DOC_ID_LEN: usize = 8;
 pub const DOC_STATE_KEY_LEN: usize = DOC_ID_LEN + 4;
@@ -115,7 +133,7 @@ pub fn make_state_vector_key(doc_id: DocID) -> Key<DOC_STATE_KEY_LEN> {
   Key(v)
 }
 
-// [1,1,  0,0,0,0,0,0,0,0,  2]
+// [1,1,  0,0,0,0,0,0,0,0,  3]
 pub fn make_remote_state_vector_key(doc_id: DocID) -> Key<DOC_STATE_KEY_LEN> {
   let mut v: SmallVec<[u8; DOC_STATE_KEY_LEN]> = smallvec![DOC_SPACE, DOC_SPACE_OBJECT_KEY];
   v.write_all(&doc_id.to_be_bytes()).unwrap();
@@ -0,0 +1,816 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt::Display;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+use collab::core::awareness::{AwarenessUpdate, Event};
+use collab::core::collab::MutexCollab;
+use collab::core::collab_plugin::CollabPluginType;
+use collab::core::collab_state::SyncState;
+use collab::core::origin::CollabOrigin;
+use collab::preclude::{Collab, CollabPlugin};
+use collab_entity::CollabType;
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use parking_lot::Mutex;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::sync::watch;
+use tracing::{error, trace, warn};
+use yrs::updates::decoder::Decode;
+use yrs::updates::encoder::Encode;
+use yrs::{ReadTxn, StateVector};
+
+use crate::sync_plugin::plugin::{handle_message, LOCK_RETRY_INTERVAL, MAX_PENDING_MESSAGES};
+use crate::sync_plugin::protocol::{Message, SyncMessage, WorkspaceMessage};
+
+/// The priority of the init sync of an object. When a workspace is opened, the objects with a
+/// higher priority are synced first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SyncPriority {
+  /// The objects that are needed to show the workspace, like the folder.
+  High,
+  /// The objects that are opened by the user, like a document or a database.
+  Normal,
+  /// The objects that are loaded in bulk, like the rows of a database.
+  Low,
+}
+
+impl From<&CollabType> for SyncPriority {
+  fn from(collab_type: &CollabType) -> Self {
+    match collab_type {
+      CollabType::Folder | CollabType::WorkspaceDatabase | CollabType::UserAwareness => {
+        SyncPriority::High
+      },
+      CollabType::Document | CollabType::Database | CollabType::Unknown => SyncPriority::Normal,
+      CollabType::DatabaseRow => SyncPriority::Low,
+    }
+  }
+}
+
+pub struct WorkspaceSyncConfig {
+  /// The maximum number of objects whose init sync is in progress at the same time. The other
+  /// objects wait in the queue.
+  pub max_concurrent_init_syncs: usize,
+  /// The time to wait for the reply of an init sync before it's sent again.
+  pub init_sync_timeout: Duration,
+  /// The number of times an init sync is sent again before the coordinator gives up on it. The
+  /// object is synced again when the coordinator reconnects.
+  pub max_init_sync_retries: usize,
+}
+
+impl Default for WorkspaceSyncConfig {
+  fn default() -> Self {
+    Self {
+      max_concurrent_init_syncs: 8,
+      init_sync_timeout: Duration::from_secs(30),
+      max_init_sync_retries: 3,
+    }
+  }
+}
+
+impl WorkspaceSyncConfig {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn with_max_concurrent_init_syncs(mut self, max_concurrent_init_syncs: usize) -> Self {
+    self.max_concurrent_init_syncs = max_concurrent_init_syncs.max(1);
+    self
+  }
+
+  pub fn with_init_sync_timeout(mut self, init_sync_timeout: Duration) -> Self {
+    self.init_sync_timeout = init_sync_timeout.max(Duration::from_millis(1));
+    self
+  }
+
+  pub fn with_max_init_sync_retries(mut self, max_init_sync_retries: usize) -> Self {
+    self.max_init_sync_retries = max_init_sync_retries;
+    self
+  }
+}
+
+/// The aggregate progress of the init syncs of a workspace.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkspaceSyncProgress {
+  /// The number of objects that are registered to the coordinator.
+  pub num_of_objects: usize,
+  /// The number of objects whose init sync is done.
+  pub num_of_synced: usize,
+  /// The number of objects whose init sync is in progress.
+  pub num_of_syncing: usize,
+  /// The number of objects whose init sync got no reply after all the retries.
+  pub num_of_failed: usize,
+}
+
+impl WorkspaceSyncProgress {
+  /// Return true if the init sync of every registered object is done.
+  pub fn is_complete(&self) -> bool {
+    self.num_of_synced == self.num_of_objects
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum InitSyncStatus {
+  /// The collab is not initialized yet.
+  Pending,
+  /// The init sync is waiting for its turn.
+  Queued,
+  /// The init sync is sent and waits for the reply of the server. The `sync_id` tells the replies
+  /// to this init sync apart from the replies to the earlier ones of the object.
+  Syncing {
+    sync_id: u64,
+    started_at: Instant,
+    num_of_retries: usize,
+  },
+  Synced,
+  /// The server didn't reply to the init sync after all the retries. A late reply still finishes
+  /// the init sync.
+  Failed {
+    sync_id: u64,
+  },
+}
+
+struct SyncObject {
+  registration_id: u64,
+  collab: Weak<MutexCollab>,
+  priority: SyncPriority,
+  /// The state vector that is sent with the init sync. It's taken when the collab is initialized,
+  /// so the queue never needs to lock the collab.
+  state_vector: StateVector,
+  status: InitSyncStatus,
+}
+
+#[derive(Default)]
+struct CoordinatorState {
+  objects: HashMap<String, SyncObject>,
+  /// The objects that wait for their init sync, ordered by priority and then by the order they
+  /// were queued.
+  queue: BTreeMap<(SyncPriority, u64), String>,
+  next_id: u64,
+  num_of_syncing: usize,
+  num_of_synced: usize,
+  num_of_failed: usize,
+}
+
+impl CoordinatorState {
+  fn next_id(&mut self) -> u64 {
+    self.next_id += 1;
+    self.next_id
+  }
+
+  fn progress(&self) -> WorkspaceSyncProgress {
+    WorkspaceSyncProgress {
+      num_of_objects: self.objects.len(),
+      num_of_synced: self.num_of_synced,
+      num_of_syncing: self.num_of_syncing,
+      num_of_failed: self.num_of_failed,
+    }
+  }
+
+  /// Update the counters when the object leaves the given status.
+  fn leave_status(&mut self, status: &InitSyncStatus) {
+    match status {
+      InitSyncStatus::Syncing { .. } => self.num_of_syncing -= 1,
+      InitSyncStatus::Synced => self.num_of_synced -= 1,
+      InitSyncStatus::Failed { .. } => self.num_of_failed -= 1,
+      InitSyncStatus::Pending | InitSyncStatus::Queued => {},
+    }
+  }
+
+  /// Put the init sync of the object in the queue. The object must be initialized, and an object
+  /// that is already queued keeps its place.
+  fn enqueue(&mut self, object_id: &str) {
+    let seq = self.next_id();
+    let object = match self.objects.get_mut(object_id) {
+      Some(object) if object.status != InitSyncStatus::Queued => object,
+      _ => return,
+    };
+    let old_status = std::mem::replace(&mut object.status, InitSyncStatus::Queued);
+    let priority = object.priority;
+    self.leave_status(&old_status);
+    self.queue.insert((priority, seq), object_id.to_string());
+  }
+
+  /// Return the id of the init sync of the object that waits for its reply.
+  fn init_sync_id(&self, object_id: &str) -> Option<u64> {
+    match self.objects.get(object_id)?.status {
+      InitSyncStatus::Syncing { sync_id, .. } | InitSyncStatus::Failed { sync_id } => Some(sync_id),
+      _ => None,
+    }
+  }
+}
+
+struct Connection {
+  id: u64,
+  msg_tx: UnboundedSender<WorkspaceMessage>,
+}
+
+/// Syncs all the objects of a workspace over one connection that speaks the y-sync protocol.
+/// Each frame of the connection is a [WorkspaceMessage], which is a y-sync message prefixed with
+/// the id of the object.
+///
+/// Compared to a [YSyncPlugin](crate::sync_plugin::YSyncPlugin) for each object, the coordinator
+/// only runs two tasks for the whole workspace, one to send and one to receive. The init syncs are
+/// queued by [SyncPriority] and only a limited number of them run at the same time, so opening a
+/// workspace with many database rows doesn't flood the connection. The local updates are sent
+/// right away, whether the init sync of the object is done or not.
+///
+/// An init sync that gets no reply in time is sent again, and the coordinator gives up on it after
+/// a few retries so it doesn't keep its slot. When the connection is lost, the owner attaches a new
+/// one with [WorkspaceSyncCoordinator::reconnect], which syncs all the objects again.
+///
+/// The coordinator doesn't go through the `RemoteCollab` and its `CollabSink` of the cloud storage.
+/// They sync a single object through a `RemoteCollabStorage`, with their own tasks and a queue of
+/// messages that the server acknowledges by id, and they are only built with the `postgres_plugin`
+/// feature. The y-sync protocol has no acknowledgements, and the point of the coordinator is to
+/// share one connection and its tasks between all the objects of the workspace.
+pub struct WorkspaceSyncCoordinator {
+  workspace_id: String,
+  config: WorkspaceSyncConfig,
+  connection: Mutex<Connection>,
+  state: Mutex<CoordinatorState>,
+  progress: watch::Sender<WorkspaceSyncProgress>,
+}
+
+impl WorkspaceSyncCoordinator {
+  pub fn new<Si, St, E>(
+    workspace_id: &str,
+    sink: Si,
+    stream: St,
+    config: WorkspaceSyncConfig,
+  ) -> Arc<Self>
+  where
+    Si: Sink<Vec<u8>, Error = E> + Send + Unpin + 'static,
+    St: Stream<Item = Result<Vec<u8>, E>> + Send + Unpin + 'static,
+    E: Display + Send + 'static,
+  {
+    let check_interval = config.init_sync_timeout / 2;
+    let coordinator = Arc::new(Self {
+      workspace_id: workspace_id.to_string(),
+      config,
+      connection: Mutex::new(Connection {
+        id: 0,
+        msg_tx: unbounded_channel().0,
+      }),
+      state: Mutex::new(CoordinatorState::default()),
+      progress: watch::channel(WorkspaceSyncProgress::default()).0,
+    });
+    coordinator.connect(sink, stream);
+    tokio::spawn(check_init_syncs(
+      Arc::downgrade(&coordinator),
+      check_interval,
+    ));
+    coordinator
+  }
+
+  pub fn workspace_id(&self) -> &str {
+    &self.workspace_id
+  }
+
+  /// Replace the connection of the workspace, e.g. after the previous one was closed. The previous
+  /// connection is closed, and the init syncs of all the initialized objects are queued again, so
+  /// the changes that were missed while disconnected are synced.
+  pub fn reconnect<Si, St, E>(self: &Arc<Self>, sink: Si, stream: St)
+  where
+    Si: Sink<Vec<u8>, Error = E> + Send + Unpin + 'static,
+    St: Stream<Item = Result<Vec<u8>, E>> + Send + Unpin + 'static,
+    E: Display + Send + 'static,
+  {
+    self.connect(sink, stream);
+    {
+      let mut state = self.state.lock();
+      let object_ids = state
+        .objects
+        .iter()
+        .filter(|(_, object)| object.status != InitSyncStatus::Pending)
+        .map(|(object_id, _)| object_id.clone())
+        .collect::<Vec<_>>();
+      for object_id in object_ids {
+        state.enqueue(&object_id);
+      }
+    }
+    self.schedule();
+  }
+
+  /// Register the collab and return the plugin that syncs it through the coordinator. The plugin
+  /// must be added to the collab before the collab is initialized. The init sync of the collab is
+  /// queued when it's initialized.
+  ///
+  /// Registering an object again replaces the previous registration.
+  pub fn register(
+    self: &Arc<Self>,
+    object_id: &str,
+    priority: SyncPriority,
+    collab: Weak<MutexCollab>,
+  ) -> WorkspaceSyncPlugin {
+    let mut state = self.state.lock();
+    let registration_id = state.next_id();
+    let object = SyncObject {
+      registration_id,
+      collab,
+      priority,
+      state_vector: StateVector::default(),
+      status: InitSyncStatus::Pending,
+    };
+    if let Some(old_object) = state.objects.insert(object_id.to_string(), object) {
+      state.leave_status(&old_object.status);
+    }
+    self.progress.send_replace(state.progress());
+    drop(state);
+    // A replaced object might have been syncing
+    self.schedule();
+
+    WorkspaceSyncPlugin {
+      object_id: object_id.to_string(),
+      registration_id,
+      coordinator: Arc::downgrade(self),
+    }
+  }
+
+  pub fn progress(&self) -> WorkspaceSyncProgress {
+    self.progress.borrow().clone()
+  }
+
+  pub fn subscribe_progress(&self) -> watch::Receiver<WorkspaceSyncProgress> {
+    self.progress.subscribe()
+  }
+
+  fn connect<Si, St, E>(self: &Arc<Self>, sink: Si, stream: St)
+  where
+    Si: Sink<Vec<u8>, Error = E> + Send + Unpin + 'static,
+    St: Stream<Item = Result<Vec<u8>, E>> + Send + Unpin + 'static,
+    E: Display + Send + 'static,
+  {
+    let (msg_tx, msg_rx) = unbounded_channel();
+    let connection_id = {
+      let mut connection = self.connection.lock();
+      connection.id += 1;
+      // Dropping the sender of the previous connection ends its sending task, which closes its sink
+      connection.msg_tx = msg_tx;
+      connection.id
+    };
+    tokio::spawn(send_messages(self.workspace_id.clone(), msg_rx, sink));
+    tokio::spawn(receive_messages(
+      Arc::downgrade(self),
+      connection_id,
+      stream,
+    ));
+  }
+
+  fn is_current_connection(&self, connection_id: u64) -> bool {
+    self.connection.lock().id == connection_id
+  }
+
+  fn send(&self, object_id: &str, msg: Message) {
+    if self
+      .connection
+      .lock()
+      .msg_tx
+      .send(WorkspaceMessage::new(object_id, msg))
+      .is_err()
+    {
+      warn!(
+        "{}: the sync connection of the workspace is closed",
+        self.workspace_id
+      );
+    }
+  }
+
+  fn queue_init_sync(&self, object_id: &str, registration_id: u64, state_vector: StateVector) {
+    {
+      let mut state = self.state.lock();
+      match state.objects.get_mut(object_id) {
+        Some(object) if object.registration_id == registration_id => {
+          object.state_vector = state_vector;
+        },
+        _ => return,
+      }
+      state.enqueue(object_id);
+    }
+    self.schedule();
+  }
+
+  fn unregister(&self, object_id: &str, registration_id: u64) {
+    {
+      let mut state = self.state.lock();
+      match state.objects.get(object_id) {
+        Some(object) if object.registration_id == registration_id => {},
+        _ => return,
+      }
+      if let Some(object) = state.objects.remove(object_id) {
+        state.leave_status(&object.status);
+      }
+      self.progress.send_replace(state.progress());
+    }
+    self.schedule();
+  }
+
+  /// Start the queued init syncs until the concurrency limit is reached.
+  fn schedule(&self) {
+    let mut init_syncs = vec![];
+    {
+      let mut state = self.state.lock();
+      while state.num_of_syncing < self.config.max_concurrent_init_syncs {
+        let object_id = match state.queue.pop_first() {
+          None => break,
+          Some((_, object_id)) => object_id,
+        };
+        let sync_id = state.next_id();
+        let state_vector = match state.objects.get_mut(&object_id) {
+          // The object might have been registered again after it was queued
+          Some(object) if object.status == InitSyncStatus::Queued => {
+            object.status = InitSyncStatus::Syncing {
+              sync_id,
+              started_at: Instant::now(),
+              num_of_retries: 0,
+            };
+            object.state_vector.clone()
+          },
+          _ => continue,
+        };
+        state.num_of_syncing += 1;
+        init_syncs.push((object_id, state_vector));
+      }
+      self.progress.send_replace(state.progress());
+    }
+
+    for (object_id, state_vector) in init_syncs {
+      trace!(
+        "{}: start the init sync of {}",
+        self.workspace_id,
+        object_id
+      );
+      self.send(
+        &object_id,
+        Message::Sync(SyncMessage::SyncStep1(state_vector)),
+      );
+    }
+  }
+
+  fn finish_init_sync(&self, object_id: &str, sync_id: u64) {
+    {
+      let mut state = self.state.lock();
+      if state.init_sync_id(object_id) != Some(sync_id) {
+        return;
+      }
+      if let Some(object) = state.objects.get_mut(object_id) {
+        let old_status = std::mem::replace(&mut object.status, InitSyncStatus::Synced);
+        state.leave_status(&old_status);
+        state.num_of_synced += 1;
+      }
+    }
+    self.schedule();
+  }
+
+  /// Queue the init sync of the object again if the reply of the given init sync was discarded
+  /// before it was applied.
+  fn retry_init_sync(&self, object_id: &str, sync_id: u64) {
+    {
+      let mut state = self.state.lock();
+      match state.objects.get(object_id).map(|object| &object.status) {
+        Some(InitSyncStatus::Syncing { sync_id: id, .. }) if *id == sync_id => {},
+        _ => return,
+      }
+      state.enqueue(object_id);
+    }
+    self.schedule();
+  }
+
+  /// Send the init syncs whose reply is late again, or give up on them after the retries so they
+  /// don't keep their slots.
+  fn check_init_syncs(&self) {
+    let mut retries = vec![];
+    let mut num_of_failed = 0;
+    {
+      let mut state = self.state.lock();
+      for (object_id, object) in state.objects.iter_mut() {
+        if let InitSyncStatus::Syncing {
+          sync_id,
+          started_at,
+          num_of_retries,
+        } = &mut object.status
+        {
+          if started_at.elapsed() < self.config.init_sync_timeout {
+            continue;
+          }
+          if *num_of_retries < self.config.max_init_sync_retries {
+            *num_of_retries += 1;
+            *started_at = Instant::now();
+            retries.push((object_id.clone(), object.state_vector.clone()));
+          } else {
+            warn!(
+              "{}: the init sync of {} got no reply, give up",
+              self.workspace_id, object_id
+            );
+            object.status = InitSyncStatus::Failed { sync_id: *sync_id };
+            num_of_failed += 1;
+          }
+        }
+      }
+      state.num_of_syncing -= num_of_failed;
+      state.num_of_failed += num_of_failed;
+    }
+
+    for (object_id, state_vector) in retries {
+      trace!(
+        "{}: retry the init sync of {}",
+        self.workspace_id,
+        object_id
+      );
+      self.send(
+        &object_id,
+        Message::Sync(SyncMessage::SyncStep1(state_vector)),
+      );
+    }
+    if num_of_failed > 0 {
+      self.schedule();
+    }
+  }
+
+  /// Queue the received message behind the pending messages of its object, then apply them if the
+  /// collab is not locked.
+  fn receive_message(
+    self: &Arc<Self>,
+    workspace_msg: WorkspaceMessage,
+    pending_msgs: &mut HashMap<String, ObjectMessages>,
+  ) {
+    let WorkspaceMessage { object_id, msg } = workspace_msg;
+    if pending_msgs.get(&object_id).map_or(false, |object_msgs| {
+      object_msgs.msgs.len() >= MAX_PENDING_MESSAGES
+    }) {
+      warn!(
+        "{}: {} is locked for too long, drop the received sync messages",
+        self.workspace_id, object_id
+      );
+      // The dropped messages are synced again by the next init sync of the object
+      pending_msgs.remove(&object_id);
+      let is_initialized = {
+        let mut state = self.state.lock();
+        let is_initialized = state
+          .objects
+          .get(&object_id)
+          .map_or(false, |object| object.status != InitSyncStatus::Pending);
+        if is_initialized {
+          state.enqueue(&object_id);
+        }
+        is_initialized
+      };
+      if is_initialized {
+        self.schedule();
+      }
+    }
+
+    let object_msgs = pending_msgs.entry(object_id.clone()).or_default();
+    if matches!(msg, Message::Sync(SyncMessage::SyncStep2(_))) && object_msgs.init_sync.is_none() {
+      object_msgs.init_sync = self.init_sync_guard(&object_id);
+    }
+    object_msgs.msgs.push_back(msg);
+    if self.apply_messages(&object_id, object_msgs) {
+      pending_msgs.remove(&object_id);
+    }
+  }
+
+  /// Apply the pending messages of the objects whose collab is not locked anymore.
+  fn apply_pending_messages(&self, pending_msgs: &mut HashMap<String, ObjectMessages>) {
+    pending_msgs.retain(|object_id, object_msgs| !self.apply_messages(object_id, object_msgs));
+  }
+
+  /// Apply the pending messages of the object if its collab is not locked. Return false if the
+  /// messages have to wait for the collab to be unlocked.
+  fn apply_messages(&self, object_id: &str, object_msgs: &mut ObjectMessages) -> bool {
+    // Don't hold the state while the collab is locked. The collab might be initializing, which
+    // needs the state to queue its init sync.
+    let collab = self
+      .state
+      .lock()
+      .objects
+      .get(object_id)
+      .and_then(|object| object.collab.upgrade());
+    let collab = match collab {
+      None => {
+        trace!(
+          "{}: ignore the messages of {}",
+          self.workspace_id,
+          object_id
+        );
+        return true;
+      },
+      Some(collab) => collab,
+    };
+
+    {
+      let mut collab = match collab.try_lock() {
+        None => {
+          trace!(
+            "{}: {} is locked, retry the sync messages later",
+            self.workspace_id,
+            object_id
+          );
+          return false;
+        },
+        Some(collab) => collab,
+      };
+      while let Some(msg) = object_msgs.msgs.pop_front() {
+        if let Some(reply) = handle_message(object_id, &mut collab, msg) {
+          self.send(object_id, reply);
+        }
+      }
+    }
+    if let Some(init_sync) = object_msgs.init_sync.take() {
+      init_sync.finish();
+    }
+    true
+  }
+
+  fn init_sync_guard(self: &Arc<Self>, object_id: &str) -> Option<InitSyncGuard> {
+    let sync_id = self.state.lock().init_sync_id(object_id)?;
+    Some(InitSyncGuard {
+      coordinator: Arc::downgrade(self),
+      object_id: object_id.to_string(),
+      sync_id,
+      is_applied: false,
+    })
+  }
+}
+
+/// The received messages of an object that wait for its collab to be unlocked, in the order they
+/// were received. The receiving task never blocks on the lock of a collab.
+#[derive(Default)]
+struct ObjectMessages {
+  msgs: VecDeque<Message>,
+  /// The init sync whose reply is in the messages.
+  init_sync: Option<InitSyncGuard>,
+}
+
+/// Keeps the slot of an init sync until its reply is applied. The slot is released when the guard
+/// is dropped on every path: the init sync is done if the reply was applied, and it's queued again
+/// if the reply was discarded, e.g. because the connection was closed before the collab was
+/// unlocked.
+struct InitSyncGuard {
+  coordinator: Weak<WorkspaceSyncCoordinator>,
+  object_id: String,
+  sync_id: u64,
+  is_applied: bool,
+}
+
+impl InitSyncGuard {
+  fn finish(mut self) {
+    self.is_applied = true;
+  }
+}
+
+impl Drop for InitSyncGuard {
+  fn drop(&mut self) {
+    if let Some(coordinator) = self.coordinator.upgrade() {
+      if self.is_applied {
+        coordinator.finish_init_sync(&self.object_id, self.sync_id);
+      } else {
+        coordinator.retry_init_sync(&self.object_id, self.sync_id);
+      }
+    }
+  }
+}
+
+/// The [CollabPlugin] of an object that is synced by a [WorkspaceSyncCoordinator]. It's created by
+/// [WorkspaceSyncCoordinator::register] and unregisters the object when it's dropped.
+pub struct WorkspaceSyncPlugin {
+  object_id: String,
+  registration_id: u64,
+  coordinator: Weak<WorkspaceSyncCoordinator>,
+}
+
+impl WorkspaceSyncPlugin {
+  fn send(&self, msg: Message) {
+    if let Some(coordinator) = self.coordinator.upgrade() {
+      coordinator.send(&self.object_id, msg);
+    }
+  }
+}
+
+impl CollabPlugin for WorkspaceSyncPlugin {
+  fn did_init(&self, collab: &Collab, _object_id: &str, _last_sync_at: i64) {
+    let coordinator = match self.coordinator.upgrade() {
+      None => return,
+      Some(coordinator) => coordinator,
+    };
+    collab.set_sync_state(SyncState::InitSyncBegin);
+    let state_vector = collab.transact().state_vector();
+    coordinator.queue_init_sync(&self.object_id, self.registration_id, state_vector);
+
+    let awareness = collab.get_awareness();
+    if awareness.get_local_state().is_some() {
+      if let Ok(update) = awareness.update_with_clients([awareness.client_id()]) {
+        coordinator.send(&self.object_id, Message::Awareness(update));
+      }
+    }
+  }
+
+  fn receive_local_update(&self, _origin: &CollabOrigin, _object_id: &str, update: &[u8]) {
+    self.send(Message::Sync(SyncMessage::Update(update.to_vec())));
+  }
+
+  fn receive_local_state(
+    &self,
+    _origin: &CollabOrigin,
+    _object_id: &str,
+    _event: &Event,
+    update: &AwarenessUpdate,
+  ) {
+    self.send(Message::Awareness(update.clone()));
+  }
+
+  fn plugin_type(&self) -> CollabPluginType {
+    CollabPluginType::CloudStorage
+  }
+}
+
+impl Drop for WorkspaceSyncPlugin {
+  fn drop(&mut self) {
+    if let Some(coordinator) = self.coordinator.upgrade() {
+      coordinator.unregister(&self.object_id, self.registration_id);
+    }
+  }
+}
+
+async fn send_messages<Si, E>(
+  workspace_id: String,
+  mut msg_rx: UnboundedReceiver<WorkspaceMessage>,
+  mut sink: Si,
+) where
+  Si: Sink<Vec<u8>, Error = E> + Unpin,
+  E: Display,
+{
+  while let Some(msg) = msg_rx.recv().await {
+    if let Err(err) = sink.send(msg.encode_v1()).await {
+      error!("{} failed to send the sync message: {}", workspace_id, err);
+      return;
+    }
+  }
+  let _ = sink.close().await;
+  trace!("{} the sync connection is closed", workspace_id);
+}
+
+/// Retry the init syncs whose reply is late until the coordinator is dropped.
+async fn check_init_syncs(coordinator: Weak<WorkspaceSyncCoordinator>, interval: Duration) {
+  let mut interval = tokio::time::interval(interval);
+  loop {
+    interval.tick().await;
+    match coordinator.upgrade() {
+      None => break,
+      Some(coordinator) => coordinator.check_init_syncs(),
+    }
+  }
+}
+
+async fn receive_messages<St, E>(
+  coordinator: Weak<WorkspaceSyncCoordinator>,
+  connection_id: u64,
+  mut stream: St,
+) where
+  St: Stream<Item = Result<Vec<u8>, E>> + Unpin,
+  E: Display,
+{
+  let mut pending_msgs = HashMap::new();
+  loop {
+    // Wait for the next message, or retry the pending ones after a while if a collab was locked
+    let frame = if pending_msgs.is_empty() {
+      stream.next().await
+    } else {
+      tokio::select! {
+        frame = stream.next() => frame,
+        _ = tokio::time::sleep(LOCK_RETRY_INTERVAL) => {
+          match coordinator.upgrade() {
+            None => break,
+            Some(coordinator) => coordinator.apply_pending_messages(&mut pending_msgs),
+          }
+          continue;
+        },
+      }
+    };
+    let coordinator = match coordinator.upgrade() {
+      None => break,
+      Some(coordinator) => coordinator,
+    };
+    // The connection was replaced by a new one
+    if !coordinator.is_current_connection(connection_id) {
+      break;
+    }
+    let frame = match frame {
+      None => break,
+      Some(Ok(frame)) => frame,
+      Some(Err(err)) => {
+        error!(
+          "{} failed to receive the sync message: {}",
+          coordinator.workspace_id, err
+        );
+        break;
+      },
+    };
+    match WorkspaceMessage::decode_v1(&frame) {
+      Ok(msg) => coordinator.receive_message(msg, &mut pending_msgs),
+      Err(err) => error!(
+        "{} failed to decode the sync message: {}",
+        coordinator.workspace_id, err
+      ),
+    }
+  }
+}
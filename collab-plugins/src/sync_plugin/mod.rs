@@ -0,0 +1,7 @@
+pub use coordinator::*;
+pub use plugin::*;
+pub use protocol::*;
+
+mod coordinator;
+mod plugin;
+mod protocol;
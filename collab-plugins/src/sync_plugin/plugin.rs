@@ -0,0 +1,297 @@
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::sync::Weak;
+use std::time::Duration;
+
+use collab::core::awareness::{AwarenessUpdate, Event};
+use collab::core::collab::{MutexCollab, TransactionMutExt};
+use collab::core::collab_plugin::CollabPluginType;
+use collab::core::collab_state::SyncState;
+use collab::core::origin::CollabOrigin;
+use collab::preclude::{Collab, CollabPlugin};
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tracing::{error, trace, warn};
+use yrs::updates::decoder::Decode;
+use yrs::updates::encoder::Encode;
+use yrs::{ReadTxn, Update};
+
+use crate::sync_plugin::protocol::{Message, SyncMessage};
+
+/// A [CollabPlugin] that syncs the collab with a server that speaks the y-protocols, e.g. a
+/// y-websocket server. Each message is sent in its own binary frame of the transport.
+///
+/// The plugin starts the sync by sending the state vector of the collab when the collab is
+/// initialized, then sends the local updates and awareness states as they happen. The updates from
+/// the server are applied without the local origin, so they are not sent back.
+///
+/// The transport is usually a WebSocket connection that is split into its sink and stream halves.
+/// The connection is closed when the plugin is dropped.
+pub struct YSyncPlugin {
+  object_id: String,
+  msg_tx: UnboundedSender<Message>,
+}
+
+impl YSyncPlugin {
+  pub fn new<Si, St, E>(
+    object_id: &str,
+    local_collab: Weak<MutexCollab>,
+    sink: Si,
+    stream: St,
+  ) -> Self
+  where
+    Si: Sink<Vec<u8>, Error = E> + Send + Unpin + 'static,
+    St: Stream<Item = Result<Vec<u8>, E>> + Send + Unpin + 'static,
+    E: Display + Send + 'static,
+  {
+    let (msg_tx, msg_rx) = unbounded_channel();
+    tokio::spawn(send_messages(object_id.to_string(), msg_rx, sink));
+    tokio::spawn(receive_messages(
+      object_id.to_string(),
+      local_collab,
+      msg_tx.clone(),
+      stream,
+    ));
+    Self {
+      object_id: object_id.to_string(),
+      msg_tx,
+    }
+  }
+
+  fn send(&self, msg: Message) {
+    if self.msg_tx.send(msg).is_err() {
+      warn!("{} the sync connection is closed", self.object_id);
+    }
+  }
+}
+
+impl CollabPlugin for YSyncPlugin {
+  fn did_init(&self, collab: &Collab, _object_id: &str, _last_sync_at: i64) {
+    collab.set_sync_state(SyncState::InitSyncBegin);
+    let state_vector = collab.transact().state_vector();
+    self.send(Message::Sync(SyncMessage::SyncStep1(state_vector)));
+
+    let awareness = collab.get_awareness();
+    if awareness.get_local_state().is_some() {
+      if let Ok(update) = awareness.update_with_clients([awareness.client_id()]) {
+        self.send(Message::Awareness(update));
+      }
+    }
+  }
+
+  fn receive_local_update(&self, _origin: &CollabOrigin, _object_id: &str, update: &[u8]) {
+    self.send(Message::Sync(SyncMessage::Update(update.to_vec())));
+  }
+
+  fn receive_local_state(
+    &self,
+    _origin: &CollabOrigin,
+    _object_id: &str,
+    _event: &Event,
+    update: &AwarenessUpdate,
+  ) {
+    self.send(Message::Awareness(update.clone()));
+  }
+
+  fn plugin_type(&self) -> CollabPluginType {
+    CollabPluginType::CloudStorage
+  }
+}
+
+async fn send_messages<Si, E>(
+  object_id: String,
+  mut msg_rx: UnboundedReceiver<Message>,
+  mut sink: Si,
+) where
+  Si: Sink<Vec<u8>, Error = E> + Unpin,
+  E: Display,
+{
+  while let Some(msg) = msg_rx.recv().await {
+    if let Err(err) = sink.send(msg.encode_v1()).await {
+      error!("{} failed to send the sync message: {}", object_id, err);
+      return;
+    }
+  }
+  let _ = sink.close().await;
+  trace!("{} the sync connection is closed", object_id);
+}
+
+/// The interval to retry applying the received messages while the collab is locked.
+pub(crate) const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+/// The received messages are dropped when more than this many are waiting for the collab to be
+/// unlocked. The collab is synced again with a [SyncMessage::SyncStep1] when it's unlocked.
+pub(crate) const MAX_PENDING_MESSAGES: usize = 100;
+
+async fn receive_messages<St, E>(
+  object_id: String,
+  local_collab: Weak<MutexCollab>,
+  msg_tx: UnboundedSender<Message>,
+  mut stream: St,
+) where
+  St: Stream<Item = Result<Vec<u8>, E>> + Unpin,
+  E: Display,
+{
+  let mut pending_msgs = PendingMessages::default();
+  loop {
+    // Wait for the next message, or retry the pending ones after a while if the collab was locked
+    let frame = if pending_msgs.is_empty() {
+      stream.next().await
+    } else {
+      tokio::select! {
+        frame = stream.next() => frame,
+        _ = tokio::time::sleep(LOCK_RETRY_INTERVAL) => {
+          match pending_msgs.apply(&object_id, &local_collab, &msg_tx) {
+            Ok(()) => continue,
+            Err(()) => break,
+          }
+        },
+      }
+    };
+    let frame = match frame {
+      None => break,
+      Some(Ok(frame)) => frame,
+      Some(Err(err)) => {
+        error!("{} failed to receive the sync message: {}", object_id, err);
+        break;
+      },
+    };
+    match Message::decode_v1(&frame) {
+      Ok(msg) => pending_msgs.push(&object_id, msg),
+      Err(err) => {
+        error!("{} failed to decode the sync message: {}", object_id, err);
+        continue;
+      },
+    }
+    if pending_msgs
+      .apply(&object_id, &local_collab, &msg_tx)
+      .is_err()
+    {
+      break;
+    }
+  }
+}
+
+/// The received messages that wait for the collab to be unlocked. The messages are applied in the
+/// order they were received. The receiving task never blocks on the lock of the collab.
+#[derive(Default)]
+struct PendingMessages {
+  msgs: VecDeque<Message>,
+  /// The messages were dropped, so the collab has to be synced again.
+  resync: bool,
+}
+
+impl PendingMessages {
+  fn is_empty(&self) -> bool {
+    self.msgs.is_empty() && !self.resync
+  }
+
+  fn push(&mut self, object_id: &str, msg: Message) {
+    if self.msgs.len() >= MAX_PENDING_MESSAGES {
+      warn!(
+        "{} the collab is locked for too long, drop the received sync messages",
+        object_id
+      );
+      self.msgs.clear();
+      self.resync = true;
+    }
+    self.msgs.push_back(msg);
+  }
+
+  /// Apply the pending messages if the collab is not locked. Return Err if the collab is dropped.
+  fn apply(
+    &mut self,
+    object_id: &str,
+    local_collab: &Weak<MutexCollab>,
+    msg_tx: &UnboundedSender<Message>,
+  ) -> Result<(), ()> {
+    let local_collab = local_collab.upgrade().ok_or(())?;
+    let mut collab = match local_collab.try_lock() {
+      None => {
+        trace!(
+          "{} the collab is locked, retry the sync messages later",
+          object_id
+        );
+        return Ok(());
+      },
+      Some(collab) => collab,
+    };
+    if std::mem::take(&mut self.resync) {
+      let state_vector = collab.transact().state_vector();
+      let _ = msg_tx.send(Message::Sync(SyncMessage::SyncStep1(state_vector)));
+    }
+    while let Some(msg) = self.msgs.pop_front() {
+      if let Some(reply) = handle_message(object_id, &mut collab, msg) {
+        let _ = msg_tx.send(reply);
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Apply the message to the collab. Return the reply to the message if it needs one.
+pub(crate) fn handle_message(
+  object_id: &str,
+  collab: &mut Collab,
+  msg: Message,
+) -> Option<Message> {
+  match msg {
+    Message::Sync(SyncMessage::SyncStep1(state_vector)) => {
+      let update = collab.transact().encode_state_as_update_v1(&state_vector);
+      Some(Message::Sync(SyncMessage::SyncStep2(update)))
+    },
+    Message::Sync(SyncMessage::SyncStep2(update)) => {
+      apply_update(object_id, collab, &update);
+      collab.set_sync_state(SyncState::InitSyncEnd);
+      None
+    },
+    Message::Sync(SyncMessage::Update(update)) => {
+      apply_update(object_id, collab, &update);
+      None
+    },
+    Message::Awareness(update) => {
+      // The awareness only passes the changes of the local origin to the plugins, so the states
+      // of the other clients are not sent back.
+      if let Err(err) = collab
+        .get_mut_awareness()
+        .apply_update(update, &CollabOrigin::Server)
+      {
+        error!(
+          "{} failed to apply the remote awareness: {}",
+          object_id, err
+        );
+      }
+      None
+    },
+    Message::AwarenessQuery => {
+      let update = collab.get_awareness().update().ok()?;
+      Some(Message::Awareness(update))
+    },
+    Message::Auth(Some(reason)) => {
+      error!(
+        "{} the sync server denied the access: {}",
+        object_id, reason
+      );
+      None
+    },
+    Message::Auth(None) | Message::Custom(_, _) => None,
+  }
+}
+
+fn apply_update(object_id: &str, collab: &Collab, update: &[u8]) {
+  let update = match Update::decode_v1(update) {
+    Ok(update) => update,
+    Err(err) => {
+      error!("{} failed to decode the remote update: {}", object_id, err);
+      return;
+    },
+  };
+  // The transaction has no origin, so the update is not sent back to the server
+  match collab.try_transaction_mut() {
+    Ok(mut txn) => {
+      if let Err(err) = txn.try_apply_update(update) {
+        error!("{} failed to apply the remote update: {:?}", object_id, err);
+      }
+    },
+    Err(err) => error!("{} failed to apply the remote update: {:?}", object_id, err),
+  }
+}
@@ -0,0 +1,166 @@
+use collab::core::awareness::AwarenessUpdate;
+use yrs::encoding::read;
+use yrs::updates::decoder::{Decode, Decoder};
+use yrs::updates::encoder::{Encode, Encoder};
+use yrs::StateVector;
+
+/// The message types of the y-protocols. See
+/// [y-protocols](https://github.com/yjs/y-protocols/blob/master/PROTOCOL.md).
+pub const MSG_SYNC: u8 = 0;
+pub const MSG_AWARENESS: u8 = 1;
+pub const MSG_AUTH: u8 = 2;
+pub const MSG_QUERY_AWARENESS: u8 = 3;
+
+pub const MSG_SYNC_STEP_1: u8 = 0;
+pub const MSG_SYNC_STEP_2: u8 = 1;
+pub const MSG_SYNC_UPDATE: u8 = 2;
+
+const PERMISSION_DENIED: u8 = 0;
+const PERMISSION_GRANTED: u8 = 1;
+
+/// A message of the y-protocols. Each message is sent in its own frame of the transport, like
+/// y-websocket does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+  Sync(SyncMessage),
+  Awareness(AwarenessUpdate),
+  /// The server denied the access to the document if the reason is not None. A flag that tells
+  /// whether the access is denied is written before the reason.
+  Auth(Option<String>),
+  /// Ask the other side to send its awareness states.
+  AwarenessQuery,
+  /// A message of a type that is not part of the y-protocols.
+  Custom(u8, Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncMessage {
+  /// Send the state vector of the local document. The other side replies with a
+  /// [SyncMessage::SyncStep2] that contains the updates missing locally.
+  SyncStep1(StateVector),
+  /// The updates that the other side is missing, encoded with the v1 encoding.
+  SyncStep2(Vec<u8>),
+  /// An incremental update, encoded with the v1 encoding.
+  Update(Vec<u8>),
+}
+
+impl Encode for Message {
+  fn encode<E: Encoder>(&self, encoder: &mut E) {
+    match self {
+      Message::Sync(msg) => {
+        encoder.write_var(MSG_SYNC);
+        msg.encode(encoder);
+      },
+      Message::Awareness(update) => {
+        encoder.write_var(MSG_AWARENESS);
+        encoder.write_buf(update.encode_v1());
+      },
+      Message::Auth(reason) => {
+        encoder.write_var(MSG_AUTH);
+        match reason {
+          Some(reason) => {
+            encoder.write_var(PERMISSION_DENIED);
+            encoder.write_string(reason);
+          },
+          None => encoder.write_var(PERMISSION_GRANTED),
+        }
+      },
+      Message::AwarenessQuery => {
+        encoder.write_var(MSG_QUERY_AWARENESS);
+      },
+      Message::Custom(tag, data) => {
+        encoder.write_var(*tag);
+        encoder.write_buf(data);
+      },
+    }
+  }
+}
+
+impl Decode for Message {
+  fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, read::Error> {
+    let tag: u8 = decoder.read_var()?;
+    match tag {
+      MSG_SYNC => Ok(Message::Sync(SyncMessage::decode(decoder)?)),
+      MSG_AWARENESS => {
+        let data = decoder.read_buf()?;
+        Ok(Message::Awareness(AwarenessUpdate::decode_v1(data)?))
+      },
+      MSG_AUTH => {
+        let permission: u8 = decoder.read_var()?;
+        if permission == PERMISSION_DENIED {
+          Ok(Message::Auth(Some(decoder.read_string()?.to_string())))
+        } else {
+          Ok(Message::Auth(None))
+        }
+      },
+      MSG_QUERY_AWARENESS => Ok(Message::AwarenessQuery),
+      tag => Ok(Message::Custom(tag, decoder.read_buf()?.to_vec())),
+    }
+  }
+}
+
+impl Encode for SyncMessage {
+  fn encode<E: Encoder>(&self, encoder: &mut E) {
+    match self {
+      SyncMessage::SyncStep1(state_vector) => {
+        encoder.write_var(MSG_SYNC_STEP_1);
+        encoder.write_buf(state_vector.encode_v1());
+      },
+      SyncMessage::SyncStep2(update) => {
+        encoder.write_var(MSG_SYNC_STEP_2);
+        encoder.write_buf(update);
+      },
+      SyncMessage::Update(update) => {
+        encoder.write_var(MSG_SYNC_UPDATE);
+        encoder.write_buf(update);
+      },
+    }
+  }
+}
+
+impl Decode for SyncMessage {
+  fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, read::Error> {
+    let tag: u8 = decoder.read_var()?;
+    match tag {
+      MSG_SYNC_STEP_1 => {
+        let data = decoder.read_buf()?;
+        Ok(SyncMessage::SyncStep1(StateVector::decode_v1(data)?))
+      },
+      MSG_SYNC_STEP_2 => Ok(SyncMessage::SyncStep2(decoder.read_buf()?.to_vec())),
+      MSG_SYNC_UPDATE => Ok(SyncMessage::Update(decoder.read_buf()?.to_vec())),
+      _ => Err(read::Error::UnexpectedValue),
+    }
+  }
+}
+
+/// A [Message] that is addressed to one object of a workspace. It's used to sync all the objects
+/// of a workspace over one connection. The object id is written before the message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceMessage {
+  pub object_id: String,
+  pub msg: Message,
+}
+
+impl WorkspaceMessage {
+  pub fn new(object_id: &str, msg: Message) -> Self {
+    Self {
+      object_id: object_id.to_string(),
+      msg,
+    }
+  }
+}
+
+impl Encode for WorkspaceMessage {
+  fn encode<E: Encoder>(&self, encoder: &mut E) {
+    encoder.write_string(&self.object_id);
+    self.msg.encode(encoder);
+  }
+}
+
+impl Decode for WorkspaceMessage {
+  fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, read::Error> {
+    let object_id = decoder.read_string()?.to_string();
+    let msg = Message::decode(decoder)?;
+    Ok(Self { object_id, msg })
+  }
+}
@@ -0,0 +1,141 @@
+use std::sync::Arc;
+
+use collab::core::collab::MutexCollab;
+use collab::preclude::Collab;
+use collab_entity::CollabType;
+use collab_plugins::cloud_storage::CollabOutbox;
+use collab_plugins::local_storage::kv::doc::CollabKVAction;
+use collab_plugins::local_storage::kv::KVTransactionDB;
+use collab_plugins::local_storage::rocksdb::rocksdb_plugin::RocksdbDiskPlugin;
+use collab_plugins::CollabKVDB;
+use serde_json::json;
+use tempfile::TempDir;
+use yrs::updates::decoder::Decode;
+use yrs::{StateVector, Update};
+
+fn open_disk_collab(collab_db: &Arc<CollabKVDB>, object_id: &str) -> Arc<MutexCollab> {
+  let collab = Arc::new(MutexCollab::new(Collab::new(
+    1,
+    object_id,
+    "1",
+    vec![],
+    false,
+  )));
+  let plugin = RocksdbDiskPlugin::new(
+    1,
+    object_id.to_string(),
+    CollabType::Document,
+    Arc::downgrade(collab_db),
+    None,
+  );
+  collab.lock().add_plugin(Box::new(plugin));
+  collab.lock().initialize();
+  collab
+}
+
+fn pending_update(outbox: &CollabOutbox, collab: &MutexCollab) -> Option<Vec<u8>> {
+  outbox.pending_update(&collab.lock().transact()).unwrap()
+}
+
+#[tokio::test]
+async fn outbox_only_returns_unacked_changes_test() {
+  let tempdir = TempDir::new().unwrap();
+  let collab_db = Arc::new(CollabKVDB::open(tempdir.path()).unwrap());
+  let collab = open_disk_collab(&collab_db, "doc");
+  let outbox = CollabOutbox::new(1, "doc", Arc::downgrade(&collab_db));
+
+  collab.lock().insert("1", "a");
+  let update = pending_update(&outbox, &collab).unwrap();
+  outbox.send_update(1, &update).unwrap();
+  outbox.ack_msg(1).unwrap();
+  assert!(pending_update(&outbox, &collab).is_none());
+
+  // Only the new change is pending, even with a new outbox
+  collab.lock().insert("2", "b");
+  drop(outbox);
+  let outbox = CollabOutbox::new(1, "doc", Arc::downgrade(&collab_db));
+  let update = pending_update(&outbox, &collab).unwrap();
+  let collab_2 = Collab::new(2, "doc", "2", vec![], false);
+  collab_2.with_origin_transact_mut(|txn| {
+    txn.apply_update(Update::decode_v1(&update).unwrap());
+  });
+  assert_eq!(collab_2.to_json_value(), json!({"2": "b"}));
+}
+
+#[tokio::test]
+async fn outbox_acks_messages_in_order_test() {
+  let tempdir = TempDir::new().unwrap();
+  let collab_db = Arc::new(CollabKVDB::open(tempdir.path()).unwrap());
+  let collab = open_disk_collab(&collab_db, "doc");
+  let outbox = CollabOutbox::new(1, "doc", Arc::downgrade(&collab_db));
+
+  collab.lock().insert("1", "a");
+  let update_1 = pending_update(&outbox, &collab).unwrap();
+  let sv_1 = collab.lock().transact().state_vector();
+  collab.lock().insert("2", "b");
+  let update_2 = collab.lock().transact().encode_state_as_update_v1(&sv_1);
+  outbox.send_update(1, &update_1).unwrap();
+  outbox.send_update(2, &update_2).unwrap();
+
+  // The second message is acked first, so the first change is still pending
+  outbox.ack_msg(2).unwrap();
+  assert_eq!(outbox.acked_state_vector().unwrap(), StateVector::default());
+  assert!(pending_update(&outbox, &collab).is_some());
+
+  outbox.ack_msg(1).unwrap();
+  assert!(pending_update(&outbox, &collab).is_none());
+}
+
+#[tokio::test]
+async fn outbox_only_acks_integrated_remote_changes_test() {
+  let tempdir = TempDir::new().unwrap();
+  let collab_db = Arc::new(CollabKVDB::open(tempdir.path()).unwrap());
+  let collab = open_disk_collab(&collab_db, "doc");
+  let outbox = CollabOutbox::new(1, "doc", Arc::downgrade(&collab_db));
+
+  // The remote update contains the changes of the client 2 up to 10, but only the ones up to 5 are
+  // integrated because the others miss their dependencies
+  let mut update_state_vector = StateVector::default();
+  update_state_vector.set_max(2, 10);
+  let mut doc_state_vector = StateVector::default();
+  doc_state_vector.set_max(2, 5);
+  outbox.ack_remote_update(&update_state_vector, &doc_state_vector);
+  assert_eq!(outbox.acked_state_vector().unwrap().get(&2), 5);
+
+  // The remote changes are saved when the outbox is dropped
+  drop(outbox);
+  let outbox = CollabOutbox::new(1, "doc", Arc::downgrade(&collab_db));
+  assert_eq!(outbox.acked_state_vector().unwrap().get(&2), 5);
+  drop(collab);
+}
+
+#[tokio::test]
+async fn flush_doc_keeps_acked_state_vector_test() {
+  let tempdir = TempDir::new().unwrap();
+  let collab_db = Arc::new(CollabKVDB::open(tempdir.path()).unwrap());
+  let collab = open_disk_collab(&collab_db, "doc");
+  collab.lock().insert("1", "a");
+
+  let outbox = CollabOutbox::new(1, "doc", Arc::downgrade(&collab_db));
+  let update = pending_update(&outbox, &collab).unwrap();
+  outbox.send_update(1, &update).unwrap();
+  outbox.ack_msg(1).unwrap();
+  let acked_state_vector = outbox.acked_state_vector().unwrap();
+
+  let encoded_collab = collab
+    .lock()
+    .encode_collab_v1(|_| Ok::<(), anyhow::Error>(()))
+    .unwrap();
+  collab_db
+    .with_write_txn(|w_db_txn| {
+      w_db_txn.flush_doc_with(
+        1,
+        "doc",
+        &encoded_collab.doc_state,
+        &encoded_collab.state_vector,
+      )
+    })
+    .unwrap();
+  let outbox = CollabOutbox::new(1, "doc", Arc::downgrade(&collab_db));
+  assert_eq!(outbox.acked_state_vector().unwrap(), acked_state_vector);
+}
@@ -1,6 +1,12 @@
+#[cfg(all(feature = "postgres_plugin", not(target_arch = "wasm32")))]
+mod cloud;
+
 #[cfg(not(target_arch = "wasm32"))]
 mod disk;
 
+#[cfg(not(target_arch = "wasm32"))]
+mod sync;
+
 #[cfg(target_arch = "wasm32")]
 mod web;
 
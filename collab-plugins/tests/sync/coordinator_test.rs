@@ -0,0 +1,222 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use collab::core::collab::MutexCollab;
+use collab::preclude::Collab;
+use collab_plugins::sync_plugin::{
+  Message, SyncMessage, SyncPriority, WorkspaceMessage, WorkspaceSyncConfig,
+  WorkspaceSyncCoordinator,
+};
+use futures::channel::mpsc::{unbounded, SendError, UnboundedReceiver, UnboundedSender};
+use futures::StreamExt;
+use yrs::updates::decoder::Decode;
+use yrs::updates::encoder::Encode;
+use yrs::{Doc, ReadTxn, StateVector, Transact};
+
+#[tokio::test]
+async fn init_sync_by_priority_test() {
+  let (client_tx, mut server_rx) = unbounded::<Vec<u8>>();
+  let (server_tx, client_rx) = unbounded::<Vec<u8>>();
+  let coordinator = WorkspaceSyncCoordinator::new(
+    "w1",
+    client_tx,
+    client_rx.map(Ok::<_, SendError>),
+    WorkspaceSyncConfig::new().with_max_concurrent_init_syncs(1),
+  );
+
+  // The first object takes the only slot, so the others are queued
+  let _first = open_collab(&coordinator, "first", SyncPriority::Low);
+  let _row = open_collab(&coordinator, "row", SyncPriority::Low);
+  let _document = open_collab(&coordinator, "document", SyncPriority::Normal);
+  let _folder = open_collab(&coordinator, "folder", SyncPriority::High);
+  assert_eq!(next_sync_step_1(&mut server_rx).await, "first");
+  tokio::time::sleep(Duration::from_millis(100)).await;
+  assert!(server_rx.try_next().is_err());
+  let progress = coordinator.progress();
+  assert_eq!(progress.num_of_objects, 4);
+  assert_eq!(progress.num_of_syncing, 1);
+  assert_eq!(progress.num_of_synced, 0);
+
+  // The next init sync starts when the running one is done, by priority
+  for (object_id, next_object_id) in [
+    ("first", "folder"),
+    ("folder", "document"),
+    ("document", "row"),
+  ] {
+    reply_sync_step_2(&server_tx, object_id);
+    assert_eq!(next_sync_step_1(&mut server_rx).await, next_object_id);
+  }
+  reply_sync_step_2(&server_tx, "row");
+  let mut progress_rx = coordinator.subscribe_progress();
+  while !progress_rx.borrow().is_complete() {
+    progress_rx.changed().await.unwrap();
+  }
+  assert_eq!(coordinator.progress().num_of_synced, 4);
+}
+
+#[tokio::test]
+async fn unregister_dropped_collab_test() {
+  let (client_tx, _server_rx) = unbounded::<Vec<u8>>();
+  let (_server_tx, client_rx) = unbounded::<Vec<u8>>();
+  let coordinator = WorkspaceSyncCoordinator::new(
+    "w1",
+    client_tx,
+    client_rx.map(Ok::<_, SendError>),
+    WorkspaceSyncConfig::new(),
+  );
+  let collab = open_collab(&coordinator, "document", SyncPriority::Normal);
+  assert_eq!(coordinator.progress().num_of_objects, 1);
+  assert_eq!(coordinator.progress().num_of_syncing, 1);
+
+  drop(collab);
+  assert_eq!(coordinator.progress().num_of_objects, 0);
+  assert_eq!(coordinator.progress().num_of_syncing, 0);
+  assert!(coordinator.progress().is_complete());
+}
+
+// The collab is locked across the awaits on purpose, the coordinator must not wait for it
+#[allow(clippy::await_holding_lock)]
+#[tokio::test]
+async fn apply_init_sync_reply_when_collab_is_unlocked_test() {
+  let (client_tx, mut server_rx) = unbounded::<Vec<u8>>();
+  let (server_tx, client_rx) = unbounded::<Vec<u8>>();
+  let coordinator = WorkspaceSyncCoordinator::new(
+    "w1",
+    client_tx,
+    client_rx.map(Ok::<_, SendError>),
+    WorkspaceSyncConfig::new().with_max_concurrent_init_syncs(2),
+  );
+  let document = open_collab(&coordinator, "document", SyncPriority::Normal);
+  let _folder = open_collab(&coordinator, "folder", SyncPriority::High);
+  let _row = open_collab(&coordinator, "row", SyncPriority::Low);
+  next_sync_step_1(&mut server_rx).await;
+  next_sync_step_1(&mut server_rx).await;
+
+  // The reply of a locked collab waits without blocking the messages of the other objects
+  let locked_document = document.lock();
+  reply_sync_step_2(&server_tx, "document");
+  reply_sync_step_2(&server_tx, "folder");
+  assert_eq!(next_sync_step_1(&mut server_rx).await, "row");
+  assert_eq!(coordinator.progress().num_of_synced, 1);
+  assert_eq!(coordinator.progress().num_of_syncing, 2);
+
+  drop(locked_document);
+  let mut progress_rx = coordinator.subscribe_progress();
+  while progress_rx.borrow().num_of_synced != 2 {
+    progress_rx.changed().await.unwrap();
+  }
+  assert_eq!(coordinator.progress().num_of_syncing, 1);
+}
+
+#[tokio::test]
+async fn retry_init_sync_without_reply_test() {
+  let (client_tx, mut server_rx) = unbounded::<Vec<u8>>();
+  let (server_tx, client_rx) = unbounded::<Vec<u8>>();
+  let coordinator = WorkspaceSyncCoordinator::new(
+    "w1",
+    client_tx,
+    client_rx.map(Ok::<_, SendError>),
+    WorkspaceSyncConfig::new()
+      .with_max_concurrent_init_syncs(1)
+      .with_init_sync_timeout(Duration::from_millis(100))
+      .with_max_init_sync_retries(1),
+  );
+  let _document = open_collab(&coordinator, "document", SyncPriority::Normal);
+  let _row = open_collab(&coordinator, "row", SyncPriority::Low);
+
+  // The init sync is sent again once, then it gives up its slot to the next one
+  assert_eq!(next_sync_step_1(&mut server_rx).await, "document");
+  assert_eq!(next_sync_step_1(&mut server_rx).await, "document");
+  assert_eq!(next_sync_step_1(&mut server_rx).await, "row");
+  assert_eq!(coordinator.progress().num_of_failed, 1);
+  assert_eq!(coordinator.progress().num_of_syncing, 1);
+
+  // A late reply still finishes the init sync
+  reply_sync_step_2(&server_tx, "document");
+  let mut progress_rx = coordinator.subscribe_progress();
+  while progress_rx.borrow().num_of_synced != 1 {
+    progress_rx.changed().await.unwrap();
+  }
+  assert_eq!(coordinator.progress().num_of_failed, 0);
+}
+
+#[tokio::test]
+async fn reconnect_test() {
+  let (client_tx, mut server_rx) = unbounded::<Vec<u8>>();
+  let (server_tx, client_rx) = unbounded::<Vec<u8>>();
+  let coordinator = WorkspaceSyncCoordinator::new(
+    "w1",
+    client_tx,
+    client_rx.map(Ok::<_, SendError>),
+    WorkspaceSyncConfig::new(),
+  );
+  let _document = open_collab(&coordinator, "document", SyncPriority::Normal);
+  next_sync_step_1(&mut server_rx).await;
+  reply_sync_step_2(&server_tx, "document");
+  let mut progress_rx = coordinator.subscribe_progress();
+  while !progress_rx.borrow().is_complete() {
+    progress_rx.changed().await.unwrap();
+  }
+
+  // The previous connection is closed and the objects are synced again over the new one
+  let (client_tx, mut server_rx_2) = unbounded::<Vec<u8>>();
+  let (server_tx_2, client_rx) = unbounded::<Vec<u8>>();
+  coordinator.reconnect(client_tx, client_rx.map(Ok::<_, SendError>));
+  assert_eq!(next_sync_step_1(&mut server_rx_2).await, "document");
+  assert!(
+    tokio::time::timeout(Duration::from_secs(5), server_rx.next())
+      .await
+      .unwrap()
+      .is_none()
+  );
+  assert_eq!(coordinator.progress().num_of_synced, 0);
+
+  // The replies on the previous connection are ignored
+  reply_sync_step_2(&server_tx, "document");
+  tokio::time::sleep(Duration::from_millis(100)).await;
+  assert_eq!(coordinator.progress().num_of_synced, 0);
+  reply_sync_step_2(&server_tx_2, "document");
+  while !progress_rx.borrow().is_complete() {
+    progress_rx.changed().await.unwrap();
+  }
+}
+
+fn open_collab(
+  coordinator: &Arc<WorkspaceSyncCoordinator>,
+  object_id: &str,
+  priority: SyncPriority,
+) -> Arc<MutexCollab> {
+  let collab = Arc::new(MutexCollab::new(Collab::new(
+    1,
+    object_id,
+    "1",
+    vec![],
+    false,
+  )));
+  let plugin = coordinator.register(object_id, priority, Arc::downgrade(&collab));
+  collab.lock().add_plugin(Box::new(plugin));
+  collab.lock().initialize();
+  collab
+}
+
+/// Return the object id of the next init sync that is sent by the coordinator.
+async fn next_sync_step_1(server_rx: &mut UnboundedReceiver<Vec<u8>>) -> String {
+  loop {
+    let frame = tokio::time::timeout(Duration::from_secs(5), server_rx.next())
+      .await
+      .unwrap()
+      .unwrap();
+    let msg = WorkspaceMessage::decode_v1(&frame).unwrap();
+    if let Message::Sync(SyncMessage::SyncStep1(_)) = msg.msg {
+      return msg.object_id;
+    }
+  }
+}
+
+fn reply_sync_step_2(server_tx: &UnboundedSender<Vec<u8>>, object_id: &str) {
+  let update = Doc::new()
+    .transact()
+    .encode_state_as_update_v1(&StateVector::default());
+  let msg = WorkspaceMessage::new(object_id, Message::Sync(SyncMessage::SyncStep2(update)));
+  server_tx.unbounded_send(msg.encode_v1()).unwrap();
+}
@@ -0,0 +1,4 @@
+mod coordinator_test;
+mod protocol_test;
+mod sync_test;
+mod util;
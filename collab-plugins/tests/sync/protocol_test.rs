@@ -0,0 +1,79 @@
+use collab::core::awareness::Awareness;
+use collab::core::origin::CollabOrigin;
+use collab_plugins::sync_plugin::{Message, SyncMessage, WorkspaceMessage};
+use serde_json::json;
+use yrs::updates::decoder::Decode;
+use yrs::updates::encoder::Encode;
+use yrs::{Doc, ReadTxn, StateVector, Text, Transact};
+
+#[test]
+fn encode_sync_step_1_like_y_protocols_test() {
+  // [messageSync, messageYjsSyncStep1, length of the state vector, empty state vector]
+  let msg = Message::Sync(SyncMessage::SyncStep1(StateVector::default()));
+  assert_eq!(msg.encode_v1(), vec![0, 0, 1, 0]);
+  assert_eq!(Message::decode_v1(&[0, 0, 1, 0]).unwrap(), msg);
+}
+
+#[test]
+fn sync_message_roundtrip_test() {
+  let doc = Doc::new();
+  let text = doc.get_or_insert_text("text");
+  text.insert(&mut doc.transact_mut(), 0, "hello world");
+  let txn = doc.transact();
+  let update = txn.encode_state_as_update_v1(&StateVector::default());
+
+  for msg in [
+    Message::Sync(SyncMessage::SyncStep1(txn.state_vector())),
+    Message::Sync(SyncMessage::SyncStep2(update.clone())),
+    Message::Sync(SyncMessage::Update(update)),
+    Message::AwarenessQuery,
+    Message::Auth(None),
+    Message::Auth(Some("permission denied".to_string())),
+    Message::Custom(100, vec![1, 2, 3]),
+  ] {
+    assert_eq!(Message::decode_v1(&msg.encode_v1()).unwrap(), msg);
+  }
+}
+
+#[test]
+fn awareness_message_roundtrip_test() {
+  let mut awareness = Awareness::new(Doc::new(), CollabOrigin::Empty);
+  awareness.set_local_state(json!({"name": "nathan"}));
+  let msg = Message::Awareness(awareness.update().unwrap());
+  let data = msg.encode_v1();
+  // messageAwareness
+  assert_eq!(data[0], 1);
+  assert_eq!(Message::decode_v1(&data).unwrap(), msg);
+}
+
+#[test]
+fn encode_auth_message_test() {
+  // [messageAuth, permission granted]
+  let msg = Message::Auth(None);
+  assert_eq!(msg.encode_v1(), vec![2, 1]);
+  assert_eq!(Message::decode_v1(&[2, 1]).unwrap(), msg);
+
+  // [messageAuth, permission denied, length of the reason, reason]
+  let msg = Message::Auth(Some("no".to_string()));
+  assert_eq!(msg.encode_v1(), vec![2, 0, 2, b'n', b'o']);
+  assert_eq!(Message::decode_v1(&[2, 0, 2, b'n', b'o']).unwrap(), msg);
+}
+
+#[test]
+fn decode_unknown_sync_message_test() {
+  assert!(Message::decode_v1(&[0, 9, 0]).is_err());
+}
+
+#[test]
+fn workspace_message_roundtrip_test() {
+  let msg = WorkspaceMessage::new(
+    "object_1",
+    Message::Sync(SyncMessage::SyncStep1(StateVector::default())),
+  );
+  let data = msg.encode_v1();
+  // The length and the bytes of the object id come before the message
+  assert_eq!(data[0], 8);
+  assert_eq!(&data[1..9], b"object_1");
+  assert_eq!(&data[9..], &[0, 0, 1, 0]);
+  assert_eq!(WorkspaceMessage::decode_v1(&data).unwrap(), msg);
+}
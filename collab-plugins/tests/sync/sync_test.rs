@@ -0,0 +1,66 @@
+use serde_json::json;
+
+use crate::sync::util::{connect_collab, wait_until, LoopbackServer};
+
+#[tokio::test]
+async fn two_clients_sync_insert_test() {
+  let server = LoopbackServer::new("1");
+  let client_1 = connect_collab(&server, 1, |_| {});
+  let client_2 = connect_collab(&server, 2, |_| {});
+
+  client_1.lock().insert("name", "appflowy");
+  wait_until(|| client_2.lock().to_json_value() == json!({"name": "appflowy"})).await;
+
+  client_2.lock().insert("language", "rust");
+  wait_until(|| client_1.lock().to_json_value() == json!({"name": "appflowy", "language": "rust"}))
+    .await;
+  wait_until(|| server.to_json_value() == json!({"name": "appflowy", "language": "rust"})).await;
+}
+
+#[tokio::test]
+async fn sync_offline_changes_during_handshake_test() {
+  let server = LoopbackServer::new("1");
+  let client_1 = connect_collab(&server, 1, |collab| {
+    collab.insert("offline", "1");
+  });
+  wait_until(|| server.to_json_value() == json!({"offline": "1"})).await;
+
+  // The new client receives the changes that were made before the first client connected
+  let client_2 = connect_collab(&server, 2, |collab| {
+    collab.insert("offline_2", "2");
+  });
+  let expected = json!({"offline": "1", "offline_2": "2"});
+  wait_until(|| client_2.lock().to_json_value() == expected).await;
+  wait_until(|| client_1.lock().to_json_value() == expected).await;
+}
+
+#[tokio::test]
+async fn sync_awareness_state_test() {
+  let server = LoopbackServer::new("1");
+  let client_1 = connect_collab(&server, 1, |_| {});
+  let client_2 = connect_collab(&server, 2, |_| {});
+
+  let client_id = client_1.lock().get_awareness().client_id();
+  client_1
+    .lock()
+    .get_mut_awareness()
+    .set_local_state(json!({"cursor": 10}));
+  wait_until(|| {
+    client_2.lock().get_awareness().get_states().get(&client_id) == Some(&json!({"cursor": 10}))
+  })
+  .await;
+}
+
+#[tokio::test]
+async fn apply_messages_received_while_collab_is_locked_test() {
+  let server = LoopbackServer::new("1");
+  let client_1 = connect_collab(&server, 1, |_| {});
+  let client_2 = connect_collab(&server, 2, |_| {});
+
+  // The messages that arrive while the collab is locked are applied once it's unlocked
+  let lock_guard = client_2.lock();
+  client_1.lock().insert("name", "appflowy");
+  tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+  drop(lock_guard);
+  wait_until(|| client_2.lock().to_json_value() == json!({"name": "appflowy"})).await;
+}
@@ -0,0 +1,154 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use collab::core::collab::{MutexCollab, TransactionMutExt};
+use collab::core::origin::CollabOrigin;
+use collab::preclude::Collab;
+use collab_plugins::sync_plugin::{Message, SyncMessage, YSyncPlugin};
+use futures::channel::mpsc::{unbounded, SendError, UnboundedSender};
+use futures::{Sink, Stream, StreamExt};
+use parking_lot::Mutex;
+use yrs::updates::decoder::Decode;
+use yrs::updates::encoder::Encode;
+use yrs::{ReadTxn, Update};
+
+/// An in-process server that speaks the y-protocols like a y-websocket server. It keeps its own
+/// copy of the document and broadcasts the updates and the awareness states of a client to the
+/// other clients.
+#[derive(Clone)]
+pub struct LoopbackServer {
+  object_id: String,
+  inner: Arc<Mutex<ServerState>>,
+}
+
+struct ServerState {
+  collab: MutexCollab,
+  next_client_id: usize,
+  clients: Vec<(usize, UnboundedSender<Vec<u8>>)>,
+}
+
+impl LoopbackServer {
+  pub fn new(object_id: &str) -> Self {
+    let collab = Collab::new_with_origin(CollabOrigin::Server, object_id, vec![], false);
+    Self {
+      object_id: object_id.to_string(),
+      inner: Arc::new(Mutex::new(ServerState {
+        collab: MutexCollab::new(collab),
+        next_client_id: 0,
+        clients: vec![],
+      })),
+    }
+  }
+
+  /// Open a connection to the server. Return the sink and the stream halves of the connection.
+  pub fn connect(
+    &self,
+  ) -> (
+    impl Sink<Vec<u8>, Error = SendError> + Send + Unpin + 'static,
+    impl Stream<Item = Result<Vec<u8>, SendError>> + Send + Unpin + 'static,
+  ) {
+    let (client_tx, mut server_rx) = unbounded::<Vec<u8>>();
+    let (server_tx, client_rx) = unbounded::<Vec<u8>>();
+
+    let client_id = {
+      let mut state = self.inner.lock();
+      let client_id = state.next_client_id;
+      state.next_client_id += 1;
+
+      // Like y-websocket, the server starts the sync with its state vector
+      let state_vector = state.collab.lock().transact().state_vector();
+      let _ =
+        server_tx.unbounded_send(Message::Sync(SyncMessage::SyncStep1(state_vector)).encode_v1());
+      state.clients.push((client_id, server_tx.clone()));
+      client_id
+    };
+
+    let server = self.clone();
+    tokio::spawn(async move {
+      while let Some(frame) = server_rx.next().await {
+        server.handle_frame(client_id, &server_tx, frame);
+      }
+      server
+        .inner
+        .lock()
+        .clients
+        .retain(|(id, _)| *id != client_id);
+    });
+
+    (client_tx, client_rx.map(Ok::<_, SendError>))
+  }
+
+  pub fn to_json_value(&self) -> serde_json::Value {
+    self.inner.lock().collab.lock().to_json_value()
+  }
+
+  fn handle_frame(&self, client_id: usize, client_tx: &UnboundedSender<Vec<u8>>, frame: Vec<u8>) {
+    let state = self.inner.lock();
+    match Message::decode_v1(&frame).unwrap() {
+      Message::Sync(SyncMessage::SyncStep1(state_vector)) => {
+        let update = state
+          .collab
+          .lock()
+          .transact()
+          .encode_state_as_update_v1(&state_vector);
+        let _ = client_tx.unbounded_send(Message::Sync(SyncMessage::SyncStep2(update)).encode_v1());
+      },
+      Message::Sync(SyncMessage::SyncStep2(update))
+      | Message::Sync(SyncMessage::Update(update)) => {
+        {
+          let collab = state.collab.lock();
+          let mut txn = collab.try_transaction_mut().unwrap();
+          txn
+            .try_apply_update(Update::decode_v1(&update).unwrap())
+            .unwrap();
+        }
+        state.broadcast(
+          client_id,
+          Message::Sync(SyncMessage::Update(update)).encode_v1(),
+        );
+      },
+      Message::Awareness(_) => state.broadcast(client_id, frame),
+      msg => tracing::trace!("{} ignore the message: {:?}", self.object_id, msg),
+    }
+  }
+}
+
+impl ServerState {
+  fn broadcast(&self, from: usize, frame: Vec<u8>) {
+    for (client_id, client_tx) in &self.clients {
+      if *client_id != from {
+        let _ = client_tx.unbounded_send(frame.clone());
+      }
+    }
+  }
+}
+
+/// Create a collab that syncs with the server through the [YSyncPlugin]. The closure is called
+/// before the collab is connected, so the data it inserts is synced during the handshake.
+pub fn connect_collab<F>(server: &LoopbackServer, uid: i64, f: F) -> Arc<MutexCollab>
+where
+  F: FnOnce(&Collab),
+{
+  let collab = Collab::new(uid, &server.object_id, "1", vec![], false);
+  f(&collab);
+  let collab = Arc::new(MutexCollab::new(collab));
+  let (sink, stream) = server.connect();
+  let plugin = YSyncPlugin::new(&server.object_id, Arc::downgrade(&collab), sink, stream);
+  collab.lock().add_plugin(Box::new(plugin));
+  collab.lock().initialize();
+  collab
+}
+
+/// Wait until the condition is true. Panic if it's still false after a few seconds.
+pub async fn wait_until<F>(mut condition: F)
+where
+  F: FnMut() -> bool,
+{
+  for _ in 0..100 {
+    if condition() {
+      return;
+    }
+    tokio::time::sleep(Duration::from_millis(30)).await;
+  }
+  panic!("the condition is not met in time");
+}
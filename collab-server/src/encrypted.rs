@@ -0,0 +1,162 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use collab_plugins::cloud_storage::{EncryptedDocState, EncryptedUpdate};
+use parking_lot::Mutex;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::trace;
+
+use crate::error::ServerError;
+use crate::group::SubscriberId;
+use crate::storage::CollabServerStorage;
+
+/// Keeps the update logs of the end-to-end encrypted documents. The server can't read the blobs,
+/// so instead of applying them to a document like a [CollabGroup](crate::group::CollabGroup), it
+/// appends them to the log of the document and broadcasts them to the other clients. The clients
+/// compact the log into an encrypted snapshot.
+///
+/// The log of a document is loaded from the [CollabServerStorage] when it's first used, and each
+/// change is saved to the storage before it's applied in memory.
+pub(crate) struct EncryptedLogs {
+  storage: Arc<dyn CollabServerStorage>,
+  logs: Mutex<HashMap<String, EncryptedLog>>,
+}
+
+struct EncryptedLog {
+  snapshot: Option<Vec<u8>>,
+  updates: Vec<EncryptedUpdate>,
+  /// The seq of the last update that is appended to the log.
+  last_seq: i64,
+  /// The seq of the last update that is compacted into the snapshot.
+  snapshot_seq: i64,
+  subscribers: HashMap<SubscriberId, UnboundedSender<Vec<u8>>>,
+}
+
+impl EncryptedLog {
+  fn from_doc_state(doc_state: EncryptedDocState) -> Self {
+    // The seqs of the updates follow each other, and the compaction removes the ones up to the
+    // seq of the snapshot, so the remaining updates start right after it.
+    let snapshot_seq = doc_state
+      .updates
+      .first()
+      .map(|update| update.seq - 1)
+      .unwrap_or(doc_state.last_seq);
+    Self {
+      snapshot: doc_state.snapshot,
+      updates: doc_state.updates,
+      last_seq: doc_state.last_seq,
+      snapshot_seq,
+      subscribers: HashMap::new(),
+    }
+  }
+}
+
+impl EncryptedLogs {
+  pub(crate) fn new(storage: Arc<dyn CollabServerStorage>) -> Self {
+    Self {
+      storage,
+      logs: Mutex::new(HashMap::new()),
+    }
+  }
+
+  /// Append the blob to the log of the document and send it to the other subscribers. Return the
+  /// seq of the blob.
+  pub(crate) fn append(
+    &self,
+    object_id: &str,
+    from: SubscriberId,
+    blob: Vec<u8>,
+  ) -> Result<i64, ServerError> {
+    let mut logs = self.logs.lock();
+    let log = self.get_or_load(&mut logs, object_id)?;
+    let update = EncryptedUpdate {
+      seq: log.last_seq + 1,
+      blob,
+    };
+    self.storage.push_encrypted_update(object_id, &update)?;
+    log.last_seq = update.seq;
+    log
+      .subscribers
+      .retain(|subscriber_id, tx| *subscriber_id == from || tx.send(update.blob.clone()).is_ok());
+    let seq = update.seq;
+    log.updates.push(update);
+    Ok(seq)
+  }
+
+  pub(crate) fn doc_state(&self, object_id: &str) -> Result<EncryptedDocState, ServerError> {
+    let mut logs = self.logs.lock();
+    let log = self.get_or_load(&mut logs, object_id)?;
+    Ok(EncryptedDocState {
+      snapshot: log.snapshot.clone(),
+      updates: log.updates.clone(),
+      last_seq: log.last_seq,
+    })
+  }
+
+  /// Replace the snapshot and the updates up to `last_seq` with the new snapshot. The snapshot of
+  /// a client that didn't see the last compaction is rejected, because it misses the updates that
+  /// were compacted.
+  pub(crate) fn compact(
+    &self,
+    object_id: &str,
+    snapshot: Vec<u8>,
+    last_seq: i64,
+  ) -> Result<(), ServerError> {
+    let mut logs = self.logs.lock();
+    let log = self.get_or_load(&mut logs, object_id)?;
+    if last_seq < log.snapshot_seq || last_seq > log.last_seq {
+      return Err(ServerError::StaleSnapshot {
+        object_id: object_id.to_string(),
+        last_seq,
+      });
+    }
+    self
+      .storage
+      .compact_encrypted_log(object_id, &snapshot, last_seq)?;
+    log.updates.retain(|update| update.seq > last_seq);
+    log.snapshot = Some(snapshot);
+    log.snapshot_seq = last_seq;
+    trace!(
+      "{} compact the encrypted updates up to {}, {} left",
+      object_id,
+      last_seq,
+      log.updates.len()
+    );
+    Ok(())
+  }
+
+  pub(crate) fn subscribe(
+    &self,
+    object_id: &str,
+    subscriber_id: SubscriberId,
+    blob_tx: UnboundedSender<Vec<u8>>,
+  ) -> Result<(), ServerError> {
+    let mut logs = self.logs.lock();
+    self
+      .get_or_load(&mut logs, object_id)?
+      .subscribers
+      .insert(subscriber_id, blob_tx);
+    Ok(())
+  }
+
+  pub(crate) fn unsubscribe(&self, object_id: &str, subscriber_id: SubscriberId) {
+    if let Some(log) = self.logs.lock().get_mut(object_id) {
+      log.subscribers.remove(&subscriber_id);
+    }
+  }
+
+  fn get_or_load<'a>(
+    &self,
+    logs: &'a mut HashMap<String, EncryptedLog>,
+    object_id: &str,
+  ) -> Result<&'a mut EncryptedLog, ServerError> {
+    match logs.entry(object_id.to_string()) {
+      Entry::Occupied(entry) => Ok(entry.into_mut()),
+      Entry::Vacant(entry) => {
+        let doc_state = self.storage.load_encrypted_log(object_id)?;
+        Ok(entry.insert(EncryptedLog::from_doc_state(doc_state)))
+      },
+    }
+  }
+}
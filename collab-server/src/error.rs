@@ -0,0 +1,23 @@
+#[derive(Debug, thiserror::Error)]
+pub enum ServerError {
+  #[error(transparent)]
+  Collab(#[from] collab::error::CollabError),
+
+  #[error(transparent)]
+  Persistence(#[from] collab_plugins::local_storage::kv::PersistenceError),
+
+  #[error("failed to decode message: {0}")]
+  Decoding(#[from] yrs::encoding::read::Error),
+
+  #[error(transparent)]
+  IO(#[from] std::io::Error),
+
+  #[error("Invalid handshake: {0}")]
+  InvalidHandshake(String),
+
+  #[error("{object_id}: the snapshot up to {last_seq} is stale")]
+  StaleSnapshot { object_id: String, last_seq: i64 },
+
+  #[error(transparent)]
+  Internal(#[from] anyhow::Error),
+}
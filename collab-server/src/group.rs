@@ -0,0 +1,204 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use collab::core::collab::{MutexCollab, TransactionMutExt};
+use collab::core::origin::CollabOrigin;
+use collab::error::CollabError;
+use collab::preclude::Collab;
+use collab_plugins::sync_plugin::{Message, SyncMessage, WorkspaceMessage};
+use parking_lot::Mutex;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{error, trace};
+use yrs::block::ClientID;
+use yrs::updates::decoder::Decode;
+use yrs::{ReadTxn, StateVector, Transact, Update};
+
+use crate::error::ServerError;
+use crate::storage::CollabServerStorage;
+
+pub(crate) type SubscriberId = u64;
+
+/// A document that is hosted by the server and the clients that are connected to it. The updates
+/// and the awareness states of a client are applied to the document and broadcast to the other
+/// clients.
+pub(crate) struct CollabGroup {
+  object_id: String,
+  collab: MutexCollab,
+  storage: Arc<dyn CollabServerStorage>,
+  subscribers: Mutex<HashMap<SubscriberId, Subscriber>>,
+}
+
+struct Subscriber {
+  /// The messages are addressed to the document, so a connection can subscribe to many
+  /// documents.
+  msg_tx: UnboundedSender<WorkspaceMessage>,
+  /// The awareness clients of the subscriber. Their states are removed when the subscriber leaves.
+  awareness_clients: HashSet<ClientID>,
+}
+
+impl CollabGroup {
+  pub(crate) fn open(
+    object_id: &str,
+    storage: Arc<dyn CollabServerStorage>,
+  ) -> Result<Self, ServerError> {
+    let collab = Collab::new_with_origin(CollabOrigin::Server, object_id, vec![], false);
+    {
+      let mut txn = collab.get_doc().transact_mut();
+      storage.load_doc(object_id, &mut txn)?;
+    }
+    Ok(Self {
+      object_id: object_id.to_string(),
+      collab: MutexCollab::new(collab),
+      storage,
+      subscribers: Mutex::new(HashMap::new()),
+    })
+  }
+
+  /// Add a subscriber. Like a y-websocket server, the group starts the sync by sending its state
+  /// vector and the awareness states of the other clients.
+  pub(crate) fn subscribe(
+    &self,
+    subscriber_id: SubscriberId,
+    msg_tx: UnboundedSender<WorkspaceMessage>,
+  ) {
+    {
+      let collab = self.collab.lock();
+      let state_vector = collab.transact().state_vector();
+      let _ = msg_tx.send(WorkspaceMessage::new(
+        &self.object_id,
+        Message::Sync(SyncMessage::SyncStep1(state_vector)),
+      ));
+      let awareness = collab.get_awareness();
+      if !awareness.get_states().is_empty() {
+        if let Ok(update) = awareness.update() {
+          let _ = msg_tx.send(WorkspaceMessage::new(
+            &self.object_id,
+            Message::Awareness(update),
+          ));
+        }
+      }
+    }
+    self.subscribers.lock().insert(
+      subscriber_id,
+      Subscriber {
+        msg_tx,
+        awareness_clients: HashSet::new(),
+      },
+    );
+  }
+
+  /// Remove the subscriber and tell the other clients that its awareness clients are gone.
+  pub(crate) fn unsubscribe(&self, subscriber_id: SubscriberId) {
+    let subscriber = match self.subscribers.lock().remove(&subscriber_id) {
+      None => return,
+      Some(subscriber) => subscriber,
+    };
+    if subscriber.awareness_clients.is_empty() {
+      return;
+    }
+
+    let mut collab = self.collab.lock();
+    let awareness = collab.get_mut_awareness();
+    for client_id in &subscriber.awareness_clients {
+      awareness.remove_state(*client_id);
+    }
+    if let Ok(update) = awareness.update_with_clients(subscriber.awareness_clients) {
+      self.broadcast(subscriber_id, Message::Awareness(update));
+    }
+  }
+
+  pub(crate) fn num_of_subscribers(&self) -> usize {
+    self.subscribers.lock().len()
+  }
+
+  /// Handle a message of the subscriber. Return the reply to the message if it needs one.
+  pub(crate) fn handle_message(
+    &self,
+    subscriber_id: SubscriberId,
+    msg: Message,
+  ) -> Result<Option<Message>, ServerError> {
+    match msg {
+      Message::Sync(SyncMessage::SyncStep1(state_vector)) => {
+        let update = self.encode_state_as_update(&state_vector);
+        Ok(Some(Message::Sync(SyncMessage::SyncStep2(update))))
+      },
+      Message::Sync(SyncMessage::SyncStep2(update))
+      | Message::Sync(SyncMessage::Update(update)) => {
+        self.apply_update(subscriber_id, update)?;
+        Ok(None)
+      },
+      Message::Awareness(update) => {
+        let mut collab = self.collab.lock();
+        if let Some(subscriber) = self.subscribers.lock().get_mut(&subscriber_id) {
+          subscriber
+            .awareness_clients
+            .extend(update.clients().keys().cloned());
+        }
+        collab
+          .get_mut_awareness()
+          .apply_update(update.clone(), &CollabOrigin::Empty)
+          .map_err(CollabError::from)?;
+        self.broadcast(subscriber_id, Message::Awareness(update));
+        Ok(None)
+      },
+      Message::AwarenessQuery => {
+        let update = self
+          .collab
+          .lock()
+          .get_awareness()
+          .update()
+          .map_err(CollabError::from)?;
+        Ok(Some(Message::Awareness(update)))
+      },
+      msg => {
+        trace!("{} ignore the message: {:?}", self.object_id, msg);
+        Ok(None)
+      },
+    }
+  }
+
+  /// Apply the update of the subscriber, save it to the storage and send it to the other
+  /// subscribers.
+  pub(crate) fn apply_update(
+    &self,
+    subscriber_id: SubscriberId,
+    update: Vec<u8>,
+  ) -> Result<(), ServerError> {
+    let decoded_update = Update::decode_v1(&update)?;
+    // Hold the lock until the update is broadcast, so the subscribers receive the updates in the
+    // same order as they are applied.
+    let collab = self.collab.lock();
+    {
+      let mut txn = collab.try_transaction_mut()?;
+      txn.try_apply_update(decoded_update)?;
+    }
+    if let Err(err) = self.storage.push_update(&self.object_id, &update) {
+      error!("{} failed to save the update: {}", self.object_id, err);
+    }
+    self.broadcast(subscriber_id, Message::Sync(SyncMessage::Update(update)));
+    Ok(())
+  }
+
+  /// Return the updates that are missing in the given state vector. Pass the default state vector
+  /// to get the whole document.
+  pub(crate) fn encode_state_as_update(&self, state_vector: &StateVector) -> Vec<u8> {
+    self
+      .collab
+      .lock()
+      .transact()
+      .encode_state_as_update_v1(state_vector)
+  }
+
+  pub(crate) fn object_id(&self) -> &str {
+    &self.object_id
+  }
+
+  fn broadcast(&self, from: SubscriberId, msg: Message) {
+    let msg = WorkspaceMessage::new(&self.object_id, msg);
+    for (subscriber_id, subscriber) in self.subscribers.lock().iter() {
+      if *subscriber_id != from {
+        let _ = subscriber.msg_tx.send(msg.clone());
+      }
+    }
+  }
+}
@@ -0,0 +1,13 @@
+pub use error::*;
+pub use remote_storage::*;
+pub use server::*;
+pub use storage::*;
+pub use tcp::*;
+
+mod encrypted;
+mod error;
+mod group;
+mod remote_storage;
+mod server;
+mod storage;
+mod tcp;
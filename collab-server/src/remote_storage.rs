@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{anyhow, Error};
+use async_trait::async_trait;
+use collab::core::collab::DataSource;
+use collab_entity::CollabObject;
+use collab_plugins::cloud_storage::{
+  decode_payload, encode_payload, EncryptedDocState, PayloadCompression, RemoteCollabSnapshot,
+  RemoteCollabState, RemoteCollabStorage, RemoteUpdateReceiver, DEFAULT_COMPRESSION_THRESHOLD,
+};
+use collab_plugins::sync_plugin::{Message, SyncMessage};
+use parking_lot::Mutex;
+use tokio::sync::mpsc::unbounded_channel;
+use tracing::error;
+
+use crate::group::SubscriberId;
+use crate::server::CollabServer;
+
+/// A [RemoteCollabStorage] that is backed by a [CollabServer] in the same process, so the cloud
+/// storage plugins can be tested against a real server instead of a hand written mock.
+///
+/// Each [ServerRemoteStorage] acts as one client of the server. The updates it sends are
+/// broadcast to the other clients and the updates of the other clients are received through
+/// [RemoteCollabStorage::subscribe_remote_updates]. The server doesn't keep snapshots.
+///
+/// Like a cloud transport, it accepts the payloads that are compressed with zstd and compresses
+/// the doc state and the remote updates it returns when the client asks for it.
+pub struct ServerRemoteStorage {
+  server: CollabServer,
+  is_encrypted: bool,
+  is_enable: AtomicBool,
+  subscriber_ids: Mutex<HashMap<String, SubscriberId>>,
+}
+
+impl ServerRemoteStorage {
+  pub fn new(server: CollabServer) -> Self {
+    Self {
+      server,
+      is_encrypted: false,
+      is_enable: AtomicBool::new(true),
+      subscriber_ids: Mutex::new(HashMap::new()),
+    }
+  }
+
+  /// Create a storage for the end-to-end encrypted documents. The server appends the blobs it
+  /// receives to the encrypted update logs of the documents without reading them.
+  pub fn new_encrypted(server: CollabServer) -> Self {
+    Self {
+      is_encrypted: true,
+      ..Self::new(server)
+    }
+  }
+
+  /// Disable the storage to simulate that the client is offline.
+  pub fn set_enable(&self, is_enable: bool) {
+    self.is_enable.store(is_enable, Ordering::SeqCst);
+  }
+
+  fn subscribe_updates(
+    &self,
+    object: &CollabObject,
+    compression: PayloadCompression,
+  ) -> Option<RemoteUpdateReceiver> {
+    let group = self.server.get_or_open_group(&object.object_id).ok()?;
+    let subscriber_id = self.subscriber_id(&object.object_id);
+    let (msg_tx, mut msg_rx) = unbounded_channel();
+    group.subscribe(subscriber_id, msg_tx);
+
+    let (update_tx, update_rx) = unbounded_channel();
+    let object_id = object.object_id.clone();
+    tokio::spawn(async move {
+      while let Some(msg) = msg_rx.recv().await {
+        if let Message::Sync(SyncMessage::Update(update)) = msg.msg {
+          let update = match encode_payload(compression, DEFAULT_COMPRESSION_THRESHOLD, update) {
+            Ok(update) => update,
+            Err(err) => {
+              error!("{}: failed to encode the update: {:?}", object_id, err);
+              continue;
+            },
+          };
+          if update_tx.send(update).is_err() {
+            break;
+          }
+        }
+      }
+      group.unsubscribe(subscriber_id);
+    });
+    Some(update_rx)
+  }
+
+  fn subscriber_id(&self, object_id: &str) -> SubscriberId {
+    *self
+      .subscriber_ids
+      .lock()
+      .entry(object_id.to_string())
+      .or_insert_with(|| self.server.next_subscriber_id())
+  }
+}
+
+#[async_trait]
+impl RemoteCollabStorage for ServerRemoteStorage {
+  fn is_enable(&self) -> bool {
+    self.is_enable.load(Ordering::SeqCst)
+  }
+
+  async fn get_doc_state(&self, object: &CollabObject) -> Result<DataSource, Error> {
+    if self.is_encrypted {
+      return Err(anyhow!(
+        "{} the encrypted document can only be read by the clients",
+        object.object_id
+      ));
+    }
+    let doc_state = self.server.get_doc_state(&object.object_id)?;
+    Ok(DataSource::DocStateV1(doc_state))
+  }
+
+  async fn get_snapshots(&self, _object_id: &str, _limit: usize) -> Vec<RemoteCollabSnapshot> {
+    vec![]
+  }
+
+  async fn get_collab_state(&self, _object_id: &str) -> Result<Option<RemoteCollabState>, Error> {
+    Ok(None)
+  }
+
+  async fn create_snapshot(&self, object: &CollabObject, _snapshot: Vec<u8>) -> Result<i64, Error> {
+    Err(anyhow!(
+      "{} the collab server doesn't support snapshots",
+      object.object_id
+    ))
+  }
+
+  async fn send_update(
+    &self,
+    object: &CollabObject,
+    _id: u64,
+    update: Vec<u8>,
+  ) -> Result<(), Error> {
+    if self.is_encrypted {
+      let subscriber_id = self.subscriber_id(&object.object_id);
+      self
+        .server
+        .encrypted_logs()
+        .append(&object.object_id, subscriber_id, update)?;
+      return Ok(());
+    }
+    let update = decode_payload(&update)?;
+    let group = self.server.get_or_open_group(&object.object_id)?;
+    group.apply_update(self.subscriber_id(&object.object_id), update)?;
+    Ok(())
+  }
+
+  async fn send_init_sync(
+    &self,
+    object: &CollabObject,
+    id: u64,
+    init_update: Vec<u8>,
+  ) -> Result<(), Error> {
+    self.send_update(object, id, init_update).await
+  }
+
+  fn subscribe_remote_updates(&self, object: &CollabObject) -> Option<RemoteUpdateReceiver> {
+    if self.is_encrypted {
+      let (blob_tx, blob_rx) = unbounded_channel();
+      let subscriber_id = self.subscriber_id(&object.object_id);
+      self
+        .server
+        .encrypted_logs()
+        .subscribe(&object.object_id, subscriber_id, blob_tx)
+        .ok()?;
+      return Some(blob_rx);
+    }
+    self.subscribe_updates(object, PayloadCompression::None)
+  }
+
+  fn supported_compressions(&self) -> Vec<PayloadCompression> {
+    vec![PayloadCompression::Zstd]
+  }
+
+  fn download_compressions(&self) -> Vec<PayloadCompression> {
+    if self.is_encrypted {
+      return vec![];
+    }
+    vec![PayloadCompression::Zstd]
+  }
+
+  async fn get_compressed_doc_state(
+    &self,
+    object: &CollabObject,
+    compression: PayloadCompression,
+  ) -> Result<Vec<u8>, Error> {
+    let doc_state = match self.get_doc_state(object).await? {
+      DataSource::DocStateV1(doc_state) => doc_state,
+      _ => vec![],
+    };
+    Ok(encode_payload(
+      compression,
+      DEFAULT_COMPRESSION_THRESHOLD,
+      doc_state,
+    )?)
+  }
+
+  fn subscribe_compressed_remote_updates(
+    &self,
+    object: &CollabObject,
+    compression: PayloadCompression,
+  ) -> Option<RemoteUpdateReceiver> {
+    if self.is_encrypted {
+      return None;
+    }
+    self.subscribe_updates(object, compression)
+  }
+
+  fn supports_encryption(&self) -> bool {
+    self.is_encrypted
+  }
+
+  async fn get_encrypted_doc_state(
+    &self,
+    object: &CollabObject,
+  ) -> Result<EncryptedDocState, Error> {
+    if !self.is_encrypted {
+      return Err(anyhow!("{} is not encrypted", object.object_id));
+    }
+    Ok(self.server.get_encrypted_doc_state(&object.object_id)?)
+  }
+
+  async fn compact_encrypted_updates(
+    &self,
+    object: &CollabObject,
+    snapshot: Vec<u8>,
+    last_seq: i64,
+  ) -> Result<(), Error> {
+    if !self.is_encrypted {
+      return Err(anyhow!("{} is not encrypted", object.object_id));
+    }
+    self
+      .server
+      .encrypted_logs()
+      .compact(&object.object_id, snapshot, last_seq)?;
+    Ok(())
+  }
+}
+
+impl Drop for ServerRemoteStorage {
+  fn drop(&mut self) {
+    for (object_id, subscriber_id) in self.subscriber_ids.lock().drain() {
+      if self.is_encrypted {
+        self
+          .server
+          .encrypted_logs()
+          .unsubscribe(&object_id, subscriber_id);
+        continue;
+      }
+      if let Ok(group) = self.server.get_or_open_group(&object_id) {
+        group.unsubscribe(subscriber_id);
+      }
+    }
+  }
+}
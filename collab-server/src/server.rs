@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use collab_plugins::cloud_storage::EncryptedDocState;
+use collab_plugins::sync_plugin::{Message, WorkspaceMessage};
+use collab_plugins::CollabKVDB;
+use futures::channel::mpsc::{unbounded, SendError, UnboundedReceiver, UnboundedSender};
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use parking_lot::Mutex;
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver as MsgReceiver};
+use tracing::{error, trace};
+use yrs::updates::decoder::Decode;
+use yrs::updates::encoder::Encode;
+use yrs::StateVector;
+
+use crate::encrypted::EncryptedLogs;
+use crate::error::ServerError;
+use crate::group::{CollabGroup, SubscriberId};
+use crate::storage::{CollabServerStorage, KVServerStorage, MemoryServerStorage};
+use crate::tcp::framed;
+
+/// A sync server for local development and tests. It hosts many documents and syncs each of them
+/// between the clients that are connected to it.
+///
+/// The clients speak the y-sync protocol, so a collab that uses the
+/// [YSyncPlugin](collab_plugins::sync_plugin::YSyncPlugin) can connect to it over an in-process
+/// channel with [CollabServer::connect_local] or over TCP with [connect_tcp](crate::connect_tcp).
+/// Each connection is bound to one document, except the workspace connections that are opened with
+/// [CollabServer::connect_local_workspace] or served with [CollabServer::serve_workspace].
+#[derive(Clone)]
+pub struct CollabServer {
+  storage: Arc<dyn CollabServerStorage>,
+  groups: Arc<Mutex<HashMap<String, Arc<CollabGroup>>>>,
+  encrypted_logs: Arc<EncryptedLogs>,
+  subscriber_id_counter: Arc<AtomicU64>,
+}
+
+impl CollabServer {
+  pub fn new(storage: Arc<dyn CollabServerStorage>) -> Self {
+    Self {
+      encrypted_logs: Arc::new(EncryptedLogs::new(storage.clone())),
+      storage,
+      groups: Arc::new(Mutex::new(HashMap::new())),
+      subscriber_id_counter: Arc::new(AtomicU64::new(1)),
+    }
+  }
+
+  /// Create a server that keeps the documents in memory.
+  pub fn new_in_memory() -> Self {
+    Self::new(Arc::new(MemoryServerStorage))
+  }
+
+  /// Create a server that persists the documents in the [CollabKVDB] under the given uid.
+  pub fn new_with_kv_db(uid: i64, collab_db: Arc<CollabKVDB>) -> Self {
+    Self::new(Arc::new(KVServerStorage::new(uid, collab_db)))
+  }
+
+  /// Return the ids of the documents that were opened since the server started.
+  pub fn object_ids(&self) -> Vec<String> {
+    self.groups.lock().keys().cloned().collect()
+  }
+
+  /// Return the number of connections to the document.
+  pub fn num_of_connections(&self, object_id: &str) -> usize {
+    self
+      .groups
+      .lock()
+      .get(object_id)
+      .map(|group| group.num_of_subscribers())
+      .unwrap_or(0)
+  }
+
+  /// Return the whole state of the document, encoded with the v1 encoding.
+  pub fn get_doc_state(&self, object_id: &str) -> Result<Vec<u8>, ServerError> {
+    let group = self.get_or_open_group(object_id)?;
+    Ok(group.encode_state_as_update(&StateVector::default()))
+  }
+
+  /// Return the encrypted update log of an end-to-end encrypted document. The blobs are sent by
+  /// the clients through a [ServerRemoteStorage](crate::ServerRemoteStorage) that is created with
+  /// `new_encrypted`.
+  pub fn get_encrypted_doc_state(&self, object_id: &str) -> Result<EncryptedDocState, ServerError> {
+    self.encrypted_logs.doc_state(object_id)
+  }
+
+  /// Open an in-process connection to the document. The returned sink and stream are meant to be
+  /// passed to the [YSyncPlugin](collab_plugins::sync_plugin::YSyncPlugin).
+  pub fn connect_local(
+    &self,
+    object_id: &str,
+  ) -> Result<(UnboundedSender<Vec<u8>>, LocalConnectionStream), ServerError> {
+    let group = self.get_or_open_group(object_id)?;
+    let (client_tx, server_rx) = unbounded::<Vec<u8>>();
+    let (server_tx, client_rx) = unbounded::<Vec<u8>>();
+    let server = self.clone();
+    tokio::spawn(async move {
+      server
+        .serve_group(group, server_tx, LocalConnectionStream(server_rx))
+        .await;
+    });
+    Ok((client_tx, LocalConnectionStream(client_rx)))
+  }
+
+  /// Open an in-process connection to all the documents of a workspace. The returned sink and
+  /// stream are meant to be passed to the
+  /// [WorkspaceSyncCoordinator](collab_plugins::sync_plugin::WorkspaceSyncCoordinator).
+  pub fn connect_local_workspace(&self) -> (UnboundedSender<Vec<u8>>, LocalConnectionStream) {
+    let (client_tx, server_rx) = unbounded::<Vec<u8>>();
+    let (server_tx, client_rx) = unbounded::<Vec<u8>>();
+    let server = self.clone();
+    tokio::spawn(async move {
+      server
+        .serve_workspace(server_tx, LocalConnectionStream(server_rx))
+        .await;
+    });
+    (client_tx, LocalConnectionStream(client_rx))
+  }
+
+  /// Serve a connection to the document. It returns when the connection is closed.
+  ///
+  /// Each frame of the connection is a message of the y-sync protocol, so any transport that can
+  /// be turned into a sink and a stream of binary frames, like a WebSocket, can be served.
+  pub async fn serve<Si, St, E>(
+    &self,
+    object_id: &str,
+    sink: Si,
+    stream: St,
+  ) -> Result<(), ServerError>
+  where
+    Si: Sink<Vec<u8>, Error = E> + Send + Unpin + 'static,
+    St: Stream<Item = Result<Vec<u8>, E>> + Unpin,
+    E: Display + Send + 'static,
+  {
+    let group = self.get_or_open_group(object_id)?;
+    self.serve_group(group, sink, stream).await;
+    Ok(())
+  }
+
+  /// Serve a connection to many documents. It returns when the connection is closed.
+  ///
+  /// Each frame of the connection is a [WorkspaceMessage], which is a message of the y-sync
+  /// protocol addressed to a document. The connection joins a document when it sends the first
+  /// message of the document, and leaves all of them when it's closed.
+  pub async fn serve_workspace<Si, St, E>(&self, sink: Si, mut stream: St)
+  where
+    Si: Sink<Vec<u8>, Error = E> + Send + Unpin + 'static,
+    St: Stream<Item = Result<Vec<u8>, E>> + Unpin,
+    E: Display + Send + 'static,
+  {
+    let subscriber_id = self.next_subscriber_id();
+    let (msg_tx, msg_rx) = unbounded_channel();
+    tokio::spawn(send_messages(subscriber_id, msg_rx, sink, |msg| {
+      msg.encode_v1()
+    }));
+
+    let mut groups: HashMap<String, Arc<CollabGroup>> = HashMap::new();
+    while let Some(frame) = stream.next().await {
+      let frame = match frame {
+        Ok(frame) => frame,
+        Err(err) => {
+          error!("{} failed to receive the message: {}", subscriber_id, err);
+          break;
+        },
+      };
+      let WorkspaceMessage { object_id, msg } = match WorkspaceMessage::decode_v1(&frame) {
+        Ok(msg) => msg,
+        Err(err) => {
+          error!("{} failed to decode the message: {}", subscriber_id, err);
+          continue;
+        },
+      };
+      let group = match groups.get(&object_id) {
+        Some(group) => group.clone(),
+        None => match self.get_or_open_group(&object_id) {
+          Ok(group) => {
+            group.subscribe(subscriber_id, msg_tx.clone());
+            groups.insert(object_id.clone(), group.clone());
+            group
+          },
+          Err(err) => {
+            error!("{} failed to open {}: {}", subscriber_id, object_id, err);
+            continue;
+          },
+        },
+      };
+      match group.handle_message(subscriber_id, msg) {
+        Ok(Some(reply)) => {
+          let _ = msg_tx.send(WorkspaceMessage::new(&object_id, reply));
+        },
+        Ok(None) => {},
+        Err(err) => error!("{} failed to handle the message: {}", subscriber_id, err),
+      }
+    }
+    for group in groups.values() {
+      group.unsubscribe(subscriber_id);
+    }
+  }
+
+  /// Listen for TCP connections on the address. Return the local address of the listener, which
+  /// is useful when binding to port 0.
+  ///
+  /// A TCP connection sends the object id of the document in its first frame, like the room name
+  /// in the url of a y-websocket connection.
+  pub async fn listen<A: ToSocketAddrs>(
+    &self,
+    addr: A,
+  ) -> Result<std::net::SocketAddr, ServerError> {
+    let listener = TcpListener::bind(addr).await?;
+    let local_addr = listener.local_addr()?;
+    let server = self.clone();
+    tokio::spawn(async move {
+      loop {
+        match listener.accept().await {
+          Ok((socket, peer_addr)) => {
+            trace!("accept the connection from {}", peer_addr);
+            let server = server.clone();
+            tokio::spawn(async move {
+              if let Err(err) = server.serve_tcp(socket).await {
+                error!("failed to serve the connection from {}: {}", peer_addr, err);
+              }
+            });
+          },
+          Err(err) => {
+            error!("failed to accept the connection: {}", err);
+            break;
+          },
+        }
+      }
+    });
+    Ok(local_addr)
+  }
+
+  pub(crate) fn get_or_open_group(&self, object_id: &str) -> Result<Arc<CollabGroup>, ServerError> {
+    let mut groups = self.groups.lock();
+    if let Some(group) = groups.get(object_id) {
+      return Ok(group.clone());
+    }
+    let group = Arc::new(CollabGroup::open(object_id, self.storage.clone())?);
+    groups.insert(object_id.to_string(), group.clone());
+    Ok(group)
+  }
+
+  pub(crate) fn encrypted_logs(&self) -> &EncryptedLogs {
+    &self.encrypted_logs
+  }
+
+  pub(crate) fn next_subscriber_id(&self) -> SubscriberId {
+    self.subscriber_id_counter.fetch_add(1, Ordering::SeqCst)
+  }
+
+  async fn serve_tcp(&self, socket: TcpStream) -> Result<(), ServerError> {
+    let (sink, mut stream) = framed(socket);
+    let object_id = match stream.next().await {
+      None => return Ok(()),
+      Some(frame) => String::from_utf8(frame?)
+        .map_err(|_| ServerError::InvalidHandshake("the object id is not utf8".to_string()))?,
+    };
+    self.serve(&object_id, sink, stream).await
+  }
+
+  async fn serve_group<Si, St, E>(&self, group: Arc<CollabGroup>, sink: Si, mut stream: St)
+  where
+    Si: Sink<Vec<u8>, Error = E> + Send + Unpin + 'static,
+    St: Stream<Item = Result<Vec<u8>, E>> + Unpin,
+    E: Display + Send + 'static,
+  {
+    let subscriber_id = self.next_subscriber_id();
+    let (msg_tx, msg_rx) = unbounded_channel();
+    // The connection is bound to the group, so the object id is not sent
+    tokio::spawn(send_messages(subscriber_id, msg_rx, sink, |msg| {
+      msg.msg.encode_v1()
+    }));
+    group.subscribe(subscriber_id, msg_tx.clone());
+
+    while let Some(frame) = stream.next().await {
+      let frame = match frame {
+        Ok(frame) => frame,
+        Err(err) => {
+          error!("{} failed to receive the message: {}", subscriber_id, err);
+          break;
+        },
+      };
+      let msg = match Message::decode_v1(&frame) {
+        Ok(msg) => msg,
+        Err(err) => {
+          error!("{} failed to decode the message: {}", subscriber_id, err);
+          continue;
+        },
+      };
+      match group.handle_message(subscriber_id, msg) {
+        Ok(Some(reply)) => {
+          let _ = msg_tx.send(WorkspaceMessage::new(group.object_id(), reply));
+        },
+        Ok(None) => {},
+        Err(err) => error!("{} failed to handle the message: {}", subscriber_id, err),
+      }
+    }
+    // The sink is closed after the subscriber and this sender are dropped
+    group.unsubscribe(subscriber_id);
+  }
+}
+
+async fn send_messages<Si, E>(
+  subscriber_id: SubscriberId,
+  mut msg_rx: MsgReceiver<WorkspaceMessage>,
+  mut sink: Si,
+  encode: fn(&WorkspaceMessage) -> Vec<u8>,
+) where
+  Si: Sink<Vec<u8>, Error = E> + Unpin,
+  E: Display,
+{
+  while let Some(msg) = msg_rx.recv().await {
+    if let Err(err) = sink.send(encode(&msg)).await {
+      error!("{} failed to send the message: {}", subscriber_id, err);
+      return;
+    }
+  }
+  let _ = sink.close().await;
+}
+
+/// The stream half of an in-process connection. See [CollabServer::connect_local].
+pub struct LocalConnectionStream(UnboundedReceiver<Vec<u8>>);
+
+impl Stream for LocalConnectionStream {
+  type Item = Result<Vec<u8>, SendError>;
+
+  fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    self.0.poll_next_unpin(cx).map(|frame| frame.map(Ok))
+  }
+}
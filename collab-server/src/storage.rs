@@ -0,0 +1,208 @@
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use collab_plugins::cloud_storage::{EncryptedDocState, EncryptedUpdate};
+use collab_plugins::local_storage::kv::doc::CollabKVAction;
+use collab_plugins::local_storage::kv::keys::{
+  ENCRYPTED_LOG_SNAPSHOT, ENCRYPTED_LOG_SPACE, ENCRYPTED_LOG_UPDATE, TERMINATOR,
+};
+use collab_plugins::local_storage::kv::{KVEntry, KVStore, KVTransactionDB};
+use collab_plugins::CollabKVDB;
+use yrs::TransactionMut;
+
+use crate::error::ServerError;
+
+/// The storage of the documents that are hosted by the [CollabServer](crate::CollabServer). A
+/// document is loaded once when the first client connects to it, then every update that the
+/// server receives is pushed to the storage.
+pub trait CollabServerStorage: Send + Sync + 'static {
+  /// Load the document into the transaction. A document that doesn't exist yet is left empty.
+  fn load_doc(&self, object_id: &str, txn: &mut TransactionMut) -> Result<(), ServerError>;
+
+  /// Save an update of the document, encoded with the v1 encoding.
+  fn push_update(&self, object_id: &str, update: &[u8]) -> Result<(), ServerError>;
+
+  /// Load the encrypted update log of an end-to-end encrypted document. A document that doesn't
+  /// exist yet has an empty log.
+  fn load_encrypted_log(&self, object_id: &str) -> Result<EncryptedDocState, ServerError>;
+
+  /// Save an encrypted update that is appended to the log of the document.
+  fn push_encrypted_update(
+    &self,
+    object_id: &str,
+    update: &EncryptedUpdate,
+  ) -> Result<(), ServerError>;
+
+  /// Replace the snapshot and the updates up to `last_seq` of the log with the encrypted snapshot.
+  fn compact_encrypted_log(
+    &self,
+    object_id: &str,
+    snapshot: &[u8],
+    last_seq: i64,
+  ) -> Result<(), ServerError>;
+}
+
+/// Keeps the documents in memory only. The documents are lost when the server is dropped.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryServerStorage;
+
+impl CollabServerStorage for MemoryServerStorage {
+  fn load_doc(&self, _object_id: &str, _txn: &mut TransactionMut) -> Result<(), ServerError> {
+    Ok(())
+  }
+
+  fn push_update(&self, _object_id: &str, _update: &[u8]) -> Result<(), ServerError> {
+    Ok(())
+  }
+
+  fn load_encrypted_log(&self, _object_id: &str) -> Result<EncryptedDocState, ServerError> {
+    Ok(EncryptedDocState::default())
+  }
+
+  fn push_encrypted_update(
+    &self,
+    _object_id: &str,
+    _update: &EncryptedUpdate,
+  ) -> Result<(), ServerError> {
+    Ok(())
+  }
+
+  fn compact_encrypted_log(
+    &self,
+    _object_id: &str,
+    _snapshot: &[u8],
+    _last_seq: i64,
+  ) -> Result<(), ServerError> {
+    Ok(())
+  }
+}
+
+/// Persists the documents in a [CollabKVDB]. All the documents are stored under the given uid, so
+/// a server that is restarted with the same database serves the same documents.
+///
+/// The encrypted update logs are stored next to the documents, one entry for the snapshot and one
+/// for each update, so appending an update doesn't rewrite the log. The snapshot entry starts with
+/// the seq of the last update that it contains.
+#[derive(Clone)]
+pub struct KVServerStorage {
+  uid: i64,
+  collab_db: Arc<CollabKVDB>,
+}
+
+impl KVServerStorage {
+  pub fn new(uid: i64, collab_db: Arc<CollabKVDB>) -> Self {
+    Self { uid, collab_db }
+  }
+}
+
+impl CollabServerStorage for KVServerStorage {
+  fn load_doc(&self, object_id: &str, txn: &mut TransactionMut) -> Result<(), ServerError> {
+    let is_exist = self.collab_db.read_txn().is_exist(self.uid, object_id);
+    if is_exist {
+      self
+        .collab_db
+        .read_txn()
+        .load_doc_with_txn(self.uid, object_id, txn)?;
+    } else {
+      self
+        .collab_db
+        .with_write_txn(|w_db_txn| w_db_txn.create_new_doc(self.uid, object_id, &*txn))?;
+    }
+    Ok(())
+  }
+
+  fn push_update(&self, object_id: &str, update: &[u8]) -> Result<(), ServerError> {
+    self.collab_db.with_write_txn(|w_db_txn| {
+      w_db_txn.push_update(self.uid, object_id, update)?;
+      Ok(())
+    })?;
+    Ok(())
+  }
+
+  fn load_encrypted_log(&self, object_id: &str) -> Result<EncryptedDocState, ServerError> {
+    let read_txn = self.collab_db.read_txn();
+    let mut doc_state = EncryptedDocState::default();
+    let snapshot_key = make_encrypted_log_key(self.uid, object_id, ENCRYPTED_LOG_SNAPSHOT);
+    if let Some(value) = read_txn.get(snapshot_key)? {
+      if value.len() < SEQ_LEN {
+        return Err(ServerError::Internal(anyhow!(
+          "{}: the encrypted snapshot is corrupted",
+          object_id
+        )));
+      }
+      doc_state.last_seq = decode_seq(&value[..SEQ_LEN]);
+      doc_state.snapshot = Some(value[SEQ_LEN..].to_vec());
+    }
+
+    let start = make_encrypted_update_key(self.uid, object_id, 0);
+    let end = make_encrypted_update_key(self.uid, object_id, i64::MAX);
+    for entry in read_txn.range(start.as_slice()..end.as_slice())? {
+      let key = entry.key();
+      let seq = decode_seq(&key[key.len() - SEQ_LEN..]);
+      doc_state.last_seq = doc_state.last_seq.max(seq);
+      doc_state.updates.push(EncryptedUpdate {
+        seq,
+        blob: entry.value().to_vec(),
+      });
+    }
+    Ok(doc_state)
+  }
+
+  fn push_encrypted_update(
+    &self,
+    object_id: &str,
+    update: &EncryptedUpdate,
+  ) -> Result<(), ServerError> {
+    let key = make_encrypted_update_key(self.uid, object_id, update.seq);
+    self
+      .collab_db
+      .with_write_txn(|w_db_txn| w_db_txn.insert(key, &update.blob))?;
+    Ok(())
+  }
+
+  fn compact_encrypted_log(
+    &self,
+    object_id: &str,
+    snapshot: &[u8],
+    last_seq: i64,
+  ) -> Result<(), ServerError> {
+    let start = make_encrypted_update_key(self.uid, object_id, 0);
+    let end = make_encrypted_update_key(self.uid, object_id, last_seq + 1);
+    let mut value = Vec::with_capacity(SEQ_LEN + snapshot.len());
+    value.extend_from_slice(&last_seq.to_be_bytes());
+    value.extend_from_slice(snapshot);
+    self.collab_db.with_write_txn(|w_db_txn| {
+      w_db_txn.remove_range(&start, &end)?;
+      w_db_txn.insert(
+        make_encrypted_log_key(self.uid, object_id, ENCRYPTED_LOG_SNAPSHOT),
+        value,
+      )?;
+      Ok(())
+    })?;
+    Ok(())
+  }
+}
+
+const SEQ_LEN: usize = 8;
+
+fn make_encrypted_log_key(uid: i64, object_id: &str, tag: u8) -> Vec<u8> {
+  let mut key = vec![ENCRYPTED_LOG_SPACE];
+  key.extend_from_slice(&uid.to_be_bytes());
+  key.extend_from_slice(object_id.as_bytes());
+  key.push(TERMINATOR);
+  key.push(tag);
+  key
+}
+
+/// The seqs are positive, so the big endian keys are ordered by seq.
+fn make_encrypted_update_key(uid: i64, object_id: &str, seq: i64) -> Vec<u8> {
+  let mut key = make_encrypted_log_key(uid, object_id, ENCRYPTED_LOG_UPDATE);
+  key.extend_from_slice(&seq.to_be_bytes());
+  key
+}
+
+fn decode_seq(bytes: &[u8]) -> i64 {
+  let mut seq = [0u8; SEQ_LEN];
+  seq.copy_from_slice(bytes);
+  i64::from_be_bytes(seq)
+}
@@ -0,0 +1,41 @@
+use std::io;
+
+use bytes::Bytes;
+use futures::{future, Sink, SinkExt, Stream, StreamExt};
+use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+
+use crate::error::ServerError;
+
+/// Open a TCP connection to the document that is hosted by the
+/// [CollabServer](crate::CollabServer) at the address. The returned sink and stream are meant to
+/// be passed to the [YSyncPlugin](collab_plugins::sync_plugin::YSyncPlugin).
+pub async fn connect_tcp<A: ToSocketAddrs>(
+  addr: A,
+  object_id: &str,
+) -> Result<
+  (
+    impl Sink<Vec<u8>, Error = io::Error> + Send + Unpin + 'static,
+    impl Stream<Item = Result<Vec<u8>, io::Error>> + Send + Unpin + 'static,
+  ),
+  ServerError,
+> {
+  let socket = TcpStream::connect(addr).await?;
+  let (mut sink, stream) = framed(socket);
+  sink.send(object_id.as_bytes().to_vec()).await?;
+  Ok((sink, stream))
+}
+
+/// Split the TCP connection into a sink and a stream of frames. Each frame is prefixed with its
+/// length.
+pub(crate) fn framed(
+  socket: TcpStream,
+) -> (
+  impl Sink<Vec<u8>, Error = io::Error> + Send + Unpin + 'static,
+  impl Stream<Item = Result<Vec<u8>, io::Error>> + Send + Unpin + 'static,
+) {
+  let (sink, stream) = Framed::new(socket, LengthDelimitedCodec::new()).split();
+  let sink = sink.with(|frame: Vec<u8>| future::ready(Ok::<_, io::Error>(Bytes::from(frame))));
+  let stream = stream.map(|frame| frame.map(|frame| frame.to_vec()));
+  (sink, stream)
+}
@@ -0,0 +1 @@
+mod server_test;
@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use collab_entity::{CollabObject, CollabType};
+use collab_plugins::cloud_storage::{
+  decode_payload, decode_payload_with_limit, encode_payload, PayloadCompression,
+  RemoteCollabStorage,
+};
+use collab_plugins::CollabKVDB;
+use collab_server::{CollabServer, ServerRemoteStorage};
+use serde_json::json;
+use tempfile::TempDir;
+
+use crate::server_test::util::{
+  new_cloud_plugin, open_cloud_collab, open_disk_collab, server_json, wait_until,
+};
+
+#[test]
+fn payload_roundtrip_test() {
+  // The small payloads are only tagged
+  let small = vec![1u8; 10];
+  let encoded = encode_payload(PayloadCompression::Zstd, 1024, small.clone()).unwrap();
+  assert_eq!(encoded.len(), small.len() + 1);
+  assert_eq!(decode_payload(&encoded).unwrap(), small);
+
+  let big = "hello world ".repeat(1000).into_bytes();
+  let encoded = encode_payload(PayloadCompression::Zstd, 1024, big.clone()).unwrap();
+  assert!(encoded.len() < big.len() / 10);
+  assert_eq!(decode_payload(&encoded).unwrap(), big);
+
+  // Without a negotiated compression, the payload is sent as is
+  let encoded = encode_payload(PayloadCompression::None, 1024, big.clone()).unwrap();
+  assert_eq!(encoded, big);
+
+  assert!(decode_payload(&[9, 1, 2]).is_err());
+  assert!(decode_payload(&[]).is_err());
+}
+
+#[test]
+fn decompressed_payload_size_limit_test() {
+  let big = vec![0u8; 1024 * 1024];
+  let encoded = encode_payload(PayloadCompression::Zstd, 1024, big.clone()).unwrap();
+  assert!(encoded.len() < 1024);
+
+  // The payload is rejected as soon as it's decompressed to more than the limit
+  assert!(decode_payload_with_limit(&encoded, big.len() - 1).is_err());
+  assert_eq!(decode_payload_with_limit(&encoded, big.len()).unwrap(), big);
+
+  // The raw payloads are not decompressed
+  let encoded = encode_payload(PayloadCompression::Zstd, usize::MAX, big.clone()).unwrap();
+  assert_eq!(decode_payload_with_limit(&encoded, 1).unwrap(), big);
+}
+
+#[test]
+fn negotiate_compression_test() {
+  let zstd = PayloadCompression::Zstd;
+  assert_eq!(PayloadCompression::negotiate(zstd, &[zstd]), zstd);
+  assert_eq!(
+    PayloadCompression::negotiate(zstd, &[]),
+    PayloadCompression::None
+  );
+  assert_eq!(
+    PayloadCompression::negotiate(PayloadCompression::None, &[zstd]),
+    PayloadCompression::None
+  );
+}
+
+#[tokio::test]
+async fn compress_large_update_test() {
+  let tempdir = TempDir::new().unwrap();
+  let collab_db = Arc::new(CollabKVDB::open(tempdir.path()).unwrap());
+  let server = CollabServer::new_in_memory();
+  let storage = Arc::new(ServerRemoteStorage::new(server.clone()));
+
+  let collab = open_disk_collab(&collab_db, "doc");
+  let plugin = new_cloud_plugin(&collab_db, storage, &collab);
+  let payload_metrics = plugin.subscribe_payload_metrics();
+  collab.lock().add_plugin(Box::new(plugin));
+  collab.lock().initialize();
+
+  let text = "hello world ".repeat(2000);
+  collab.lock().insert("text", text.clone());
+  wait_until(|| server_json(&server, "doc") == json!({ "text": text })).await;
+
+  let metrics = payload_metrics.borrow().clone();
+  assert!(metrics.num_of_compressed_payloads >= 1);
+  assert!(metrics.raw_bytes >= text.len() as u64);
+  assert!(metrics.sent_bytes < metrics.raw_bytes / 5);
+  assert!(metrics.compression_ratio() < 0.2);
+}
+
+#[tokio::test]
+async fn compress_doc_state_and_remote_updates_test() {
+  let tempdir_1 = TempDir::new().unwrap();
+  let tempdir_2 = TempDir::new().unwrap();
+  let server = CollabServer::new_in_memory();
+  let text = "hello world ".repeat(2000);
+
+  let collab_db_1 = Arc::new(CollabKVDB::open(tempdir_1.path()).unwrap());
+  let storage_1 = Arc::new(ServerRemoteStorage::new(server.clone()));
+  let (client_1, _) = open_cloud_collab(&collab_db_1, storage_1, "doc", None);
+  client_1.lock().insert("1", text.clone());
+  wait_until(|| server_json(&server, "doc") == json!({ "1": text })).await;
+
+  // The doc state is compressed by the server
+  let storage_2 = Arc::new(ServerRemoteStorage::new(server.clone()));
+  let object = CollabObject::new(
+    1,
+    "doc".to_string(),
+    CollabType::Document,
+    "w1".to_string(),
+    "1".to_string(),
+  );
+  let doc_state = storage_2
+    .get_compressed_doc_state(&object, PayloadCompression::Zstd)
+    .await
+    .unwrap();
+  assert!(doc_state.len() < text.len() / 5);
+  assert_eq!(
+    decode_payload(&doc_state).unwrap(),
+    server.get_doc_state("doc").unwrap()
+  );
+
+  // The client receives the compressed doc state on the init sync, then the compressed updates
+  let collab_db_2 = Arc::new(CollabKVDB::open(tempdir_2.path()).unwrap());
+  let (client_2, _) = open_cloud_collab(&collab_db_2, storage_2, "doc", None);
+  wait_until(|| client_2.lock().to_json_value() == json!({ "1": text })).await;
+
+  client_1.lock().insert("2", text.clone());
+  wait_until(|| client_2.lock().to_json_value() == json!({ "1": text, "2": text })).await;
+}
@@ -0,0 +1,220 @@
+use std::sync::Arc;
+
+use collab::preclude::Collab;
+use collab_entity::{CollabObject, CollabType};
+use collab_plugins::cloud_storage::postgres::SupabaseDBPlugin;
+use collab_plugins::cloud_storage::{
+  decode_payload, EncryptionError, WorkspaceKey, WorkspaceKeyRing,
+};
+use collab_plugins::CollabKVDB;
+use collab_server::{CollabServer, ServerRemoteStorage};
+use serde_json::json;
+use tempfile::TempDir;
+use yrs::updates::decoder::Decode;
+use yrs::Update;
+
+use crate::server_test::util::{open_disk_collab, open_encrypted_collab, wait_until};
+
+#[test]
+fn encrypt_and_decrypt_blob_test() {
+  let key_ring = WorkspaceKeyRing::new("w1", WorkspaceKey::generate(1));
+  let blob = key_ring.encrypt("doc", b"hello world").unwrap();
+  assert_eq!(WorkspaceKeyRing::blob_key_id(&blob).unwrap(), 1);
+  assert_eq!(key_ring.decrypt("doc", &blob).unwrap(), b"hello world");
+
+  // A blob can't be moved to another object or decrypted with another key
+  assert!(matches!(
+    key_ring.decrypt("other_doc", &blob),
+    Err(EncryptionError::Decrypt)
+  ));
+  let other_key_ring = WorkspaceKeyRing::new("w1", WorkspaceKey::generate(1));
+  assert!(matches!(
+    other_key_ring.decrypt("doc", &blob),
+    Err(EncryptionError::Decrypt)
+  ));
+  assert!(matches!(
+    key_ring.decrypt("doc", &blob[..10]),
+    Err(EncryptionError::InvalidBlob(_))
+  ));
+}
+
+#[test]
+fn rotate_workspace_key_test() {
+  let key_ring = WorkspaceKeyRing::new("w1", WorkspaceKey::generate(1));
+  let old_blob = key_ring.encrypt("doc", b"old").unwrap();
+  key_ring.rotate(WorkspaceKey::generate(2)).unwrap();
+  assert!(key_ring.rotate(WorkspaceKey::generate(2)).is_err());
+
+  let new_blob = key_ring.encrypt("doc", b"new").unwrap();
+  assert_eq!(WorkspaceKeyRing::blob_key_id(&new_blob).unwrap(), 2);
+  assert_eq!(key_ring.decrypt("doc", &old_blob).unwrap(), b"old");
+
+  assert!(matches!(
+    key_ring.remove_key(2),
+    Err(EncryptionError::RemoveCurrentKey(2))
+  ));
+  key_ring.remove_key(1).unwrap();
+  assert!(matches!(
+    key_ring.decrypt("doc", &old_blob),
+    Err(EncryptionError::UnknownKey(1))
+  ));
+  assert_eq!(key_ring.decrypt("doc", &new_blob).unwrap(), b"new");
+}
+
+#[tokio::test]
+async fn server_only_stores_encrypted_blobs_test() {
+  let server = CollabServer::new_in_memory();
+  let key_ring = Arc::new(WorkspaceKeyRing::new("w1", WorkspaceKey::generate(1)));
+  let tempdir_1 = TempDir::new().unwrap();
+  let collab_db_1 = Arc::new(CollabKVDB::open(tempdir_1.path()).unwrap());
+  let tempdir_2 = TempDir::new().unwrap();
+  let collab_db_2 = Arc::new(CollabKVDB::open(tempdir_2.path()).unwrap());
+
+  let storage_1 = Arc::new(ServerRemoteStorage::new_encrypted(server.clone()));
+  let collab_1 = open_encrypted_collab(&collab_db_1, storage_1, "doc", key_ring.clone());
+  collab_1.lock().insert("1", "top secret");
+  let expected = json!({"1": "top secret"});
+  wait_until(|| decrypt_server_json(&server, &key_ring, "doc") == expected).await;
+  for blob in server.get_encrypted_doc_state("doc").unwrap().blobs() {
+    assert!(!contains(blob, b"top secret"));
+  }
+  // The server doesn't have a readable document
+  assert!(server.get_doc_state("doc").unwrap().len() <= 2);
+
+  // Another device reads the document from the encrypted update log
+  let storage_2 = Arc::new(ServerRemoteStorage::new_encrypted(server.clone()));
+  let collab_2 = open_encrypted_collab(&collab_db_2, storage_2, "doc", key_ring);
+  wait_until(|| collab_2.lock().to_json_value() == expected).await;
+
+  // The updates are exchanged between the devices through the server
+  collab_2.lock().insert("2", "classified");
+  let expected = json!({"1": "top secret", "2": "classified"});
+  wait_until(|| collab_1.lock().to_json_value() == expected).await;
+}
+
+#[tokio::test]
+async fn compact_encrypted_updates_after_rotating_key_test() {
+  let server = CollabServer::new_in_memory();
+  let key_ring = Arc::new(WorkspaceKeyRing::new("w1", WorkspaceKey::generate(1)));
+  let tempdir_1 = TempDir::new().unwrap();
+  let collab_db_1 = Arc::new(CollabKVDB::open(tempdir_1.path()).unwrap());
+  let storage_1 = Arc::new(ServerRemoteStorage::new_encrypted(server.clone()));
+  let collab_1 = open_encrypted_collab(&collab_db_1, storage_1, "doc", key_ring.clone());
+  for i in 0..5 {
+    collab_1.lock().insert(&i.to_string(), i as i64);
+  }
+  let expected = json!({"0": 0, "1": 1, "2": 2, "3": 3, "4": 4});
+  wait_until(|| decrypt_server_json(&server, &key_ring, "doc") == expected).await;
+  assert!(server
+    .get_encrypted_doc_state("doc")
+    .unwrap()
+    .snapshot
+    .is_none());
+
+  // The init sync of the next device compacts the blobs that are encrypted with the old key
+  key_ring.rotate(WorkspaceKey::generate(2)).unwrap();
+  let tempdir_2 = TempDir::new().unwrap();
+  let collab_db_2 = Arc::new(CollabKVDB::open(tempdir_2.path()).unwrap());
+  let storage_2 = Arc::new(ServerRemoteStorage::new_encrypted(server.clone()));
+  let collab_2 = open_encrypted_collab(&collab_db_2, storage_2, "doc", key_ring.clone());
+  wait_until(|| collab_2.lock().to_json_value() == expected).await;
+  wait_until(|| {
+    let doc_state = server.get_encrypted_doc_state("doc").unwrap();
+    doc_state.snapshot.is_some()
+      && doc_state
+        .blobs()
+        .all(|blob| WorkspaceKeyRing::blob_key_id(blob).unwrap() == 2)
+  })
+  .await;
+
+  // The old key is not needed anymore
+  key_ring.remove_key(1).unwrap();
+  let tempdir_3 = TempDir::new().unwrap();
+  let collab_db_3 = Arc::new(CollabKVDB::open(tempdir_3.path()).unwrap());
+  let storage_3 = Arc::new(ServerRemoteStorage::new_encrypted(server.clone()));
+  let collab_3 = open_encrypted_collab(&collab_db_3, storage_3, "doc", key_ring);
+  wait_until(|| collab_3.lock().to_json_value() == expected).await;
+}
+
+#[tokio::test]
+async fn restore_encrypted_updates_after_restart_test() {
+  let server_tempdir = TempDir::new().unwrap();
+  let server_db = Arc::new(CollabKVDB::open(server_tempdir.path()).unwrap());
+  let server = CollabServer::new_with_kv_db(1, server_db.clone());
+  let key_ring = Arc::new(WorkspaceKeyRing::new("w1", WorkspaceKey::generate(1)));
+  let tempdir_1 = TempDir::new().unwrap();
+  let collab_db_1 = Arc::new(CollabKVDB::open(tempdir_1.path()).unwrap());
+  let storage_1 = Arc::new(ServerRemoteStorage::new_encrypted(server.clone()));
+  let collab_1 = open_encrypted_collab(&collab_db_1, storage_1, "doc", key_ring.clone());
+  collab_1.lock().insert("1", "top secret");
+  let expected = json!({"1": "top secret"});
+  wait_until(|| decrypt_server_json(&server, &key_ring, "doc") == expected).await;
+  let doc_state = server.get_encrypted_doc_state("doc").unwrap();
+  drop(collab_1);
+  drop(server);
+
+  // The restarted server serves the same log, and the next blobs follow it
+  let server = CollabServer::new_with_kv_db(1, server_db);
+  let restored_doc_state = server.get_encrypted_doc_state("doc").unwrap();
+  assert_eq!(restored_doc_state.last_seq, doc_state.last_seq);
+  assert!(restored_doc_state.blobs().eq(doc_state.blobs()));
+  let tempdir_2 = TempDir::new().unwrap();
+  let collab_db_2 = Arc::new(CollabKVDB::open(tempdir_2.path()).unwrap());
+  let storage_2 = Arc::new(ServerRemoteStorage::new_encrypted(server.clone()));
+  let collab_2 = open_encrypted_collab(&collab_db_2, storage_2, "doc", key_ring.clone());
+  wait_until(|| collab_2.lock().to_json_value() == expected).await;
+  collab_2.lock().insert("2", "classified");
+  let expected = json!({"1": "top secret", "2": "classified"});
+  wait_until(|| decrypt_server_json(&server, &key_ring, "doc") == expected).await;
+  let updates = server.get_encrypted_doc_state("doc").unwrap().updates;
+  assert!(updates
+    .windows(2)
+    .all(|pair| pair[1].seq == pair[0].seq + 1));
+}
+
+#[tokio::test]
+async fn encrypted_plugin_requires_encrypted_storage_test() {
+  let server = CollabServer::new_in_memory();
+  let key_ring = Arc::new(WorkspaceKeyRing::new("w1", WorkspaceKey::generate(1)));
+  let tempdir = TempDir::new().unwrap();
+  let collab_db = Arc::new(CollabKVDB::open(tempdir.path()).unwrap());
+  let collab = open_disk_collab(&collab_db, "doc");
+  let object = CollabObject::new(
+    1,
+    "doc".to_string(),
+    CollabType::Document,
+    "w1".to_string(),
+    "1".to_string(),
+  );
+  let result = SupabaseDBPlugin::new_with_encryption(
+    1,
+    object,
+    Arc::downgrade(&collab),
+    1,
+    Arc::new(ServerRemoteStorage::new(server)),
+    Arc::downgrade(&collab_db),
+    key_ring,
+  );
+  assert!(result.is_err());
+}
+
+/// Decrypt the encrypted update log of the document that is stored by the server.
+fn decrypt_server_json(
+  server: &CollabServer,
+  key_ring: &WorkspaceKeyRing,
+  object_id: &str,
+) -> serde_json::Value {
+  let doc_state = server.get_encrypted_doc_state(object_id).unwrap();
+  let collab = Collab::new(1, object_id, "1", vec![], false);
+  collab.with_origin_transact_mut(|txn| {
+    for blob in doc_state.blobs() {
+      let payload = decode_payload(&key_ring.decrypt(object_id, blob).unwrap()).unwrap();
+      txn.apply_update(Update::decode_v1(&payload).unwrap());
+    }
+  });
+  collab.to_json_value()
+}
+
+fn contains(data: &[u8], needle: &[u8]) -> bool {
+  data.windows(needle.len()).any(|window| window == needle)
+}
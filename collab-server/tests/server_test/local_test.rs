@@ -0,0 +1,83 @@
+use collab_server::CollabServer;
+use serde_json::json;
+
+use crate::server_test::util::{connect_local_collab, server_json, wait_until};
+
+#[tokio::test]
+async fn multiple_clients_sync_test() {
+  let server = CollabServer::new_in_memory();
+  let client_1 = connect_local_collab(&server, 1, "doc");
+  let client_2 = connect_local_collab(&server, 2, "doc");
+  let client_3 = connect_local_collab(&server, 3, "doc");
+  assert_eq!(server.num_of_connections("doc"), 3);
+
+  client_1.lock().insert("1", "a");
+  client_2.lock().insert("2", "b");
+  let expected = json!({"1": "a", "2": "b"});
+  wait_until(|| client_3.lock().to_json_value() == expected).await;
+  wait_until(|| client_1.lock().to_json_value() == expected).await;
+  wait_until(|| client_2.lock().to_json_value() == expected).await;
+  assert_eq!(server_json(&server, "doc"), expected);
+}
+
+#[tokio::test]
+async fn late_client_receives_existing_state_test() {
+  let server = CollabServer::new_in_memory();
+  let client_1 = connect_local_collab(&server, 1, "doc");
+  client_1.lock().insert("1", "a");
+  wait_until(|| server_json(&server, "doc") == json!({"1": "a"})).await;
+
+  let client_2 = connect_local_collab(&server, 2, "doc");
+  wait_until(|| client_2.lock().to_json_value() == json!({"1": "a"})).await;
+}
+
+#[tokio::test]
+async fn documents_are_isolated_test() {
+  let server = CollabServer::new_in_memory();
+  let doc_a_client = connect_local_collab(&server, 1, "a");
+  let doc_b_client = connect_local_collab(&server, 1, "b");
+
+  doc_a_client.lock().insert("name", "a");
+  doc_b_client.lock().insert("name", "b");
+  wait_until(|| server_json(&server, "a") == json!({"name": "a"})).await;
+  wait_until(|| server_json(&server, "b") == json!({"name": "b"})).await;
+  assert_eq!(doc_a_client.lock().to_json_value(), json!({"name": "a"}));
+
+  let mut object_ids = server.object_ids();
+  object_ids.sort();
+  assert_eq!(object_ids, vec!["a".to_string(), "b".to_string()]);
+}
+
+#[tokio::test]
+async fn rebroadcast_awareness_test() {
+  let server = CollabServer::new_in_memory();
+  let client_1 = connect_local_collab(&server, 1, "doc");
+  let client_2 = connect_local_collab(&server, 2, "doc");
+
+  let client_id = client_1.lock().get_awareness().client_id();
+  client_1
+    .lock()
+    .get_mut_awareness()
+    .set_local_state(json!({"cursor": 1}));
+  wait_until(|| {
+    client_2.lock().get_awareness().get_states().get(&client_id) == Some(&json!({"cursor": 1}))
+  })
+  .await;
+
+  // A client that connects later receives the awareness states that the server knows
+  let client_3 = connect_local_collab(&server, 3, "doc");
+  wait_until(|| {
+    client_3.lock().get_awareness().get_states().get(&client_id) == Some(&json!({"cursor": 1}))
+  })
+  .await;
+
+  client_1.lock().clean_awareness_state();
+  wait_until(|| {
+    !client_2
+      .lock()
+      .get_awareness()
+      .get_states()
+      .contains_key(&client_id)
+  })
+  .await;
+}
@@ -0,0 +1,10 @@
+mod compression_test;
+mod encryption_test;
+mod local_test;
+mod outbox_test;
+mod persistence_test;
+mod reachability_test;
+mod remote_storage_test;
+mod tcp_test;
+mod util;
+mod workspace_test;
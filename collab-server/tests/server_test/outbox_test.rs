@@ -0,0 +1,41 @@
+use std::sync::Arc;
+
+use collab_plugins::cloud_storage::CollabOutbox;
+use collab_plugins::CollabKVDB;
+use collab_server::{CollabServer, ServerRemoteStorage};
+use serde_json::json;
+use tempfile::TempDir;
+
+use crate::server_test::util::{open_cloud_collab, server_json, wait_until};
+
+#[tokio::test]
+async fn offline_changes_are_sent_after_restart_test() {
+  let tempdir = TempDir::new().unwrap();
+  let collab_db = Arc::new(CollabKVDB::open(tempdir.path()).unwrap());
+  let server = CollabServer::new_in_memory();
+
+  // Edit while offline, then quit the app
+  let storage = Arc::new(ServerRemoteStorage::new(server.clone()));
+  storage.set_enable(false);
+  let (collab, _) = open_cloud_collab(&collab_db, storage.clone(), "doc", None);
+  collab.lock().insert("1", "offline");
+  tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+  drop(collab);
+  drop(storage);
+  assert_eq!(server_json(&server, "doc"), json!({}));
+
+  // The pending change is sent once the app is restarted online
+  let storage = Arc::new(ServerRemoteStorage::new(server.clone()));
+  let (collab, _) = open_cloud_collab(&collab_db, storage, "doc", None);
+  wait_until(|| server_json(&server, "doc") == json!({"1": "offline"})).await;
+
+  // The acked change is not pending anymore
+  let outbox = CollabOutbox::new(1, "doc", Arc::downgrade(&collab_db));
+  wait_until(|| {
+    outbox
+      .pending_update(&collab.lock().transact())
+      .unwrap()
+      .is_none()
+  })
+  .await;
+}
@@ -0,0 +1,29 @@
+use std::sync::Arc;
+
+use collab_plugins::CollabKVDB;
+use collab_server::CollabServer;
+use serde_json::json;
+use tempfile::TempDir;
+
+use crate::server_test::util::{connect_local_collab, server_json, wait_until};
+
+#[tokio::test]
+async fn restore_documents_after_restart_test() {
+  let tempdir = TempDir::new().unwrap();
+  let collab_db = Arc::new(CollabKVDB::open(tempdir.path()).unwrap());
+
+  let server = CollabServer::new_with_kv_db(1, collab_db.clone());
+  let client = connect_local_collab(&server, 1, "doc");
+  client.lock().insert("1", "a");
+  client.lock().insert("2", "b");
+  wait_until(|| server_json(&server, "doc") == json!({"1": "a", "2": "b"})).await;
+  drop(client);
+  drop(server);
+
+  let server = CollabServer::new_with_kv_db(1, collab_db);
+  assert_eq!(server_json(&server, "doc"), json!({"1": "a", "2": "b"}));
+
+  // A new client receives the persisted state
+  let client = connect_local_collab(&server, 2, "doc");
+  wait_until(|| client.lock().to_json_value() == json!({"1": "a", "2": "b"})).await;
+}
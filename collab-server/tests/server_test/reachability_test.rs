@@ -0,0 +1,62 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use collab_plugins::connect_state::{CollabConnectReachability, CollabConnectState};
+use collab_plugins::CollabKVDB;
+use collab_server::{CollabServer, ServerRemoteStorage};
+use serde_json::json;
+use tempfile::TempDir;
+
+use crate::server_test::util::{connect_local_collab, open_cloud_collab, server_json, wait_until};
+
+#[tokio::test]
+async fn pause_sync_while_disconnected_test() {
+  let tempdir = TempDir::new().unwrap();
+  let collab_db = Arc::new(CollabKVDB::open(tempdir.path()).unwrap());
+  let server = CollabServer::new_in_memory();
+  let reachability = CollabConnectReachability::new();
+  reachability.set_state(CollabConnectState::Disconnected);
+
+  let storage = Arc::new(ServerRemoteStorage::new(server.clone()));
+  let (collab, sync_status) = open_cloud_collab(&collab_db, storage, "doc", Some(&reachability));
+  collab.lock().insert("1", "a");
+  wait_until(|| sync_status.borrow().pending_bytes > 0).await;
+
+  // Nothing is sent while disconnected
+  tokio::time::sleep(Duration::from_millis(1500)).await;
+  assert_eq!(server_json(&server, "doc"), json!({}));
+  assert!(sync_status.borrow().is_paused);
+  assert!(sync_status.borrow().last_ack_at.is_none());
+
+  reachability.set_state(CollabConnectState::Connected);
+  wait_until(|| server_json(&server, "doc") == json!({"1": "a"})).await;
+  wait_until(|| {
+    let status = sync_status.borrow();
+    status.is_synced() && status.last_ack_at.is_some() && !status.is_paused
+  })
+  .await;
+}
+
+#[tokio::test]
+async fn init_sync_after_reconnect_test() {
+  let tempdir = TempDir::new().unwrap();
+  let collab_db = Arc::new(CollabKVDB::open(tempdir.path()).unwrap());
+  let server = CollabServer::new_in_memory();
+  let reachability = CollabConnectReachability::new();
+  reachability.set_state(CollabConnectState::Disconnected);
+
+  let storage = Arc::new(ServerRemoteStorage::new(server.clone()));
+  let (collab, _) = open_cloud_collab(&collab_db, storage, "doc", Some(&reachability));
+  collab.lock().insert("1", "local");
+
+  // Another client edits the document while the collab is disconnected
+  let other_client = connect_local_collab(&server, 2, "doc");
+  other_client.lock().insert("2", "remote");
+  wait_until(|| server_json(&server, "doc") == json!({"2": "remote"})).await;
+
+  // The init sync after reconnecting exchanges the changes in both directions
+  reachability.set_state(CollabConnectState::Connected);
+  let expected = json!({"1": "local", "2": "remote"});
+  wait_until(|| collab.lock().to_json_value() == expected).await;
+  wait_until(|| server_json(&server, "doc") == expected).await;
+}
@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use collab::core::collab::DataSource;
+use collab::core::origin::CollabOrigin;
+use collab::preclude::Collab;
+use collab_entity::{CollabObject, CollabType};
+use collab_plugins::cloud_storage::RemoteCollabStorage;
+use collab_server::{CollabServer, ServerRemoteStorage};
+use serde_json::json;
+use yrs::{ReadTxn, StateVector};
+
+use crate::server_test::util::{connect_local_collab, server_json, wait_until};
+
+fn collab_object(object_id: &str) -> CollabObject {
+  CollabObject::new(
+    1,
+    object_id.to_string(),
+    CollabType::Document,
+    "w1".to_string(),
+    "1".to_string(),
+  )
+}
+
+fn collab_from_doc_state(object_id: &str, doc_state: DataSource) -> Collab {
+  Collab::new_with_source(CollabOrigin::Empty, object_id, doc_state, vec![], false).unwrap()
+}
+
+#[tokio::test]
+async fn remote_storage_sends_update_to_clients_test() {
+  let server = CollabServer::new_in_memory();
+  let storage = ServerRemoteStorage::new(server.clone());
+  let client = connect_local_collab(&server, 1, "doc");
+
+  let collab = Collab::new(2, "doc", "2", vec![], false);
+  collab.insert("from", "remote storage");
+  let update = collab
+    .transact()
+    .encode_state_as_update_v1(&StateVector::default());
+  storage
+    .send_init_sync(&collab_object("doc"), 1, update)
+    .await
+    .unwrap();
+
+  wait_until(|| client.lock().to_json_value() == json!({"from": "remote storage"})).await;
+  assert_eq!(
+    server_json(&server, "doc"),
+    json!({"from": "remote storage"})
+  );
+
+  let doc_state = storage.get_doc_state(&collab_object("doc")).await.unwrap();
+  assert_eq!(
+    collab_from_doc_state("doc", doc_state).to_json_value(),
+    json!({"from": "remote storage"})
+  );
+}
+
+#[tokio::test]
+async fn remote_storage_receives_updates_of_clients_test() {
+  let server = CollabServer::new_in_memory();
+  let storage = ServerRemoteStorage::new(server.clone());
+  let mut remote_updates = storage
+    .subscribe_remote_updates(&collab_object("doc"))
+    .unwrap();
+
+  let client = connect_local_collab(&server, 1, "doc");
+  client.lock().insert("from", "client");
+
+  let update = tokio::time::timeout(Duration::from_secs(3), remote_updates.recv())
+    .await
+    .unwrap()
+    .unwrap();
+  assert_eq!(
+    collab_from_doc_state("doc", DataSource::DocStateV1(update)).to_json_value(),
+    json!({"from": "client"})
+  );
+}
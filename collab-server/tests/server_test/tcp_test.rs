@@ -0,0 +1,35 @@
+use collab_server::{connect_tcp, CollabServer};
+use serde_json::json;
+
+use crate::server_test::util::{connect_collab, server_json, wait_until};
+
+#[tokio::test]
+async fn sync_over_tcp_test() {
+  let server = CollabServer::new_in_memory();
+  let addr = server.listen("127.0.0.1:0").await.unwrap();
+
+  let (sink, stream) = connect_tcp(addr, "doc").await.unwrap();
+  let client_1 = connect_collab(1, "doc", sink, stream);
+  let (sink, stream) = connect_tcp(addr, "doc").await.unwrap();
+  let client_2 = connect_collab(2, "doc", sink, stream);
+
+  client_1.lock().insert("1", "a");
+  wait_until(|| client_2.lock().to_json_value() == json!({"1": "a"})).await;
+  client_2.lock().insert("2", "b");
+  wait_until(|| client_1.lock().to_json_value() == json!({"1": "a", "2": "b"})).await;
+  assert_eq!(server_json(&server, "doc"), json!({"1": "a", "2": "b"}));
+}
+
+#[tokio::test]
+async fn tcp_and_local_clients_sync_test() {
+  let server = CollabServer::new_in_memory();
+  let addr = server.listen("127.0.0.1:0").await.unwrap();
+
+  let (sink, stream) = connect_tcp(addr, "doc").await.unwrap();
+  let tcp_client = connect_collab(1, "doc", sink, stream);
+  let (sink, stream) = server.connect_local("doc").unwrap();
+  let local_client = connect_collab(2, "doc", sink, stream);
+
+  tcp_client.lock().insert("from", "tcp");
+  wait_until(|| local_client.lock().to_json_value() == json!({"from": "tcp"})).await;
+}
@@ -0,0 +1,189 @@
+use std::fmt::Display;
+use std::sync::Arc;
+use std::time::Duration;
+
+use collab::core::collab::{DataSource, MutexCollab};
+use collab::core::origin::CollabOrigin;
+use collab::preclude::Collab;
+use collab_entity::{CollabObject, CollabType};
+use collab_plugins::cloud_storage::postgres::SupabaseDBPlugin;
+use collab_plugins::cloud_storage::{CollabSyncStatus, WorkspaceKeyRing};
+use collab_plugins::connect_state::CollabConnectReachability;
+use collab_plugins::local_storage::rocksdb::rocksdb_plugin::RocksdbDiskPlugin;
+use collab_plugins::sync_plugin::{SyncPriority, WorkspaceSyncCoordinator, YSyncPlugin};
+use collab_plugins::CollabKVDB;
+use collab_server::{CollabServer, ServerRemoteStorage};
+use futures::{Sink, Stream};
+use tokio::sync::watch;
+
+/// Create a collab that syncs with the server through the given connection.
+pub fn connect_collab<Si, St, E>(
+  uid: i64,
+  object_id: &str,
+  sink: Si,
+  stream: St,
+) -> Arc<MutexCollab>
+where
+  Si: Sink<Vec<u8>, Error = E> + Send + Unpin + 'static,
+  St: Stream<Item = Result<Vec<u8>, E>> + Send + Unpin + 'static,
+  E: Display + Send + 'static,
+{
+  let collab = Arc::new(MutexCollab::new(Collab::new(
+    uid,
+    object_id,
+    "1",
+    vec![],
+    false,
+  )));
+  let plugin = YSyncPlugin::new(object_id, Arc::downgrade(&collab), sink, stream);
+  collab.lock().add_plugin(Box::new(plugin));
+  collab.lock().initialize();
+  collab
+}
+
+pub fn connect_local_collab(server: &CollabServer, uid: i64, object_id: &str) -> Arc<MutexCollab> {
+  let (sink, stream) = server.connect_local(object_id).unwrap();
+  connect_collab(uid, object_id, sink, stream)
+}
+
+/// Create a collab that syncs with the server through the workspace connection of the
+/// coordinator.
+pub fn open_workspace_collab(
+  coordinator: &Arc<WorkspaceSyncCoordinator>,
+  object_id: &str,
+  priority: SyncPriority,
+) -> Arc<MutexCollab> {
+  let collab = Arc::new(MutexCollab::new(Collab::new(
+    1,
+    object_id,
+    "1",
+    vec![],
+    false,
+  )));
+  let plugin = coordinator.register(object_id, priority, Arc::downgrade(&collab));
+  collab.lock().add_plugin(Box::new(plugin));
+  collab.lock().initialize();
+  collab
+}
+
+/// Create a collab that is persisted in the given database. The collab is not initialized.
+pub fn open_disk_collab(collab_db: &Arc<CollabKVDB>, object_id: &str) -> Arc<MutexCollab> {
+  let collab = Arc::new(MutexCollab::new(Collab::new(
+    1,
+    object_id,
+    "1",
+    vec![],
+    false,
+  )));
+  let plugin = RocksdbDiskPlugin::new(
+    1,
+    object_id.to_string(),
+    CollabType::Document,
+    Arc::downgrade(collab_db),
+    None,
+  );
+  collab.lock().add_plugin(Box::new(plugin));
+  collab
+}
+
+/// Create a collab that is persisted in the given database and synced with the server through
+/// the cloud storage plugin.
+pub fn open_cloud_collab(
+  collab_db: &Arc<CollabKVDB>,
+  storage: Arc<ServerRemoteStorage>,
+  object_id: &str,
+  reachability: Option<&CollabConnectReachability>,
+) -> (Arc<MutexCollab>, watch::Receiver<CollabSyncStatus>) {
+  let collab = open_disk_collab(collab_db, object_id);
+  let mut plugin = new_cloud_plugin(collab_db, storage, &collab);
+  if let Some(reachability) = reachability {
+    plugin = plugin.with_reachability(reachability);
+  }
+  let sync_status = plugin.subscribe_sync_status();
+  collab.lock().add_plugin(Box::new(plugin));
+  collab.lock().initialize();
+  (collab, sync_status)
+}
+
+/// Create a collab that is persisted in the given database and synced with the server through
+/// the cloud storage plugin, with the updates encrypted by the key ring.
+pub fn open_encrypted_collab(
+  collab_db: &Arc<CollabKVDB>,
+  storage: Arc<ServerRemoteStorage>,
+  object_id: &str,
+  key_ring: Arc<WorkspaceKeyRing>,
+) -> Arc<MutexCollab> {
+  let collab = open_disk_collab(collab_db, object_id);
+  let object = CollabObject::new(
+    1,
+    object_id.to_string(),
+    CollabType::Document,
+    "w1".to_string(),
+    "1".to_string(),
+  );
+  let plugin = SupabaseDBPlugin::new_with_encryption(
+    1,
+    object,
+    Arc::downgrade(&collab),
+    1,
+    storage,
+    Arc::downgrade(collab_db),
+    key_ring,
+  )
+  .unwrap();
+  collab.lock().add_plugin(Box::new(plugin));
+  collab.lock().initialize();
+  collab
+}
+
+/// Create the cloud storage plugin of the collab. The plugin is not added to the collab.
+pub fn new_cloud_plugin(
+  collab_db: &Arc<CollabKVDB>,
+  storage: Arc<ServerRemoteStorage>,
+  collab: &Arc<MutexCollab>,
+) -> SupabaseDBPlugin {
+  let object_id = collab.lock().object_id.clone();
+  let object = CollabObject::new(
+    1,
+    object_id,
+    CollabType::Document,
+    "w1".to_string(),
+    "1".to_string(),
+  );
+  SupabaseDBPlugin::new(
+    1,
+    object,
+    Arc::downgrade(collab),
+    1,
+    storage,
+    Arc::downgrade(collab_db),
+  )
+}
+
+/// Return the json of the document that is hosted by the server.
+pub fn server_json(server: &CollabServer, object_id: &str) -> serde_json::Value {
+  let doc_state = server.get_doc_state(object_id).unwrap();
+  Collab::new_with_source(
+    CollabOrigin::Empty,
+    object_id,
+    DataSource::DocStateV1(doc_state),
+    vec![],
+    false,
+  )
+  .unwrap()
+  .to_json_value()
+}
+
+/// Wait until the condition is true. Panic if it's still false after a few seconds.
+pub async fn wait_until<F>(mut condition: F)
+where
+  F: FnMut() -> bool,
+{
+  for _ in 0..200 {
+    if condition() {
+      return;
+    }
+    tokio::time::sleep(Duration::from_millis(30)).await;
+  }
+  panic!("the condition is not met in time");
+}
@@ -0,0 +1,59 @@
+use collab_plugins::sync_plugin::{SyncPriority, WorkspaceSyncConfig, WorkspaceSyncCoordinator};
+use collab_server::CollabServer;
+use serde_json::json;
+
+use crate::server_test::util::{
+  connect_local_collab, open_workspace_collab, server_json, wait_until,
+};
+
+#[tokio::test]
+async fn sync_many_objects_over_one_connection_test() {
+  let server = CollabServer::new_in_memory();
+  let other_client = connect_local_collab(&server, 2, "doc_0");
+  other_client.lock().insert("0", "remote");
+  wait_until(|| server_json(&server, "doc_0") == json!({"0": "remote"})).await;
+
+  let (sink, stream) = server.connect_local_workspace();
+  let coordinator = WorkspaceSyncCoordinator::new(
+    "w1",
+    sink,
+    stream,
+    WorkspaceSyncConfig::new().with_max_concurrent_init_syncs(2),
+  );
+  let collabs = (0..10)
+    .map(|i| open_workspace_collab(&coordinator, &format!("doc_{}", i), SyncPriority::Normal))
+    .collect::<Vec<_>>();
+  wait_until(|| coordinator.progress().is_complete()).await;
+  assert_eq!(coordinator.progress().num_of_synced, 10);
+  assert_eq!(collabs[0].lock().to_json_value(), json!({"0": "remote"}));
+
+  for (i, collab) in collabs.iter().enumerate() {
+    collab.lock().insert(&i.to_string(), "local");
+  }
+  for i in 1..10 {
+    let object_id = format!("doc_{}", i);
+    wait_until(|| server_json(&server, &object_id)[i.to_string()] == json!("local")).await;
+  }
+
+  // The updates of the other clients are received through the workspace connection
+  let expected = json!({"0": "local", "1": "remote"});
+  other_client.lock().insert("1", "remote");
+  wait_until(|| other_client.lock().to_json_value() == expected).await;
+  wait_until(|| collabs[0].lock().to_json_value() == expected).await;
+}
+
+#[tokio::test]
+async fn leave_all_objects_after_close_test() {
+  let server = CollabServer::new_in_memory();
+  let (sink, stream) = server.connect_local_workspace();
+  let coordinator = WorkspaceSyncCoordinator::new("w1", sink, stream, WorkspaceSyncConfig::new());
+  let collabs = (0..3)
+    .map(|i| open_workspace_collab(&coordinator, &format!("doc_{}", i), SyncPriority::Normal))
+    .collect::<Vec<_>>();
+  wait_until(|| coordinator.progress().is_complete()).await;
+  assert_eq!(server.num_of_connections("doc_1"), 1);
+
+  drop(coordinator);
+  drop(collabs);
+  wait_until(|| (0..3).all(|i| server.num_of_connections(&format!("doc_{}", i)) == 0)).await;
+}
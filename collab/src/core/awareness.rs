@@ -1,8 +1,11 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::core::origin::CollabOrigin;
 use crate::error::CollabError;
@@ -12,6 +15,10 @@ use yrs::updates::decoder::{Decode, Decoder};
 use yrs::updates::encoder::{Encode, Encoder};
 use yrs::{Doc, Observer, Subscription};
 
+/// The time after which the state of a client that is not updated is considered outdated. It's the
+/// same as the `outdatedTimeout` of the y-protocols awareness.
+pub const OUTDATED_TIMEOUT: Duration = Duration::from_secs(30);
+
 /// The Awareness class implements a simple shared state protocol that can be used for non-persistent
 /// data like awareness information (cursor, username, status, ..). Each client can update its own
 /// local state and listen to state changes of remote clients.
@@ -24,6 +31,13 @@ use yrs::{Doc, Observer, Subscription};
 /// received, and the known clock of that client equals the received clock, it will clean the state.
 ///
 /// Before a client disconnects, it should propagate a `null` state with an updated clock.
+///
+/// Like the y-protocols awareness, a state that isn't updated for the [OUTDATED_TIMEOUT] is
+/// considered outdated. [Awareness::check_outdated] renews the local state before it's outdated
+/// and removes the outdated states of the remote clients, so it should be called periodically.
+/// A [MutexCollab] that is created within a tokio runtime calls it in the background.
+///
+/// [MutexCollab]: crate::core::collab::MutexCollab
 pub struct Awareness {
   doc: Doc,
   states: HashMap<ClientID, Value>,
@@ -93,6 +107,38 @@ impl Awareness {
     self.states.get(&self.doc.client_id())
   }
 
+  /// Returns the structured local state of the current client, see [AwarenessState].
+  pub fn get_local_typed_state<T: AwarenessState>(&self) -> Result<Option<T>, Error> {
+    self
+      .get_local_state()
+      .map(decode_awareness_state)
+      .transpose()
+  }
+
+  /// Returns the structured states of the clients. The states that can't be decoded, like the
+  /// states of the clients that publish another kind of state or an unsupported version, are
+  /// skipped.
+  pub fn get_typed_states<T: AwarenessState>(&self) -> HashMap<ClientID, T> {
+    self
+      .states
+      .iter()
+      .filter_map(|(client_id, state)| match decode_awareness_state(state) {
+        Ok(state) => Some((*client_id, state)),
+        Err(err) => {
+          tracing::trace!("skip the awareness state of {}: {}", client_id, err);
+          None
+        },
+      })
+      .collect()
+  }
+
+  /// Sets the structured local state of the current client, see [AwarenessState].
+  pub fn set_local_typed_state<T: AwarenessState>(&mut self, state: &T) -> Result<(), Error> {
+    let json = state.encode()?;
+    self.set_local_state(json);
+    Ok(())
+  }
+
   /// Sets the local state for the current [Awareness] instance to a specified JSON string.
   ///
   /// This method updates the state associated with the client ID obtained from `self.doc.client_id()`.
@@ -108,8 +154,12 @@ impl Awareness {
   /// * `json` - A string or a type that can be converted into a String, representing the new state
   ///   to be set for the current client ID.
   pub fn set_local_state<S: Into<Value>>(&mut self, json: S) {
+    self.set_local_state_at(json, now_millis());
+  }
+
+  fn set_local_state_at<S: Into<Value>>(&mut self, json: S, now: i64) {
     let client_id = self.doc.client_id();
-    self.update_meta(client_id);
+    self.update_meta(client_id, now);
 
     let is_new_client = !self.states.contains_key(&client_id);
     self.states.insert(client_id, json.into());
@@ -139,7 +189,7 @@ impl Awareness {
   /// the disconnection of the client and notifies all registered callbacks.
   pub fn remove_state(&mut self, client_id: ClientID) {
     let prev_state = self.states.remove(&client_id);
-    self.update_meta(client_id);
+    self.update_meta(client_id, now_millis());
 
     if prev_state.is_some() {
       if let Some(eh) = self.on_update.as_ref() {
@@ -158,8 +208,84 @@ impl Awareness {
     self.remove_state(client_id);
   }
 
-  fn update_meta(&mut self, client_id: ClientID) {
-    let now = chrono::Utc::now().timestamp();
+  /// Renew the local state if it's going to be outdated soon, and remove the states of the remote
+  /// clients that are outdated.
+  pub fn check_outdated(&mut self) {
+    self.check_outdated_at(now_millis());
+  }
+
+  /// Same as [Awareness::check_outdated], with the current time in milliseconds.
+  ///
+  /// The local state is renewed when it's older than half of the [OUTDATED_TIMEOUT], which bumps
+  /// its clock and emits an updated event, so the other clients receive it in time. Like the
+  /// y-protocols awareness, the event of the removed clients is emitted with the origin of the
+  /// awareness, so it's propagated to the other clients as the `null` states of those clients.
+  pub fn check_outdated_at(&mut self, now: i64) {
+    let timeout = OUTDATED_TIMEOUT.as_millis() as i64;
+    let local_client_id = self.doc.client_id();
+    if let Some(local_state) = self.states.get(&local_client_id) {
+      let is_outdating = self
+        .meta
+        .get(&local_client_id)
+        .map(|meta| now - meta.last_updated >= timeout / 2)
+        .unwrap_or(true);
+      if is_outdating {
+        let local_state = local_state.clone();
+        self.set_local_state_at(local_state, now);
+      }
+    }
+
+    let mut removed = vec![];
+    for (client_id, meta) in self.meta.iter() {
+      if *client_id != local_client_id
+        && now - meta.last_updated >= timeout
+        && self.states.contains_key(client_id)
+      {
+        removed.push(*client_id);
+      }
+    }
+    if removed.is_empty() {
+      return;
+    }
+    for client_id in removed.iter() {
+      self.states.remove(client_id);
+    }
+    if let Some(eh) = self.on_update.as_ref() {
+      let e = Event::new(vec![], vec![], removed);
+      for cb in eh.callbacks() {
+        cb(self, &e, &self.origin);
+      }
+    }
+  }
+
+  /// Return the time in milliseconds when the state of the client was last updated or renewed.
+  pub fn last_seen(&self, client_id: ClientID) -> Option<i64> {
+    self.meta.get(&client_id).map(|meta| meta.last_updated)
+  }
+
+  /// Return the clients that are online, which are the clients whose states are known, ordered
+  /// by the time they were last seen, the most recent first.
+  pub fn presences(&self) -> Vec<Presence> {
+    let local_client_id = self.doc.client_id();
+    let mut presences = self
+      .states
+      .iter()
+      .map(|(client_id, state)| Presence {
+        client_id: *client_id,
+        state: state.clone(),
+        last_seen: self.last_seen(*client_id).unwrap_or_default(),
+        is_local: *client_id == local_client_id,
+      })
+      .collect::<Vec<_>>();
+    presences.sort_by(|a, b| {
+      b.last_seen
+        .cmp(&a.last_seen)
+        .then(a.client_id.cmp(&b.client_id))
+    });
+    presences
+  }
+
+  fn update_meta(&mut self, client_id: ClientID, now: i64) {
     match self.meta.entry(client_id) {
       Entry::Occupied(mut e) => {
         let clock = e.get().clock + 1;
@@ -220,7 +346,7 @@ impl Awareness {
     update: AwarenessUpdate,
     origin: &CollabOrigin,
   ) -> Result<(), Error> {
-    let now = chrono::Utc::now().timestamp();
+    let now = now_millis();
 
     let mut added = Vec::new();
     let mut updated = Vec::new();
@@ -367,6 +493,10 @@ impl AwarenessUpdateEntry {
   pub fn json(&self) -> &Value {
     &self.json
   }
+
+  pub fn clock(&self) -> u32 {
+    self.clock
+  }
 }
 
 impl Display for AwarenessUpdateEntry {
@@ -385,11 +515,109 @@ pub enum Error {
   /// Client ID was not found in [Awareness] metadata.
   #[error("client ID `{0}` not found")]
   ClientNotFound(ClientID),
+
+  /// The state can't be encoded or decoded as an [AwarenessState].
+  #[error("invalid awareness state: {0}")]
+  InvalidState(#[from] serde_json::Error),
+
+  /// The state is published with a version that the [AwarenessState] can't decode.
+  #[error("unsupported awareness state version: {0}")]
+  UnsupportedStateVersion(u32),
+}
+
+/// A structured awareness state, like the selection of a document or the selected cell of a
+/// database. The state is published with its version:
+///
+/// ```json
+/// { "version": 1, "state": { ... } }
+/// ```
+///
+/// so a client can decode the states that are published by the older or the newer clients with
+/// [AwarenessState::decode_version]. A state that isn't published in this format, like the initial
+/// `{"uid": 1}` state of a [Collab](crate::core::collab::Collab), is decoded as version 0.
+pub trait AwarenessState: Serialize + DeserializeOwned {
+  /// The version of the state that is published by this client. It must be greater than 0.
+  const VERSION: u32;
+
+  /// Encode the state that is published by this client. It's the versioned state by default, see
+  /// [encode_awareness_state]. A state can keep publishing the format of the older clients while
+  /// they are around, as long as [AwarenessState::decode_version] decodes it.
+  fn encode(&self) -> Result<Value, Error> {
+    encode_awareness_state(self)
+  }
+
+  /// Decode a state that is published with another version. The states of the other versions are
+  /// not supported by default.
+  fn decode_version(version: u32, _state: &Value) -> Result<Self, Error> {
+    Err(Error::UnsupportedStateVersion(version))
+  }
+}
+
+/// The user that publishes an awareness state, identified by the user id and the device id. The
+/// awareness states of the document, the database and the folder share it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AwarenessUser {
+  pub uid: i64,
+  pub device_id: String,
+}
+
+#[derive(Serialize)]
+struct VersionedState<'a, T> {
+  version: u32,
+  state: &'a T,
+}
+
+/// Encode the state with its version, see [AwarenessState].
+pub fn encode_awareness_state<T: AwarenessState>(state: &T) -> Result<Value, Error> {
+  let versioned = VersionedState {
+    version: T::VERSION,
+    state,
+  };
+  Ok(serde_json::to_value(versioned)?)
+}
+
+/// Decode the state that is encoded by [encode_awareness_state], or a state that is published with
+/// another version.
+pub fn decode_awareness_state<T: AwarenessState>(value: &Value) -> Result<T, Error> {
+  let versioned = value.as_object().and_then(|object| {
+    let version = object.get("version")?.as_u64()?;
+    Some((u32::try_from(version).ok()?, object.get("state")?))
+  });
+  match versioned {
+    Some((version, state)) if version == T::VERSION => Ok(T::deserialize(state)?),
+    Some((version, state)) => T::decode_version(version, state),
+    None => T::decode_version(0, value),
+  }
+}
+
+/// A client whose awareness state is known, see [Awareness::presences].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Presence {
+  pub client_id: ClientID,
+  pub state: Value,
+  /// The time in milliseconds when the state was last updated or renewed.
+  pub last_seen: i64,
+  pub is_local: bool,
+}
+
+impl Presence {
+  /// Return the uid of the user if the state contains it, like the initial state of a [Collab].
+  ///
+  /// [Collab]: crate::core::collab::Collab
+  pub fn uid(&self) -> Option<i64> {
+    self.state.get("uid").and_then(|uid| uid.as_i64())
+  }
+
+  /// Decode the state as an [AwarenessState].
+  pub fn typed_state<T: AwarenessState>(&self) -> Result<T, Error> {
+    decode_awareness_state(&self.state)
+  }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct MetaClientState {
   clock: u32,
+  /// The time in milliseconds when the state was last updated.
   last_updated: i64,
 }
 
@@ -402,6 +630,10 @@ impl MetaClientState {
   }
 }
 
+fn now_millis() -> i64 {
+  chrono::Utc::now().timestamp_millis()
+}
+
 /// Event type emitted by an [Awareness] struct.
 #[derive(Debug, Default, Clone, Eq, PartialEq)]
 pub struct Event {
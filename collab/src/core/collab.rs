@@ -1,4 +1,5 @@
-use std::fmt::{Display, Formatter};
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter};
 use std::ops::{Deref, DerefMut};
 use std::panic;
 use std::panic::AssertUnwindSafe;
@@ -13,7 +14,7 @@ use serde_json::json;
 
 use tokio_stream::wrappers::WatchStream;
 use tracing::{error, instrument, trace};
-use yrs::block::Prelim;
+use yrs::block::{ClientID, Prelim};
 use yrs::types::map::MapEvent;
 use yrs::types::{ToJson, Value};
 use yrs::updates::decoder::Decode;
@@ -26,7 +27,8 @@ use yrs::{
 };
 
 use crate::core::awareness::{
-  gen_awareness_update_message, Awareness, AwarenessUpdateSubscription, Event,
+  gen_awareness_update_message, Awareness, AwarenessState, AwarenessUpdateSubscription, Event,
+  OUTDATED_TIMEOUT,
 };
 use crate::core::collab_plugin::{CollabPlugin, CollabPluginType};
 use crate::core::collab_state::{InitState, SnapshotState, State, SyncState};
@@ -89,6 +91,8 @@ pub struct Collab {
   undo_manager: Mutex<Option<UndoManager>>,
   update_subscription: RwLock<Option<UpdateSubscription>>,
   awareness_subscription: RwLock<Option<AwarenessUpdateSubscription>>,
+  /// The subscription of [Collab::subscribe_awareness_states].
+  awareness_states_subscription: Option<AwarenessUpdateSubscription>,
   after_txn_subscription: RwLock<Option<AfterTransactionSubscription>>,
   pub index_json_sender: IndexContentSender,
 }
@@ -162,9 +166,19 @@ impl Collab {
     object_id: T,
     plugins: Vec<Box<dyn CollabPlugin>>,
     skip_gc: bool,
+  ) -> Collab {
+    Self::new_with_doc(origin, object_id, make_yrs_doc(skip_gc), plugins)
+  }
+
+  /// Create a [Collab] on top of the given [Doc]. Used when the options of the [Doc], like its
+  /// client id, have to be chosen by the caller.
+  pub(crate) fn new_with_doc<T: AsRef<str>>(
+    origin: CollabOrigin,
+    object_id: T,
+    doc: Doc,
+    plugins: Vec<Box<dyn CollabPlugin>>,
   ) -> Collab {
     let object_id = object_id.as_ref().to_string();
-    let doc = make_yrs_doc(skip_gc);
     let data = doc.get_or_insert_map(DATA_SECTION);
     let meta = doc.get_or_insert_map(META_SECTION);
     let undo_manager = Mutex::new(None);
@@ -184,6 +198,7 @@ impl Collab {
       update_subscription: Default::default(),
       after_txn_subscription: Default::default(),
       awareness_subscription: Default::default(),
+      awareness_states_subscription: None,
       index_json_sender: tokio::sync::broadcast::channel(100).0,
     }
   }
@@ -392,6 +407,20 @@ impl Collab {
     self.awareness.on_update(f)
   }
 
+  /// Subscribe to the awareness states of the clients. The states that are not `T`, or are
+  /// published with an unsupported version, are skipped. The callback is called until the collab
+  /// is dropped, and subscribing again replaces the previous callback.
+  pub fn subscribe_awareness_states<T, F>(&mut self, f: F)
+  where
+    T: AwarenessState,
+    F: Fn(HashMap<ClientID, T>) + 'static,
+  {
+    let subscription = self
+      .awareness
+      .on_update(move |awareness, _event, _origin| f(awareness.get_typed_states()));
+    self.awareness_states_subscription = Some(subscription);
+  }
+
   pub fn get(&self, key: &str) -> Option<Value> {
     let txn = self.doc.transact();
     self.data.get(&txn, key)
@@ -945,14 +974,80 @@ impl Deref for Plugins {
 #[derive(Clone)]
 pub struct MutexCollab(Arc<Mutex<Collab>>);
 impl MutexCollab {
+  /// Wrap the collab. If it's created within a tokio runtime, the awareness of the collab is
+  /// checked periodically until the collab is dropped, so the local state is renewed and the
+  /// outdated states of the other clients are removed.
   pub fn new(collab: Collab) -> Self {
     #[allow(clippy::arc_with_non_send_sync)]
-    Self(Arc::new(Mutex::new(collab)))
+    let collab = Self(Arc::new(Mutex::new(collab)));
+    if tokio::runtime::Handle::try_current().is_ok() {
+      collab.spawn_awareness_check();
+    }
+    collab
   }
 
   pub fn downgrade(&self) -> WeakMutexCollab {
     WeakMutexCollab(Arc::downgrade(&self.0))
   }
+
+  /// Set the local awareness state. It overrides the previous state.
+  pub fn set_awareness_state<T: AwarenessState + Debug>(&self, state: &T) {
+    if let Err(err) = self.lock().get_mut_awareness().set_local_typed_state(state) {
+      error!(
+        "Failed to set the awareness state: {}, state: {:?}",
+        err, state
+      );
+    }
+  }
+
+  /// Return the local awareness state. None if it's not set or can't be decoded as `T`.
+  pub fn get_awareness_state<T: AwarenessState>(&self) -> Option<T> {
+    match self.lock().get_awareness().get_local_typed_state() {
+      Ok(state) => state,
+      Err(err) => {
+        error!("Failed to get the awareness state: {}", err);
+        None
+      },
+    }
+  }
+
+  /// Clean the local awareness state. It should be called when the collab is closed.
+  pub fn clean_awareness_state(&self) {
+    self.lock().get_mut_awareness().clean_local_state()
+  }
+
+  /// Same as [Collab::subscribe_awareness_states].
+  pub fn subscribe_awareness_states<T, F>(&self, f: F)
+  where
+    T: AwarenessState,
+    F: Fn(HashMap<ClientID, T>) + 'static,
+  {
+    self.lock().subscribe_awareness_states(f)
+  }
+
+  /// Check the awareness every tenth of the [OUTDATED_TIMEOUT] until the collab is dropped, so the
+  /// local state is renewed before the other clients consider it outdated and the outdated states
+  /// of the other clients are removed.
+  ///
+  /// Like the remote updates that the sync plugins apply, the check runs on a tokio worker while
+  /// it holds the lock of the collab, so the awareness observers don't need to be [Send]. It's
+  /// skipped while the collab is locked, so it never blocks the worker.
+  fn spawn_awareness_check(&self) {
+    let weak_collab = self.downgrade();
+    tokio::spawn(async move {
+      let mut interval = tokio::time::interval(OUTDATED_TIMEOUT / 10);
+      loop {
+        interval.tick().await;
+        let collab = match weak_collab.upgrade() {
+          None => break,
+          Some(collab) => collab,
+        };
+        if let Some(mut collab) = collab.try_lock() {
+          collab.get_mut_awareness().check_outdated();
+        }
+      }
+    });
+  }
 }
 
 impl Deref for MutexCollab {
@@ -17,6 +17,8 @@ macro_rules! if_wasm {
 pub mod core;
 pub mod entity;
 pub mod error;
+#[cfg(feature = "sync_sim")]
+pub mod sync_sim;
 pub mod util;
 
 pub mod preclude {
@@ -0,0 +1,461 @@
+//! A deterministic simulation of peers that sync a [Collab] over an unreliable network.
+//!
+//! Each peer has a [Collab] for every object of the simulation, e.g. a database and its rows. The
+//! local updates of each collab are captured by a plugin and sent to the same object of the other
+//! peers through a simulated network. The network runs on a virtual clock and uses a seeded
+//! random number generator, so a run can be replayed by using the same seed. Messages can be
+//! delayed, reordered, duplicated, dropped or blocked by a partition; the messages that are on the
+//! way over a link are lost when the link is cut. After the network is healed,
+//! [SyncSimulation::sync_all] exchanges the missing updates like a reconnection would, and
+//! [SyncSimulation::assert_converged] checks that all the peers end up with the same content.
+//!
+//! ```ignore
+//! let mut sim = SyncSimulation::new("doc", 3, 42, NetworkConfig::default().with_drop_rate(0.2));
+//! sim.edit(0, |collab| collab.insert("1", "a"));
+//! sim.edit(1, |collab| collab.insert("2", "b"));
+//! sim.run_until_quiet();
+//! sim.sync_all();
+//! sim.assert_converged();
+//! ```
+use std::collections::{BTreeMap, HashSet};
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use serde_json::Value as JsonValue;
+use yrs::updates::decoder::Decode;
+use yrs::{Doc, OffsetKind, Options, ReadTxn, Update};
+
+use crate::core::collab::{MutexCollab, TransactionMutExt};
+use crate::core::origin::{CollabClient, CollabOrigin};
+use crate::preclude::{Collab, CollabPlugin};
+
+/// The conditions of the simulated network. The latency of each message is picked in
+/// `[min_latency, max_latency]`, so the messages are reordered when the range is not empty.
+#[derive(Debug, Clone)]
+pub struct NetworkConfig {
+  /// The minimum latency of a message in virtual milliseconds.
+  pub min_latency: u64,
+  /// The maximum latency of a message in virtual milliseconds.
+  pub max_latency: u64,
+  /// The probability in [0, 1] that a message is lost.
+  pub drop_rate: f64,
+  /// The probability in [0, 1] that a message is delivered twice.
+  pub duplicate_rate: f64,
+}
+
+impl Default for NetworkConfig {
+  fn default() -> Self {
+    Self {
+      min_latency: 1,
+      max_latency: 1,
+      drop_rate: 0.0,
+      duplicate_rate: 0.0,
+    }
+  }
+}
+
+impl NetworkConfig {
+  pub fn with_latency(mut self, min_latency: u64, max_latency: u64) -> Self {
+    debug_assert!(min_latency <= max_latency);
+    self.min_latency = min_latency;
+    self.max_latency = max_latency;
+    self
+  }
+
+  pub fn with_drop_rate(mut self, drop_rate: f64) -> Self {
+    self.drop_rate = drop_rate;
+    self
+  }
+
+  pub fn with_duplicate_rate(mut self, duplicate_rate: f64) -> Self {
+    self.duplicate_rate = duplicate_rate;
+    self
+  }
+}
+
+/// A small seeded random number generator (SplitMix64). It's used instead of a thread local
+/// generator to make the simulation reproducible.
+#[derive(Debug, Clone)]
+pub struct SimRng(u64);
+
+impl SimRng {
+  pub fn new(seed: u64) -> Self {
+    Self(seed)
+  }
+
+  pub fn next_u64(&mut self) -> u64 {
+    self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = self.0;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+  }
+
+  /// Return a number in the range.
+  pub fn gen_range(&mut self, range: RangeInclusive<u64>) -> u64 {
+    let (start, end) = range.into_inner();
+    if start >= end {
+      return start;
+    }
+    start + self.next_u64() % (end - start + 1)
+  }
+
+  /// Return an index that is less than len. The len must not be zero.
+  pub fn gen_index(&mut self, len: usize) -> usize {
+    (self.next_u64() % len as u64) as usize
+  }
+
+  /// Return true with the probability in [0, 1].
+  pub fn gen_bool(&mut self, probability: f64) -> bool {
+    if probability <= 0.0 {
+      return false;
+    }
+    (self.next_u64() as f64 / u64::MAX as f64) < probability
+  }
+}
+
+pub type PeerId = usize;
+
+struct SimPeer {
+  /// The collab of each object by object id.
+  objects: BTreeMap<String, SimObject>,
+}
+
+struct SimObject {
+  collab: Arc<MutexCollab>,
+  outbox: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+impl SimObject {
+  /// Create the collab of the object for the peer. The peer `i` uses `i + 1` as its uid and the
+  /// yrs client id, so the conflicts are resolved in the same way for the same seed.
+  fn new(object_id: &str, peer_id: PeerId) -> Self {
+    let uid = peer_id as i64 + 1;
+    let outbox = Arc::new(Mutex::new(vec![]));
+    let doc = Doc::with_options(Options {
+      client_id: uid as u64,
+      offset_kind: OffsetKind::Utf16,
+      ..Options::default()
+    });
+    let origin = CollabOrigin::Client(CollabClient::new(uid, format!("peer-{}", peer_id)));
+    let plugin = OutboxPlugin {
+      outbox: outbox.clone(),
+    };
+    let mut collab = Collab::new_with_doc(origin, object_id, doc, vec![Box::new(plugin)]);
+    collab.initialize();
+    Self {
+      collab: Arc::new(MutexCollab::new(collab)),
+      outbox,
+    }
+  }
+}
+
+/// Captures the local updates of a peer until the simulation sends them.
+struct OutboxPlugin {
+  outbox: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+impl CollabPlugin for OutboxPlugin {
+  fn receive_local_update(&self, _origin: &CollabOrigin, _object_id: &str, update: &[u8]) {
+    self.outbox.lock().push(update.to_vec());
+  }
+}
+
+#[derive(Clone)]
+struct Envelope {
+  from: PeerId,
+  to: PeerId,
+  object_id: String,
+  update: Vec<u8>,
+}
+
+/// See the [module documentation](self).
+pub struct SyncSimulation {
+  /// The object that the simulation is created with.
+  object_id: String,
+  peers: Vec<SimPeer>,
+  config: NetworkConfig,
+  rng: SimRng,
+  /// The virtual time in milliseconds.
+  now: u64,
+  /// The messages that are on the way, ordered by their delivery time and then by the order
+  /// they were sent.
+  in_flight: BTreeMap<(u64, u64), Envelope>,
+  seq: u64,
+  /// The links that are cut. A link is stored with the smaller peer id first.
+  partitions: HashSet<(PeerId, PeerId)>,
+}
+
+/// The maximum number of steps of [SyncSimulation::run_until_quiet]. The simulation is expected
+/// to become quiet long before.
+const MAX_STEPS: usize = 1_000_000;
+
+impl SyncSimulation {
+  /// Create the peers with the collab of the object. The peer `i` uses `i + 1` as its uid and the
+  /// yrs client id, so the conflicts are resolved in the same way for the same seed. Use
+  /// [SyncSimulation::add_object] to sync more objects.
+  pub fn new(object_id: &str, num_of_peers: usize, seed: u64, config: NetworkConfig) -> Self {
+    let peers = (0..num_of_peers)
+      .map(|peer_id| {
+        let mut objects = BTreeMap::new();
+        objects.insert(object_id.to_string(), SimObject::new(object_id, peer_id));
+        SimPeer { objects }
+      })
+      .collect();
+
+    Self {
+      object_id: object_id.to_string(),
+      peers,
+      config,
+      rng: SimRng::new(seed),
+      now: 0,
+      in_flight: BTreeMap::new(),
+      seq: 0,
+      partitions: HashSet::new(),
+    }
+  }
+
+  pub fn object_id(&self) -> &str {
+    &self.object_id
+  }
+
+  /// Create the collab of the object on every peer. The object is synced like the one the
+  /// simulation is created with, over the same network.
+  pub fn add_object(&mut self, object_id: &str) {
+    for (peer_id, peer) in self.peers.iter_mut().enumerate() {
+      peer
+        .objects
+        .entry(object_id.to_string())
+        .or_insert_with(|| SimObject::new(object_id, peer_id));
+    }
+  }
+
+  /// Return the ids of the objects in order.
+  pub fn object_ids(&self) -> Vec<String> {
+    self.peers[0].objects.keys().cloned().collect()
+  }
+
+  pub fn num_of_peers(&self) -> usize {
+    self.peers.len()
+  }
+
+  /// Return the collab of the peer. Wrap it in a folder, a document or a database to edit it
+  /// through their APIs; the local updates are sent on the next [SyncSimulation::step].
+  pub fn peer(&self, peer_id: PeerId) -> Arc<MutexCollab> {
+    self.peer_object(peer_id, &self.object_id)
+  }
+
+  /// Return the collab of the object of the peer, see [SyncSimulation::peer]. Panic if the object
+  /// was not added.
+  pub fn peer_object(&self, peer_id: PeerId, object_id: &str) -> Arc<MutexCollab> {
+    self.object(peer_id, object_id).collab.clone()
+  }
+
+  /// The random number generator of the simulation. Use it to generate random edits, so they are
+  /// reproducible with the same seed.
+  pub fn rng(&mut self) -> &mut SimRng {
+    &mut self.rng
+  }
+
+  pub fn now(&self) -> u64 {
+    self.now
+  }
+
+  /// Edit the collab of the peer.
+  pub fn edit<F, T>(&self, peer_id: PeerId, f: F) -> T
+  where
+    F: FnOnce(&mut Collab) -> T,
+  {
+    self.edit_object(peer_id, &self.object_id, f)
+  }
+
+  /// Edit the collab of the object of the peer.
+  pub fn edit_object<F, T>(&self, peer_id: PeerId, object_id: &str, f: F) -> T
+  where
+    F: FnOnce(&mut Collab) -> T,
+  {
+    f(&mut self.object(peer_id, object_id).collab.lock())
+  }
+
+  /// Cut the links between the two groups of peers. The messages that are on the way over a cut
+  /// link and the ones that are sent over it later are lost.
+  pub fn partition(&mut self, group_a: &[PeerId], group_b: &[PeerId]) {
+    for a in group_a {
+      for b in group_b {
+        if a != b {
+          self.partitions.insert(link(*a, *b));
+        }
+      }
+    }
+    let partitions = &self.partitions;
+    self
+      .in_flight
+      .retain(|_, envelope| !partitions.contains(&link(envelope.from, envelope.to)));
+  }
+
+  /// Restore all the links. The updates that were lost are not resent, call
+  /// [SyncSimulation::sync_all] to exchange them.
+  pub fn heal(&mut self) {
+    self.partitions.clear();
+  }
+
+  pub fn is_connected(&self, a: PeerId, b: PeerId) -> bool {
+    !self.partitions.contains(&link(a, b))
+  }
+
+  /// Send the pending local updates of every peer without delivering them. The updates arrive on
+  /// the next [SyncSimulation::step]s.
+  pub fn send_pending_updates(&mut self) {
+    for from in 0..self.peers.len() {
+      for object_id in self.object_ids() {
+        let updates = std::mem::take(&mut *self.object(from, &object_id).outbox.lock());
+        for update in updates {
+          for to in 0..self.peers.len() {
+            if to != from {
+              self.send(from, to, &object_id, update.clone());
+            }
+          }
+        }
+      }
+    }
+  }
+
+  /// Send the pending local updates, then deliver the messages that arrive next. Return false if
+  /// there is nothing left to deliver.
+  pub fn step(&mut self) -> bool {
+    self.send_pending_updates();
+    let deliver_at = match self.in_flight.keys().next() {
+      None => return false,
+      Some((deliver_at, _)) => *deliver_at,
+    };
+    self.now = deliver_at;
+    while let Some(entry) = self.in_flight.first_entry() {
+      if entry.key().0 != deliver_at {
+        break;
+      }
+      let envelope = entry.remove();
+      self.apply_update(envelope.to, &envelope.object_id, &envelope.update);
+    }
+    true
+  }
+
+  /// Run the simulation until all the messages are delivered or lost.
+  pub fn run_until_quiet(&mut self) {
+    for _ in 0..MAX_STEPS {
+      if !self.step() {
+        return;
+      }
+    }
+    panic!("the simulation of {} doesn't become quiet", self.object_id);
+  }
+
+  /// Exchange the missing updates of every object between every pair of connected peers, like the
+  /// sync that happens when a peer reconnects. The exchange bypasses the network conditions.
+  pub fn sync_all(&mut self) {
+    self.run_until_quiet();
+    for object_id in self.object_ids() {
+      for a in 0..self.peers.len() {
+        for b in 0..self.peers.len() {
+          if a == b || !self.is_connected(a, b) {
+            continue;
+          }
+          let state_vector =
+            self.edit_object(b, &object_id, |collab| collab.transact().state_vector());
+          let update = self.edit_object(a, &object_id, |collab| {
+            collab.transact().encode_state_as_update_v1(&state_vector)
+          });
+          self.apply_update(b, &object_id, &update);
+        }
+      }
+    }
+  }
+
+  /// Return the content of the object that the simulation is created with on each peer.
+  pub fn to_json_values(&self) -> Vec<JsonValue> {
+    self.object_json_values(&self.object_id)
+  }
+
+  /// Return the content of the object on each peer.
+  pub fn object_json_values(&self, object_id: &str) -> Vec<JsonValue> {
+    (0..self.peers.len())
+      .map(|peer_id| self.edit_object(peer_id, object_id, |collab| collab.to_json_value()))
+      .collect()
+  }
+
+  /// Return true if all the peers have the same content for every object.
+  pub fn is_converged(&self) -> bool {
+    self.object_ids().iter().all(|object_id| {
+      let values = self.object_json_values(object_id);
+      values.windows(2).all(|pair| pair[0] == pair[1])
+    })
+  }
+
+  /// Panic with the content of each peer if they are not the same for one of the objects.
+  pub fn assert_converged(&self) {
+    for object_id in self.object_ids() {
+      let values = self.object_json_values(&object_id);
+      for (peer_id, value) in values.iter().enumerate().skip(1) {
+        assert_eq!(
+          &values[0], value,
+          "{}: peer 0 and peer {} don't converge",
+          object_id, peer_id
+        );
+      }
+    }
+  }
+
+  fn object(&self, peer_id: PeerId, object_id: &str) -> &SimObject {
+    self.peers[peer_id]
+      .objects
+      .get(object_id)
+      .unwrap_or_else(|| panic!("the object {} is not in the simulation", object_id))
+  }
+
+  fn send(&mut self, from: PeerId, to: PeerId, object_id: &str, update: Vec<u8>) {
+    if !self.is_connected(from, to) || self.rng.gen_bool(self.config.drop_rate) {
+      return;
+    }
+    let envelope = Envelope {
+      from,
+      to,
+      object_id: object_id.to_string(),
+      update,
+    };
+    if self.rng.gen_bool(self.config.duplicate_rate) {
+      self.schedule(envelope.clone());
+    }
+    self.schedule(envelope);
+  }
+
+  fn schedule(&mut self, envelope: Envelope) {
+    let latency = self
+      .rng
+      .gen_range(self.config.min_latency..=self.config.max_latency);
+    self.seq += 1;
+    self
+      .in_flight
+      .insert((self.now + latency, self.seq), envelope);
+  }
+
+  /// Apply the update without an origin, so it's not captured as a local update of the peer. An
+  /// update whose dependencies are missing is kept by yrs until they arrive.
+  fn apply_update(&self, to: PeerId, object_id: &str, update: &[u8]) {
+    let update = Update::decode_v1(update).expect("the update of a peer should be valid");
+    let collab = self.object(to, object_id).collab.lock();
+    let mut txn = collab
+      .try_transaction_mut()
+      .expect("the peer should not be in a transaction");
+    txn
+      .try_apply_update(update)
+      .expect("the update of a peer should be applied");
+  }
+}
+
+fn link(a: PeerId, b: PeerId) -> (PeerId, PeerId) {
+  if a < b {
+    (a, b)
+  } else {
+    (b, a)
+  }
+}
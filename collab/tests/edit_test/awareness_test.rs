@@ -1,6 +1,9 @@
-use collab::core::awareness::gen_awareness_update_message;
+use collab::core::awareness::{
+  decode_awareness_state, gen_awareness_update_message, AwarenessState, Error, OUTDATED_TIMEOUT,
+};
 use collab::preclude::Collab;
-use serde_json::json;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::sync::mpsc;
 use std::time::Duration;
@@ -92,3 +95,248 @@ async fn clean_awareness_state_sync_test() {
   let states = collab_b.get_awareness().get_states();
   assert_eq!(states.len(), 1);
 }
+
+#[tokio::test]
+async fn remove_outdated_awareness_state_test() {
+  let mut collab_a = Collab::new(0, "1", "1", vec![], true);
+  collab_a.emit_awareness_state();
+  let mut collab_b = Collab::new(1, "1", "2", vec![], true);
+  collab_b.emit_awareness_state();
+  let client_a = collab_a.get_doc().client_id();
+  let update = collab_a.get_awareness().update().unwrap();
+  collab_b
+    .get_mut_awareness()
+    .apply_update(update, &collab_a.origin)
+    .unwrap();
+
+  let (tx, rx) = mpsc::channel();
+  let _update = collab_b.observe_awareness(move |_awareness, event, origin| {
+    tx.send((event.clone(), origin.clone())).unwrap();
+  });
+
+  // collab_a's state is kept until it's outdated
+  let last_seen = collab_b.get_awareness().last_seen(client_a).unwrap();
+  let timeout = OUTDATED_TIMEOUT.as_millis() as i64;
+  collab_b
+    .get_mut_awareness()
+    .check_outdated_at(last_seen + timeout - 1);
+  assert_eq!(collab_b.get_awareness().get_states().len(), 2);
+
+  collab_b
+    .get_mut_awareness()
+    .check_outdated_at(last_seen + timeout);
+  assert_eq!(collab_b.get_awareness().get_states().len(), 1);
+  assert!(!collab_b
+    .get_awareness()
+    .get_states()
+    .contains_key(&client_a));
+
+  // The removal is emitted with the local origin, so it's propagated to the other clients
+  let (event, origin) = rx
+    .try_iter()
+    .find(|(event, _)| !event.removed().is_empty())
+    .unwrap();
+  assert_eq!(event.removed(), &[client_a]);
+  assert_eq!(origin, collab_b.origin);
+
+  let mut collab_c = Collab::new(2, "1", "3", vec![], true);
+  let update = collab_a.get_awareness().update().unwrap();
+  collab_c
+    .get_mut_awareness()
+    .apply_update(update, &collab_a.origin)
+    .unwrap();
+  let removal = gen_awareness_update_message(collab_b.get_awareness(), &event).unwrap();
+  collab_c
+    .get_mut_awareness()
+    .apply_update(removal, &collab_b.origin)
+    .unwrap();
+  assert!(collab_c.get_awareness().get_states().is_empty());
+}
+
+#[tokio::test]
+async fn renew_local_awareness_state_test() {
+  let mut collab = Collab::new(1, "1", "1", vec![], true);
+  collab.emit_awareness_state();
+  let client_id = collab.get_doc().client_id();
+  let clock = |collab: &Collab| {
+    let update = collab.get_awareness().update().unwrap();
+    update.clients().get(&client_id).unwrap().clock()
+  };
+  let prev_clock = clock(&collab);
+  let last_seen = collab.get_awareness().last_seen(client_id).unwrap();
+
+  let (tx, rx) = mpsc::channel();
+  let _update = collab.observe_awareness(move |_awareness, event, origin| {
+    tx.send((event.clone(), origin.clone())).unwrap();
+  });
+
+  // The local state is renewed when half of the timeout has passed
+  let half_timeout = OUTDATED_TIMEOUT.as_millis() as i64 / 2;
+  collab
+    .get_mut_awareness()
+    .check_outdated_at(last_seen + half_timeout - 1);
+  assert_eq!(clock(&collab), prev_clock);
+  assert!(rx.try_recv().is_err());
+
+  collab
+    .get_mut_awareness()
+    .check_outdated_at(last_seen + half_timeout);
+  assert_eq!(clock(&collab), prev_clock + 1);
+  assert_eq!(
+    collab.get_awareness().last_seen(client_id),
+    Some(last_seen + half_timeout)
+  );
+  assert_eq!(
+    collab.get_awareness().get_local_state(),
+    Some(&json!({"uid": 1}))
+  );
+
+  // The renewed state is emitted with the local origin, so it's sent to the other clients
+  let (event, origin) = rx.recv().unwrap();
+  assert_eq!(event.updated(), &[client_id]);
+  assert_eq!(origin, collab.origin);
+}
+
+#[tokio::test]
+async fn awareness_presences_test() {
+  let mut collab_a = Collab::new(0, "1", "1", vec![], true);
+  collab_a.emit_awareness_state();
+  let mut collab_b = Collab::new(1, "1", "2", vec![], true);
+  collab_b.emit_awareness_state();
+  let update = collab_a.get_awareness().update().unwrap();
+  collab_b
+    .get_mut_awareness()
+    .apply_update(update, &collab_a.origin)
+    .unwrap();
+
+  let presences = collab_b.get_awareness().presences();
+  assert_eq!(presences.len(), 2);
+  let local = presences.iter().find(|presence| presence.is_local).unwrap();
+  assert_eq!(local.uid(), Some(1));
+  assert_eq!(local.client_id, collab_b.get_doc().client_id());
+  let remote = presences
+    .iter()
+    .find(|presence| !presence.is_local)
+    .unwrap();
+  assert_eq!(remote.uid(), Some(0));
+  assert_eq!(
+    Some(remote.last_seen),
+    collab_b
+      .get_awareness()
+      .last_seen(collab_a.get_doc().client_id())
+  );
+
+  // The outdated client is not online anymore
+  let timeout = OUTDATED_TIMEOUT.as_millis() as i64;
+  collab_b
+    .get_mut_awareness()
+    .check_outdated_at(remote.last_seen + timeout);
+  let presences = collab_b.get_awareness().presences();
+  assert_eq!(presences.len(), 1);
+  assert!(presences[0].is_local);
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct CursorState {
+  uid: i64,
+  cursor: u32,
+}
+
+impl AwarenessState for CursorState {
+  const VERSION: u32 = 2;
+
+  fn decode_version(version: u32, state: &Value) -> Result<Self, Error> {
+    match version {
+      // The version 1 only contains the uid
+      1 => Ok(CursorState {
+        uid: state["uid"].as_i64().unwrap_or_default(),
+        cursor: 0,
+      }),
+      _ => Err(Error::UnsupportedStateVersion(version)),
+    }
+  }
+}
+
+#[tokio::test]
+async fn typed_awareness_state_test() {
+  let mut collab = Collab::new(1, "1", "1", vec![], true);
+  collab.emit_awareness_state();
+  // The initial state is not a CursorState
+  assert!(collab
+    .get_awareness()
+    .get_local_typed_state::<CursorState>()
+    .is_err());
+
+  let state = CursorState { uid: 1, cursor: 10 };
+  collab
+    .get_mut_awareness()
+    .set_local_typed_state(&state)
+    .unwrap();
+  assert_eq!(
+    collab.get_awareness().get_local_state(),
+    Some(&json!({"version": 2, "state": {"uid": 1, "cursor": 10}}))
+  );
+  assert_eq!(
+    collab.get_awareness().get_local_typed_state().unwrap(),
+    Some(state)
+  );
+}
+
+#[tokio::test]
+async fn typed_awareness_state_version_test() {
+  assert_eq!(
+    decode_awareness_state::<CursorState>(&json!({"version": 1, "state": {"uid": 3}})).unwrap(),
+    CursorState { uid: 3, cursor: 0 }
+  );
+  assert!(matches!(
+    decode_awareness_state::<CursorState>(&json!({"version": 3, "state": {"uid": 3}})),
+    Err(Error::UnsupportedStateVersion(3))
+  ));
+  assert!(matches!(
+    decode_awareness_state::<CursorState>(&json!({"uid": 3})),
+    Err(Error::UnsupportedStateVersion(0))
+  ));
+  assert!(matches!(
+    decode_awareness_state::<CursorState>(&json!({"version": 2, "state": {"uid": "3"}})),
+    Err(Error::InvalidState(_))
+  ));
+}
+
+#[tokio::test]
+async fn typed_awareness_states_test() {
+  let mut collab_a = Collab::new(0, "1", "1", vec![], true);
+  collab_a.emit_awareness_state();
+  let mut collab_b = Collab::new(1, "1", "2", vec![], true);
+  collab_b.emit_awareness_state();
+  let mut collab_c = Collab::new(2, "1", "3", vec![], true);
+  collab_c
+    .get_mut_awareness()
+    .set_local_typed_state(&CursorState { uid: 2, cursor: 5 })
+    .unwrap();
+  for collab in [&collab_a, &collab_c] {
+    let update = collab.get_awareness().update().unwrap();
+    collab_b
+      .get_mut_awareness()
+      .apply_update(update, &collab.origin)
+      .unwrap();
+  }
+
+  // Only the state of collab_c is a CursorState
+  let states = collab_b.get_awareness().get_typed_states::<CursorState>();
+  assert_eq!(states.len(), 1);
+  assert_eq!(
+    states.get(&collab_c.get_doc().client_id()),
+    Some(&CursorState { uid: 2, cursor: 5 })
+  );
+
+  let presence = collab_b
+    .get_awareness()
+    .presences()
+    .into_iter()
+    .find(|presence| presence.client_id == collab_c.get_doc().client_id())
+    .unwrap();
+  assert_eq!(
+    presence.typed_state::<CursorState>().unwrap(),
+    CursorState { uid: 2, cursor: 5 }
+  );
+}
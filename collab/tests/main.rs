@@ -1,2 +1,3 @@
 mod edit_test;
+mod sync_test;
 mod util;
@@ -0,0 +1 @@
+mod sync_sim_test;
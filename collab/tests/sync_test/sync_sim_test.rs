@@ -0,0 +1,229 @@
+use collab::core::value::YrsValueExtension;
+use collab::preclude::{Array, ArrayPrelim, Collab, Text, TextPrelim};
+use collab::sync_sim::{NetworkConfig, SimRng, SyncSimulation};
+use serde_json::json;
+
+#[test]
+fn reliable_network_converge_test() {
+  let mut sim = SyncSimulation::new("doc", 3, 1, NetworkConfig::default());
+  sim.edit(0, |collab| collab.insert("1", "a"));
+  sim.edit(1, |collab| collab.insert("2", "b"));
+  sim.edit(2, |collab| collab.insert("3", "c"));
+  sim.run_until_quiet();
+
+  sim.assert_converged();
+  assert_eq!(
+    sim.to_json_values()[0],
+    json!({"1": "a", "2": "b", "3": "c"})
+  );
+}
+
+#[test]
+fn concurrent_edits_of_same_key_converge_test() {
+  let mut sim = SyncSimulation::new("doc", 4, 2, NetworkConfig::default().with_latency(1, 20));
+  for peer_id in 0..sim.num_of_peers() {
+    sim.edit(peer_id, |collab| {
+      collab.insert("name", format!("peer {}", peer_id));
+    });
+  }
+  sim.run_until_quiet();
+  sim.assert_converged();
+}
+
+#[test]
+fn reordered_and_duplicated_updates_converge_test() {
+  let config = NetworkConfig::default()
+    .with_latency(1, 100)
+    .with_duplicate_rate(0.5);
+  let mut sim = SyncSimulation::new("doc", 3, 3, config);
+  sim.edit(0, |collab| collab.insert("text", TextPrelim::new("")));
+  sim.run_until_quiet();
+
+  // The later updates of a peer depend on the earlier ones, so they can't be integrated until
+  // the earlier ones arrive.
+  for i in 0..20 {
+    let peer_id = i % 3;
+    sim.edit(peer_id, |collab| insert_text(collab, 0, &i.to_string()));
+    sim.step();
+  }
+  sim.run_until_quiet();
+  sim.assert_converged();
+}
+
+#[test]
+fn dropped_updates_converge_after_sync_test() {
+  let mut sim = SyncSimulation::new("doc", 3, 4, NetworkConfig::default().with_drop_rate(0.5));
+  for i in 0..10 {
+    sim.edit(i % 3, |collab| collab.insert(&i.to_string(), i as i64));
+    sim.step();
+  }
+  sim.run_until_quiet();
+
+  sim.sync_all();
+  sim.run_until_quiet();
+  sim.assert_converged();
+  assert_eq!(sim.to_json_values()[0].as_object().unwrap().len(), 10);
+}
+
+#[test]
+fn partition_converge_after_heal_test() {
+  let mut sim = SyncSimulation::new("doc", 4, 5, NetworkConfig::default());
+  sim.partition(&[0, 1], &[2, 3]);
+  sim.edit(0, |collab| collab.insert("left", "0"));
+  sim.edit(3, |collab| collab.insert("right", "3"));
+  sim.run_until_quiet();
+
+  // Each side of the partition only sees its own edits
+  assert_eq!(sim.to_json_values()[1], json!({"left": "0"}));
+  assert_eq!(sim.to_json_values()[2], json!({"right": "3"}));
+  assert!(!sim.is_converged());
+
+  sim.heal();
+  sim.sync_all();
+  sim.assert_converged();
+  assert_eq!(sim.to_json_values()[0], json!({"left": "0", "right": "3"}));
+}
+
+#[test]
+fn partition_drops_in_flight_updates_test() {
+  let mut sim = SyncSimulation::new("doc", 2, 6, NetworkConfig::default());
+  sim.edit(0, |collab| collab.insert("1", "a"));
+  // The update is on the way when the link is cut
+  sim.send_pending_updates();
+  sim.partition(&[0], &[1]);
+  sim.heal();
+  sim.run_until_quiet();
+  assert_eq!(sim.to_json_values()[1], json!({}));
+
+  sim.sync_all();
+  sim.assert_converged();
+}
+
+#[test]
+fn multiple_objects_converge_test() {
+  let mut sim = SyncSimulation::new("doc_1", 3, 7, NetworkConfig::default().with_latency(1, 20));
+  sim.add_object("doc_2");
+  assert_eq!(sim.object_ids(), vec!["doc_1", "doc_2"]);
+
+  sim.partition(&[0], &[1, 2]);
+  sim.edit_object(0, "doc_1", |collab| collab.insert("1", "a"));
+  sim.edit_object(1, "doc_2", |collab| collab.insert("2", "b"));
+  sim.run_until_quiet();
+  assert!(!sim.is_converged());
+
+  sim.heal();
+  sim.sync_all();
+  sim.assert_converged();
+  // The updates of an object are only applied to the same object of the other peers
+  assert_eq!(sim.object_json_values("doc_1")[2], json!({"1": "a"}));
+  assert_eq!(sim.object_json_values("doc_2")[0], json!({"2": "b"}));
+}
+
+#[test]
+fn same_seed_same_result_test() {
+  let (values_1, now_1) = run_random_simulation(7);
+  let (values_2, now_2) = run_random_simulation(7);
+  assert_eq!(values_1, values_2);
+  assert_eq!(now_1, now_2);
+}
+
+#[test]
+fn randomized_edits_converge_test() {
+  for seed in 0..20 {
+    run_random_simulation(seed);
+  }
+}
+
+/// Run random edits on a map, a text and an array under random network conditions and
+/// partitions. Return the converged content and the virtual time of the simulation.
+fn run_random_simulation(seed: u64) -> (serde_json::Value, u64) {
+  let config = NetworkConfig::default()
+    .with_latency(1, 50)
+    .with_drop_rate(0.1)
+    .with_duplicate_rate(0.1);
+  let mut sim = SyncSimulation::new("doc", 4, seed, config);
+  sim.edit(0, |collab| {
+    collab.insert("text", TextPrelim::new(""));
+    collab.insert("list", ArrayPrelim::from(Vec::<String>::new()));
+  });
+  sim.sync_all();
+
+  let num_of_peers = sim.num_of_peers();
+  for _ in 0..100 {
+    let peer_id = sim.rng().gen_index(num_of_peers);
+    let action = sim.rng().gen_range(0..=9);
+    match action {
+      0 => {
+        let a = sim.rng().gen_index(num_of_peers);
+        let b = sim.rng().gen_index(num_of_peers);
+        sim.partition(&[a], &[b]);
+      },
+      1 => sim.heal(),
+      _ => {
+        let mut rng = sim.rng().clone();
+        sim.edit(peer_id, |collab| random_edit(&mut rng, collab));
+        *sim.rng() = rng;
+      },
+    }
+    let num_of_steps = sim.rng().gen_range(0..=3);
+    for _ in 0..num_of_steps {
+      sim.step();
+    }
+  }
+
+  sim.heal();
+  sim.sync_all();
+  sim.assert_converged();
+  (sim.to_json_values().remove(0), sim.now())
+}
+
+fn random_edit(rng: &mut SimRng, collab: &mut Collab) {
+  let key = format!("key_{}", rng.gen_range(0..=5));
+  match rng.gen_range(0..=4) {
+    0 => {
+      collab.insert(&key, rng.next_u64().to_string());
+    },
+    1 => {
+      collab.remove(&key);
+    },
+    2 => {
+      let len = text_len(collab);
+      let index = rng.gen_range(0..=len as u64) as u32;
+      insert_text(collab, index, "ab");
+    },
+    3 => {
+      let len = text_len(collab);
+      if len > 0 {
+        let index = rng.gen_index(len as usize) as u32;
+        collab.with_origin_transact_mut(|txn| {
+          let text = collab.get_with_txn(txn, "text").unwrap();
+          text.to_ytext().unwrap().remove_range(txn, index, 1);
+        });
+      }
+    },
+    _ => {
+      let value = rng.next_u64().to_string();
+      collab.with_origin_transact_mut(|txn| {
+        let list = collab.get_with_txn(txn, "list").unwrap();
+        let list = list.to_yarray().unwrap();
+        let index = rng.gen_range(0..=list.len(txn) as u64) as u32;
+        list.insert(txn, index, value);
+      });
+    },
+  }
+}
+
+fn insert_text(collab: &Collab, index: u32, chunk: &str) {
+  collab.with_origin_transact_mut(|txn| {
+    let text = collab.get_with_txn(txn, "text").unwrap();
+    text.to_ytext().unwrap().insert(txn, index, chunk);
+  });
+}
+
+fn text_len(collab: &Collab) -> u32 {
+  let txn = collab.transact();
+  collab
+    .get_with_txn(&txn, "text")
+    .and_then(|text| text.to_ytext().map(|text| text.len(&txn)))
+    .unwrap_or(0)
+}